@@ -4,6 +4,8 @@
 //! to the unified `ChibiInput` format.
 
 use chibi_core::input::{Command, DebugKey, ExecutionFlags, Inspectable};
+use chibi_core::state::ExportFormat;
+use chibi_core::tools::tool_test::TestReportFormat;
 
 use crate::input::{ChibiInput, ContextSelection, UsernameOverride};
 use clap::Parser;
@@ -34,6 +36,7 @@ impl InspectableExt for Inspectable {
             "goals" => Some(Inspectable::Goals),
             // Global items
             "home" => Some(Inspectable::Home),
+            "hooks" => Some(Inspectable::Hooks),
             "list" => Some(Inspectable::List),
             // Check if it's a valid config field path
             other => {
@@ -53,6 +56,7 @@ impl InspectableExt for Inspectable {
             "tasks",
             "goals",
             "home",
+            "hooks",
             "list",
         ];
         names.extend(ResolvedConfig::list_fields());
@@ -90,6 +94,14 @@ pub struct Cli {
     )]
     pub ephemeral_context: Option<String>,
 
+    /// Save current context on the session's stack, then switch (persistent)
+    #[arg(long = "push-context", value_name = "NAME", allow_hyphen_values = true)]
+    pub push_context: Option<String>,
+
+    /// Switch back to the most recently pushed context
+    #[arg(long = "pop-context")]
+    pub pop_context: bool,
+
     /// Show current context info
     #[arg(short = 'l', long = "list-current-context")]
     pub list_current_context: bool,
@@ -174,6 +186,28 @@ pub struct Cli {
     #[arg(long = "flock-list")]
     pub flock_list: bool,
 
+    // === Context templates ===
+    /// Create contexts from a template in ~/.chibi/templates (remaining arguments
+    /// are name patterns such as worker-{1..3}; the template name when absent)
+    #[arg(long = "from-template", value_name = "TEMPLATE")]
+    pub from_template: Option<String>,
+
+    /// With --from-template: also join every created context to FLOCK
+    #[arg(
+        long = "template-flock",
+        value_name = "FLOCK",
+        requires = "from_template"
+    )]
+    pub template_flock: Option<String>,
+
+    /// List the context templates in ~/.chibi/templates
+    #[arg(long = "list-templates")]
+    pub list_templates: bool,
+
+    /// Report VFS usage and quota limits per zone (context homes, flocks, shared, tools)
+    #[arg(long = "vfs-usage")]
+    pub vfs_usage: bool,
+
     /// Compact current context (summarize and clear)
     #[arg(short = 'z', long = "compact-current-context")]
     pub compact_current_context: bool,
@@ -200,6 +234,26 @@ pub struct Cli {
     #[arg(short = 'R', long = "rename-context", value_names = ["OLD", "NEW"], num_args = 2, allow_hyphen_values = true)]
     pub rename_context: Option<Vec<String>>,
 
+    /// Export current context to PATH (.md/.html render the transcript; anything else is a full archive)
+    #[arg(long = "export", value_name = "PATH")]
+    pub export: Option<String>,
+
+    /// Export specified context (requires CTX and PATH)
+    #[arg(long = "export-context", value_names = ["CTX", "PATH"], num_args = 2, allow_hyphen_values = true)]
+    pub export_context: Option<Vec<String>>,
+
+    /// Override the export format inferred from PATH (archive, markdown, html)
+    #[arg(long = "export-format", value_name = "FORMAT")]
+    pub export_format: Option<String>,
+
+    /// Import a context archive under its archived name (suffixed if taken)
+    #[arg(long = "import", value_name = "PATH")]
+    pub import: Option<String>,
+
+    /// Import a context archive under a new name (requires PATH and NAME)
+    #[arg(long = "import-as", value_names = ["PATH", "NAME"], num_args = 2, allow_hyphen_values = true)]
+    pub import_as: Option<Vec<String>>,
+
     /// Show last N log entries (current context). Use negative for first N
     #[arg(
         short = 'g',
@@ -213,7 +267,7 @@ pub struct Cli {
     #[arg(short = 'G', long = "show-log", value_names = ["CTX", "N"], num_args = 2, allow_hyphen_values = true)]
     pub show_log: Option<Vec<String>>,
 
-    /// Inspect current context (system_prompt, reflection, tasks, goals, list)
+    /// Inspect current context (system_prompt, reflection, tasks, goals, hooks, list)
     #[arg(short = 'n', long = "inspect-current", value_name = "THING")]
     pub inspect_current: Option<String>,
 
@@ -234,6 +288,20 @@ pub struct Cli {
     #[arg(short = 'Y', long = "set-system-prompt", value_names = ["CTX", "PROMPT"], num_args = 2, allow_hyphen_values = true)]
     pub set_system_prompt: Option<Vec<String>>,
 
+    /// Compose the current context's system prompt from library templates
+    /// (comma-separated names; remaining arguments are template arguments)
+    #[arg(long = "compose-system-prompt", value_name = "NAME[,NAME...]")]
+    pub compose_system_prompt: Option<String>,
+
+    // === Prompt library ===
+    /// Send a template from the prompt library (remaining arguments are template arguments)
+    #[arg(long = "prompt-template", value_name = "NAME")]
+    pub prompt_template: Option<String>,
+
+    /// List the templates in the prompt library
+    #[arg(long = "list-prompts")]
+    pub list_prompts: bool,
+
     // === Username options ===
     /// Set username (persists to local.toml)
     #[arg(
@@ -320,6 +388,14 @@ pub struct Cli {
     #[arg(long = "no-tool-calls")]
     pub no_tool_calls: bool,
 
+    /// Start an interactive REPL (any prompt is sent as the first turn)
+    #[arg(short = 'i', long = "interactive")]
+    pub interactive: bool,
+
+    /// Browse contexts, transcripts, inboxes, flocks and the VFS full-screen
+    #[arg(long = "tui")]
+    pub tui: bool,
+
     /// Trust mode: auto-approve all permission checks (for automation/piping)
     #[arg(short = 't', long = "trust")]
     pub trust: bool,
@@ -340,11 +416,43 @@ pub struct Cli {
     #[arg(short = 's', long = "set", value_name = "KEY=VALUE")]
     pub set: Vec<String>,
 
+    /// Attach an image or text file to the prompt (repeatable, PATH or image URL)
+    #[arg(long = "attach", value_name = "PATH|URL")]
+    pub attach: Vec<String>,
+
+    /// Reply with JSON validated against a JSON Schema file (re-prompts on mismatch)
+    #[arg(long = "schema", value_name = "PATH")]
+    pub schema: Option<String>,
+
     // === Debug options ===
     /// Enable debug features (request-log, response-meta, all)
     #[arg(long = "debug", value_name = "KEY")]
     pub debug: Option<String>,
 
+    /// Record model responses and tool results to a cassette (JSONL) for --replay
+    #[arg(long = "record", value_name = "PATH")]
+    pub record: Option<String>,
+
+    /// Re-run a recorded session against its recorded responses and diff the results
+    #[arg(long = "replay", value_name = "CASSETTE")]
+    pub replay: Option<String>,
+
+    /// With --replay: run live against MODEL instead of the recorded responses
+    #[arg(long = "replay-model", value_name = "MODEL", requires = "replay")]
+    pub replay_model: Option<String>,
+
+    /// With --replay: serve recorded tool results instead of executing tools
+    #[arg(long = "replay-tools", requires = "replay")]
+    pub replay_tools: bool,
+
+    /// Run the define-test tests in a synthesised tool file or directory (VFS path)
+    #[arg(long = "test-tool", value_name = "VFS_PATH")]
+    pub test_tool: Option<String>,
+
+    /// With --test-tool: report format (tap, json)
+    #[arg(long = "test-format", value_name = "FORMAT", requires = "test_tool")]
+    pub test_format: Option<String>,
+
     /// Auto-destroy this context at a Unix timestamp
     #[arg(long = "destroy-at", value_name = "TIMESTAMP")]
     pub destroy_at: Option<u64>,
@@ -362,6 +470,10 @@ pub struct Cli {
     #[arg(long = "project-root", value_name = "PATH")]
     pub project_root: Option<String>,
 
+    /// Session to use (default: CHIBI_SESSION env var, else the terminal; 'global' for the shared one)
+    #[arg(long = "session", value_name = "ID")]
+    pub session: Option<String>,
+
     // === Help and version ===
     /// Show help
     #[arg(short = 'h', long = "help")]
@@ -381,6 +493,10 @@ pub struct Cli {
 const CLI_AFTER_HELP: &str = r#"EXAMPLES:
   chibi What is Rust?             Send prompt to LLM
   chibi -c coding write code      Switch context, then send prompt
+  chibi --push-context review     Switch, remembering where you were
+  chibi --pop-context             Return to the context before the push
+  chibi -i                        Start an interactive session
+  chibi --tui                     Browse contexts and flocks full-screen
   chibi -L                        List all contexts
   chibi -l                        Show current context info
   chibi -Dold                     Destroy 'old' context (attached arg)
@@ -395,13 +511,26 @@ const CLI_AFTER_HELP: &str = r#"EXAMPLES:
   chibi -p myplugin "arg1 arg2"   Run plugin with args (shell-style split)
   chibi -P mytool '{}'            Call tool with empty JSON args
   chibi -P send '{"to":"x"}'      Call tool with JSON args
+  chibi --attach shot.png why?    Attach an image to the prompt
+  chibi --schema s.json triage    Print the reply as JSON matching s.json
+  chibi --prompt-template review src/lib.rs focus=errors
+                                  Send the 'review' library prompt
+  chibi --compose-system-prompt persona,rules lang=Go
+                                  Build the system prompt from fragments
+  chibi --from-template '{reviewer,implementer,tester}' --template-flock squad
+                                  Create three contexts in one flock
+  chibi --record s.jsonl fix it   Record responses and tool results
+  chibi --replay s.jsonl          Replay a recording and diff the outcome
+  chibi --test-tool /tools/shared Run the tests in every shared tool
 
 FLAG BEHAVIOR:
   Some flags imply --no-chibi (operations that produce output or
   operate on other contexts). Use -X to override and invoke LLM after.
 
-  Implied --no-chibi: -l, -L, -d, -D, -A, -Z, -R, -g, -G, -n, -N, -Y, -M, -p, -P, --model-metadata, --model-metadata-full
-  Combinable with prompt: -c, -C, -a, -z, -r, -m, -y, -u, -U, -v
+  Implied --no-chibi: -l, -L, -d, -D, -A, -Z, -R, -g, -G, -n, -N, -Y, -M, -p, -P, --model-metadata, --model-metadata-full,
+    --export, --import, --replay, --test-tool, --list-prompts, --compose-system-prompt, --from-template, --list-templates
+  Combinable with prompt: -c, -C, -a, -z, -r, -m, -y, -u, -U, -v, -i,
+    --push-context, --pop-context
 
 PROMPT INPUT:
   Arguments after options are joined as the prompt.
@@ -429,7 +558,7 @@ fn extract_string_pair(v: &Option<Vec<String>>) -> Option<(String, String)> {
 }
 
 /// Parse an Inspectable from a string, returning an io::Error on failure.
-fn parse_inspectable(s: &str) -> io::Result<Inspectable> {
+pub(crate) fn parse_inspectable(s: &str) -> io::Result<Inspectable> {
     Inspectable::from_str_cli(s).ok_or_else(|| {
         io::Error::new(
             ErrorKind::InvalidInput,
@@ -469,6 +598,23 @@ impl Cli {
         }
     }
 
+    /// `--attach` sources with local paths made absolute, so they resolve
+    /// against the caller's cwd rather than the project root.
+    fn attachments(&self) -> Vec<String> {
+        self.attach
+            .iter()
+            .map(|source| {
+                if source.starts_with("http://") || source.starts_with("https://") {
+                    source.clone()
+                } else {
+                    std::path::absolute(source)
+                        .map(|p| p.to_string_lossy().into_owned())
+                        .unwrap_or_else(|_| source.clone())
+                }
+            })
+            .collect()
+    }
+
     /// Convert to ChibiInput format
     pub fn to_input(&self) -> io::Result<ChibiInput> {
         // Validate inspect values
@@ -506,6 +652,36 @@ impl Cli {
         let rename_context = extract_string_pair(&self.rename_context);
         let set_system_prompt = extract_string_pair(&self.set_system_prompt);
 
+        let export_context = extract_string_pair(&self.export_context);
+        let import_as = extract_string_pair(&self.import_as);
+        let export_format = self
+            .export_format
+            .as_deref()
+            .map(|f| {
+                ExportFormat::parse(f).ok_or_else(|| {
+                    io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Unknown export format '{}' (expected archive, markdown or html)",
+                            f
+                        ),
+                    )
+                })
+            })
+            .transpose()?;
+        let test_format = self
+            .test_format
+            .as_deref()
+            .map(|f| {
+                TestReportFormat::parse(f).ok_or_else(|| {
+                    io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Unknown test format '{}' (expected tap or json)", f),
+                    )
+                })
+            })
+            .transpose()?;
+
         // Parse plugin invocation with shell-style arg splitting
         let plugin = if let Some(v) = &self.plugin {
             let name = v.first().cloned().unwrap_or_default();
@@ -579,14 +755,25 @@ impl Cli {
             || flock_join.is_some()
             || flock_leave.is_some()
             || self.flock_list
+            || self.from_template.is_some()
+            || self.list_templates
+            || self.vfs_usage
             || self.archive_history.is_some()
             || self.compact_context.is_some()
             || rename_context.is_some()
+            || self.export.is_some()
+            || export_context.is_some()
+            || self.import.is_some()
+            || import_as.is_some()
+            || self.replay.is_some()
+            || self.test_tool.is_some()
             || self.show_current_log.is_some()
             || show_log.is_some()
             || inspect_current.is_some()
             || inspect.is_some()
             || set_system_prompt.is_some()
+            || self.compose_system_prompt.is_some()
+            || self.list_prompts
             || plugin.is_some()
             || call_tool.is_some()
             || debug_implies_force_call_user
@@ -615,6 +802,10 @@ impl Cli {
                 name: name.clone(),
                 persistent: true,
             }
+        } else if let Some(ref name) = self.push_context {
+            ContextSelection::Push { name: name.clone() }
+        } else if self.pop_context {
+            ContextSelection::Pop
         } else {
             ContextSelection::Current
         };
@@ -630,9 +821,18 @@ impl Cli {
 
         // Determine command
         // First check prompt (highest priority when not force_call_user)
-        let command = if !self.prompt.is_empty() && !force_call_user {
+        let command = if let Some(ref name) = self.prompt_template
+            && !force_call_user
+        {
+            Command::RunPromptTemplate {
+                name: name.clone(),
+                args: self.prompt.clone(),
+                attachments: self.attachments(),
+            }
+        } else if !self.prompt.is_empty() && !force_call_user {
             Command::SendPrompt {
                 prompt: self.prompt.join(" "),
+                attachments: self.attachments(),
             }
         // Simple standalone commands
         } else if self.list_contexts {
@@ -667,6 +867,28 @@ impl Cli {
                 old: Some(old.clone()),
                 new: new.clone(),
             }
+        } else if let Some(ref path) = self.export {
+            Command::ExportContext {
+                name: None,
+                path: path.clone(),
+                format: export_format,
+            }
+        } else if let Some((ref ctx, ref path)) = export_context {
+            Command::ExportContext {
+                name: Some(ctx.clone()),
+                path: path.clone(),
+                format: export_format,
+            }
+        } else if let Some(ref path) = self.import {
+            Command::ImportContext {
+                path: path.clone(),
+                name: None,
+            }
+        } else if let Some((ref path, ref name)) = import_as {
+            Command::ImportContext {
+                path: path.clone(),
+                name: Some(name.clone()),
+            }
         } else if let Some(count) = self.show_current_log {
             Command::ShowLog {
                 context: None,
@@ -697,6 +919,19 @@ impl Cli {
                 context: Some(ctx.clone()),
                 prompt: prompt_val.clone(),
             }
+        } else if let Some(ref names) = self.compose_system_prompt {
+            Command::ComposeSystemPrompt {
+                context: None,
+                fragments: names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty())
+                    .map(String::from)
+                    .collect(),
+                args: self.prompt.clone(),
+            }
+        } else if self.list_prompts {
+            Command::ListPromptTemplates
         } else if let Some(ref invocation) = plugin {
             Command::RunPlugin {
                 name: invocation.name.clone(),
@@ -753,6 +988,27 @@ impl Cli {
             }
         } else if self.flock_list {
             Command::FlockList
+        } else if let Some(ref template) = self.from_template {
+            Command::CreateFromTemplate {
+                template: template.clone(),
+                names: self.prompt.clone(),
+                flock: self.template_flock.clone(),
+            }
+        } else if self.list_templates {
+            Command::ListContextTemplates
+        } else if self.vfs_usage {
+            Command::VfsUsage
+        } else if let Some(ref cassette) = self.replay {
+            Command::Replay {
+                cassette: cassette.clone(),
+                model: self.replay_model.clone(),
+                replay_tools: self.replay_tools,
+            }
+        } else if let Some(ref path) = self.test_tool {
+            Command::TestTool {
+                path: path.clone(),
+                format: test_format,
+            }
         } else {
             Command::NoOp
         };
@@ -763,6 +1019,7 @@ impl Cli {
             debug: debug_keys,
             destroy_at: self.destroy_at,
             destroy_after_seconds_inactive: self.destroy_after_inactive,
+            record: self.record.clone(),
         };
 
         // Parse -s/--set KEY=VALUE pairs
@@ -797,6 +1054,8 @@ impl Cli {
             verbose_flag: self.verbose,
             hide_tool_calls_flag: self.hide_tool_calls,
             show_thinking_flag: self.show_thinking,
+            interactive: self.interactive,
+            tui: self.tui,
         })
     }
 
@@ -950,7 +1209,11 @@ pub fn parse() -> io::Result<ChibiInput> {
     // Handle stdin prompt reading (CLI-specific behavior)
     // This happens when there's no command that produces output and we might need
     // to read from stdin or interactive input
-    let should_read_prompt = !input.flags.force_call_user && matches!(input.command, Command::NoOp);
+    // The REPL and TUI read their own input, so they never consume stdin here.
+    let should_read_prompt = !input.flags.force_call_user
+        && !input.interactive
+        && !input.tui
+        && matches!(input.command, Command::NoOp);
 
     if should_read_prompt {
         let stdin_is_pipe = !io::stdin().is_terminal();
@@ -979,13 +1242,49 @@ pub fn parse() -> io::Result<ChibiInput> {
         };
 
         if !prompt.trim().is_empty() {
-            input.command = Command::SendPrompt { prompt };
+            input.command = Command::SendPrompt {
+                prompt,
+                attachments: cli.attachments(),
+            };
         }
     }
 
+    if let Some(path) = &cli.schema {
+        input.command = structured_command(input.command, path)?;
+    }
+
     Ok(input)
 }
 
+/// Turn a prompt into a structured-output command using the schema at `path`.
+fn structured_command(command: Command, path: &str) -> io::Result<Command> {
+    let Command::SendPrompt {
+        prompt,
+        attachments,
+    } = command
+    else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "--schema needs a prompt",
+        ));
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(e.kind(), format!("Failed to read schema '{}': {}", path, e))
+    })?;
+    let schema = serde_json::from_str(&text).map_err(|e| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Schema '{}' is not valid JSON: {}", path, e),
+        )
+    })?;
+    Ok(Command::SendStructured {
+        prompt,
+        schema,
+        max_attempts: None,
+        attachments,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1010,6 +1309,40 @@ mod tests {
 
     // === Basic flag tests ===
 
+    #[test]
+    fn test_structured_command_reads_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.json");
+        std::fs::write(&path, r#"{"type":"object"}"#).unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let cli = parse_cli("--schema x triage this").unwrap();
+        assert_eq!(cli.schema.as_deref(), Some("x"));
+        let input = cli.to_input().unwrap();
+        let cmd = structured_command(input.command, &path).unwrap();
+        let Command::SendStructured { prompt, schema, .. } = cmd else {
+            panic!("expected SendStructured");
+        };
+        assert_eq!(prompt, "triage this");
+        assert_eq!(schema, serde_json::json!({"type": "object"}));
+    }
+
+    #[test]
+    fn test_structured_command_errors() {
+        let err = structured_command(Command::ListContexts, "s.json").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schema.json");
+        std::fs::write(&path, "not json").unwrap();
+        let prompt = Command::SendPrompt {
+            prompt: "hi".to_string(),
+            attachments: vec![],
+        };
+        let err = structured_command(prompt, &path.to_string_lossy()).unwrap_err();
+        assert!(err.to_string().contains("is not valid JSON"));
+    }
+
     #[test]
     fn test_no_args() {
         let cli = parse_cli("").unwrap();
@@ -1076,7 +1409,7 @@ mod tests {
         // With --, it works
         let input = parse_input("-- -starts-with-dash").unwrap();
         assert!(
-            matches!(input.command, Command::SendPrompt { ref prompt } if prompt == "-starts-with-dash")
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "-starts-with-dash")
         );
     }
 
@@ -1087,10 +1420,43 @@ mod tests {
             matches!(input.context, ContextSelection::Switch { ref name, .. } if name == "coding")
         );
         assert!(
-            matches!(input.command, Command::SendPrompt { ref prompt } if prompt == "hello world")
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "hello world")
         );
     }
 
+    #[test]
+    fn test_attach_repeatable_with_prompt() {
+        let input =
+            parse_input("--attach /tmp/a.png --attach https://example.com/b.jpg describe these")
+                .unwrap();
+        match input.command {
+            Command::SendPrompt {
+                ref prompt,
+                ref attachments,
+            } => {
+                assert_eq!(prompt, "describe these");
+                assert_eq!(
+                    attachments,
+                    &[
+                        "/tmp/a.png".to_string(),
+                        "https://example.com/b.jpg".to_string()
+                    ]
+                );
+            }
+            ref other => panic!("expected SendPrompt, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_attach_relative_path_made_absolute() {
+        let input = parse_input("--attach notes.md summarise").unwrap();
+        let Command::SendPrompt { attachments, .. } = input.command else {
+            panic!("expected SendPrompt");
+        };
+        assert!(std::path::Path::new(&attachments[0]).is_absolute());
+        assert!(attachments[0].ends_with("notes.md"));
+    }
+
     // === Ephemeral context tests ===
 
     #[test]
@@ -1108,10 +1474,33 @@ mod tests {
             matches!(input.context, ContextSelection::Ephemeral { ref name } if name == "agent")
         );
         assert!(
-            matches!(input.command, Command::SendPrompt { ref prompt } if prompt == "run task")
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "run task")
         );
     }
 
+    // === Context stack tests ===
+
+    #[test]
+    fn test_push_context_with_prompt() {
+        let input = parse_input("--push-context review look at this").unwrap();
+        assert!(matches!(input.context, ContextSelection::Push { ref name } if name == "review"));
+        assert!(
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "look at this")
+        );
+    }
+
+    #[test]
+    fn test_pop_context() {
+        let input = parse_input("--pop-context -x").unwrap();
+        assert!(matches!(input.context, ContextSelection::Pop));
+    }
+
+    #[test]
+    fn test_session_flag_accepted() {
+        let cli = parse_cli("--session work -l").unwrap();
+        assert_eq!(cli.session.as_deref(), Some("work"));
+    }
+
     // === List tests ===
 
     #[test]
@@ -1255,6 +1644,113 @@ mod tests {
         assert!(input.flags.force_call_user);
     }
 
+    #[test]
+    fn test_export_and_import_flags() {
+        let input = parse_input("--export out.md").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::ExportContext { name: None, ref path, format: None } if path == "out.md"
+        ));
+        assert!(input.flags.force_call_user);
+
+        let input = parse_input("--export-context work out --export-format html").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::ExportContext { ref name, format: Some(ExportFormat::Html), .. }
+                if *name == Some("work".to_string())
+        ));
+
+        let input = parse_input("--import-as a.json copy").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::ImportContext { ref path, ref name }
+                if path == "a.json" && *name == Some("copy".to_string())
+        ));
+
+        assert!(parse_input("--export out --export-format pdf").is_err());
+    }
+
+    #[test]
+    fn test_record_and_replay_flags() {
+        let input = parse_input("--record s.jsonl hello").unwrap();
+        assert_eq!(input.flags.record.as_deref(), Some("s.jsonl"));
+        assert!(matches!(input.command, Command::SendPrompt { .. }));
+
+        let input = parse_input("--replay s.jsonl --replay-model other --replay-tools").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::Replay { ref cassette, ref model, replay_tools: true }
+                if cassette == "s.jsonl" && *model == Some("other".to_string())
+        ));
+        assert!(input.flags.force_call_user);
+
+        assert!(parse_input("--replay-tools").is_err());
+    }
+
+    #[test]
+    fn test_prompt_template_flags() {
+        let input = parse_input("--prompt-template review src/lib.rs focus=errors").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::RunPromptTemplate { ref name, ref args, .. }
+                if name == "review" && args == &["src/lib.rs", "focus=errors"]
+        ));
+        assert!(!input.flags.force_call_user);
+
+        let input = parse_input("--list-prompts").unwrap();
+        assert!(matches!(input.command, Command::ListPromptTemplates));
+        assert!(input.flags.force_call_user);
+
+        let input = parse_input("--compose-system-prompt persona,rules lang=Go").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::ComposeSystemPrompt { context: None, ref fragments, ref args }
+                if fragments == &["persona", "rules"] && args == &["lang=Go"]
+        ));
+        assert!(input.flags.force_call_user);
+    }
+
+    #[test]
+    fn test_context_template_flags() {
+        let input =
+            parse_input("--from-template worker --template-flock crew w-{1..3} lead").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::CreateFromTemplate { ref template, ref names, ref flock }
+                if template == "worker"
+                    && names == &["w-{1..3}", "lead"]
+                    && flock.as_deref() == Some("crew")
+        ));
+        assert!(input.flags.force_call_user);
+
+        let input = parse_input("--list-templates").unwrap();
+        assert!(matches!(input.command, Command::ListContextTemplates));
+        assert!(input.flags.force_call_user);
+
+        assert!(parse_input("--template-flock crew").is_err());
+    }
+
+    #[test]
+    fn test_vfs_usage_flag() {
+        let input = parse_input("--vfs-usage").unwrap();
+        assert!(matches!(input.command, Command::VfsUsage));
+        assert!(input.flags.force_call_user);
+    }
+
+    #[test]
+    fn test_test_tool_flags() {
+        let input = parse_input("--test-tool /tools/shared --test-format json").unwrap();
+        assert!(matches!(
+            input.command,
+            Command::TestTool { ref path, format: Some(TestReportFormat::Json) }
+                if path == "/tools/shared"
+        ));
+        assert!(input.flags.force_call_user);
+
+        assert!(parse_input("--test-tool a.scm --test-format xml").is_err());
+        assert!(parse_input("--test-format tap").is_err());
+    }
+
     // === Log/history tests ===
 
     #[test]
@@ -1405,6 +1901,32 @@ mod tests {
         assert!(input.verbose_flag);
     }
 
+    #[test]
+    fn test_interactive_short() {
+        let input = parse_input("-i").unwrap();
+        assert!(input.interactive);
+        assert!(matches!(input.command, Command::NoOp));
+    }
+
+    #[test]
+    fn test_interactive_with_prompt() {
+        let input = parse_input("-i -c work hello").unwrap();
+        assert!(input.interactive);
+        assert!(
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "hello")
+        );
+        assert!(
+            matches!(input.context, ContextSelection::Switch { ref name, .. } if name == "work")
+        );
+    }
+
+    #[test]
+    fn test_tui_flag() {
+        let input = parse_input("--tui").unwrap();
+        assert!(input.tui);
+        assert!(matches!(input.command, Command::NoOp));
+    }
+
     #[test]
     fn test_force_call_user_explicit() {
         let input = parse_input("-x").unwrap();
@@ -1741,6 +2263,7 @@ mod tests {
         assert_eq!(Inspectable::from_str_cli("todos"), Some(Inspectable::Tasks));
         assert_eq!(Inspectable::from_str_cli("tasks"), Some(Inspectable::Tasks));
         assert_eq!(Inspectable::from_str_cli("goals"), Some(Inspectable::Goals));
+        assert_eq!(Inspectable::from_str_cli("hooks"), Some(Inspectable::Hooks));
         assert_eq!(Inspectable::from_str_cli("list"), Some(Inspectable::List));
         assert_eq!(Inspectable::from_str_cli("unknown"), None);
         assert_eq!(Inspectable::from_str_cli(""), None);
@@ -2032,7 +2555,7 @@ mod tests {
             vec![("fuel".to_string(), "5".to_string())]
         );
         assert!(
-            matches!(input.command, Command::SendPrompt { ref prompt } if prompt == "hello world")
+            matches!(input.command, Command::SendPrompt { ref prompt, .. } if prompt == "hello world")
         );
     }
 }
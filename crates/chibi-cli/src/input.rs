@@ -6,9 +6,10 @@
 //!
 //! ## Context Selection
 //!
-//! - `Current` — use the implied context from session.json
+//! - `Current` — use the implied context from the session
 //! - `Switch` — switch to a named context (persistent or non-persistent)
 //! - `Ephemeral` — use a context for this invocation only, without updating session
+//! - `Push` / `Pop` — switch via the session's context stack
 //!
 //! ## Username Override
 //!
@@ -24,18 +25,22 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ContextSelection {
-    /// Use the implied context from session.json (no switch)
+    /// Use the implied context from the session (no switch)
     #[default]
     Current,
     /// Switch to a named context (-c)
     Switch {
         name: String,
-        /// Whether to persist the switch to session.json
+        /// Whether to persist the switch to the session
         #[serde(default = "default_true")]
         persistent: bool,
     },
-    /// Use a context ephemerally (-C) — does NOT update session.json
+    /// Use a context ephemerally (-C) — does NOT update the session
     Ephemeral { name: String },
+    /// Push the current context on the stack and switch (--push-context)
+    Push { name: String },
+    /// Switch back to the most recently pushed context (--pop-context)
+    Pop,
 }
 
 fn default_true() -> bool {
@@ -92,6 +97,12 @@ pub struct ChibiInput {
     /// CLI flag: show thinking/reasoning content (overrides cli.toml)
     #[serde(default)]
     pub show_thinking_flag: bool,
+    /// Run the interactive REPL (-i, CLI-only)
+    #[serde(default)]
+    pub interactive: bool,
+    /// Run the full-screen TUI (--tui, CLI-only)
+    #[serde(default)]
+    pub tui: bool,
 }
 
 impl Default for ChibiInput {
@@ -108,6 +119,8 @@ impl Default for ChibiInput {
             verbose_flag: false,
             hide_tool_calls_flag: false,
             show_thinking_flag: false,
+            interactive: false,
+            tui: false,
         }
     }
 }
@@ -243,6 +256,8 @@ mod tests {
             verbose_flag: false,
             hide_tool_calls_flag: false,
             show_thinking_flag: false,
+            interactive: false,
+            tui: false,
         };
 
         let json = serde_json::to_string(&input).unwrap();
@@ -276,6 +291,8 @@ mod tests {
             verbose_flag: false,
             hide_tool_calls_flag: false,
             show_thinking_flag: false,
+            interactive: false,
+            tui: false,
         };
 
         let json = serde_json::to_string(&input).unwrap();
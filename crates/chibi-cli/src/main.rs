@@ -7,8 +7,10 @@ mod image_cache;
 mod input;
 mod markdown;
 mod output;
+mod repl;
 mod session;
 mod sink;
+mod tui;
 
 use chibi_core::input::Command;
 
@@ -17,7 +19,7 @@ use crate::config::{ImageConfig, ResolvedConfig, default_markdown_style, load_cl
 use crate::input::{ChibiInput, ContextSelection, UsernameOverride};
 use crate::markdown::{MarkdownConfig, MarkdownStream};
 use crate::output::OutputHandler;
-use crate::session::Session;
+use crate::session::{Session, SessionKey};
 use crate::sink::CliResponseSink;
 use chibi_core::{Chibi, CommandEffect, LoadOptions, OutputSink, PermissionHandler, StatePaths};
 use std::io::{self, ErrorKind, Write};
@@ -211,6 +213,11 @@ fn resolve_command_names(
             old: resolve_opt(old)?,
             new: new.clone(),
         }),
+        Command::ExportContext { name, path, format } => Ok(Command::ExportContext {
+            name: resolve_opt(name)?,
+            path: path.clone(),
+            format: *format,
+        }),
         Command::ShowLog { context, count } => Ok(Command::ShowLog {
             context: resolve_opt(context)?,
             count: *count,
@@ -223,6 +230,15 @@ fn resolve_command_names(
             context: resolve_opt(context)?,
             prompt: prompt.clone(),
         }),
+        Command::ComposeSystemPrompt {
+            context,
+            fragments,
+            args,
+        } => Ok(Command::ComposeSystemPrompt {
+            context: resolve_opt(context)?,
+            fragments: fragments.clone(),
+            args: args.clone(),
+        }),
         Command::ClearCache { name } => Ok(Command::ClearCache {
             name: resolve_opt(name)?,
         }),
@@ -252,7 +268,7 @@ async fn execute_from_input(
 
     // --- CLI-specific: context selection ---
     // working_context: the context we're actually operating on this invocation
-    // implied_context: persisted in session.json, what you get when no context is specified
+    // implied_context: persisted in the session file, what you get when no context is specified
     let working_context = match &input.context {
         ContextSelection::Current => session.implied_context.clone(),
         ContextSelection::Ephemeral { name } => {
@@ -263,16 +279,30 @@ async fn execute_from_input(
             }
             actual_name
         }
-        ContextSelection::Switch { name, persistent } => {
-            if name == "-" {
-                session.swap_with_previous()?;
-            } else {
-                let actual_name = resolve_context_name(chibi, session, name)?;
-                session.switch_context(actual_name);
-            }
+        ContextSelection::Switch { .. } | ContextSelection::Push { .. } | ContextSelection::Pop => {
+            let persistent = match &input.context {
+                ContextSelection::Switch { name, persistent } => {
+                    if name == "-" {
+                        session.swap_with_previous()?;
+                    } else {
+                        let actual_name = resolve_context_name(chibi, session, name)?;
+                        session.switch_context(actual_name);
+                    }
+                    *persistent
+                }
+                ContextSelection::Push { name } => {
+                    let actual_name = resolve_context_name(chibi, session, name)?;
+                    session.push_context(actual_name);
+                    true
+                }
+                _ => {
+                    session.pop_context()?;
+                    true
+                }
+            };
             chibi.app.ensure_context_dir(&session.implied_context)?;
 
-            if *persistent {
+            if persistent {
                 session.save(chibi.home_dir())?;
                 chibi.save()?;
             }
@@ -366,8 +396,20 @@ async fn execute_from_input(
         None => OutputHandler::new(verbose),
     };
 
-    let mut sink =
-        CliResponseSink::new(&handler, md_config, verbose, show_tool_calls, show_thinking);
+    // Structured output prints only the validated JSON on stdout, so the
+    // streamed reply is not rendered
+    let stream_config = if matches!(command, Command::SendStructured { .. }) {
+        None
+    } else {
+        md_config
+    };
+    let mut sink = CliResponseSink::new(
+        &handler,
+        stream_config,
+        verbose,
+        show_tool_calls,
+        show_thinking,
+    );
 
     // --- delegate to core ---
     let effect = chibi_core::execute_command(
@@ -395,12 +437,7 @@ async fn execute_from_input(
             }
         }
         CommandEffect::ContextRenamed { old, new } => {
-            if session.implied_context == *old {
-                session.implied_context = new.clone();
-                session.save(chibi.home_dir())?;
-            }
-            if session.previous_context.as_deref() == Some(old.as_str()) {
-                session.previous_context = Some(new.clone());
+            if session.rename_context(old, new) {
                 session.save(chibi.home_dir())?;
             }
         }
@@ -436,6 +473,9 @@ async fn execute_from_input(
     if !chibi.app.context_dir(&session.implied_context).exists() {
         session.implied_context = "default".to_string();
         session.previous_context = None;
+        session
+            .stack
+            .retain(|name| chibi.app.context_dir(name).exists());
         session.save(chibi.home_dir())?;
     }
 
@@ -486,6 +526,20 @@ fn extract_home_override(args: &[String]) -> Option<PathBuf> {
     None
 }
 
+/// Extract --session flag value from args (before full CLI parsing)
+fn extract_session_override(args: &[String]) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--session" {
+            return iter.next().cloned();
+        }
+        if let Some(id) = arg.strip_prefix("--session=") {
+            return Some(id.to_string());
+        }
+    }
+    None
+}
+
 /// Extract --project-root flag value from args (before full CLI parsing)
 fn extract_project_root_override(args: &[String]) -> Option<PathBuf> {
     let mut iter = args.iter();
@@ -508,6 +562,7 @@ async fn main() -> io::Result<()> {
     let trust_mode = args.iter().any(|a| a == "--trust" || a == "-t");
     let home_override = extract_home_override(&args);
     let project_root_override = extract_project_root_override(&args);
+    let session_key = SessionKey::resolve(extract_session_override(&args).as_deref());
 
     // Parse CLI arguments to ChibiInput
     let input = cli::parse()?;
@@ -541,7 +596,27 @@ async fn main() -> io::Result<()> {
         &load_output,
     )?;
     chibi.set_permission_handler(select_permission_handler(trust_mode));
-    let mut session = Session::load(chibi.home_dir())?;
+    // Best effort, like the image cache cleanup
+    if let Ok(removed) =
+        session::cleanup_stale_sessions(chibi.home_dir(), session::STALE_SESSION_AGE)
+        && removed > 0
+        && input.verbose_flag
+    {
+        eprintln!("[Session cleanup: removed {} stale sessions]", removed);
+    }
+    let mut session = Session::load(chibi.home_dir(), session_key.as_ref())?;
+    if input.verbose_flag
+        && let Some(id) = &session.id
+    {
+        eprintln!("[Session: {}]", id);
+    }
+
+    if input.tui {
+        return tui::run(input, &mut chibi, &mut session, force_markdown).await;
+    }
+    if input.interactive {
+        return repl::run(input, &mut chibi, &mut session, force_markdown).await;
+    }
 
     execute_from_input(input, &mut chibi, &mut session, force_markdown).await
 }
@@ -1,4 +1,5 @@
 use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
 
 use base64::Engine;
 use image::GenericImageView;
@@ -76,23 +77,37 @@ enum ImageRenderMode {
     Placeholder,
 }
 
-/// Zero-sized newtype so `Renderer<StdoutWriter>` can persist across the stream
-/// while each write locks stdout independently.
-struct StdoutWriter;
+/// Render target, so `Renderer<StreamWriter>` can persist across the stream.
+///
+/// `Stdout` locks stdout independently for each write; `Buffer` collects
+/// output in memory for callers that draw it themselves (the TUI).
+enum StreamWriter {
+    Stdout,
+    Buffer(Arc<Mutex<Vec<u8>>>),
+}
 
-impl Write for StdoutWriter {
+impl Write for StreamWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        io::stdout().lock().write(buf)
+        match self {
+            StreamWriter::Stdout => io::stdout().lock().write(buf),
+            StreamWriter::Buffer(buffer) => {
+                buffer.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+        }
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        io::stdout().lock().flush()
+        match self {
+            StreamWriter::Stdout => io::stdout().lock().flush(),
+            StreamWriter::Buffer(_) => Ok(()),
+        }
     }
 }
 
 struct RenderPipeline {
     parser: Parser,
-    renderer: Renderer<StdoutWriter>,
+    renderer: Renderer<StreamWriter>,
 }
 
 /// Streaming markdown renderer with TTY detection.
@@ -112,6 +127,8 @@ pub struct MarkdownStream {
     fetch_config: ImageFetchConfig,
     display_config: ImageDisplayConfig,
     render_mode: ImageRenderMode,
+    /// In-memory output for buffered streams; `None` writes to stdout
+    buffer: Option<Arc<Mutex<Vec<u8>>>>,
 }
 
 /// Detect terminal rendering capabilities from environment variables
@@ -194,7 +211,7 @@ impl MarkdownStream {
                 (
                     Some(RenderPipeline {
                         parser: Parser::new(),
-                        renderer: Renderer::with_style(StdoutWriter, width, style),
+                        renderer: Renderer::with_style(StreamWriter::Stdout, width, style),
                     }),
                     width,
                 )
@@ -229,6 +246,46 @@ impl MarkdownStream {
                 alignment: config.image.alignment,
             },
             render_mode,
+            buffer: None,
+        }
+    }
+
+    /// Creates a stream that renders into memory at `width` columns.
+    ///
+    /// Rendering does not depend on stdout being a TTY, and images are never
+    /// drawn inline. Collect the ANSI output with [`Self::take_output`].
+    pub fn buffered(config: MarkdownConfig, width: usize) -> Self {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let pipeline = config.render_markdown.then(|| RenderPipeline {
+            parser: Parser::new(),
+            renderer: Renderer::with_style(
+                StreamWriter::Buffer(Arc::clone(&buffer)),
+                width,
+                config.markdown_style.clone(),
+            ),
+        });
+        MarkdownStream {
+            pipeline,
+            render_images: false,
+            terminal_width: width,
+            buffer: Some(buffer),
+            ..Self::new(MarkdownConfig {
+                render_markdown: false,
+                ..config
+            })
+        }
+    }
+
+    /// Take everything rendered so far by a buffered stream.
+    ///
+    /// Always empty for streams writing to stdout.
+    pub fn take_output(&mut self) -> String {
+        match &self.buffer {
+            Some(buffer) => {
+                let bytes = std::mem::take(&mut *buffer.lock().unwrap());
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+            None => String::new(),
         }
     }
 
@@ -273,6 +330,10 @@ impl MarkdownStream {
         let pipeline = match self.pipeline.as_mut() {
             Some(p) => p,
             None => {
+                if let Some(buffer) = &self.buffer {
+                    buffer.lock().unwrap().extend_from_slice(chunk.as_bytes());
+                    return Ok(());
+                }
                 // Passthrough: write raw bytes directly
                 let mut lock = io::stdout().lock();
                 lock.write_all(chunk.as_bytes())?;
@@ -1096,6 +1157,7 @@ mod tests {
             fetch_config: default_fetch_config(),
             display_config: default_display_config(),
             render_mode: ImageRenderMode::Placeholder,
+            buffer: None,
         }
     }
 
@@ -1138,13 +1200,14 @@ mod tests {
             line_buffer: String::new(),
             pipeline: Some(RenderPipeline {
                 parser: streamdown_parser::Parser::new(),
-                renderer: streamdown_render::Renderer::with_style(StdoutWriter, 80, style),
+                renderer: streamdown_render::Renderer::with_style(StreamWriter::Stdout, 80, style),
             }),
             render_images: false,
             terminal_width: 80,
             fetch_config: default_fetch_config(),
             display_config: default_display_config(),
             render_mode: ImageRenderMode::Placeholder,
+            buffer: None,
         }
     }
 
@@ -1203,6 +1266,37 @@ mod tests {
         );
     }
 
+    // ========== Buffered stream tests ==========
+
+    fn buffered_config(render_markdown: bool) -> MarkdownConfig {
+        MarkdownConfig {
+            render_markdown,
+            force_render: false,
+            image: ImageConfig::default(),
+            image_cache_dir: None,
+            markdown_style: default_markdown_style(),
+        }
+    }
+
+    #[test]
+    fn buffered_passthrough_collects_raw_text() {
+        let mut stream = MarkdownStream::buffered(buffered_config(false), 40);
+        stream.write_chunk("# title\nbody").unwrap();
+        stream.finish().unwrap();
+        assert_eq!(stream.take_output(), "# title\nbody");
+        assert!(stream.take_output().is_empty(), "take_output drains");
+    }
+
+    #[test]
+    fn buffered_render_does_not_need_a_tty() {
+        let mut stream = MarkdownStream::buffered(buffered_config(true), 40);
+        stream.write_chunk("some **bold** text\n").unwrap();
+        stream.finish().unwrap();
+        let output = stream.take_output();
+        assert!(output.contains("bold"));
+        assert!(!output.contains("**"), "markdown syntax is rendered away");
+    }
+
     #[test]
     fn write_chunk_empty_string_is_noop() {
         let mut stream = rendering_stream();
@@ -47,6 +47,41 @@ impl OutputSink for OutputHandler {
     }
 
     fn emit_event(&self, event: CommandEvent) {
+        // Lock contention is shown regardless of verbosity: without it a
+        // waiting or queued prompt looks like a hang or a silent no-op
+        let always = match &event {
+            CommandEvent::LockWaiting {
+                context,
+                waited_secs,
+                timeout_secs,
+            } => Some(format!(
+                "[Context '{}' is locked by another process, waiting ({}s of {}s)]",
+                context, waited_secs, timeout_secs
+            )),
+            CommandEvent::PromptQueued { context } => Some(format!(
+                "[Context '{}' is locked by another process, prompt queued in its inbox]",
+                context
+            )),
+            CommandEvent::QueuedPromptsRunning { context, count } => Some(format!(
+                "[Running {} queued prompt(s) for '{}']",
+                count, context
+            )),
+            CommandEvent::StructuredOutputRetry {
+                attempt,
+                max_attempts,
+                errors,
+            } => Some(format!(
+                "[Reply did not match the schema ({}/{}), asking for a fix: {}]",
+                attempt,
+                max_attempts,
+                errors.join("; ")
+            )),
+            _ => None,
+        };
+        if let Some(text) = always {
+            eprintln!("{}", text);
+            return;
+        }
         if !self.verbose {
             return;
         }
@@ -115,6 +150,18 @@ impl OutputSink for OutputHandler {
                 "[Rolling compaction complete: {} archived, {} remaining]",
                 archived, remaining
             ),
+            CommandEvent::CompactionStrategyFallback { strategy } => format!(
+                "[Compaction strategy '{}' not handled by any hook, using rolling compaction]",
+                strategy
+            ),
+            CommandEvent::LockAcquired {
+                context,
+                waited_secs,
+            } => format!("[Acquired lock for '{}' after {}s]", context, waited_secs),
+            CommandEvent::LockWaiting { .. }
+            | CommandEvent::PromptQueued { .. }
+            | CommandEvent::QueuedPromptsRunning { .. }
+            | CommandEvent::StructuredOutputRetry { .. } => return,
             CommandEvent::LoadSummary {
                 builtin_count,
                 builtin_names,
@@ -151,6 +198,9 @@ impl OutputSink for OutputHandler {
         match entry.entry_type.as_str() {
             context::ENTRY_TYPE_MESSAGE => {
                 self.emit_result(&format!("[{}]", entry.from.to_uppercase()));
+                for attachment in &entry.attachments {
+                    self.emit_result(&format!("[attachment: {}]", attachment.describe()));
+                }
                 self.emit_markdown(&entry.content)?;
                 self.newline();
             }
@@ -247,6 +297,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
         // Should not panic — formats as human-readable text
         handler.emit_entry(&entry).unwrap();
@@ -266,6 +317,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
         // Compact mode: shows tool name + truncated args
         handler.emit_entry(&entry).unwrap();
@@ -285,6 +337,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
         // Verbose mode: shows full content
         handler.emit_entry(&entry).unwrap();
@@ -304,6 +357,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
         // Compact mode: shows size only
         handler.emit_entry(&entry).unwrap();
@@ -323,6 +377,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
         // Non-verbose: compaction entries are silently skipped
         handler.emit_entry(&entry).unwrap();
@@ -404,5 +459,24 @@ mod tests {
             archived: 8,
             remaining: 12,
         });
+        handler.emit_event(CommandEvent::CompactionStrategyFallback {
+            strategy: "custom".to_string(),
+        });
+        handler.emit_event(CommandEvent::LockWaiting {
+            context: "ctx".to_string(),
+            waited_secs: 5,
+            timeout_secs: 300,
+        });
+        handler.emit_event(CommandEvent::LockAcquired {
+            context: "ctx".to_string(),
+            waited_secs: 7,
+        });
+        handler.emit_event(CommandEvent::PromptQueued {
+            context: "ctx".to_string(),
+        });
+        handler.emit_event(CommandEvent::QueuedPromptsRunning {
+            context: "ctx".to_string(),
+            count: 2,
+        });
     }
 }
@@ -0,0 +1,682 @@
+//! Interactive REPL (`chibi -i`).
+//!
+//! Keeps one loaded `Chibi` and `Session` for the whole session and feeds
+//! every line through the same `execute_from_input()` path as a one-shot
+//! invocation. Lines starting with `/` are slash commands that map onto
+//! existing `Command` variants; anything else is sent as a prompt.
+//!
+//! Multi-line input: end a line with `\` to continue it, or open a ```
+//! fence and keep typing until it is closed. `/edit` hands the prompt to
+//! `$EDITOR`. Ctrl-C at the prompt clears the line; Ctrl-C while a turn is
+//! running cancels it.
+
+use std::io::{self, ErrorKind};
+
+use chibi_core::input::{Command, ExecutionFlags};
+use chibi_core::{Chibi, Inspectable};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+
+use crate::cli::{InspectableExt, parse_inspectable};
+use crate::input::{ChibiInput, ContextSelection, UsernameOverride};
+use crate::session::Session;
+
+/// Slash commands: (name, argument synopsis, description).
+const SLASH_COMMANDS: &[(&str, &str, &str)] = &[
+    ("help", "", "Show this list"),
+    ("quit", "", "Leave the REPL (also Ctrl-D)"),
+    (
+        "switch",
+        "CTX",
+        "Switch context (new, new:prefix and - work as with -c)",
+    ),
+    (
+        "push",
+        "CTX",
+        "Switch context, saving the current one on the stack",
+    ),
+    ("pop", "", "Return to the most recently pushed context"),
+    ("list", "", "List all contexts"),
+    ("info", "", "Show current context info"),
+    ("log", "[N]", "Show the last N log entries (default 10)"),
+    ("inspect", "THING", "Inspect the current context (see -n)"),
+    ("model", "MODEL", "Set the model for the current context"),
+    ("compact", "", "Compact the current context"),
+    ("archive", "", "Archive the current context's history"),
+    ("call", "TOOL [JSON]", "Call a tool directly"),
+    (
+        "set",
+        "KEY=VALUE",
+        "Override a config value for the rest of the session",
+    ),
+    ("edit", "", "Compose the next prompt in $EDITOR"),
+];
+
+/// Entry count for `/log` without an argument.
+const DEFAULT_LOG_COUNT: isize = 10;
+
+/// REPL history file, stored in the chibi home directory.
+const HISTORY_FILE: &str = "repl_history";
+
+/// What a line of REPL input asks for.
+#[derive(Debug)]
+enum ReplAction {
+    /// Blank line
+    Empty,
+    /// Send a prompt to the LLM
+    Prompt(String),
+    /// Run a core command against the current context
+    Run(Command),
+    /// Switch to another context
+    Switch(String),
+    /// Push the current context and switch to another
+    Push(String),
+    /// Return to the most recently pushed context
+    Pop,
+    /// Add a config override for the rest of the session
+    Set(String, String),
+    /// Compose a prompt in $EDITOR
+    Edit,
+    /// Show the slash command list
+    Help,
+    /// Leave the REPL
+    Quit,
+}
+
+/// Parse one (already joined) line of input.
+fn parse_line(line: &str) -> io::Result<ReplAction> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Ok(ReplAction::Empty);
+    }
+    let Some(rest) = trimmed.strip_prefix('/') else {
+        return Ok(ReplAction::Prompt(trimmed.to_string()));
+    };
+    // `//text` sends a prompt that starts with a slash
+    if rest.starts_with('/') {
+        return Ok(ReplAction::Prompt(rest.to_string()));
+    }
+
+    let (name, arg) = match rest.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (rest, ""),
+    };
+    let missing =
+        |what: &str| io::Error::new(ErrorKind::InvalidInput, format!("/{} needs {}", name, what));
+
+    match name {
+        "help" | "?" => Ok(ReplAction::Help),
+        "quit" | "exit" | "q" => Ok(ReplAction::Quit),
+        "switch" | "c" if arg.is_empty() => Err(missing("a context name")),
+        "switch" | "c" => Ok(ReplAction::Switch(arg.to_string())),
+        "push" if arg.is_empty() => Err(missing("a context name")),
+        "push" => Ok(ReplAction::Push(arg.to_string())),
+        "pop" => Ok(ReplAction::Pop),
+        "list" => Ok(ReplAction::Run(Command::ListContexts)),
+        "info" => Ok(ReplAction::Run(Command::ListCurrentContext)),
+        "log" => {
+            let count = if arg.is_empty() {
+                DEFAULT_LOG_COUNT
+            } else {
+                arg.parse().map_err(|_| {
+                    io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("/log count must be a number, got: {}", arg),
+                    )
+                })?
+            };
+            Ok(ReplAction::Run(Command::ShowLog {
+                context: None,
+                count,
+            }))
+        }
+        "inspect" if arg.is_empty() => Err(missing("something to inspect")),
+        "inspect" => Ok(ReplAction::Run(Command::Inspect {
+            context: None,
+            thing: parse_inspectable(arg)?,
+        })),
+        "model" if arg.is_empty() => Err(missing("a model name")),
+        "model" => Ok(ReplAction::Run(Command::SetModel {
+            context: None,
+            model: arg.to_string(),
+        })),
+        "compact" => Ok(ReplAction::Run(Command::CompactContext { name: None })),
+        "archive" => Ok(ReplAction::Run(Command::ArchiveHistory { name: None })),
+        "call" if arg.is_empty() => Err(missing("a tool name")),
+        "call" => {
+            let (tool, json) = match arg.split_once(char::is_whitespace) {
+                Some((tool, json)) => (tool, json.trim()),
+                None => (arg, ""),
+            };
+            Ok(ReplAction::Run(Command::CallTool {
+                name: tool.to_string(),
+                args: if json.is_empty() {
+                    vec![]
+                } else {
+                    vec![json.to_string()]
+                },
+            }))
+        }
+        "set" => {
+            let (key, value) = arg.split_once('=').ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("/set value must be KEY=VALUE, got: {}", arg),
+                )
+            })?;
+            Ok(ReplAction::Set(
+                key.trim().to_string(),
+                value.trim().to_string(),
+            ))
+        }
+        "edit" => Ok(ReplAction::Edit),
+        other => Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown command: /{} (try /help)", other),
+        )),
+    }
+}
+
+/// Whether the editor should keep reading lines before submitting.
+///
+/// True after a trailing `\` or while a ``` fence is open.
+fn needs_continuation(input: &str) -> bool {
+    let fences = input
+        .lines()
+        .filter(|line| line.trim_start().starts_with("```"))
+        .count();
+    input.ends_with('\\') || fences % 2 == 1
+}
+
+/// Join composed lines, dropping the `\` continuation markers.
+fn finish_compose(input: &str) -> String {
+    input
+        .lines()
+        .map(|line| line.strip_suffix('\\').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Compose a prompt in `$VISUAL`/`$EDITOR` (falling back to `vi`).
+///
+/// Returns `None` if the buffer was left empty.
+fn compose_in_editor() -> io::Result<Option<String>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut argv = shlex::split(&editor)
+        .filter(|argv| !argv.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Cannot parse editor command: {}", editor),
+            )
+        })?;
+    let program = argv.remove(0);
+
+    let path = std::env::temp_dir().join(format!("chibi_compose_{}.md", std::process::id()));
+    std::fs::write(&path, "")?;
+    let status = std::process::Command::new(&program)
+        .args(&argv)
+        .arg(&path)
+        .status();
+    let content = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    let status = status?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+    let content = content?;
+    let prompt = content.trim();
+    Ok((!prompt.is_empty()).then(|| prompt.to_string()))
+}
+
+/// Line editor helper: slash command completion and multi-line input.
+///
+/// Context and tool names are refreshed before every prompt.
+#[derive(Default)]
+struct ReplHelper {
+    contexts: Vec<String>,
+    tools: Vec<String>,
+}
+
+impl ReplHelper {
+    /// Completion candidates for the word ending at `pos`, with its start offset.
+    ///
+    /// Only slash commands complete: the command name itself, then context
+    /// names for `/switch`, tool names for `/call`, inspectables for
+    /// `/inspect` and config keys for `/set`.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        if !before.starts_with('/') {
+            return (pos, vec![]);
+        }
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        let word = &before[start..];
+
+        let pool: Vec<String> = if start == 0 {
+            SLASH_COMMANDS
+                .iter()
+                .map(|(name, _, _)| format!("/{}", name))
+                .collect()
+        } else {
+            // Only the first argument completes
+            let mut words = before[1..start].split_whitespace();
+            let command = words.next().unwrap_or("");
+            if words.next().is_some() {
+                return (pos, vec![]);
+            }
+            match command {
+                "switch" | "c" | "push" => self.contexts.clone(),
+                "call" => self.tools.clone(),
+                "inspect" => Inspectable::all_names_cli()
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                "set" => chibi_core::ResolvedConfig::list_fields()
+                    .iter()
+                    .map(|field| format!("{}=", field))
+                    .collect(),
+                _ => vec![],
+            }
+        };
+
+        let mut matches: Vec<String> = pool
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        (start, matches)
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, words) = self.candidates(line, pos);
+        let pairs = words
+            .into_iter()
+            .map(|word| Pair {
+                display: word.clone(),
+                replacement: word,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if needs_continuation(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+fn readline_error(e: ReadlineError) -> io::Error {
+    match e {
+        ReadlineError::Io(e) => e,
+        other => io::Error::other(other.to_string()),
+    }
+}
+
+fn print_help() {
+    println!("Slash commands:");
+    for (name, args, description) in SLASH_COMMANDS {
+        let usage = if args.is_empty() {
+            format!("/{}", name)
+        } else {
+            format!("/{} {}", name, args)
+        };
+        println!("  {:<20} {}", usage, description);
+    }
+    println!();
+    println!("Anything else is sent as a prompt (start it with // for a leading slash).");
+    println!("End a line with \\ or open a ``` fence to keep typing on the next line.");
+}
+
+fn report(result: io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+    }
+}
+
+/// Run one turn. Ctrl-C cancels it, dropping any in-flight request.
+async fn run_turn(
+    input: ChibiInput,
+    chibi: &mut Chibi,
+    session: &mut Session,
+    force_markdown: bool,
+) -> io::Result<()> {
+    tokio::select! {
+        result = crate::execute_from_input(input, chibi, session, force_markdown) => result,
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!("\n[cancelled]");
+            Ok(())
+        }
+    }
+}
+
+/// Run the REPL until `/quit` or end of input.
+///
+/// The invocation's own command and selection (a prompt, `-c`, `-u`) run
+/// as the first turn. With `-C` every turn, and `/switch`, stays ephemeral
+/// and the session is left alone.
+pub async fn run(
+    input: ChibiInput,
+    chibi: &mut Chibi,
+    session: &mut Session,
+    force_markdown: bool,
+) -> io::Result<()> {
+    // Resolve `-C new` once so every turn lands in the same context
+    let mut ephemeral = match &input.context {
+        ContextSelection::Ephemeral { name } => {
+            let name = crate::resolve_context_name(chibi, session, name)?;
+            chibi.app.ensure_context_dir(&name)?;
+            Some(name)
+        }
+        _ => None,
+    };
+
+    let mut template = ChibiInput {
+        command: Command::NoOp,
+        flags: ExecutionFlags {
+            debug: input.flags.debug.clone(),
+            record: input.flags.record.clone(),
+            ..Default::default()
+        },
+        context: ContextSelection::Current,
+        // A persistent -u is saved by the first turn
+        username_override: match &input.username_override {
+            Some(UsernameOverride::Ephemeral(name)) => {
+                Some(UsernameOverride::Ephemeral(name.clone()))
+            }
+            _ => None,
+        },
+        md_file: None,
+        ..input.clone()
+    };
+
+    let wants_first_turn = !matches!(input.command, Command::NoOp)
+        || matches!(
+            input.context,
+            ContextSelection::Switch { .. } | ContextSelection::Push { .. } | ContextSelection::Pop
+        )
+        || matches!(
+            input.username_override,
+            Some(UsernameOverride::Persistent(_))
+        );
+    if wants_first_turn {
+        let mut first = input;
+        if let Some(name) = &ephemeral {
+            first.context = ContextSelection::Ephemeral { name: name.clone() };
+        }
+        report(run_turn(first, chibi, session, force_markdown).await);
+    }
+
+    let history_path = chibi.home_dir().join(HISTORY_FILE);
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new().map_err(readline_error)?;
+    editor.set_helper(Some(ReplHelper::default()));
+    // Missing on first run
+    let _ = editor.load_history(&history_path);
+
+    eprintln!("[chibi interactive: /help for commands, Ctrl-D to quit]");
+
+    loop {
+        let context = ephemeral
+            .clone()
+            .unwrap_or_else(|| session.implied_context.clone());
+        if let Some(helper) = editor.helper_mut() {
+            helper.contexts = chibi.list_contexts();
+            helper.tools = chibi
+                .registry
+                .read()
+                .unwrap()
+                .all()
+                .map(|tool| tool.name.clone())
+                .collect();
+        }
+
+        let line = match editor.readline(&format!("{}> ", context)) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                let _ = editor.save_history(&history_path);
+                return Err(readline_error(e));
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+
+        let action = match parse_line(&finish_compose(&line)) {
+            Ok(action) => action,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+        };
+
+        let mut turn = template.clone();
+        if let Some(name) = &ephemeral {
+            turn.context = ContextSelection::Ephemeral { name: name.clone() };
+        }
+        match action {
+            ReplAction::Empty => continue,
+            ReplAction::Quit => break,
+            ReplAction::Help => {
+                print_help();
+                continue;
+            }
+            ReplAction::Prompt(prompt) => {
+                turn.command = Command::SendPrompt {
+                    prompt,
+                    attachments: vec![],
+                };
+            }
+            ReplAction::Edit => match compose_in_editor() {
+                Ok(Some(prompt)) => {
+                    turn.command = Command::SendPrompt {
+                        prompt,
+                        attachments: vec![],
+                    };
+                }
+                Ok(None) => {
+                    eprintln!("[Empty prompt, nothing sent]");
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    continue;
+                }
+            },
+            ReplAction::Run(command) => {
+                turn.command = command;
+                turn.flags.force_call_user = true;
+            }
+            ReplAction::Switch(name) => {
+                if ephemeral.is_some() {
+                    match crate::resolve_context_name(chibi, session, &name) {
+                        Ok(name) => {
+                            report(chibi.app.ensure_context_dir(&name));
+                            ephemeral = Some(name);
+                        }
+                        Err(e) => eprintln!("Error: {}", e),
+                    }
+                    continue;
+                }
+                turn.context = ContextSelection::Switch {
+                    name,
+                    persistent: true,
+                };
+                turn.flags.force_call_user = true;
+            }
+            ReplAction::Push(_) | ReplAction::Pop if ephemeral.is_some() => {
+                eprintln!("Error: the context stack is not used with -C");
+                continue;
+            }
+            ReplAction::Push(name) => {
+                turn.context = ContextSelection::Push { name };
+                turn.flags.force_call_user = true;
+            }
+            ReplAction::Pop => {
+                turn.context = ContextSelection::Pop;
+                turn.flags.force_call_user = true;
+            }
+            ReplAction::Set(key, value) => {
+                // Validate now rather than failing on every later turn
+                let pair = vec![(key, value)];
+                let checked = chibi.resolve_config(&context, None).and_then(|mut cfg| {
+                    cfg.apply_overrides_from_pairs(&pair)
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))
+                });
+                match checked {
+                    Ok(()) => template.config_overrides.extend(pair),
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+                continue;
+            }
+        }
+
+        report(run_turn(turn, chibi, session, force_markdown).await);
+    }
+
+    editor.save_history(&history_path).map_err(readline_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_prompt_and_escape() {
+        assert!(matches!(parse_line("   ").unwrap(), ReplAction::Empty));
+        assert!(
+            matches!(parse_line(" hi there ").unwrap(), ReplAction::Prompt(ref p) if p == "hi there")
+        );
+        assert!(
+            matches!(parse_line("//etc/hosts?").unwrap(), ReplAction::Prompt(ref p) if p == "/etc/hosts?")
+        );
+    }
+
+    #[test]
+    fn test_parse_line_slash_commands() {
+        assert!(
+            matches!(parse_line("/switch work").unwrap(), ReplAction::Switch(ref n) if n == "work")
+        );
+        assert!(
+            matches!(parse_line("/push review").unwrap(), ReplAction::Push(ref n) if n == "review")
+        );
+        assert!(matches!(parse_line("/pop").unwrap(), ReplAction::Pop));
+        assert!(parse_line("/push").is_err());
+        assert!(matches!(
+            parse_line("/log").unwrap(),
+            ReplAction::Run(Command::ShowLog {
+                context: None,
+                count: 10
+            })
+        ));
+        assert!(matches!(
+            parse_line("/log 3").unwrap(),
+            ReplAction::Run(Command::ShowLog { count: 3, .. })
+        ));
+        assert!(matches!(
+            parse_line("/model gpt-x").unwrap(),
+            ReplAction::Run(Command::SetModel { context: None, ref model }) if model == "gpt-x"
+        ));
+        assert!(matches!(
+            parse_line("/compact").unwrap(),
+            ReplAction::Run(Command::CompactContext { name: None })
+        ));
+        assert!(matches!(
+            parse_line("/inspect goals").unwrap(),
+            ReplAction::Run(Command::Inspect {
+                thing: Inspectable::Goals,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_line("/call send {\"to\": \"x\"}").unwrap(),
+            ReplAction::Run(Command::CallTool { ref name, ref args }) if name == "send" && args == &["{\"to\": \"x\"}"]
+        ));
+        assert!(matches!(
+            parse_line("/set model = foo").unwrap(),
+            ReplAction::Set(ref k, ref v) if k == "model" && v == "foo"
+        ));
+        assert!(matches!(parse_line("/exit").unwrap(), ReplAction::Quit));
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert!(parse_line("/switch").is_err());
+        assert!(parse_line("/log many").is_err());
+        assert!(parse_line("/inspect nonsense").is_err());
+        assert!(parse_line("/set model").is_err());
+        assert!(parse_line("/frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_multiline_compose() {
+        assert!(needs_continuation("first line\\"));
+        assert!(needs_continuation("look:\n```rust\nfn main() {}"));
+        assert!(!needs_continuation("look:\n```rust\nfn main() {}\n```"));
+        assert!(!needs_continuation("plain"));
+        assert_eq!(finish_compose("one\\\ntwo"), "one\ntwo");
+    }
+
+    #[test]
+    fn test_completion() {
+        let helper = ReplHelper {
+            contexts: vec!["default".to_string(), "work".to_string()],
+            tools: vec!["shell_exec".to_string(), "send_message".to_string()],
+        };
+
+        let (start, words) = helper.candidates("/sw", 3);
+        assert_eq!((start, words), (0, vec!["/switch".to_string()]));
+
+        let (start, words) = helper.candidates("/switch wo", 10);
+        assert_eq!((start, words), (8, vec!["work".to_string()]));
+
+        let (_, words) = helper.candidates("/call s", 7);
+        assert_eq!(words, vec!["send_message", "shell_exec"]);
+
+        let (_, words) = helper.candidates("/inspect go", 11);
+        assert_eq!(words, vec!["goals"]);
+
+        // Plain prompts and later arguments don't complete
+        assert!(helper.candidates("hello wo", 8).1.is_empty());
+        assert!(helper.candidates("/call send x", 12).1.is_empty());
+    }
+}
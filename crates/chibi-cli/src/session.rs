@@ -1,24 +1,207 @@
-// session.rs: CLI session state (implied/previous context)
+// session.rs: CLI session state (implied/previous context, context stack)
 //
 // This tracks which context the user is working with across CLI invocations.
 // Separate from chibi-core's ContextState, which manages context metadata.
+//
+// Each terminal gets its own session, keyed by `--session`, `$CHIBI_SESSION`
+// or the controlling TTY, so tmux panes working on different projects don't
+// clobber each other. Without any of those the shared `session.json` is used.
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Environment variable naming the session (e.g. `export CHIBI_SESSION=$$`)
+pub const SESSION_ENV: &str = "CHIBI_SESSION";
+
+/// Session id that selects the shared `session.json`
+pub const GLOBAL_SESSION: &str = "global";
+
+/// Directory (under the chibi home) holding per-terminal session files
+const SESSIONS_DIR: &str = "sessions";
+
+/// Sessions not tied to a terminal are removed after this long unchanged
+pub const STALE_SESSION_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Identifies which session file an invocation uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionKey {
+    /// File stem under `sessions/`
+    pub id: String,
+    /// Controlling terminal, when the id was derived from it
+    pub tty: Option<String>,
+    /// Terminal session leader (the login shell), used to tell a reused
+    /// pty apart from the one that created the session
+    pub leader: Option<u32>,
+}
+
+impl SessionKey {
+    /// Resolve the session for this process.
+    ///
+    /// Precedence: `--session` → `$CHIBI_SESSION` → controlling TTY.
+    /// Returns `None` (shared `session.json`) when none is available or the
+    /// id is `global`.
+    pub fn resolve(explicit: Option<&str>) -> Option<Self> {
+        let named = explicit
+            .map(str::to_string)
+            .or_else(|| std::env::var(SESSION_ENV).ok())
+            .filter(|s| !s.trim().is_empty());
+        if let Some(name) = named {
+            if name == GLOBAL_SESSION {
+                return None;
+            }
+            return Some(Self {
+                id: named_id(&name),
+                tty: None,
+                leader: None,
+            });
+        }
+        let tty = controlling_tty()?;
+        Some(Self {
+            id: format!("tty-{}", sanitize_id(tty.trim_start_matches("/dev/"))),
+            tty: Some(tty),
+            leader: session_leader(),
+        })
+    }
+}
+
+/// File stem for a session named by `--session` or `$CHIBI_SESSION`.
+///
+/// Names that needed sanitizing get a short hash of the raw name appended,
+/// so `my proj` and `my_proj` don't share a session.
+fn named_id(name: &str) -> String {
+    let id = sanitize_id(name);
+    if id == name {
+        return id;
+    }
+    let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    format!("{}-{}", id, &hash[..8])
+}
+
+/// Map an id onto a safe file stem: anything but `[A-Za-z0-9_-]` becomes `_`.
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// The terminal attached to stdin, stdout or stderr (`/dev/pts/3`).
+///
+/// `/dev/tty` itself is an alias for "my terminal" and is skipped.
+#[cfg(target_os = "linux")]
+fn controlling_tty() -> Option<String> {
+    (0..3).find_map(|fd| {
+        let target = std::fs::read_link(format!("/proc/self/fd/{}", fd)).ok()?;
+        let target = target.to_str()?;
+        (target.starts_with("/dev/pts/")
+            || (target.starts_with("/dev/tty") && target != "/dev/tty"))
+            .then(|| target.to_string())
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn controlling_tty() -> Option<String> {
+    None
+}
+
+/// Session id (in the `setsid` sense) of this process: the pid of the
+/// terminal's login shell.
+#[cfg(target_os = "linux")]
+fn session_leader() -> Option<u32> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // pid (comm) state ppid pgrp session ...; comm may contain spaces
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(3)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn session_leader() -> Option<u32> {
+    None
+}
+
+/// Whether a session leader recorded in a session file is still running.
+#[cfg(target_os = "linux")]
+fn leader_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn leader_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Remove session files whose terminal has gone away.
+///
+/// Terminal-derived sessions are removed once their session leader exits;
+/// named sessions (`--session`, `$CHIBI_SESSION`) once unchanged for
+/// `max_age`. Returns the number of files removed.
+pub fn cleanup_stale_sessions(chibi_dir: &Path, max_age: Duration) -> io::Result<usize> {
+    let dir = chibi_dir.join(SESSIONS_DIR);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let leader = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Session>(&content).ok())
+            .and_then(|session| session.leader);
+        let stale = match leader {
+            Some(pid) => !leader_alive(pid),
+            None => entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > max_age),
+        };
+        if stale && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
 
 /// Session state persisted between CLI invocations.
 ///
 /// Tracks the implied and previous context names for context switching.
 /// - `implied_context`: The context used when no `-c`/`-C` is specified
 /// - `previous_context`: The last context switched away from (for `-c -`)
+/// - `stack`: Contexts saved by `--push-context`, most recent last
 ///
-/// Stored in `~/.chibi/session.json`.
+/// Stored in `~/.chibi/sessions/<id>.json`, or `~/.chibi/session.json`
+/// when no session id is available.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub implied_context: String,
     #[serde(default)]
     pub previous_context: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+    /// Controlling terminal (informational)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tty: Option<String>,
+    /// Terminal session leader, for stale session cleanup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader: Option<u32>,
+    /// Session id this state is saved under; `None` is the shared file
+    #[serde(skip)]
+    pub id: Option<String>,
 }
 
 impl Default for Session {
@@ -26,18 +209,51 @@ impl Default for Session {
         Self {
             implied_context: "default".to_string(),
             previous_context: None,
+            stack: Vec::new(),
+            tty: None,
+            leader: None,
+            id: None,
         }
     }
 }
 
 impl Session {
-    /// Load session from `session.json` in the given chibi directory.
+    /// Load the session for `key` from the given chibi directory.
     ///
-    /// Returns default session if file doesn't exist.
-    pub fn load(chibi_dir: &Path) -> io::Result<Self> {
-        let path = chibi_dir.join("session.json");
+    /// With no key, reads the shared `session.json`. A session that doesn't
+    /// exist yet (or whose pty now belongs to a different login) starts from
+    /// the shared session's contexts, with an empty stack.
+    pub fn load(chibi_dir: &Path, key: Option<&SessionKey>) -> io::Result<Self> {
+        let Some(key) = key else {
+            return Self::read(&chibi_dir.join("session.json"));
+        };
+        let path = Self::session_path(chibi_dir, &key.id);
+        let existing = if path.exists() {
+            Some(Self::read(&path)?).filter(|s| key.leader.is_none() || s.leader == key.leader)
+        } else {
+            None
+        };
+        let mut session = match existing {
+            Some(session) => session,
+            None => {
+                let shared = Self::read(&chibi_dir.join("session.json"))?;
+                Self {
+                    implied_context: shared.implied_context,
+                    previous_context: shared.previous_context,
+                    ..Self::default()
+                }
+            }
+        };
+        session.id = Some(key.id.clone());
+        session.tty = key.tty.clone();
+        session.leader = key.leader;
+        Ok(session)
+    }
+
+    /// Read a session file, or the default session if it doesn't exist.
+    fn read(path: &Path) -> io::Result<Self> {
         if path.exists() {
-            let content = std::fs::read_to_string(&path)?;
+            let content = std::fs::read_to_string(path)?;
             serde_json::from_str(&content)
                 .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
         } else {
@@ -45,9 +261,19 @@ impl Session {
         }
     }
 
-    /// Save session to `session.json` in the given chibi directory.
+    fn session_path(chibi_dir: &Path, id: &str) -> PathBuf {
+        chibi_dir.join(SESSIONS_DIR).join(format!("{}.json", id))
+    }
+
+    /// Save session to its file in the given chibi directory.
     pub fn save(&self, chibi_dir: &Path) -> io::Result<()> {
-        let path = chibi_dir.join("session.json");
+        let path = match &self.id {
+            Some(id) => {
+                std::fs::create_dir_all(chibi_dir.join(SESSIONS_DIR))?;
+                Self::session_path(chibi_dir, id)
+            }
+            None => chibi_dir.join("session.json"),
+        };
         chibi_core::safe_io::atomic_write_json(&path, self)
     }
 
@@ -95,12 +321,52 @@ impl Session {
             })
     }
 
+    /// Save the current context on the stack and switch to `name`.
+    pub fn push_context(&mut self, name: String) {
+        self.stack.push(self.implied_context.clone());
+        self.switch_context(name);
+    }
+
+    /// Switch back to the most recently pushed context.
+    ///
+    /// Returns the name of the context switched to, or an error if the
+    /// stack is empty.
+    pub fn pop_context(&mut self) -> io::Result<String> {
+        let name = self.stack.pop().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Context stack is empty (use --push-context first)",
+            )
+        })?;
+        self.switch_context(name.clone());
+        Ok(name)
+    }
+
+    /// Follow a context rename in the implied, previous and stacked names.
+    ///
+    /// Returns true if anything changed.
+    pub fn rename_context(&mut self, old: &str, new: &str) -> bool {
+        let mut changed = false;
+        let names = std::iter::once(&mut self.implied_context)
+            .chain(self.previous_context.as_mut())
+            .chain(self.stack.iter_mut());
+        for name in names {
+            if name == old {
+                *name = new.to_string();
+                changed = true;
+            }
+        }
+        changed
+    }
+
     /// Handle session state after a context is destroyed.
     ///
     /// If the destroyed context was the implied context, switches to a fallback:
     /// - Previous context if it exists and is valid
     /// - Otherwise "default"
     ///
+    /// The destroyed context is also dropped from the context stack.
+    ///
     /// The `context_exists` closure is used to check if a context directory exists.
     /// Returns the new implied context name if a switch occurred, None otherwise.
     pub fn handle_context_destroyed<F>(
@@ -111,6 +377,7 @@ impl Session {
     where
         F: Fn(&str) -> bool,
     {
+        self.stack.retain(|name| name != destroyed);
         if self.implied_context != destroyed {
             // Not destroying implied context, no session changes needed
             // (but clear previous if it was the destroyed one)
@@ -200,6 +467,7 @@ mod tests {
         let mut session = Session {
             implied_context: "test".to_string(),
             previous_context: Some("".to_string()),
+            ..Default::default()
         };
         let result = session.swap_with_previous();
         assert!(result.is_err());
@@ -226,6 +494,7 @@ mod tests {
         let session = Session {
             implied_context: "test".to_string(),
             previous_context: Some("".to_string()),
+            ..Default::default()
         };
         let result = session.get_previous();
         assert!(result.is_err());
@@ -239,7 +508,7 @@ mod tests {
 
         session.save(temp_dir.path()).unwrap();
 
-        let loaded = Session::load(temp_dir.path()).unwrap();
+        let loaded = Session::load(temp_dir.path(), None).unwrap();
         assert_eq!(loaded.implied_context, "test");
         assert_eq!(loaded.previous_context, Some("default".to_string()));
     }
@@ -247,7 +516,7 @@ mod tests {
     #[test]
     fn test_load_missing_file() {
         let temp_dir = TempDir::new().unwrap();
-        let session = Session::load(temp_dir.path()).unwrap();
+        let session = Session::load(temp_dir.path(), None).unwrap();
         assert_eq!(session.implied_context, "default");
         assert!(session.previous_context.is_none());
     }
@@ -258,7 +527,7 @@ mod tests {
         let path = temp_dir.path().join("session.json");
         fs::write(&path, "not valid json").unwrap();
 
-        let result = Session::load(temp_dir.path());
+        let result = Session::load(temp_dir.path(), None);
         assert!(result.is_err());
     }
 
@@ -317,6 +586,7 @@ mod tests {
         let mut session = Session {
             implied_context: "current".to_string(),
             previous_context: Some("current".to_string()), // edge case: same as current
+            ..Default::default()
         };
 
         // Destroy current, but previous points to same context
@@ -332,6 +602,7 @@ mod tests {
         let mut session = Session {
             implied_context: "only_ctx".to_string(),
             previous_context: None,
+            ..Default::default()
         };
 
         let result = session.handle_context_destroyed("only_ctx", |_| true);
@@ -340,4 +611,145 @@ mod tests {
         assert_eq!(session.implied_context, "default");
         assert!(session.previous_context.is_none());
     }
+
+    #[test]
+    fn test_push_and_pop_context() {
+        let mut session = Session::default();
+        session.push_context("a".to_string());
+        session.push_context("b".to_string());
+        assert_eq!(session.implied_context, "b");
+        assert_eq!(session.stack, vec!["default", "a"]);
+
+        assert_eq!(session.pop_context().unwrap(), "a");
+        assert_eq!(session.implied_context, "a");
+        assert_eq!(session.previous_context, Some("b".to_string()));
+        assert_eq!(session.pop_context().unwrap(), "default");
+        assert!(session.pop_context().is_err());
+        assert_eq!(session.implied_context, "default");
+    }
+
+    #[test]
+    fn test_rename_context_updates_all_names() {
+        let mut session = Session::default();
+        session.push_context("old".to_string());
+        session.push_context("x".to_string());
+        session.switch_context("old".to_string());
+
+        assert!(session.rename_context("old", "new"));
+        assert_eq!(session.implied_context, "new");
+        assert_eq!(session.previous_context, Some("x".to_string()));
+        assert_eq!(session.stack, vec!["default", "new"]);
+        assert!(!session.rename_context("missing", "other"));
+    }
+
+    #[test]
+    fn test_handle_context_destroyed_clears_stack_entries() {
+        let mut session = Session::default();
+        session.push_context("gone".to_string());
+        session.push_context("here".to_string());
+
+        session.handle_context_destroyed("gone", |_| true);
+        assert_eq!(session.stack, vec!["default"]);
+    }
+
+    fn key(id: &str, leader: Option<u32>) -> SessionKey {
+        SessionKey {
+            id: id.to_string(),
+            tty: None,
+            leader,
+        }
+    }
+
+    #[test]
+    fn test_keyed_sessions_are_independent() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut one = Session::load(temp_dir.path(), Some(&key("one", None))).unwrap();
+        let mut two = Session::load(temp_dir.path(), Some(&key("two", None))).unwrap();
+        one.switch_context("alpha".to_string());
+        two.push_context("beta".to_string());
+        one.save(temp_dir.path()).unwrap();
+        two.save(temp_dir.path()).unwrap();
+
+        let one = Session::load(temp_dir.path(), Some(&key("one", None))).unwrap();
+        let two = Session::load(temp_dir.path(), Some(&key("two", None))).unwrap();
+        assert_eq!(one.implied_context, "alpha");
+        assert_eq!(two.implied_context, "beta");
+        assert_eq!(two.stack, vec!["default"]);
+        assert!(temp_dir.path().join("sessions/one.json").exists());
+        assert!(!temp_dir.path().join("session.json").exists());
+    }
+
+    #[test]
+    fn test_new_keyed_session_starts_from_shared() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut shared = Session::default();
+        shared.switch_context("work".to_string());
+        shared.save(temp_dir.path()).unwrap();
+
+        let session = Session::load(temp_dir.path(), Some(&key("fresh", None))).unwrap();
+        assert_eq!(session.implied_context, "work");
+        assert_eq!(session.id.as_deref(), Some("fresh"));
+    }
+
+    #[test]
+    fn test_reused_tty_with_new_leader_starts_fresh() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut old = Session::load(temp_dir.path(), Some(&key("tty-pts_3", Some(100)))).unwrap();
+        old.push_context("old_login".to_string());
+        old.save(temp_dir.path()).unwrap();
+
+        let same = Session::load(temp_dir.path(), Some(&key("tty-pts_3", Some(100)))).unwrap();
+        assert_eq!(same.implied_context, "old_login");
+        let reused = Session::load(temp_dir.path(), Some(&key("tty-pts_3", Some(200)))).unwrap();
+        assert_eq!(reused.implied_context, "default");
+        assert!(reused.stack.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_explicit_session() {
+        let resolved = SessionKey::resolve(Some("my proj/1")).unwrap();
+        assert!(resolved.id.starts_with("my_proj_1-"));
+        assert!(resolved.tty.is_none());
+        assert_ne!(
+            resolved.id,
+            SessionKey::resolve(Some("my_proj/1")).unwrap().id
+        );
+        assert_eq!(
+            SessionKey::resolve(Some("my_proj_1")).unwrap().id,
+            "my_proj_1"
+        );
+        assert!(SessionKey::resolve(Some(GLOBAL_SESSION)).is_none());
+    }
+
+    #[test]
+    fn test_cleanup_stale_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(
+            cleanup_stale_sessions(temp_dir.path(), STALE_SESSION_AGE).unwrap(),
+            0
+        );
+
+        let named = Session::load(temp_dir.path(), Some(&key("named", None))).unwrap();
+        named.save(temp_dir.path()).unwrap();
+        let live = Session::load(
+            temp_dir.path(),
+            Some(&key("tty-live", Some(std::process::id()))),
+        )
+        .unwrap();
+        live.save(temp_dir.path()).unwrap();
+
+        // Nothing is old enough and our own pid is alive
+        assert_eq!(
+            cleanup_stale_sessions(temp_dir.path(), STALE_SESSION_AGE).unwrap(),
+            0
+        );
+        // A zero max age expires the named session, never the live terminal
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(
+            cleanup_stale_sessions(temp_dir.path(), Duration::ZERO).unwrap(),
+            1
+        );
+        assert!(!temp_dir.path().join("sessions/named.json").exists());
+        assert!(temp_dir.path().join("sessions/tty-live.json").exists());
+    }
 }
@@ -69,6 +69,23 @@ impl CliResponseSink<'_> {
         self.in_reasoning = false;
         Ok(())
     }
+
+    /// Close the current markdown stream and open a fresh one, so a retried
+    /// attempt starts on a clean line after the notice.
+    fn restart_stream(&mut self, notice: &str) -> io::Result<()> {
+        if self.in_reasoning {
+            self.close_reasoning()?;
+        }
+        if let Some(mut md) = self.markdown.take() {
+            md.finish()?;
+        }
+        eprintln!("{}", notice);
+        self.markdown = self
+            .markdown_config
+            .as_ref()
+            .map(|cfg| MarkdownStream::new(cfg.clone()));
+        Ok(())
+    }
 }
 
 impl ResponseSink for CliResponseSink<'_> {
@@ -181,6 +198,26 @@ impl ResponseSink for CliResponseSink<'_> {
                     eprintln!("[Inbox: {} message(s) injected]", count);
                 }
             }
+            ResponseEvent::ProviderRetry {
+                attempt,
+                max_retries,
+                delay_ms,
+                error,
+            } => {
+                self.restart_stream(&format!(
+                    "\n[provider error, retrying in {:.1}s ({}/{}): {}]",
+                    delay_ms as f64 / 1000.0,
+                    attempt,
+                    max_retries,
+                    error
+                ))?;
+            }
+            ResponseEvent::ModelFallback { from, to, reason } => {
+                self.restart_stream(&format!(
+                    "\n[model fallback: {} -> {} ({})]",
+                    from, to, reason
+                ))?;
+            }
         }
         Ok(())
     }
@@ -226,6 +263,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         };
 
         // TranscriptEntry is a no-op in CLI sink (content displayed via streaming events)
@@ -0,0 +1,185 @@
+//! ANSI escape sequences to ratatui text.
+//!
+//! `MarkdownStream` renders to ANSI-styled text for terminals; the TUI needs
+//! the same output as styled `Line`s. Only SGR (`ESC [ ... m`) sequences
+//! carry style; other CSI and OSC sequences (cursor movement, hyperlinks)
+//! are dropped.
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+
+/// Basic colours for SGR 30–37 / 40–47.
+const BASIC: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+];
+
+/// Bright colours for SGR 90–97 / 100–107.
+const BRIGHT: [Color; 8] = [
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Convert ANSI-styled text into ratatui `Text`, one `Line` per input line.
+pub fn ansi_to_text(input: &str) -> Text<'static> {
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut style = Style::default();
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                flush_span(&mut current, &mut spans, style);
+                match chars.next() {
+                    // CSI: parameters up to a final byte in 0x40..=0x7e
+                    Some('[') => {
+                        let mut params = String::new();
+                        for c in chars.by_ref() {
+                            if ('\x40'..='\x7e').contains(&c) {
+                                if c == 'm' {
+                                    apply_sgr(&mut style, &params);
+                                }
+                                break;
+                            }
+                            params.push(c);
+                        }
+                    }
+                    // OSC: terminated by BEL or ST (ESC \)
+                    Some(']') => {
+                        while let Some(c) = chars.next() {
+                            if c == '\x07' {
+                                break;
+                            }
+                            if c == '\x1b' {
+                                chars.next();
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            '\n' => {
+                flush_span(&mut current, &mut spans, style);
+                lines.push(Line::from(std::mem::take(&mut spans)));
+            }
+            '\r' => {}
+            c => current.push(c),
+        }
+    }
+    flush_span(&mut current, &mut spans, style);
+    if !spans.is_empty() {
+        lines.push(Line::from(spans));
+    }
+    Text::from(lines)
+}
+
+fn flush_span(current: &mut String, spans: &mut Vec<Span<'static>>, style: Style) {
+    if !current.is_empty() {
+        spans.push(Span::styled(std::mem::take(current), style));
+    }
+}
+
+/// Apply one SGR parameter list (`1;38;5;208`) to `style`.
+fn apply_sgr(style: &mut Style, params: &str) {
+    let mut codes = params
+        .split(';')
+        .map(|code| code.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        *style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            21 | 22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(BASIC[(code - 30) as usize]),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => *style,
+            },
+            39 => Style { fg: None, ..*style },
+            40..=47 => style.bg(BASIC[(code - 40) as usize]),
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => *style,
+            },
+            49 => Style { bg: None, ..*style },
+            90..=97 => style.fg(BRIGHT[(code - 90) as usize]),
+            100..=107 => style.bg(BRIGHT[(code - 100) as usize]),
+            _ => *style,
+        };
+    }
+}
+
+/// Parse the tail of a 38/48 sequence: `5;n` or `2;r;g;b`.
+fn extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()?)),
+        2 => Some(Color::Rgb(codes.next()?, codes.next()?, codes.next()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text_keeps_lines() {
+        let text = ansi_to_text("one\ntwo\n");
+        assert_eq!(text.lines.len(), 2);
+        assert_eq!(text.lines[1].spans[0].content, "two");
+    }
+
+    #[test]
+    fn test_sgr_styles_and_reset() {
+        let text = ansi_to_text("\x1b[1;31mbold red\x1b[0m plain");
+        let spans = &text.lines[0].spans;
+        assert_eq!(spans[0].content, "bold red");
+        assert_eq!(spans[0].style.fg, Some(Color::Red));
+        assert!(spans[0].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(spans[1].content, " plain");
+        assert_eq!(spans[1].style, Style::default());
+    }
+
+    #[test]
+    fn test_extended_colors() {
+        let text = ansi_to_text("\x1b[38;5;208ma\x1b[48;2;1;2;3mb");
+        let spans = &text.lines[0].spans;
+        assert_eq!(spans[0].style.fg, Some(Color::Indexed(208)));
+        assert_eq!(spans[1].style.bg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(spans[1].style.fg, Some(Color::Indexed(208)));
+    }
+
+    #[test]
+    fn test_non_sgr_sequences_dropped() {
+        let text = ansi_to_text("\x1b]8;;https://x.y\x1b\\link\x1b]8;;\x07\x1b[2K done");
+        let content: String = text.lines[0]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect();
+        assert_eq!(content, "link done");
+    }
+}
@@ -0,0 +1,887 @@
+//! Full-screen TUI (`chibi --tui`) for browsing chibi state.
+//!
+//! The left pane lists contexts with their lock status, unread inbox count
+//! and flocks. The right pane shows the selected context through one of
+//! several tabs:
+//!
+//! - **Transcript**: rendered with `MarkdownStream` into a buffer
+//! - **Tools**: tool calls with arguments and results, including cached
+//!   outputs read back from the VFS blob store
+//! - **Inbox**: unread messages (peeked, not consumed)
+//! - **Flocks**: every flock and its members
+//! - **VFS**: directory browser, as seen by the selected context
+//!
+//! Prompts composed with `i` are sent to the selected context through the
+//! normal `execute_from_input()` path. The TUI steps aside while the turn
+//! streams, so permission prompts and markdown output work as usual.
+
+mod ansi;
+mod ui;
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use chibi_core::Chibi;
+use chibi_core::context::{
+    ENTRY_TYPE_TOOL_CALL, ENTRY_TYPE_TOOL_RESULT, InboxEntry, TranscriptEntry,
+};
+use chibi_core::input::{Command, ExecutionFlags};
+use chibi_core::lock::ContextLock;
+use chibi_core::vfs::flock::FlockEntry;
+use chibi_core::vfs::{VfsCaller, VfsEntry, VfsEntryKind, VfsPath};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::text::Text;
+use ratatui::widgets::ListState;
+
+use crate::input::{ChibiInput, ContextSelection};
+use crate::markdown::{MarkdownConfig, MarkdownStream};
+use crate::session::Session;
+
+/// How long to wait for input before checking for background changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often context status and the selected transcript are re-read.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Largest file or cached output shown in a preview.
+const MAX_PREVIEW_BYTES: usize = 256 * 1024;
+
+/// Lines moved by PageUp/PageDown.
+const PAGE_LINES: u16 = 10;
+
+/// Right-hand tabs, in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Transcript,
+    Tools,
+    Inbox,
+    Flocks,
+    Vfs,
+}
+
+impl Tab {
+    const ALL: [Tab; 5] = [
+        Tab::Transcript,
+        Tab::Tools,
+        Tab::Inbox,
+        Tab::Flocks,
+        Tab::Vfs,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Transcript => "Transcript",
+            Tab::Tools => "Tools",
+            Tab::Inbox => "Inbox",
+            Tab::Flocks => "Flocks",
+            Tab::Vfs => "VFS",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|t| *t == self).unwrap_or(0)
+    }
+}
+
+/// Which pane receives navigation keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Contexts,
+    Pane,
+}
+
+/// One row of the context list.
+struct ContextRow {
+    name: String,
+    /// `[active]` / `[stale]` from `ContextLock::get_status`
+    status: Option<&'static str>,
+    unread: usize,
+    flocks: Vec<String>,
+}
+
+/// A tool call paired with its result.
+struct ToolCall {
+    id: Option<String>,
+    name: String,
+    args: String,
+    result: Option<String>,
+}
+
+/// What the event loop should do after a key press.
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    /// Re-read everything
+    Refresh,
+    /// The selected context changed
+    LoadContext,
+    /// The selected tool call changed
+    LoadToolDetail,
+    /// Open the selected VFS entry
+    VfsOpen,
+    /// Go up one VFS directory
+    VfsUp,
+    /// Send a composed prompt to the selected context
+    Send(String),
+}
+
+struct TuiState {
+    contexts: Vec<ContextRow>,
+    context_list: ListState,
+    /// The session's implied context, marked with `*`
+    implied: String,
+    focus: Focus,
+    tab: Tab,
+    /// Scroll offset of the tab's main text; clamped when drawn, so
+    /// `u16::MAX` means "bottom"
+    scroll: u16,
+    /// Prompt being composed, when in compose mode
+    compose: Option<String>,
+    /// One-line message under the panes
+    status: String,
+
+    entries: Vec<TranscriptEntry>,
+    md_config: Option<MarkdownConfig>,
+    /// Rendered transcript and the width it was rendered at
+    transcript: Text<'static>,
+    transcript_width: u16,
+
+    tool_calls: Vec<ToolCall>,
+    tool_list: ListState,
+    tool_detail: String,
+
+    inbox: Vec<InboxEntry>,
+    flocks: Vec<FlockEntry>,
+
+    vfs_dir: VfsPath,
+    vfs_entries: Vec<VfsEntry>,
+    vfs_list: ListState,
+    vfs_preview: String,
+}
+
+impl TuiState {
+    fn new(implied: String) -> io::Result<Self> {
+        Ok(Self {
+            contexts: Vec::new(),
+            context_list: ListState::default(),
+            implied,
+            focus: Focus::Contexts,
+            tab: Tab::Transcript,
+            scroll: u16::MAX,
+            compose: None,
+            status: String::new(),
+            entries: Vec::new(),
+            md_config: None,
+            transcript: Text::default(),
+            transcript_width: 0,
+            tool_calls: Vec::new(),
+            tool_list: ListState::default(),
+            tool_detail: String::new(),
+            inbox: Vec::new(),
+            flocks: Vec::new(),
+            vfs_dir: VfsPath::new("/")?,
+            vfs_entries: Vec::new(),
+            vfs_list: ListState::default(),
+            vfs_preview: String::new(),
+        })
+    }
+
+    fn selected_context(&self) -> Option<&str> {
+        self.context_list
+            .selected()
+            .and_then(|i| self.contexts.get(i))
+            .map(|row| row.name.as_str())
+    }
+
+    /// Move a list selection by `delta`, staying in bounds.
+    fn step(list: &mut ListState, len: usize, delta: isize) -> bool {
+        if len == 0 {
+            return false;
+        }
+        let current = list.selected().unwrap_or(0);
+        let next = current.saturating_add_signed(delta).min(len - 1);
+        list.select(Some(next));
+        next != current
+    }
+
+    /// Reset per-context view state before loading another context.
+    fn reset_view(&mut self) {
+        self.scroll = if self.tab == Tab::Transcript {
+            u16::MAX
+        } else {
+            0
+        };
+        self.tool_list.select(None);
+        self.tool_detail.clear();
+        self.vfs_preview.clear();
+    }
+
+    /// Handle a key press. Pure state transitions; I/O is left to the
+    /// returned `Action`.
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if let Some(compose) = self.compose.as_mut() {
+            match key.code {
+                KeyCode::Esc => self.compose = None,
+                KeyCode::Enter => {
+                    let prompt = self.compose.take().unwrap_or_default();
+                    if !prompt.trim().is_empty() {
+                        return Action::Send(prompt.trim().to_string());
+                    }
+                }
+                KeyCode::Backspace => {
+                    compose.pop();
+                }
+                KeyCode::Char(c) => compose.push(c),
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Action::Quit;
+        }
+
+        match key.code {
+            KeyCode::Char('q') => Action::Quit,
+            KeyCode::Char('r') => Action::Refresh,
+            KeyCode::Char('i') => {
+                if self.selected_context().is_some() {
+                    self.compose = Some(String::new());
+                }
+                Action::None
+            }
+            KeyCode::Tab => self.select_tab((self.tab.index() + 1) % Tab::ALL.len()),
+            KeyCode::BackTab => {
+                self.select_tab((self.tab.index() + Tab::ALL.len() - 1) % Tab::ALL.len())
+            }
+            KeyCode::Char(c @ '1'..='5') => self.select_tab(c as usize - '1' as usize),
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = Focus::Contexts;
+                Action::None
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = Focus::Pane;
+                Action::None
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.navigate(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.navigate(1),
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(PAGE_LINES);
+                Action::None
+            }
+            KeyCode::PageDown => {
+                self.scroll = self.scroll.saturating_add(PAGE_LINES);
+                Action::None
+            }
+            KeyCode::Home => {
+                self.scroll = 0;
+                Action::None
+            }
+            KeyCode::End => {
+                self.scroll = u16::MAX;
+                Action::None
+            }
+            KeyCode::Enter if self.focus == Focus::Pane && self.tab == Tab::Vfs => Action::VfsOpen,
+            KeyCode::Backspace if self.tab == Tab::Vfs => Action::VfsUp,
+            _ => Action::None,
+        }
+    }
+
+    fn select_tab(&mut self, index: usize) -> Action {
+        self.tab = Tab::ALL[index];
+        self.scroll = if self.tab == Tab::Transcript {
+            u16::MAX
+        } else {
+            0
+        };
+        Action::None
+    }
+
+    /// Up/down: move the context selection, a tab's list, or scroll.
+    fn navigate(&mut self, delta: isize) -> Action {
+        if self.focus == Focus::Contexts {
+            return if Self::step(&mut self.context_list, self.contexts.len(), delta) {
+                Action::LoadContext
+            } else {
+                Action::None
+            };
+        }
+        match self.tab {
+            Tab::Tools => {
+                if Self::step(&mut self.tool_list, self.tool_calls.len(), delta)
+                    || self.tool_detail.is_empty()
+                {
+                    self.scroll = 0;
+                    Action::LoadToolDetail
+                } else {
+                    Action::None
+                }
+            }
+            Tab::Vfs => {
+                Self::step(&mut self.vfs_list, self.vfs_entries.len(), delta);
+                Action::None
+            }
+            Tab::Transcript | Tab::Inbox | Tab::Flocks => {
+                self.scroll = if delta < 0 {
+                    self.scroll.saturating_sub(1)
+                } else {
+                    self.scroll.saturating_add(1)
+                };
+                Action::None
+            }
+        }
+    }
+
+    /// Render the transcript at `width` columns if it isn't already.
+    fn render_transcript(&mut self, width: u16) {
+        if width == self.transcript_width {
+            return;
+        }
+        self.transcript_width = width;
+        let markdown = transcript_markdown(&self.entries);
+        self.transcript = match &self.md_config {
+            Some(config) => {
+                let mut stream = MarkdownStream::buffered(config.clone(), width as usize);
+                let rendered = stream
+                    .write_chunk(&markdown)
+                    .and_then(|_| stream.finish())
+                    .map(|_| stream.take_output());
+                match rendered {
+                    Ok(output) => ansi::ansi_to_text(&output),
+                    Err(e) => Text::raw(format!("render error: {}", e)),
+                }
+            }
+            None => Text::raw(markdown),
+        };
+    }
+}
+
+/// Build the transcript document shown in the Transcript tab.
+///
+/// Messages are rendered in full; tool traffic is summarised (details live
+/// in the Tools tab) and other entry types show as one-line markers.
+fn transcript_markdown(entries: &[TranscriptEntry]) -> String {
+    use chibi_core::context::ENTRY_TYPE_MESSAGE;
+
+    let mut out = String::new();
+    for entry in entries {
+        match entry.entry_type.as_str() {
+            ENTRY_TYPE_MESSAGE => {
+                out.push_str(&format!("**[{}]**\n\n", entry.from.to_uppercase()));
+                for attachment in &entry.attachments {
+                    out.push_str(&format!("_[attachment: {}]_\n\n", attachment.describe()));
+                }
+                out.push_str(entry.content.trim_end());
+                out.push_str("\n\n");
+            }
+            ENTRY_TYPE_TOOL_CALL => {
+                out.push_str(&format!("`[TOOL: {}]`\n\n", entry.to));
+            }
+            ENTRY_TYPE_TOOL_RESULT => {
+                out.push_str(&format!(
+                    "`  -> {} ({} bytes)`\n\n",
+                    entry.from,
+                    entry.content.len()
+                ));
+            }
+            other => {
+                out.push_str(&format!("_[{}]_\n\n", other.to_uppercase()));
+            }
+        }
+    }
+    out
+}
+
+/// Pair tool_call entries with their results by tool call id.
+fn collect_tool_calls(entries: &[TranscriptEntry]) -> Vec<ToolCall> {
+    let mut calls: Vec<ToolCall> = Vec::new();
+    for entry in entries {
+        match entry.entry_type.as_str() {
+            ENTRY_TYPE_TOOL_CALL => calls.push(ToolCall {
+                id: entry.tool_call_id.clone(),
+                name: entry.to.clone(),
+                args: entry.content.clone(),
+                result: None,
+            }),
+            ENTRY_TYPE_TOOL_RESULT => {
+                // Match by id; fall back to the latest unanswered call of the same tool
+                let slot = calls.iter_mut().rev().find(|call| {
+                    call.result.is_none()
+                        && match (&call.id, &entry.tool_call_id) {
+                            (Some(a), Some(b)) => a == b,
+                            _ => call.name == entry.from,
+                        }
+                });
+                if let Some(call) = slot {
+                    call.result = Some(entry.content.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    calls
+}
+
+/// The `vfs:///` URI of a cached output, from the stub left in its tool result.
+fn cached_output_uri(result: &str) -> Option<&str> {
+    result
+        .strip_prefix("[Output cached: ")?
+        .split(']')
+        .next()
+        .filter(|uri| VfsPath::is_vfs_uri(uri))
+}
+
+/// Lossy UTF-8 view of `bytes`, cut at `MAX_PREVIEW_BYTES`.
+fn preview_text(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(MAX_PREVIEW_BYTES)];
+    let mut text = String::from_utf8_lossy(shown).into_owned();
+    if bytes.len() > MAX_PREVIEW_BYTES {
+        text.push_str(&format!(
+            "\n[... {} more bytes not shown]",
+            bytes.len() - MAX_PREVIEW_BYTES
+        ));
+    }
+    text
+}
+
+/// Re-read the context list and its status columns.
+async fn load_contexts(chibi: &Chibi, state: &mut TuiState) {
+    let selected = state.selected_context().map(String::from);
+    let mut rows = Vec::new();
+    for name in chibi.list_contexts() {
+        let status = ContextLock::get_status(
+            &chibi.app.context_dir(&name),
+            chibi.app.config.lock_heartbeat_seconds,
+        );
+        let unread = chibi.app.peek_inbox(&name).map(|i| i.len()).unwrap_or(0);
+        let flocks = chibi
+            .app
+            .vfs
+            .flock_list_for(&name)
+            .await
+            .unwrap_or_default();
+        rows.push(ContextRow {
+            name,
+            status,
+            unread,
+            flocks,
+        });
+    }
+    state.contexts = rows;
+
+    let index = selected
+        .and_then(|name| state.contexts.iter().position(|row| row.name == name))
+        .or_else(|| {
+            state
+                .contexts
+                .iter()
+                .position(|row| row.name == state.implied)
+        })
+        .or(if state.contexts.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    state.context_list.select(index);
+}
+
+/// Load everything shown for the selected context.
+async fn load_context(chibi: &Chibi, state: &mut TuiState) {
+    let Some(name) = state.selected_context().map(String::from) else {
+        return;
+    };
+    state.entries = chibi.app.read_jsonl_transcript(&name).unwrap_or_default();
+    state.tool_calls = collect_tool_calls(&state.entries);
+    state.inbox = chibi.app.peek_inbox(&name).unwrap_or_default();
+    state.flocks = chibi.app.vfs.flock_list_all().await.unwrap_or_default();
+    state.md_config = crate::resolve_cli_config(chibi, &name, None)
+        .ok()
+        .map(|cfg| crate::md_config_from_resolved(&cfg, chibi.home_dir(), true));
+    // Force a re-render at the next draw
+    state.transcript_width = 0;
+    load_vfs_dir(chibi, state).await;
+}
+
+/// Build the detail view for the selected tool call.
+async fn load_tool_detail(chibi: &Chibi, state: &mut TuiState) {
+    let (Some(context), Some(call)) = (
+        state.selected_context().map(String::from),
+        state
+            .tool_list
+            .selected()
+            .and_then(|i| state.tool_calls.get(i)),
+    ) else {
+        return;
+    };
+
+    let args = serde_json::from_str::<serde_json::Value>(&call.args)
+        .and_then(|v| serde_json::to_string_pretty(&v))
+        .unwrap_or_else(|_| call.args.clone());
+    let mut detail = format!(
+        "Tool: {}\nCall id: {}\n\nArguments:\n{}\n\nResult:\n{}",
+        call.name,
+        call.id.as_deref().unwrap_or("-"),
+        args,
+        call.result.as_deref().unwrap_or("(no result recorded)"),
+    );
+
+    if let Some(uri) = call.result.as_deref().and_then(cached_output_uri) {
+        let cached = match VfsPath::from_uri(uri) {
+            Ok(path) => chibi
+                .app
+                .vfs
+                .read(VfsCaller::Context(&context), &path)
+                .await
+                .map(|bytes| preview_text(&bytes)),
+            Err(e) => Err(e),
+        };
+        match cached {
+            Ok(content) => detail.push_str(&format!("\n\nCached output ({}):\n{}", uri, content)),
+            Err(e) => detail.push_str(&format!("\n\nCached output unavailable: {}", e)),
+        }
+    }
+    state.tool_detail = detail;
+}
+
+/// List the current VFS directory as the selected context sees it.
+async fn load_vfs_dir(chibi: &Chibi, state: &mut TuiState) {
+    let Some(context) = state.selected_context().map(String::from) else {
+        return;
+    };
+    match chibi
+        .app
+        .vfs
+        .list(VfsCaller::Context(&context), &state.vfs_dir)
+        .await
+    {
+        Ok(mut entries) => {
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            state
+                .vfs_list
+                .select(if entries.is_empty() { None } else { Some(0) });
+            state.vfs_entries = entries;
+        }
+        Err(e) => {
+            state.vfs_entries.clear();
+            state.vfs_list.select(None);
+            state.status = format!("{}: {}", state.vfs_dir.as_str(), e);
+        }
+    }
+}
+
+/// Enter the selected directory, or preview the selected file.
+async fn vfs_open(chibi: &Chibi, state: &mut TuiState) -> io::Result<()> {
+    let Some(context) = state.selected_context().map(String::from) else {
+        return Ok(());
+    };
+    let Some(entry) = state
+        .vfs_list
+        .selected()
+        .and_then(|i| state.vfs_entries.get(i))
+    else {
+        return Ok(());
+    };
+    let path = state.vfs_dir.join(&entry.name)?;
+    match entry.kind {
+        VfsEntryKind::Directory => {
+            state.vfs_dir = path;
+            state.vfs_preview.clear();
+            load_vfs_dir(chibi, state).await;
+        }
+        VfsEntryKind::File => {
+            let bytes = chibi
+                .app
+                .vfs
+                .read(VfsCaller::Context(&context), &path)
+                .await?;
+            state.vfs_preview = preview_text(&bytes);
+            state.scroll = 0;
+        }
+    }
+    Ok(())
+}
+
+/// Leave the TUI, send `prompt` to `context` as a normal turn, then wait
+/// for Enter so the streamed reply can be read before returning.
+async fn send_prompt(
+    terminal: &mut DefaultTerminal,
+    base: &ChibiInput,
+    context: &str,
+    prompt: String,
+    chibi: &mut Chibi,
+    session: &mut Session,
+    force_markdown: bool,
+) -> io::Result<()> {
+    ratatui::restore();
+    println!("[{}] {}\n", context, prompt);
+
+    let input = ChibiInput {
+        command: Command::SendPrompt {
+            prompt,
+            attachments: vec![],
+        },
+        flags: ExecutionFlags {
+            debug: base.flags.debug.clone(),
+            ..Default::default()
+        },
+        // Ephemeral: browsing in the TUI never moves the session
+        context: ContextSelection::Ephemeral {
+            name: context.to_string(),
+        },
+        md_file: None,
+        tui: false,
+        ..base.clone()
+    };
+    if let Err(e) = crate::execute_from_input(input, chibi, session, force_markdown).await {
+        eprintln!("Error: {}", e);
+    }
+
+    print!("\n[Press Enter to return]");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    *terminal = ratatui::try_init()?;
+    Ok(())
+}
+
+/// Run the TUI until `q`.
+pub async fn run(
+    input: ChibiInput,
+    chibi: &mut Chibi,
+    session: &mut Session,
+    force_markdown: bool,
+) -> io::Result<()> {
+    let mut state = TuiState::new(session.implied_context.clone())?;
+    load_contexts(chibi, &mut state).await;
+    load_context(chibi, &mut state).await;
+
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(
+        &mut terminal,
+        &mut state,
+        &input,
+        chibi,
+        session,
+        force_markdown,
+    )
+    .await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut TuiState,
+    input: &ChibiInput,
+    chibi: &mut Chibi,
+    session: &mut Session,
+    force_markdown: bool,
+) -> io::Result<()> {
+    let mut last_refresh = Instant::now();
+    loop {
+        terminal.draw(|frame| ui::draw(frame, state))?;
+
+        if !event::poll(POLL_INTERVAL)? {
+            if last_refresh.elapsed() >= REFRESH_INTERVAL {
+                last_refresh = Instant::now();
+                load_contexts(chibi, state).await;
+                // Pick up new turns from agents running elsewhere
+                if let Some(name) = state.selected_context()
+                    && chibi
+                        .app
+                        .read_jsonl_transcript(name)
+                        .map(|e| e.len())
+                        .unwrap_or(0)
+                        != state.entries.len()
+                {
+                    load_context(chibi, state).await;
+                }
+            }
+            continue;
+        }
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        state.status.clear();
+
+        match state.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Refresh => {
+                load_contexts(chibi, state).await;
+                load_context(chibi, state).await;
+            }
+            Action::LoadContext => {
+                state.reset_view();
+                load_context(chibi, state).await;
+            }
+            Action::LoadToolDetail => load_tool_detail(chibi, state).await,
+            Action::VfsOpen => {
+                if let Err(e) = vfs_open(chibi, state).await {
+                    state.status = e.to_string();
+                }
+            }
+            Action::VfsUp => {
+                if let Some(parent) = state.vfs_dir.parent() {
+                    state.vfs_dir = parent;
+                    state.vfs_preview.clear();
+                    load_vfs_dir(chibi, state).await;
+                }
+            }
+            Action::Send(prompt) => {
+                let Some(context) = state.selected_context().map(String::from) else {
+                    continue;
+                };
+                send_prompt(
+                    terminal,
+                    input,
+                    &context,
+                    prompt,
+                    chibi,
+                    session,
+                    force_markdown,
+                )
+                .await?;
+                load_contexts(chibi, state).await;
+                state.reset_view();
+                load_context(chibi, state).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        entry_type: &str,
+        from: &str,
+        to: &str,
+        content: &str,
+        id: Option<&str>,
+    ) -> TranscriptEntry {
+        let mut builder = TranscriptEntry::builder()
+            .from(from)
+            .to(to)
+            .content(content)
+            .entry_type(entry_type);
+        if let Some(id) = id {
+            builder = builder.tool_call_id(id);
+        }
+        builder.build()
+    }
+
+    fn press(state: &mut TuiState, code: KeyCode) -> Action {
+        state.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn state_with_contexts(names: &[&str]) -> TuiState {
+        let mut state = TuiState::new("default".to_string()).unwrap();
+        state.contexts = names
+            .iter()
+            .map(|name| ContextRow {
+                name: name.to_string(),
+                status: None,
+                unread: 0,
+                flocks: vec![],
+            })
+            .collect();
+        state.context_list.select(Some(0));
+        state
+    }
+
+    #[test]
+    fn test_collect_tool_calls_pairs_results_by_id() {
+        let entries = vec![
+            entry("message", "user", "chibi", "hi", None),
+            entry(
+                "tool_call",
+                "chibi",
+                "shell_exec",
+                "{\"command\":\"ls\"}",
+                Some("a"),
+            ),
+            entry(
+                "tool_call",
+                "chibi",
+                "shell_exec",
+                "{\"command\":\"pwd\"}",
+                Some("b"),
+            ),
+            entry("tool_result", "shell_exec", "chibi", "/home", Some("b")),
+            entry("tool_result", "shell_exec", "chibi", "file.txt", Some("a")),
+        ];
+        let calls = collect_tool_calls(&entries);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].result.as_deref(), Some("file.txt"));
+        assert_eq!(calls[1].result.as_deref(), Some("/home"));
+    }
+
+    #[test]
+    fn test_cached_output_uri() {
+        let stub = chibi_core::vfs_cache::truncated_message(
+            "vfs:///sys/blobs/ab/abcdef",
+            "shell_exec",
+            "lots of output\n",
+            100,
+        );
+        assert_eq!(cached_output_uri(&stub), Some("vfs:///sys/blobs/ab/abcdef"));
+        assert_eq!(cached_output_uri("plain result"), None);
+    }
+
+    #[test]
+    fn test_transcript_markdown_summarises_tools() {
+        let entries = vec![
+            entry("message", "user", "chibi", "hello", None),
+            entry("tool_call", "chibi", "file_head", "{}", Some("a")),
+            entry("tool_result", "file_head", "chibi", "12345", Some("a")),
+        ];
+        let markdown = transcript_markdown(&entries);
+        assert!(markdown.contains("**[USER]**\n\nhello"));
+        assert!(markdown.contains("`[TOOL: file_head]`"));
+        assert!(markdown.contains("-> file_head (5 bytes)"));
+    }
+
+    #[test]
+    fn test_keys_navigate_contexts_and_tabs() {
+        let mut state = state_with_contexts(&["a", "b"]);
+        assert_eq!(press(&mut state, KeyCode::Down), Action::LoadContext);
+        assert_eq!(state.selected_context(), Some("b"));
+        // Already at the bottom
+        assert_eq!(press(&mut state, KeyCode::Down), Action::None);
+
+        press(&mut state, KeyCode::Tab);
+        assert_eq!(state.tab, Tab::Tools);
+        press(&mut state, KeyCode::Char('5'));
+        assert_eq!(state.tab, Tab::Vfs);
+        press(&mut state, KeyCode::Right);
+        assert_eq!(press(&mut state, KeyCode::Enter), Action::VfsOpen);
+        assert_eq!(press(&mut state, KeyCode::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn test_compose_mode() {
+        let mut state = state_with_contexts(&["a"]);
+        press(&mut state, KeyCode::Char('i'));
+        for c in "hi q".chars() {
+            assert_eq!(press(&mut state, KeyCode::Char(c)), Action::None);
+        }
+        press(&mut state, KeyCode::Backspace);
+        assert_eq!(
+            press(&mut state, KeyCode::Enter),
+            Action::Send("hi".to_string())
+        );
+        assert!(state.compose.is_none());
+
+        press(&mut state, KeyCode::Char('i'));
+        press(&mut state, KeyCode::Char('x'));
+        press(&mut state, KeyCode::Esc);
+        assert!(state.compose.is_none());
+    }
+}
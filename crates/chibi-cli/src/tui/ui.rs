@@ -0,0 +1,274 @@
+//! TUI drawing. Reads `TuiState`; the only mutation is rendering the
+//! transcript lazily at the pane's width and clamping the scroll offset.
+
+use chibi_core::vfs::VfsEntryKind;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Tabs, Wrap};
+
+use super::{Focus, Tab, TuiState};
+
+const HELP: &str =
+    "q quit  ←/→ focus  ↑/↓ move  Tab/1-5 pane  PgUp/PgDn scroll  i prompt  r refresh";
+
+fn highlight() -> Style {
+    Style::default().add_modifier(Modifier::REVERSED)
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::default().fg(Color::Cyan))
+    } else {
+        block
+    }
+}
+
+pub(super) fn draw(frame: &mut Frame, state: &mut TuiState) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(25), Constraint::Fill(1)]).areas(main);
+
+    draw_contexts(frame, state, left);
+
+    let [tabs_area, body] =
+        Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(right);
+    let tabs = Tabs::new(Tab::ALL.iter().map(|t| t.title()))
+        .select(state.tab.index())
+        .highlight_style(highlight());
+    frame.render_widget(tabs, tabs_area);
+
+    let focused = state.focus == Focus::Pane;
+    match state.tab {
+        Tab::Transcript => draw_transcript(frame, state, body, focused),
+        Tab::Tools => draw_tools(frame, state, body, focused),
+        Tab::Inbox => {
+            let text = inbox_text(state);
+            draw_scrolled(frame, state, text, pane_block("Inbox", focused), body);
+        }
+        Tab::Flocks => {
+            let text = flocks_text(state);
+            draw_scrolled(frame, state, text, pane_block("Flocks", focused), body);
+        }
+        Tab::Vfs => draw_vfs(frame, state, body, focused),
+    }
+
+    let footer_line = match &state.compose {
+        Some(prompt) => Line::from(vec![
+            Span::styled(
+                format!("{}> ", state.selected_context().unwrap_or("")),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(prompt.clone()),
+        ]),
+        None if !state.status.is_empty() => {
+            Line::styled(state.status.clone(), Style::default().fg(Color::Yellow))
+        }
+        None => Line::styled(HELP, Style::default().add_modifier(Modifier::DIM)),
+    };
+    frame.render_widget(Paragraph::new(footer_line), footer);
+    if let Some(prompt) = &state.compose {
+        let prefix = state.selected_context().unwrap_or("").chars().count() + 2;
+        let x = footer.x + (prefix + prompt.chars().count()) as u16;
+        frame.set_cursor_position((x.min(footer.right().saturating_sub(1)), footer.y));
+    }
+}
+
+fn draw_contexts(frame: &mut Frame, state: &mut TuiState, area: Rect) {
+    let items: Vec<ListItem> = state
+        .contexts
+        .iter()
+        .map(|row| {
+            let marker = if row.name == state.implied {
+                "* "
+            } else {
+                "  "
+            };
+            let mut spans = vec![Span::raw(format!("{}{}", marker, row.name))];
+            if let Some(status) = row.status {
+                let color = if status == "[active]" {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                spans.push(Span::styled(
+                    format!(" {}", status),
+                    Style::default().fg(color),
+                ));
+            }
+            if row.unread > 0 {
+                spans.push(Span::styled(
+                    format!(" ✉{}", row.unread),
+                    Style::default().fg(Color::Yellow),
+                ));
+            }
+            if !row.flocks.is_empty() {
+                spans.push(Span::styled(
+                    format!(" ({})", row.flocks.join(", ")),
+                    Style::default().add_modifier(Modifier::DIM),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(pane_block("Contexts", state.focus == Focus::Contexts))
+        .highlight_style(highlight());
+    frame.render_stateful_widget(list, area, &mut state.context_list);
+}
+
+/// Draw `text` in `block`, clamping `state.scroll` to the text's length.
+///
+/// Wrapped lines are counted once, so the clamp is approximate for long
+/// lines; PageDown past the end simply stops at the last line.
+fn draw_scrolled(
+    frame: &mut Frame,
+    state: &mut TuiState,
+    text: Text<'static>,
+    block: Block<'_>,
+    area: Rect,
+) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let max = text.lines.len().saturating_sub(visible);
+    state.scroll = state.scroll.min(max.min(u16::MAX as usize) as u16);
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((state.scroll, 0));
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_transcript(frame: &mut Frame, state: &mut TuiState, area: Rect, focused: bool) {
+    state.render_transcript(area.width.saturating_sub(2));
+    let visible = area.height.saturating_sub(2) as usize;
+    let max = state.transcript.lines.len().saturating_sub(visible);
+    state.scroll = state.scroll.min(max.min(u16::MAX as usize) as u16);
+    // Already wrapped by the markdown renderer
+    let paragraph = Paragraph::new(state.transcript.clone())
+        .block(pane_block("Transcript", focused))
+        .scroll((state.scroll, 0));
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_tools(frame: &mut Frame, state: &mut TuiState, area: Rect, focused: bool) {
+    let [list_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(area);
+    let items: Vec<ListItem> = state
+        .tool_calls
+        .iter()
+        .map(|call| {
+            let cached = call
+                .result
+                .as_deref()
+                .and_then(super::cached_output_uri)
+                .is_some();
+            let mut spans = vec![Span::raw(call.name.clone())];
+            if call.result.is_none() {
+                spans.push(Span::styled(" …", Style::default().fg(Color::Yellow)));
+            } else if cached {
+                spans.push(Span::styled(" [cached]", Style::default().fg(Color::Cyan)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(pane_block("Tool calls", focused))
+        .highlight_style(highlight());
+    frame.render_stateful_widget(list, list_area, &mut state.tool_list);
+
+    let detail = if state.tool_detail.is_empty() {
+        Text::raw("Select a tool call (→ then ↑/↓)")
+    } else {
+        Text::raw(state.tool_detail.clone())
+    };
+    draw_scrolled(
+        frame,
+        state,
+        detail,
+        pane_block("Detail", false),
+        detail_area,
+    );
+}
+
+fn draw_vfs(frame: &mut Frame, state: &mut TuiState, area: Rect, focused: bool) {
+    let [list_area, preview_area] =
+        Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(area);
+    let items: Vec<ListItem> = state
+        .vfs_entries
+        .iter()
+        .map(|entry| match entry.kind {
+            VfsEntryKind::Directory => ListItem::new(Line::styled(
+                format!("{}/", entry.name),
+                Style::default().fg(Color::Blue),
+            )),
+            VfsEntryKind::File => ListItem::new(entry.name.clone()),
+        })
+        .collect();
+    let title = state.vfs_dir.as_str().to_string();
+    let list = List::new(items)
+        .block(pane_block(&title, focused))
+        .highlight_style(highlight());
+    frame.render_stateful_widget(list, list_area, &mut state.vfs_list);
+
+    let preview = if state.vfs_preview.is_empty() {
+        Text::raw("Enter opens, Backspace goes up")
+    } else {
+        Text::raw(state.vfs_preview.clone())
+    };
+    draw_scrolled(
+        frame,
+        state,
+        preview,
+        pane_block("Preview", false),
+        preview_area,
+    );
+}
+
+fn inbox_text(state: &TuiState) -> Text<'static> {
+    if state.inbox.is_empty() {
+        return Text::raw("(inbox empty)");
+    }
+    let mut lines = Vec::new();
+    for message in &state.inbox {
+        let when = chrono::DateTime::from_timestamp(message.timestamp as i64, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        lines.push(Line::styled(
+            format!("{} → {}  {}", message.from, message.to, when),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        lines.extend(message.content.lines().map(|l| Line::raw(l.to_string())));
+        lines.push(Line::raw(""));
+    }
+    Text::from(lines)
+}
+
+fn flocks_text(state: &TuiState) -> Text<'static> {
+    if state.flocks.is_empty() {
+        return Text::raw("(no flocks)");
+    }
+    let selected = state.selected_context().unwrap_or("");
+    let mut lines = Vec::new();
+    for flock in &state.flocks {
+        lines.push(Line::styled(
+            format!("{} ({} members)", flock.name, flock.members.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        for member in &flock.members {
+            let style = if member.context == selected {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(
+                format!("  {} @ {}", member.context, member.site),
+                style,
+            ));
+        }
+        lines.push(Line::raw(""));
+    }
+    Text::from(lines)
+}
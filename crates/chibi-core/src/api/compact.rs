@@ -4,7 +4,11 @@
 //! - Rolling compaction: strips messages and integrates them into the summary
 //! - Full compaction: summarizes all messages and starts fresh
 //! - By-name compaction: compact a specific context without LLM
+//!
+//! Auto-compaction picks one of these, or a strategy from `compact_strategy`,
+//! by the context's `compaction_strategy` setting.
 
+use super::compact_strategy::{self, CompactionStrategy};
 use crate::config::ResolvedConfig;
 use crate::context::{Context, now_timestamp};
 use crate::gateway;
@@ -364,19 +368,38 @@ pub async fn rolling_compact(
     Ok(())
 }
 
-/// Auto-triggered compaction: delegates to rolling compaction.
+/// Auto-triggered compaction: runs the context's `compaction_strategy`.
 ///
-/// Rolling compaction summarises older messages while preserving recent ones,
-/// keeping the context window within budget without discarding everything.
-/// For full compaction (manual `-c` flag), see `compact_context_with_llm_manual`.
+/// The default, rolling compaction, summarises older messages while preserving
+/// recent ones, keeping the context window within budget without discarding
+/// everything. For full compaction (manual `-c` flag), see
+/// `compact_context_with_llm_manual`.
 pub async fn compact_context_with_llm(
     app: &AppState,
     context_name: &str,
     resolved_config: &ResolvedConfig,
     sink: &dyn OutputSink,
 ) -> io::Result<()> {
-    // Use rolling compaction for auto-triggered compaction
-    rolling_compact(app, context_name, resolved_config, sink).await
+    match CompactionStrategy::parse(&resolved_config.compaction_strategy)? {
+        CompactionStrategy::Rolling => {
+            rolling_compact(app, context_name, resolved_config, sink).await
+        }
+        CompactionStrategy::Summarize => {
+            compact_context_with_llm_internal(app, context_name, resolved_config, sink).await
+        }
+        CompactionStrategy::KeepLastTurns => {
+            compact_strategy::keep_last_turns_compact(app, context_name, resolved_config, sink)
+        }
+        CompactionStrategy::ElideToolResults => {
+            compact_strategy::elide_compact(app, context_name, resolved_config, sink).await
+        }
+        CompactionStrategy::Structured => {
+            compact_strategy::structured_compact(app, context_name, resolved_config, sink).await
+        }
+        CompactionStrategy::Custom(name) => {
+            compact_strategy::custom_compact(app, context_name, &name, resolved_config, sink).await
+        }
+    }
 }
 
 /// Full compaction: summarizes all messages and starts fresh (manual -c flag)
@@ -450,6 +473,7 @@ async fn compact_context_with_llm_internal(
         "context_name": context.name,
         "message_count": context.messages.len(),
         "summary": context.summary,
+        "strategy": CompactionStrategy::Summarize.as_str(),
     });
     let _ = tools::execute_hook(&tools, tools::HookPoint::PreCompact, &hook_data, None);
 
@@ -564,6 +588,7 @@ async fn compact_context_with_llm_internal(
         "context_name": new_context.name,
         "message_count": new_context.messages.len(),
         "summary": new_context.summary,
+        "strategy": CompactionStrategy::Summarize.as_str(),
     });
     let _ = tools::execute_hook(&tools, tools::HookPoint::PostCompact, &hook_data, None);
 
@@ -634,8 +659,20 @@ mod tests {
             username: "testuser".to_string(),
             lock_heartbeat_seconds: 30,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
+            tool_cache_max_bytes: 0,
             auto_cleanup_cache: true,
             tool_cache_preview_chars: 500,
             file_tools_allowed_paths: vec![],
@@ -1583,6 +1620,17 @@ mod tests {
             reflection_enabled: false,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: true,
@@ -0,0 +1,767 @@
+//! Selectable auto-compaction strategies.
+//!
+//! Auto-compaction runs the strategy named by `compaction_strategy` in the
+//! context's config:
+//!
+//! - `rolling` (default): the LLM picks messages to archive into the summary
+//! - `summarize`: summarise everything and start fresh
+//! - `keep_last_turns`: drop all but the last `compact_keep_turns` user turns, no LLM
+//! - `elide_tool_results`: shrink bulky tool outputs older than the last
+//!   `compact_keep_turns` turns while keeping the calls, then roll if that was
+//!   not enough
+//! - `structured`: archive the oldest messages into a `CompactionRecord` of
+//!   decisions, open questions and touched files under a prose narrative
+//!
+//! - `custom:<name>`: offered to `custom_compact` hooks (plugins or
+//!   synthesised tools) as `<name>`. When no hook handles it, rolling
+//!   compaction runs instead.
+//!
+//! Any other name is rejected when the config is resolved, so a typo fails
+//! loudly instead of quietly rolling.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::compact::{collect_tool_call_ids, drop_count, filter_messages, rolling_compact};
+use crate::config::ResolvedConfig;
+use crate::context::{Context, now_timestamp};
+use crate::error::ChibiError;
+use crate::gateway;
+use crate::output::{CommandEvent, OutputSink};
+use crate::state::{AppState, StatePaths, format_flock_sections, load_flock_contexts};
+use crate::tools::{self, HookPoint, Tool};
+
+const STRUCTURED_COMPACT_TEMPLATE: &str = include_str!("../../prompts/structured-compact.md");
+
+/// Tool outputs at or below this many characters are never elided.
+const ELIDE_MIN_CHARS: usize = 500;
+/// Characters of an elided tool output kept as a preview.
+const ELIDE_PREVIEW_CHARS: usize = 120;
+/// Start of an elided tool output, so later passes leave it alone.
+const ELIDED_MARKER: &str = "[tool output elided";
+
+/// How auto-compaction shrinks a context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompactionStrategy {
+    /// The LLM picks messages to archive into the free-text summary.
+    Rolling,
+    /// Summarise everything and start fresh, like manual compaction.
+    Summarize,
+    /// Drop all but the most recent user turns, without an LLM call.
+    KeepLastTurns,
+    /// Shrink old tool outputs but keep the calls; roll if still over threshold.
+    ElideToolResults,
+    /// Archive into a structured record of decisions, open questions and files.
+    Structured,
+    /// `custom:<name>`, offered to `custom_compact` hooks as `<name>`.
+    Custom(String),
+}
+
+impl CompactionStrategy {
+    /// Parse a strategy name. Custom strategies need the `custom:` prefix;
+    /// anything else unknown is an error.
+    pub fn parse(name: &str) -> io::Result<Self> {
+        match name.trim() {
+            "" | "rolling" => Ok(Self::Rolling),
+            "summarize" => Ok(Self::Summarize),
+            "keep_last_turns" => Ok(Self::KeepLastTurns),
+            "elide_tool_results" => Ok(Self::ElideToolResults),
+            "structured" => Ok(Self::Structured),
+            other => match other.strip_prefix("custom:").map(str::trim) {
+                Some(custom) if !custom.is_empty() => Ok(Self::Custom(custom.to_string())),
+                _ => Err(ChibiError::ConfigInvalid {
+                    message: format!(
+                        "Unknown compaction_strategy '{}' (expected rolling, summarize, \
+                         keep_last_turns, elide_tool_results, structured or custom:<name>)",
+                        other
+                    ),
+                }
+                .into()),
+            },
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Rolling => "rolling",
+            Self::Summarize => "summarize",
+            Self::KeepLastTurns => "keep_last_turns",
+            Self::ElideToolResults => "elide_tool_results",
+            Self::Structured => "structured",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+/// Structured compaction state, stored as `compaction.json` beside `summary.md`.
+///
+/// The narrative is condensed again on every pass. The lists are carried
+/// over verbatim (open questions drop out once answered), so file paths and
+/// decisions survive any number of compactions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompactionRecord {
+    #[serde(default)]
+    pub narrative: String,
+    #[serde(default)]
+    pub decisions: Vec<String>,
+    #[serde(default)]
+    pub open_questions: Vec<String>,
+    #[serde(default)]
+    pub touched_files: Vec<String>,
+}
+
+/// The LLM's reply to the structured compaction prompt.
+#[derive(Debug, Default, Deserialize)]
+struct RecordUpdate {
+    #[serde(default)]
+    narrative: String,
+    #[serde(default)]
+    decisions: Vec<String>,
+    #[serde(default)]
+    open_questions: Vec<String>,
+    #[serde(default)]
+    resolved_questions: Vec<String>,
+    #[serde(default)]
+    touched_files: Vec<String>,
+}
+
+impl CompactionRecord {
+    /// Load a context's record. Empty when there is none, or when the
+    /// summary it belongs to has since been cleared.
+    pub fn load(app: &AppState, context_name: &str) -> io::Result<Self> {
+        if !app.summary_file(context_name).exists() {
+            return Ok(Self::default());
+        }
+        match fs::read(app.compaction_record_file(context_name)) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| {
+                io::Error::new(ErrorKind::InvalidData, format!("compaction record: {e}"))
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, app: &AppState, context_name: &str) -> io::Result<()> {
+        crate::safe_io::atomic_write_json(&app.compaction_record_file(context_name), self)
+    }
+
+    /// Fold an LLM update and the files seen in archived tool calls into the record.
+    fn apply(&mut self, update: RecordUpdate, files: &[String]) {
+        if !update.narrative.trim().is_empty() {
+            self.narrative = update.narrative.trim().to_string();
+        }
+        for decision in &update.decisions {
+            push_unique(&mut self.decisions, decision);
+        }
+        self.open_questions.retain(|q| {
+            !update
+                .resolved_questions
+                .iter()
+                .any(|r| r.trim() == q.as_str())
+        });
+        for question in &update.open_questions {
+            push_unique(&mut self.open_questions, question);
+        }
+        for file in update.touched_files.iter().chain(files) {
+            push_unique(&mut self.touched_files, file);
+        }
+    }
+
+    /// Render as the context summary: the narrative, then each non-empty list.
+    pub fn render(&self) -> String {
+        let mut out = self.narrative.clone();
+        let sections = [
+            ("Decisions", &self.decisions),
+            ("Open questions", &self.open_questions),
+            ("Touched files", &self.touched_files),
+        ];
+        for (title, items) in sections {
+            if items.is_empty() {
+                continue;
+            }
+            if !out.is_empty() {
+                out.push_str("\n\n");
+            }
+            out.push_str(&format!("## {title}\n"));
+            for item in items {
+                out.push_str(&format!("\n- {item}"));
+            }
+        }
+        out
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    let item = item.trim();
+    if !item.is_empty() && !list.iter().any(|existing| existing == item) {
+        list.push(item.to_string());
+    }
+}
+
+/// Index where the last `turns` user turns begin, or `None` when there are
+/// no more turns than that. A turn starts at a user message.
+fn recent_turns_start(messages: &[Value], turns: usize) -> Option<usize> {
+    let starts: Vec<usize> = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m["role"].as_str() == Some("user"))
+        .map(|(i, _)| i)
+        .collect();
+    if starts.len() <= turns {
+        return None;
+    }
+    Some(
+        starts
+            .get(starts.len() - turns)
+            .copied()
+            .unwrap_or(messages.len()),
+    )
+}
+
+/// Keep system messages and the last `turns` user turns. Turns start at user
+/// messages, so tool exchanges are never split.
+pub(crate) fn keep_last_turns(messages: &[Value], turns: usize) -> Vec<Value> {
+    let Some(start) = recent_turns_start(messages, turns) else {
+        return messages.to_vec();
+    };
+    messages
+        .iter()
+        .enumerate()
+        .filter(|(i, m)| *i >= start || m["role"].as_str() == Some("system"))
+        .map(|(_, m)| m.clone())
+        .collect()
+}
+
+/// Replace bulky tool outputs before the last `turns` user turns with a short
+/// marker. The calls stay, so the model still knows what it did. Returns the
+/// number of outputs elided.
+pub(crate) fn elide_tool_results(messages: &mut [Value], turns: usize) -> usize {
+    let Some(start) = recent_turns_start(messages, turns) else {
+        return 0;
+    };
+    let mut elided = 0;
+    for m in &mut messages[..start] {
+        if m["role"].as_str() != Some("tool") {
+            continue;
+        }
+        let Some(content) = m["content"].as_str() else {
+            continue;
+        };
+        let chars = content.chars().count();
+        if chars <= ELIDE_MIN_CHARS || content.starts_with(ELIDED_MARKER) {
+            continue;
+        }
+        let preview: String = content.chars().take(ELIDE_PREVIEW_CHARS).collect();
+        let marker =
+            format!("{ELIDED_MARKER} during compaction: {chars} chars, began: {preview}...]");
+        m["content"] = json!(marker);
+        elided += 1;
+    }
+    elided
+}
+
+fn is_path_key(key: &str) -> bool {
+    matches!(key, "path" | "paths" | "file" | "files")
+        || key.ends_with("_path")
+        || key.ends_with("_paths")
+}
+
+/// File paths named in tool call arguments, in first-seen order.
+pub(crate) fn touched_files(messages: &[Value]) -> Vec<String> {
+    let mut files = Vec::new();
+    for m in messages {
+        let Some(calls) = m["tool_calls"].as_array() else {
+            continue;
+        };
+        for tc in calls {
+            let raw = &tc["function"]["arguments"];
+            // Arguments are stored as a JSON string, as the API sends them.
+            let args = match raw.as_str() {
+                Some(s) => serde_json::from_str(s).unwrap_or_default(),
+                None => raw.clone(),
+            };
+            let Some(args) = args.as_object() else {
+                continue;
+            };
+            for (key, value) in args {
+                if !is_path_key(key) {
+                    continue;
+                }
+                let values: Vec<&Value> = match value {
+                    Value::Array(items) => items.iter().collect(),
+                    other => vec![other],
+                };
+                for path in values.into_iter().filter_map(|v| v.as_str()) {
+                    push_unique(&mut files, path);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Transcript text of archived messages, with tool arguments kept so the
+/// LLM can see which files were involved.
+fn archive_text(messages: &[Value]) -> String {
+    let mut text = String::new();
+    for m in messages {
+        let role = m["role"].as_str().unwrap_or("unknown").to_uppercase();
+        if let Some(tool_calls) = m["tool_calls"].as_array() {
+            let calls: Vec<String> = tool_calls
+                .iter()
+                .map(|tc| {
+                    let name = tc["function"]["name"].as_str().unwrap_or("");
+                    let args = &tc["function"]["arguments"];
+                    match args.as_str() {
+                        Some(s) => format!("{name} {s}"),
+                        None => format!("{name} {args}"),
+                    }
+                })
+                .collect();
+            text.push_str(&format!(
+                "[{role}]: [called tools: {}]\n\n",
+                calls.join("; ")
+            ));
+        } else {
+            let content = m["content"].as_str().unwrap_or("");
+            let content = if role == "TOOL" && content.len() > 500 {
+                let end = content.floor_char_boundary(500);
+                format!("{}... [truncated]", &content[..end])
+            } else {
+                content.to_string()
+            };
+            text.push_str(&format!("[{role}]: {content}\n\n"));
+        }
+    }
+    text
+}
+
+/// Parse the structured compaction reply. A reply that is not JSON becomes
+/// the narrative, so a weak model degrades to a prose summary.
+fn parse_update(reply: &str) -> RecordUpdate {
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => reply,
+    };
+    serde_json::from_str(json).unwrap_or_else(|_| RecordUpdate {
+        narrative: reply.trim().to_string(),
+        ..Default::default()
+    })
+}
+
+/// Fire `pre_compact`/`post_compact` for a strategy run.
+///
+/// TeinHookContext is None for the same reason as in `rolling_compact`.
+fn compact_hook(tools: &[Tool], hook: HookPoint, context: &Context, strategy: &str) {
+    let hook_data = json!({
+        "context_name": context.name,
+        "message_count": context.messages.len(),
+        "summary": context.summary,
+        "strategy": strategy,
+    });
+    let _ = tools::execute_hook(tools, hook, &hook_data, None);
+}
+
+/// Persist a strategy's result and report it.
+fn finish(
+    app: &AppState,
+    context: &mut Context,
+    anchor: &str,
+    archived: usize,
+    sink: &dyn OutputSink,
+) -> io::Result<()> {
+    context.updated_at = now_timestamp();
+    app.finalize_compaction(&context.name, anchor)?;
+    app.save_context(context)?;
+    sink.emit_event(CommandEvent::CompactionComplete {
+        context: context.name.clone(),
+        archived,
+        remaining: context.messages.len(),
+    });
+    Ok(())
+}
+
+/// `keep_last_turns`: drop everything before the last `compact_keep_turns`
+/// user turns. The summary is left alone; dropped messages stay in the transcript.
+pub fn keep_last_turns_compact(
+    app: &AppState,
+    context_name: &str,
+    resolved_config: &ResolvedConfig,
+    sink: &dyn OutputSink,
+) -> io::Result<()> {
+    let mut context = app.get_or_create_context(context_name)?;
+    let turns = resolved_config.compact_keep_turns;
+    let kept = keep_last_turns(&context.messages, turns);
+    let archived = context.messages.len() - kept.len();
+    if archived == 0 {
+        return Ok(());
+    }
+
+    let strategy = CompactionStrategy::KeepLastTurns;
+    let tools = tools::load_tools(&app.plugins_dir)?;
+    compact_hook(&tools, HookPoint::PreCompact, &context, strategy.as_str());
+
+    context.messages = kept;
+    let anchor = format!(
+        "Kept the last {} turns. {} messages archived to transcript.",
+        turns, archived
+    );
+    finish(app, &mut context, &anchor, archived, sink)?;
+
+    compact_hook(&tools, HookPoint::PostCompact, &context, strategy.as_str());
+    Ok(())
+}
+
+/// `elide_tool_results`: shrink old tool outputs first, and fall back to
+/// rolling compaction when nothing could be elided or the context is still
+/// over the auto-compaction threshold.
+pub async fn elide_compact(
+    app: &AppState,
+    context_name: &str,
+    resolved_config: &ResolvedConfig,
+    sink: &dyn OutputSink,
+) -> io::Result<()> {
+    let mut context = app.get_or_create_context(context_name)?;
+    let strategy = CompactionStrategy::ElideToolResults;
+    let tools = tools::load_tools(&app.plugins_dir)?;
+
+    let mut messages = context.messages.clone();
+    let elided = elide_tool_results(&mut messages, resolved_config.compact_keep_turns);
+    if elided > 0 {
+        compact_hook(&tools, HookPoint::PreCompact, &context, strategy.as_str());
+        context.messages = messages;
+        let anchor = format!("{} tool outputs elided.", elided);
+        finish(app, &mut context, &anchor, elided, sink)?;
+        compact_hook(&tools, HookPoint::PostCompact, &context, strategy.as_str());
+    }
+
+    if elided == 0 || app.should_auto_compact(&context, resolved_config) {
+        return rolling_compact(app, context_name, resolved_config, sink).await;
+    }
+    Ok(())
+}
+
+/// `structured`: archive the oldest `rolling_compact_drop_percentage` of
+/// messages into the context's `CompactionRecord` and render it as the summary.
+pub async fn structured_compact(
+    app: &AppState,
+    context_name: &str,
+    resolved_config: &ResolvedConfig,
+    sink: &dyn OutputSink,
+) -> io::Result<()> {
+    let mut context = app.get_or_create_context(context_name)?;
+    let non_system: Vec<&Value> = context
+        .messages
+        .iter()
+        .filter(|m| m["role"].as_str() != Some("system"))
+        .collect();
+    if non_system.len() <= 4 {
+        return Ok(());
+    }
+
+    let strategy = CompactionStrategy::Structured;
+    let tools = tools::load_tools(&app.plugins_dir)?;
+    compact_hook(&tools, HookPoint::PreCompact, &context, strategy.as_str());
+
+    let target = drop_count(
+        non_system.len(),
+        resolved_config.rolling_compact_drop_percentage,
+    );
+    let archived: Vec<Value> = non_system
+        .iter()
+        .take(target)
+        .map(|m| (*m).clone())
+        .collect();
+
+    let mut record = CompactionRecord::load(app, context_name)?;
+    // Switching from a free-text strategy: carry the old summary over as the narrative.
+    if record == CompactionRecord::default() {
+        record.narrative = context.summary.clone();
+    }
+
+    let task_metas = crate::state::tasks::collect_tasks(&app.vfs, context_name).await;
+    let task_table = crate::state::tasks::build_summary_table(&task_metas);
+    let flock_contexts = load_flock_contexts(&app.vfs, context_name).unwrap_or_default();
+    let goals = format_flock_sections(&flock_contexts);
+    let list = |items: &[String]| {
+        if items.is_empty() {
+            "(none)".to_string()
+        } else {
+            items
+                .iter()
+                .map(|i| format!("- {i}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    let prompt = STRUCTURED_COMPACT_TEMPLATE
+        .replace(
+            "{NARRATIVE}",
+            if record.narrative.is_empty() {
+                "(No existing narrative)"
+            } else {
+                &record.narrative
+            },
+        )
+        .replace("{DECISIONS}", &list(&record.decisions))
+        .replace("{OPEN_QUESTIONS}", &list(&record.open_questions))
+        .replace("{ARCHIVED}", &archive_text(&archived))
+        .replace(
+            "{GOALS}",
+            &if goals.is_empty() {
+                String::new()
+            } else {
+                format!("{}\n\n", goals)
+            },
+        )
+        .replace(
+            "{TASKS}",
+            &if task_table.is_empty() {
+                String::new()
+            } else {
+                format!("CURRENT TASKS:\n{}\n\n", task_table)
+            },
+        );
+
+    let reply = gateway::chat(
+        resolved_config,
+        &[json!({
+            "role": "user",
+            "content": prompt,
+        })],
+    )
+    .await?;
+    if reply.trim().is_empty() {
+        return Ok(());
+    }
+    record.apply(parse_update(&reply), &touched_files(&archived));
+
+    let drop_ids = archived
+        .iter()
+        .filter_map(|m| m["_id"].as_str().map(|s| s.to_string()))
+        .collect();
+    let drop_tool_call_ids = collect_tool_call_ids(&archived);
+    context.messages = filter_messages(&context.messages, &drop_ids, &drop_tool_call_ids);
+    context.summary = record.render();
+    let summary = context.summary.clone();
+    finish(app, &mut context, &summary, archived.len(), sink)?;
+    // After save_context, which writes the summary the record is tied to.
+    record.save(app, context_name)?;
+
+    compact_hook(&tools, HookPoint::PostCompact, &context, strategy.as_str());
+    Ok(())
+}
+
+/// A custom strategy: the first `custom_compact` hook to return a well-formed
+/// `messages` array decides the new context (and optionally the summary).
+pub async fn custom_compact(
+    app: &AppState,
+    context_name: &str,
+    strategy: &str,
+    resolved_config: &ResolvedConfig,
+    sink: &dyn OutputSink,
+) -> io::Result<()> {
+    let mut context = app.get_or_create_context(context_name)?;
+    let tools = tools::load_tools(&app.plugins_dir)?;
+    compact_hook(&tools, HookPoint::PreCompact, &context, strategy);
+
+    // TeinHookContext: None — see PreRollingCompact in rolling_compact.
+    let hook_data = json!({
+        "context_name": context.name,
+        "strategy": strategy,
+        "messages": context.messages,
+        "summary": context.summary,
+        "keep_turns": resolved_config.compact_keep_turns,
+        "drop_percentage": resolved_config.rolling_compact_drop_percentage,
+    });
+    let results =
+        tools::execute_hook(&tools, HookPoint::CustomCompact, &hook_data, None).unwrap_or_default();
+    let handled = results.into_iter().find_map(|(_, result)| {
+        let messages = hook_messages(result.get("messages")?)?;
+        let summary = result
+            .get("summary")
+            .and_then(|s| s.as_str())
+            .map(String::from);
+        Some((messages, summary))
+    });
+    let Some((messages, summary)) = handled else {
+        sink.emit_event(CommandEvent::CompactionStrategyFallback {
+            strategy: strategy.to_string(),
+        });
+        return rolling_compact(app, context_name, resolved_config, sink).await;
+    };
+
+    let archived = context.messages.len().saturating_sub(messages.len());
+    context.messages = messages;
+    if let Some(summary) = summary {
+        context.summary = summary;
+    }
+    let summary = context.summary.clone();
+    finish(app, &mut context, &summary, archived, sink)?;
+
+    compact_hook(&tools, HookPoint::PostCompact, &context, strategy);
+    Ok(())
+}
+
+/// Validate a hook's `messages` array: every entry must be an object with a
+/// string `role`. Entries missing an `_id` get a fresh one. `None` when the
+/// hook returned anything else, so the hook counts as not handling it.
+fn hook_messages(value: &Value) -> Option<Vec<Value>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|m| {
+            let mut m = m.as_object()?.clone();
+            m.get("role")?.as_str()?;
+            if m.get("_id").and_then(Value::as_str).is_none() {
+                m.insert("_id".into(), json!(uuid::Uuid::new_v4().to_string()));
+            }
+            Some(Value::Object(m))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> Value {
+        json!({ "role": role, "content": content })
+    }
+
+    fn call(path: &str) -> Value {
+        json!({
+            "role": "assistant",
+            "tool_calls": [{
+                "id": "tc",
+                "function": {
+                    "name": "file_edit",
+                    "arguments": json!({ "path": path, "old": "a" }).to_string(),
+                },
+            }],
+        })
+    }
+
+    #[test]
+    fn test_parse_strategy_names() {
+        for name in [
+            "rolling",
+            "summarize",
+            "keep_last_turns",
+            "elide_tool_results",
+            "structured",
+        ] {
+            assert_eq!(CompactionStrategy::parse(name).unwrap().as_str(), name);
+        }
+        assert_eq!(
+            CompactionStrategy::parse("").unwrap(),
+            CompactionStrategy::Rolling
+        );
+        assert_eq!(
+            CompactionStrategy::parse("custom:my_plugin").unwrap(),
+            CompactionStrategy::Custom("my_plugin".to_string())
+        );
+        assert!(CompactionStrategy::parse("structed").is_err());
+        assert!(CompactionStrategy::parse("custom:").is_err());
+    }
+
+    #[test]
+    fn test_hook_messages_rejects_malformed_entries() {
+        assert!(hook_messages(&json!(["hi"])).is_none());
+        assert!(hook_messages(&json!([{ "content": "no role" }])).is_none());
+        assert!(hook_messages(&json!([{ "role": 1 }])).is_none());
+        assert!(hook_messages(&json!("messages")).is_none());
+
+        let kept = hook_messages(&json!([
+            { "role": "user", "content": "a", "_id": "keep" },
+            { "role": "assistant", "content": "b" },
+        ]))
+        .unwrap();
+        assert_eq!(kept[0]["_id"], "keep");
+        assert!(kept[1]["_id"].as_str().is_some());
+    }
+
+    #[test]
+    fn test_keep_last_turns_and_elision_respect_turn_boundaries() {
+        let big = "x".repeat(2000);
+        let messages = vec![
+            msg("system", "prompt"),
+            msg("user", "first"),
+            call("src/a.rs"),
+            msg("tool", &big),
+            msg("user", "second"),
+            call("src/b.rs"),
+            msg("tool", &big),
+            msg("assistant", "done"),
+        ];
+
+        let kept = keep_last_turns(&messages, 1);
+        assert_eq!(kept.len(), 5);
+        assert_eq!(kept[0]["role"], "system");
+        assert_eq!(kept[1]["content"], "second");
+        assert_eq!(keep_last_turns(&messages, 2), messages);
+        assert_eq!(keep_last_turns(&messages, 0).len(), 1);
+
+        let mut elided = messages.clone();
+        assert_eq!(elide_tool_results(&mut elided, 1), 1);
+        let marker = elided[3]["content"].as_str().unwrap();
+        assert!(marker.starts_with(ELIDED_MARKER) && marker.contains("2000 chars"));
+        assert_eq!(elided[6]["content"], big.as_str());
+        assert!(elided[2]["tool_calls"].is_array());
+        // Already elided outputs are left alone.
+        assert_eq!(elide_tool_results(&mut elided, 1), 0);
+    }
+
+    #[test]
+    fn test_elision_counts_characters_not_bytes() {
+        let short = "é".repeat(400);
+        let long = "é".repeat(1000);
+        let mut messages = vec![
+            msg("user", "first"),
+            msg("tool", &short),
+            msg("tool", &long),
+            msg("user", "second"),
+        ];
+        assert_eq!(elide_tool_results(&mut messages, 1), 1);
+        assert_eq!(messages[1]["content"], short.as_str());
+        let marker = messages[2]["content"].as_str().unwrap();
+        assert!(marker.contains("1000 chars"));
+        assert!(marker.contains(&"é".repeat(ELIDE_PREVIEW_CHARS)));
+    }
+
+    #[test]
+    fn test_record_keeps_files_and_resolves_questions() {
+        let archived = vec![msg("user", "fix it"), call("src/lib.rs"), call("docs/a.md")];
+        let files = touched_files(&archived);
+        assert_eq!(files, vec!["src/lib.rs", "docs/a.md"]);
+
+        let mut record = CompactionRecord {
+            narrative: "old".to_string(),
+            decisions: vec!["use sqlite".to_string()],
+            open_questions: vec!["which port?".to_string()],
+            touched_files: vec!["src/lib.rs".to_string()],
+        };
+        let reply = r#"Here you go: {"narrative": "new", "decisions": ["use sqlite", "keep wal"],
+            "open_questions": ["who reviews?"], "resolved_questions": ["which port?"],
+            "touched_files": ["Cargo.toml"]}"#;
+        record.apply(parse_update(reply), &files);
+        assert_eq!(record.narrative, "new");
+        assert_eq!(record.decisions, vec!["use sqlite", "keep wal"]);
+        assert_eq!(record.open_questions, vec!["who reviews?"]);
+        assert_eq!(
+            record.touched_files,
+            vec!["src/lib.rs", "Cargo.toml", "docs/a.md"]
+        );
+        let rendered = record.render();
+        assert!(rendered.starts_with("new\n\n## Decisions\n\n- use sqlite"));
+        assert!(rendered.ends_with("## Touched files\n\n- src/lib.rs\n- Cargo.toml\n- docs/a.md"));
+
+        // Prose replies become the narrative and keep the lists.
+        record.apply(parse_update("just prose"), &[]);
+        assert_eq!(record.narrative, "just prose");
+        assert_eq!(record.decisions.len(), 2);
+    }
+}
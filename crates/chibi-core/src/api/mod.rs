@@ -4,13 +4,19 @@
 //! decoupled from presentation concerns through the `ResponseSink` trait.
 
 pub mod compact;
+pub mod compact_strategy;
 pub mod logging;
+pub mod progress;
+pub mod replay;
 pub mod send;
 pub mod sink;
+pub mod structured;
 
 pub use compact::{
     compact_context_by_name, compact_context_with_llm, compact_context_with_llm_manual,
     rolling_compact,
 };
+pub use compact_strategy::{CompactionRecord, CompactionStrategy};
+pub use replay::{Cassette, CassetteRecord, ReplayOptions, ReplayReport, run_replay};
 pub use send::{PromptOptions, send_prompt};
 pub use sink::{CollectingSink, ResponseEvent, ResponseSink};
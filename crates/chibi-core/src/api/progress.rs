@@ -0,0 +1,657 @@
+//! Agentic loop progress monitoring.
+//!
+//! A `ProgressMonitor` watches every tool call in a user-message turn and
+//! reports when the agent stops making progress. It runs a set of detectors:
+//!
+//! - `repeat`: the same call returns the same result back to back
+//! - `cycle`: the recent calls repeat a sequence of two or more calls
+//!   (A→B→A→B) three times within `progress_window`
+//! - `error_streak`: one tool fails `progress_error_streak` times in a row,
+//!   whatever its arguments
+//! - `stall`: `progress_stall_calls` calls in a row without a successful
+//!   file write
+//!
+//! What happens next is decided by `progress_action` (`nudge`, `fuel` or
+//! `call_user`) and can be overridden per signal by `on_no_progress` hooks.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use crate::config::ResolvedConfig;
+use crate::error::ChibiError;
+
+/// Number of back-to-back repetitions of a sequence that counts as a cycle.
+const CYCLE_REPEATS: usize = 3;
+/// Shortest sequence considered a cycle (period 1 is the `repeat` detector).
+const MIN_CYCLE_PERIOD: usize = 2;
+
+/// How the agentic loop responds to a progress signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressAction {
+    /// Inject a warning into the conversation
+    Nudge,
+    /// Inject a warning and charge `fuel_empty_response_cost`
+    Fuel,
+    /// End the turn and hand control back to the user
+    CallUser,
+}
+
+impl ProgressAction {
+    /// Parse a `progress_action` config value (or hook override).
+    pub fn parse(s: &str) -> io::Result<Self> {
+        match s {
+            "nudge" => Ok(Self::Nudge),
+            "fuel" => Ok(Self::Fuel),
+            "call_user" => Ok(Self::CallUser),
+            other => Err(ChibiError::ConfigInvalid {
+                message: format!(
+                    "Unknown progress_action '{}' (expected nudge, fuel or call_user)",
+                    other
+                ),
+            }
+            .into()),
+        }
+    }
+
+    /// Config/hook spelling of this action.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nudge => "nudge",
+            Self::Fuel => "fuel",
+            Self::CallUser => "call_user",
+        }
+    }
+}
+
+/// Which detector raised a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    Repeat,
+    Cycle,
+    ErrorStreak,
+    Stall,
+}
+
+impl ProgressKind {
+    /// String key used in hook payloads and diagnostics.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Repeat => "repeat",
+            Self::Cycle => "cycle",
+            Self::ErrorStreak => "error_streak",
+            Self::Stall => "stall",
+        }
+    }
+}
+
+/// One tool call as seen by the detectors.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolObservation<'a> {
+    pub tool: &'a str,
+    pub args: &'a str,
+    /// Result as returned to the LLM (cache stub when cached).
+    pub result: &'a str,
+    pub was_cached: bool,
+    /// The tool reported an error.
+    pub failed: bool,
+    /// The call changed something (a successful file write).
+    pub progressed: bool,
+}
+
+/// A detector's report that the loop is not making progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgressSignal {
+    pub kind: ProgressKind,
+    /// Tool whose call raised the signal.
+    pub tool: String,
+    /// Repetitions, failures or calls, depending on `kind`.
+    pub count: usize,
+    /// Human-readable description of what was detected.
+    pub detail: String,
+}
+
+impl ProgressSignal {
+    /// Warning injected into the conversation for `nudge` and `fuel`.
+    pub fn nudge_message(&self) -> String {
+        let advice = match self.kind {
+            ProgressKind::Repeat | ProgressKind::Cycle => {
+                "This is not making progress. Try a different approach, use a different tool, \
+                 or ask the user for help."
+            }
+            ProgressKind::ErrorStreak => {
+                "Stop retrying the same operation. Re-read the relevant input, check the error \
+                 message carefully, or ask the user for help."
+            }
+            ProgressKind::Stall => {
+                "Decide what you have learned, then act on it or answer. Ask the user if you \
+                 are missing information."
+            }
+        };
+        format!("[{}] {} {}", self.label(), self.detail, advice)
+    }
+
+    /// Message shown to the user when the turn is ended with `call_user`.
+    pub fn call_user_message(&self) -> String {
+        format!(
+            "[{}] {} Stopping and returning control to the user.",
+            self.label(),
+            self.detail
+        )
+    }
+
+    fn label(&self) -> &'static str {
+        match self.kind {
+            ProgressKind::Repeat => "Loop detected",
+            ProgressKind::Cycle => "Cycle detected",
+            ProgressKind::ErrorStreak => "Repeated errors",
+            ProgressKind::Stall => "No progress",
+        }
+    }
+}
+
+/// A single progress heuristic. Detectors see every tool call in order.
+pub trait ProgressDetector: Send {
+    fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal>;
+}
+
+/// Detects when the same tool call produces the same result consecutively.
+pub struct LoopDetector {
+    last_tool_name: String,
+    last_args: String,
+    last_result: String,
+    /// Whether the last recorded call had a cached result.
+    last_was_cached: bool,
+    /// How many times this call has repeated consecutively.
+    /// Starts at 0 (no previous call). Becomes 1 on first call, 2 on first repeat, etc.
+    pub consecutive_count: u32,
+}
+
+impl LoopDetector {
+    pub fn new() -> Self {
+        Self {
+            last_tool_name: String::new(),
+            last_args: String::new(),
+            last_result: String::new(),
+            last_was_cached: false,
+            consecutive_count: 0,
+        }
+    }
+
+    /// Record a tool call result. Returns `true` if this is a duplicate (loop detected).
+    ///
+    /// When both the current and previous calls were cached, compares `(tool_name, args)`
+    /// only — cached stubs contain timestamp-based VFS URIs that differ every time,
+    /// defeating result-based comparison (#207).
+    pub fn check_and_update(
+        &mut self,
+        tool_name: &str,
+        args: &str,
+        result: &str,
+        was_cached: bool,
+    ) -> bool {
+        let is_repeat = tool_name == self.last_tool_name
+            && args == self.last_args
+            && if was_cached && self.last_was_cached {
+                true // both cached: (tool, args) match is sufficient
+            } else {
+                result == self.last_result
+            };
+
+        if is_repeat {
+            self.consecutive_count += 1;
+            true
+        } else {
+            self.last_tool_name = tool_name.to_string();
+            self.last_args = args.to_string();
+            self.last_result = result.to_string();
+            self.last_was_cached = was_cached;
+            self.consecutive_count = 1;
+            false
+        }
+    }
+}
+
+impl Default for LoopDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressDetector for LoopDetector {
+    fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal> {
+        if !self.check_and_update(call.tool, call.args, call.result, call.was_cached) {
+            return None;
+        }
+        Some(ProgressSignal {
+            kind: ProgressKind::Repeat,
+            tool: call.tool.to_string(),
+            count: self.consecutive_count as usize,
+            detail: format!(
+                "You have called {}({}) {} time(s) in a row and received the same result.",
+                call.tool, call.args, self.consecutive_count
+            ),
+        })
+    }
+}
+
+/// Detects a sequence of calls repeating back to back (A→B→A→B→A→B).
+///
+/// Compares `(tool, args)` only: oscillating agents often see slightly
+/// different results each time (timestamps, cache stubs).
+pub struct CycleDetector {
+    window: VecDeque<(String, String)>,
+    size: usize,
+}
+
+impl CycleDetector {
+    pub fn new(size: usize) -> Self {
+        Self {
+            window: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    /// Shortest period (≥ `MIN_CYCLE_PERIOD`) that the tail of the window
+    /// repeats `CYCLE_REPEATS` times, ignoring runs of a single call.
+    fn period(&self) -> Option<usize> {
+        let len = self.window.len();
+        (MIN_CYCLE_PERIOD..=len / CYCLE_REPEATS).find(|&p| {
+            let start = len - p * CYCLE_REPEATS;
+            let periodic = (start + p..len).all(|i| self.window[i] == self.window[i - p]);
+            let varied = (start + 1..start + p).any(|i| self.window[i] != self.window[start]);
+            periodic && varied
+        })
+    }
+}
+
+impl ProgressDetector for CycleDetector {
+    fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal> {
+        if self.size < MIN_CYCLE_PERIOD * CYCLE_REPEATS {
+            return None;
+        }
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window
+            .push_back((call.tool.to_string(), call.args.to_string()));
+
+        let period = self.period()?;
+        let start = self.window.len() - period;
+        let sequence: Vec<&str> = self
+            .window
+            .range(start..)
+            .map(|(tool, _)| tool.as_str())
+            .collect();
+        // Start afresh so the same cycle is reported once per full recurrence.
+        self.window.clear();
+        Some(ProgressSignal {
+            kind: ProgressKind::Cycle,
+            tool: call.tool.to_string(),
+            count: CYCLE_REPEATS,
+            detail: format!(
+                "You have repeated the same {} calls ({}) {} times in a row.",
+                period,
+                sequence.join(" → "),
+                CYCLE_REPEATS
+            ),
+        })
+    }
+}
+
+/// Detects one tool failing repeatedly, even with different arguments.
+pub struct ErrorStreakDetector {
+    streaks: HashMap<String, usize>,
+    limit: usize,
+}
+
+impl ErrorStreakDetector {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            streaks: HashMap::new(),
+            limit,
+        }
+    }
+}
+
+impl ProgressDetector for ErrorStreakDetector {
+    fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal> {
+        if self.limit == 0 {
+            return None;
+        }
+        if !call.failed {
+            self.streaks.remove(call.tool);
+            return None;
+        }
+        let streak = self.streaks.entry(call.tool.to_string()).or_insert(0);
+        *streak += 1;
+        if *streak % self.limit != 0 {
+            return None;
+        }
+        Some(ProgressSignal {
+            kind: ProgressKind::ErrorStreak,
+            tool: call.tool.to_string(),
+            count: *streak,
+            detail: format!("{} has failed {} times in a row.", call.tool, streak),
+        })
+    }
+}
+
+/// Detects long runs of calls that change nothing.
+pub struct StallDetector {
+    since_progress: usize,
+    limit: usize,
+}
+
+impl StallDetector {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            since_progress: 0,
+            limit,
+        }
+    }
+}
+
+impl ProgressDetector for StallDetector {
+    fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal> {
+        if self.limit == 0 {
+            return None;
+        }
+        if call.progressed {
+            self.since_progress = 0;
+            return None;
+        }
+        self.since_progress += 1;
+        if self.since_progress < self.limit {
+            return None;
+        }
+        self.since_progress = 0;
+        Some(ProgressSignal {
+            kind: ProgressKind::Stall,
+            tool: call.tool.to_string(),
+            count: self.limit,
+            detail: format!(
+                "You have made {} tool calls without writing anything or answering.",
+                self.limit
+            ),
+        })
+    }
+}
+
+/// Runs every detector over each tool call in a user-message turn.
+pub struct ProgressMonitor {
+    detectors: Vec<Box<dyn ProgressDetector>>,
+    /// Configured response to signals (hooks may override it per signal).
+    pub action: ProgressAction,
+}
+
+impl ProgressMonitor {
+    /// Build the default detector set from `progress_*` config.
+    pub fn from_config(config: &ResolvedConfig) -> io::Result<Self> {
+        Ok(Self {
+            detectors: vec![
+                Box::new(LoopDetector::new()),
+                Box::new(CycleDetector::new(config.progress_window)),
+                Box::new(ErrorStreakDetector::new(config.progress_error_streak)),
+                Box::new(StallDetector::new(config.progress_stall_calls)),
+            ],
+            action: ProgressAction::parse(&config.progress_action)?,
+        })
+    }
+
+    /// Add a detector after the built-in ones.
+    pub fn with_detector(mut self, detector: Box<dyn ProgressDetector>) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Feed a tool call to every detector. Returns the first signal raised;
+    /// every detector still sees the call.
+    pub fn observe(&mut self, call: &ToolObservation<'_>) -> Option<ProgressSignal> {
+        let mut first = None;
+        for detector in &mut self.detectors {
+            if let Some(signal) = detector.observe(call) {
+                first.get_or_insert(signal);
+            }
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    fn call<'a>(tool: &'a str, args: &'a str, result: &'a str) -> ToolObservation<'a> {
+        ToolObservation {
+            tool,
+            args,
+            result,
+            was_cached: false,
+            failed: result.starts_with("Error"),
+            progressed: false,
+        }
+    }
+
+    #[test]
+    fn test_no_loop_on_first_call() {
+        let mut d = LoopDetector::new();
+        assert!(!d.check_and_update("grep", r#"{"path":"x"}"#, "result", false));
+    }
+
+    #[test]
+    fn test_no_loop_on_different_args() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", r#"{"path":"a"}"#, "result", false);
+        assert!(!d.check_and_update("grep", r#"{"path":"b"}"#, "result", false));
+    }
+
+    #[test]
+    fn test_no_loop_on_different_result() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", r#"{"path":"a"}"#, "result1", false);
+        assert!(!d.check_and_update("grep", r#"{"path":"a"}"#, "result2", false));
+    }
+
+    #[test]
+    fn test_loop_detected_on_second_identical_call() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", r#"{"path":"a"}"#, "result", false);
+        assert!(d.check_and_update("grep", r#"{"path":"a"}"#, "result", false));
+    }
+
+    #[test]
+    fn test_loop_count_increments() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", "args", "res", false);
+        d.check_and_update("grep", "args", "res", false); // count=2, loop
+        d.check_and_update("grep", "args", "res", false); // count=3, loop
+        assert_eq!(d.consecutive_count, 3);
+    }
+
+    #[test]
+    fn test_loop_resets_on_different_call() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", "args", "res", false);
+        d.check_and_update("grep", "args", "res", false); // loop detected
+        d.check_and_update("ls", "{}", "files", false); // resets
+        assert_eq!(d.consecutive_count, 1);
+        assert!(!d.check_and_update("ls", "{}", "files2", false)); // different result → no loop
+    }
+
+    #[test]
+    fn test_cached_loop_detected_despite_different_stubs() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("web_fetch", r#"{"url":"x"}"#, "stub_ts1", true);
+        assert!(d.check_and_update("web_fetch", r#"{"url":"x"}"#, "stub_ts2", true));
+    }
+
+    #[test]
+    fn test_cached_then_uncached_no_loop() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", "args", "stub_cached", true);
+        assert!(!d.check_and_update("grep", "args", "real_result", false));
+    }
+
+    #[test]
+    fn test_uncached_then_cached_no_loop() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("grep", "args", "real_result", false);
+        assert!(!d.check_and_update("grep", "args", "stub_cached", true));
+    }
+
+    #[test]
+    fn test_cached_loop_count_increments() {
+        let mut d = LoopDetector::new();
+        d.check_and_update("fetch", "args", "stub1", true);
+        d.check_and_update("fetch", "args", "stub2", true);
+        d.check_and_update("fetch", "args", "stub3", true);
+        assert_eq!(d.consecutive_count, 3);
+    }
+
+    #[test]
+    fn test_cycle_detected_after_three_repeats() {
+        let mut d = CycleDetector::new(12);
+        let seq = [("read", "a"), ("edit", "b")];
+        let mut signals = Vec::new();
+        for i in 0..6 {
+            let (tool, args) = seq[i % 2];
+            signals.push(d.observe(&call(tool, args, &format!("r{}", i))));
+        }
+        assert!(signals[..5].iter().all(Option::is_none));
+        let signal = signals[5].clone().unwrap();
+        assert_eq!(signal.kind, ProgressKind::Cycle);
+        assert!(signal.detail.contains("read → edit"), "{}", signal.detail);
+    }
+
+    #[test]
+    fn test_cycle_of_three_calls() {
+        let mut d = CycleDetector::new(12);
+        let seq = ["a", "b", "c"];
+        let fired: Vec<bool> = (0..9)
+            .map(|i| d.observe(&call(seq[i % 3], "{}", "r")).is_some())
+            .collect();
+        assert_eq!(fired.iter().filter(|f| **f).count(), 1);
+        assert!(fired[8]);
+    }
+
+    #[test]
+    fn test_cycle_ignores_single_call_runs() {
+        let mut d = CycleDetector::new(12);
+        for i in 0..12 {
+            assert!(d.observe(&call("poll", "{}", &i.to_string())).is_none());
+        }
+    }
+
+    #[test]
+    fn test_cycle_needs_matching_args() {
+        let mut d = CycleDetector::new(12);
+        for i in 0..12 {
+            let tool = if i % 2 == 0 { "read" } else { "edit" };
+            assert!(d.observe(&call(tool, &i.to_string(), "r")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_cycle_disabled_by_small_window() {
+        let mut d = CycleDetector::new(0);
+        for i in 0..12 {
+            let tool = if i % 2 == 0 { "read" } else { "edit" };
+            assert!(d.observe(&call(tool, "{}", "r")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_error_streak_per_tool() {
+        let mut d = ErrorStreakDetector::new(3);
+        assert!(
+            d.observe(&call("file_edit", "1", "Error: no match"))
+                .is_none()
+        );
+        assert!(
+            d.observe(&call("file_edit", "2", "Error: no match"))
+                .is_none()
+        );
+        // A different tool failing does not break the streak
+        assert!(d.observe(&call("shell_exec", "x", "Error: boom")).is_none());
+        let signal = d
+            .observe(&call("file_edit", "3", "Error: no match"))
+            .unwrap();
+        assert_eq!(signal.kind, ProgressKind::ErrorStreak);
+        assert_eq!(signal.count, 3);
+    }
+
+    #[test]
+    fn test_error_streak_resets_on_success() {
+        let mut d = ErrorStreakDetector::new(2);
+        assert!(
+            d.observe(&call("file_edit", "1", "Error: no match"))
+                .is_none()
+        );
+        assert!(d.observe(&call("file_edit", "2", "ok")).is_none());
+        assert!(
+            d.observe(&call("file_edit", "3", "Error: no match"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_stall_fires_and_resets() {
+        let mut d = StallDetector::new(3);
+        assert!(d.observe(&call("read", "1", "r")).is_none());
+        assert!(d.observe(&call("read", "2", "r")).is_none());
+        assert_eq!(
+            d.observe(&call("read", "3", "r")).unwrap().kind,
+            ProgressKind::Stall
+        );
+        assert!(d.observe(&call("read", "4", "r")).is_none());
+    }
+
+    #[test]
+    fn test_stall_reset_by_write() {
+        let mut d = StallDetector::new(2);
+        assert!(d.observe(&call("read", "1", "r")).is_none());
+        let mut write = call("write_file", "2", "ok");
+        write.progressed = true;
+        assert!(d.observe(&write).is_none());
+        assert!(d.observe(&call("read", "3", "r")).is_none());
+    }
+
+    #[test]
+    fn test_stall_disabled_with_zero() {
+        let mut d = StallDetector::new(0);
+        for i in 0..50 {
+            assert!(d.observe(&call("read", &i.to_string(), "r")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_monitor_repeat_message_matches_loop_warning() {
+        let mut m = ProgressMonitor {
+            detectors: vec![Box::new(LoopDetector::new())],
+            action: ProgressAction::Fuel,
+        };
+        assert!(m.observe(&call("grep", "{}", "same")).is_none());
+        let signal = m.observe(&call("grep", "{}", "same")).unwrap();
+        assert!(
+            signal
+                .nudge_message()
+                .starts_with("[Loop detected] You have called grep({}) 2 time(s) in a row")
+        );
+    }
+
+    #[test]
+    fn test_progress_action_parse() {
+        assert_eq!(
+            ProgressAction::parse("nudge").unwrap(),
+            ProgressAction::Nudge
+        );
+        assert_eq!(ProgressAction::parse("fuel").unwrap(), ProgressAction::Fuel);
+        assert_eq!(
+            ProgressAction::parse("call_user").unwrap(),
+            ProgressAction::CallUser
+        );
+        let err = ProgressAction::parse("panic").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
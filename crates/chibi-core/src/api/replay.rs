@@ -0,0 +1,684 @@
+//! Record/replay of agentic sessions for regression checks.
+//!
+//! A [`Cassette`] sits between `send_prompt` and the model gateway. In record
+//! mode it appends every prompt, model response and tool result to a JSONL
+//! file. In replay mode it serves recorded model responses instead of calling
+//! the network (and, optionally, recorded tool results instead of running
+//! tools), while still recording what actually happened.
+//!
+//! [`run_replay`] re-runs a recorded session's prompts in scratch contexts —
+//! against the recorded responses, or live against a different model — and
+//! diffs tool-call sequences, tool results and final answers per prompt. The
+//! scratch contexts copy the source context's current system prompt and
+//! `local.toml`, so prompt and hook changes are exercised.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::send::PromptOptions;
+use super::sink::ResponseSink;
+use crate::Chibi;
+use crate::context::{Context, now_timestamp};
+use crate::jsonl::read_jsonl_file;
+use crate::state::StatePaths;
+
+/// A tool call as requested by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+/// One line of a cassette file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CassetteRecord {
+    /// A user prompt starting a `send_prompt` run.
+    Prompt {
+        context: String,
+        prompt: String,
+        model: String,
+        timestamp: u64,
+    },
+    /// A complete model response (text and/or tool calls).
+    Response {
+        text: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<RecordedToolCall>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<serde_json::Value>,
+    },
+    /// The result of executing one (non flow-control) tool call.
+    ToolResult {
+        tool: String,
+        arguments: String,
+        result: String,
+    },
+}
+
+/// Recorded responses and tool results for the prompt being replayed.
+#[derive(Debug, Default)]
+struct Segment {
+    responses: VecDeque<CassetteRecord>,
+    tool_results: HashMap<(String, String), VecDeque<String>>,
+}
+
+#[derive(Debug)]
+struct Playback {
+    segments: VecDeque<Segment>,
+    current: Segment,
+    /// Serve recorded model responses (false when a live model stands in).
+    replay_responses: bool,
+    replay_tools: bool,
+}
+
+/// Records a session and optionally plays one back. See the module docs.
+#[derive(Debug)]
+pub struct Cassette {
+    path: Option<PathBuf>,
+    records: Mutex<Vec<CassetteRecord>>,
+    playback: Option<Mutex<Playback>>,
+}
+
+impl Cassette {
+    /// Record mode: append records to `path` (created if missing).
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            records: Mutex::new(Vec::new()),
+            playback: None,
+        }
+    }
+
+    /// Capture mode: record in memory only, calling the live gateway.
+    pub fn capture() -> Self {
+        Self {
+            path: None,
+            records: Mutex::new(Vec::new()),
+            playback: None,
+        }
+    }
+
+    /// Replay mode: serve model responses from `recorded`, and tool results
+    /// too when `replay_tools` is set. Actual records are kept in memory.
+    pub fn replay(recorded: &[CassetteRecord], replay_tools: bool) -> Self {
+        let segments = split_prompts(recorded)
+            .into_iter()
+            .map(|(_, records)| {
+                let mut segment = Segment::default();
+                for record in records {
+                    match record {
+                        CassetteRecord::ToolResult {
+                            tool,
+                            arguments,
+                            result,
+                        } => segment
+                            .tool_results
+                            .entry((tool.clone(), arguments.clone()))
+                            .or_default()
+                            .push_back(result.clone()),
+                        CassetteRecord::Response { .. } => {
+                            segment.responses.push_back(record.clone())
+                        }
+                        CassetteRecord::Prompt { .. } => {}
+                    }
+                }
+                segment
+            })
+            .collect();
+        Self {
+            path: None,
+            records: Mutex::new(Vec::new()),
+            playback: Some(Mutex::new(Playback {
+                segments,
+                current: Segment::default(),
+                replay_responses: true,
+                replay_tools,
+            })),
+        }
+    }
+
+    /// Call the live gateway for model responses, keeping any recorded tool
+    /// results. Tool calls the recording lacks run for real.
+    pub fn with_live_model(self) -> Self {
+        if let Some(playback) = &self.playback {
+            playback.lock().unwrap().replay_responses = false;
+        }
+        self
+    }
+
+    /// Load a cassette file.
+    pub fn load(path: &Path) -> io::Result<Vec<CassetteRecord>> {
+        if !path.exists() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("cassette not found: {}", path.display()),
+            ));
+        }
+        read_jsonl_file(path)
+    }
+
+    /// Whether model responses come from the cassette rather than the gateway.
+    pub fn is_replaying(&self) -> bool {
+        self.playback
+            .as_ref()
+            .is_some_and(|p| p.lock().unwrap().replay_responses)
+    }
+
+    /// Everything recorded so far, in order.
+    pub fn records(&self) -> Vec<CassetteRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Append a record, also writing it to the cassette file in record mode.
+    pub fn push(&self, record: CassetteRecord) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        self.records.lock().unwrap().push(record);
+        Ok(())
+    }
+
+    /// Record the start of a prompt and, in replay mode, move to its segment.
+    pub fn begin_prompt(&self, context: &str, prompt: &str, model: &str) -> io::Result<()> {
+        if let Some(playback) = &self.playback {
+            let mut playback = playback.lock().unwrap();
+            playback.current = playback.segments.pop_front().ok_or_else(|| {
+                io::Error::new(ErrorKind::UnexpectedEof, "cassette has no more prompts")
+            })?;
+        }
+        self.push(CassetteRecord::Prompt {
+            context: context.to_string(),
+            prompt: prompt.to_string(),
+            model: model.to_string(),
+            timestamp: now_timestamp(),
+        })
+    }
+
+    /// Next recorded model response for the current prompt.
+    ///
+    /// Returns `Ok(None)` when not replaying. Errors when the run asks for
+    /// more responses than were recorded — the workflow has diverged.
+    pub fn next_response(&self) -> io::Result<Option<CassetteRecord>> {
+        let Some(playback) = &self.playback else {
+            return Ok(None);
+        };
+        let mut playback = playback.lock().unwrap();
+        if !playback.replay_responses {
+            return Ok(None);
+        }
+        playback
+            .current
+            .responses
+            .pop_front()
+            .map(Some)
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "run requested more model responses than the cassette recorded",
+                )
+            })
+    }
+
+    /// Recorded result for a tool call, when replaying tool results.
+    pub fn recorded_tool_result(&self, tool: &str, arguments: &str) -> Option<String> {
+        let mut playback = self.playback.as_ref()?.lock().unwrap();
+        if !playback.replay_tools {
+            return None;
+        }
+        playback
+            .current
+            .tool_results
+            .get_mut(&(tool.to_string(), arguments.to_string()))?
+            .pop_front()
+    }
+}
+
+/// Split records into `(prompt record, following records)` groups.
+/// Records before the first prompt are ignored.
+fn split_prompts(records: &[CassetteRecord]) -> Vec<(&CassetteRecord, Vec<&CassetteRecord>)> {
+    let mut out: Vec<(&CassetteRecord, Vec<&CassetteRecord>)> = Vec::new();
+    for record in records {
+        match record {
+            CassetteRecord::Prompt { .. } => out.push((record, Vec::new())),
+            _ => {
+                if let Some((_, rest)) = out.last_mut() {
+                    rest.push(record);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Observable behaviour of one prompt, extracted from its records.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PromptOutcome {
+    /// `name arguments` for each tool call, in order.
+    pub tool_calls: Vec<String>,
+    /// `name: result` for each executed tool, in order.
+    pub tool_results: Vec<String>,
+    /// Text of the last non-empty model response.
+    pub final_answer: String,
+}
+
+impl PromptOutcome {
+    fn from_records(records: &[&CassetteRecord]) -> Self {
+        let mut outcome = PromptOutcome::default();
+        for record in records {
+            match record {
+                CassetteRecord::Response {
+                    text, tool_calls, ..
+                } => {
+                    outcome.tool_calls.extend(
+                        tool_calls
+                            .iter()
+                            .map(|tc| format!("{} {}", tc.name, tc.arguments)),
+                    );
+                    if !text.trim().is_empty() {
+                        outcome.final_answer = text.clone();
+                    }
+                }
+                CassetteRecord::ToolResult { tool, result, .. } => {
+                    outcome.tool_results.push(format!("{}: {}", tool, result));
+                }
+                CassetteRecord::Prompt { .. } => {}
+            }
+        }
+        outcome
+    }
+}
+
+/// Expected vs actual behaviour for one replayed prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptDiff {
+    pub context: String,
+    pub prompt: String,
+    pub expected: PromptOutcome,
+    pub actual: PromptOutcome,
+    /// Set when the replayed run failed (e.g. it outran the recording).
+    pub error: Option<String>,
+}
+
+impl PromptDiff {
+    pub fn matches(&self) -> bool {
+        self.error.is_none() && self.expected == self.actual
+    }
+}
+
+/// Result of [`run_replay`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub prompts: Vec<PromptDiff>,
+}
+
+impl ReplayReport {
+    pub fn diverged(&self) -> usize {
+        self.prompts.iter().filter(|p| !p.matches()).count()
+    }
+
+    /// Human-readable report: one status line per prompt, with `-`/`+`
+    /// lines for expected/actual values that differ.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (i, diff) in self.prompts.iter().enumerate() {
+            let status = if diff.matches() { "ok" } else { "DIVERGED" };
+            out.push_str(&format!(
+                "prompt {} [{}] {}: {}\n",
+                i + 1,
+                diff.context,
+                status,
+                first_line(&diff.prompt)
+            ));
+            if let Some(error) = &diff.error {
+                out.push_str(&format!("  error: {}\n", error));
+            }
+            diff_lists(
+                &mut out,
+                "tool calls",
+                &diff.expected.tool_calls,
+                &diff.actual.tool_calls,
+            );
+            diff_lists(
+                &mut out,
+                "tool results",
+                &diff.expected.tool_results,
+                &diff.actual.tool_results,
+            );
+            diff_lists(
+                &mut out,
+                "final answer",
+                &[diff.expected.final_answer.clone()],
+                &[diff.actual.final_answer.clone()],
+            );
+        }
+        out.push_str(&format!(
+            "{} of {} prompts diverged\n",
+            self.diverged(),
+            self.prompts.len()
+        ));
+        out
+    }
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("")
+}
+
+fn diff_lists(out: &mut String, label: &str, expected: &[String], actual: &[String]) {
+    if expected == actual {
+        return;
+    }
+    out.push_str(&format!("  {}:\n", label));
+    for line in expected {
+        out.push_str(&format!("    - {}\n", first_line(line)));
+    }
+    for line in actual {
+        out.push_str(&format!("    + {}\n", first_line(line)));
+    }
+}
+
+/// How [`run_replay`] drives the model.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayOptions {
+    /// Run live against this model instead of serving recorded responses.
+    pub model: Option<String>,
+    /// Serve recorded tool results instead of executing tools.
+    pub replay_tools: bool,
+}
+
+/// Re-run a recorded session and diff it against the recording.
+///
+/// Each source context gets a scratch context (`replay-<ctx>-<timestamp>`)
+/// seeded with its current system prompt and `local.toml`; scratch contexts
+/// are destroyed afterwards.
+pub async fn run_replay<S: ResponseSink>(
+    chibi: &mut Chibi,
+    recorded: &[CassetteRecord],
+    options: &ReplayOptions,
+    sink: &mut S,
+) -> io::Result<ReplayReport> {
+    let prompts = split_prompts(recorded);
+    if prompts.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "cassette contains no prompts",
+        ));
+    }
+
+    let cassette = match (&options.model, options.replay_tools) {
+        (None, replay_tools) => Cassette::replay(recorded, replay_tools),
+        (Some(_), true) => Cassette::replay(recorded, true).with_live_model(),
+        (Some(_), false) => Cassette::capture(),
+    };
+
+    let mut scratch: HashMap<String, String> = HashMap::new();
+    let run = replay_prompts(chibi, &prompts, options, &cassette, &mut scratch, sink).await;
+    // Destroy scratch contexts even when the run stopped early.
+    let mut cleanup = Ok(());
+    for name in scratch.values() {
+        if let Err(e) = chibi.app.destroy_context(name) {
+            cleanup = Err(e);
+        }
+    }
+    let errors = run?;
+    cleanup?;
+
+    let actual_records = cassette.records();
+    let actual = split_prompts(&actual_records);
+    let prompts = prompts
+        .iter()
+        .zip(errors)
+        .enumerate()
+        .map(|(i, ((record, expected), error))| {
+            let CassetteRecord::Prompt {
+                context, prompt, ..
+            } = record
+            else {
+                unreachable!("split_prompts groups start with a prompt record");
+            };
+            PromptDiff {
+                context: context.clone(),
+                prompt: prompt.clone(),
+                expected: PromptOutcome::from_records(expected),
+                actual: actual
+                    .get(i)
+                    .map(|(_, records)| PromptOutcome::from_records(records))
+                    .unwrap_or_default(),
+                error,
+            }
+        })
+        .collect();
+    Ok(ReplayReport { prompts })
+}
+
+/// Send each recorded prompt in its scratch context, collecting per-prompt
+/// errors. Scratch contexts are recorded in `scratch` as soon as they are
+/// created, so the caller can clean up after a failure here.
+async fn replay_prompts<S: ResponseSink>(
+    chibi: &mut Chibi,
+    prompts: &[(&CassetteRecord, Vec<&CassetteRecord>)],
+    options: &ReplayOptions,
+    cassette: &Cassette,
+    scratch: &mut HashMap<String, String>,
+    sink: &mut S,
+) -> io::Result<Vec<Option<String>>> {
+    let stamp = now_timestamp();
+    let mut errors = Vec::with_capacity(prompts.len());
+    for (record, _) in prompts {
+        let CassetteRecord::Prompt {
+            context, prompt, ..
+        } = record
+        else {
+            unreachable!("split_prompts groups start with a prompt record");
+        };
+        let scratch_name = match scratch.get(context) {
+            Some(name) => name.clone(),
+            None => {
+                let name = format!("replay-{}-{}", context, stamp);
+                scratch.insert(context.clone(), name.clone());
+                seed_scratch_context(chibi, context, &name)?;
+                name
+            }
+        };
+
+        let mut config = chibi.resolve_config(&scratch_name, None)?;
+        if let Some(model) = &options.model {
+            config.model = model.clone();
+        }
+        crate::gateway::ensure_context_window(&mut config);
+        let prompt_options =
+            PromptOptions::new(config.reflection_enabled, &[], false).with_cassette(cassette);
+        let result = chibi
+            .send_prompt_streaming(&scratch_name, prompt, &config, &prompt_options, sink)
+            .await;
+        errors.push(result.err().map(|e| e.to_string()));
+    }
+    Ok(errors)
+}
+
+/// Create a scratch context carrying `source`'s system prompt and local config.
+fn seed_scratch_context(chibi: &Chibi, source: &str, name: &str) -> io::Result<()> {
+    chibi
+        .app
+        .save_and_register_context(&Context::new(name.to_string()))?;
+    for (from, to) in [
+        (
+            chibi.app.context_prompt_file(source),
+            chibi.app.context_prompt_file(name),
+        ),
+        (
+            chibi.app.local_config_file(source),
+            chibi.app.local_config_file(name),
+        ),
+    ] {
+        if from.exists() {
+            std::fs::copy(&from, &to)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt(p: &str) -> CassetteRecord {
+        CassetteRecord::Prompt {
+            context: "ctx".to_string(),
+            prompt: p.to_string(),
+            model: "m".to_string(),
+            timestamp: 0,
+        }
+    }
+
+    fn response(text: &str, calls: &[(&str, &str)]) -> CassetteRecord {
+        CassetteRecord::Response {
+            text: text.to_string(),
+            tool_calls: calls
+                .iter()
+                .enumerate()
+                .map(|(i, (name, args))| RecordedToolCall {
+                    id: format!("c{i}"),
+                    name: name.to_string(),
+                    arguments: args.to_string(),
+                })
+                .collect(),
+            usage: None,
+        }
+    }
+
+    fn tool_result(tool: &str, args: &str, result: &str) -> CassetteRecord {
+        CassetteRecord::ToolResult {
+            tool: tool.to_string(),
+            arguments: args.to_string(),
+            result: result.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_appends_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let cassette = Cassette::record(&path);
+        cassette.begin_prompt("ctx", "hi", "m").unwrap();
+        cassette.push(response("hello", &[])).unwrap();
+
+        let loaded = Cassette::load(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert!(matches!(loaded[0], CassetteRecord::Prompt { ref prompt, .. } if prompt == "hi"));
+        assert_eq!(loaded[1], response("hello", &[]));
+        assert!(!cassette.is_replaying());
+        assert_eq!(cassette.next_response().unwrap(), None);
+    }
+
+    #[test]
+    fn test_replay_serves_responses_per_prompt() {
+        let recorded = vec![
+            prompt("one"),
+            response("", &[("shell", "{}")]),
+            tool_result("shell", "{}", "out"),
+            response("done", &[]),
+            prompt("two"),
+            response("second", &[]),
+        ];
+        let cassette = Cassette::replay(&recorded, true);
+        cassette.begin_prompt("ctx", "one", "m").unwrap();
+        assert_eq!(
+            cassette.next_response().unwrap(),
+            Some(response("", &[("shell", "{}")]))
+        );
+        assert_eq!(
+            cassette.recorded_tool_result("shell", "{}"),
+            Some("out".to_string())
+        );
+        assert_eq!(cassette.recorded_tool_result("shell", "{}"), None);
+        assert_eq!(
+            cassette.next_response().unwrap(),
+            Some(response("done", &[]))
+        );
+        // Outrunning the recording for a prompt is a divergence.
+        assert_eq!(
+            cassette.next_response().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        cassette.begin_prompt("ctx", "two", "m").unwrap();
+        assert_eq!(
+            cassette.next_response().unwrap(),
+            Some(response("second", &[]))
+        );
+        assert!(cassette.begin_prompt("ctx", "three", "m").is_err());
+    }
+
+    #[test]
+    fn test_replay_without_tools_executes_live() {
+        let recorded = vec![prompt("one"), tool_result("shell", "{}", "out")];
+        let cassette = Cassette::replay(&recorded, false);
+        cassette.begin_prompt("ctx", "one", "m").unwrap();
+        assert_eq!(cassette.recorded_tool_result("shell", "{}"), None);
+    }
+
+    #[test]
+    fn test_live_model_keeps_recorded_tool_results() {
+        let recorded = vec![
+            prompt("one"),
+            response("", &[("shell", "{}")]),
+            tool_result("shell", "{}", "out"),
+        ];
+        let cassette = Cassette::replay(&recorded, true).with_live_model();
+        assert!(!cassette.is_replaying());
+        cassette.begin_prompt("ctx", "one", "m").unwrap();
+        assert_eq!(cassette.next_response().unwrap(), None);
+        assert_eq!(
+            cassette.recorded_tool_result("shell", "{}"),
+            Some("out".to_string())
+        );
+    }
+
+    #[test]
+    fn test_report_renders_divergence() {
+        let expected = PromptOutcome::from_records(&[
+            &response("", &[("shell", "{\"cmd\":\"ls\"}")]),
+            &response("all good", &[]),
+        ]);
+        assert_eq!(expected.tool_calls, vec!["shell {\"cmd\":\"ls\"}"]);
+        assert_eq!(expected.final_answer, "all good");
+
+        let mut actual = expected.clone();
+        actual.final_answer = "something else".to_string();
+        let report = ReplayReport {
+            prompts: vec![
+                PromptDiff {
+                    context: "ctx".to_string(),
+                    prompt: "same".to_string(),
+                    expected: expected.clone(),
+                    actual: expected.clone(),
+                    error: None,
+                },
+                PromptDiff {
+                    context: "ctx".to_string(),
+                    prompt: "changed".to_string(),
+                    expected,
+                    actual,
+                    error: None,
+                },
+            ],
+        };
+        assert_eq!(report.diverged(), 1);
+        let text = report.render();
+        assert!(text.contains("prompt 1 [ctx] ok: same"));
+        assert!(text.contains("prompt 2 [ctx] DIVERGED: changed"));
+        assert!(text.contains("    - all good\n    + something else"));
+        assert!(!text.contains("tool calls:"));
+        assert!(text.ends_with("1 of 2 prompts diverged\n"));
+    }
+}
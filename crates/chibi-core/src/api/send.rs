@@ -6,10 +6,13 @@
 
 use super::compact::compact_context_with_llm;
 use super::logging::{log_request_if_enabled, log_response_meta_if_enabled};
+use super::progress::{ProgressAction, ProgressMonitor, ProgressSignal, ToolObservation};
+use super::replay::{Cassette, CassetteRecord, RecordedToolCall};
 use super::sink::{ResponseEvent, ResponseSink};
 use crate::chibi::PermissionHandler;
 use crate::config::{ResolvedConfig, ToolsConfig};
 use crate::context::{InboxEntry, now_timestamp};
+use crate::error::ChibiError;
 use crate::gateway::{
     build_gateway, json_tool_to_definition, to_chat_options, to_ratatoskr_message,
 };
@@ -17,7 +20,7 @@ use crate::input::DebugKey;
 use crate::json_ext::JsonExt;
 use crate::output::NoopSink;
 use crate::state::{
-    AppState, create_assistant_message_entry, create_control_transfer_entry,
+    AppState, StatePaths, create_assistant_message_entry, create_control_transfer_entry,
     create_flow_control_message_entry, create_tool_call_entry, create_tool_result_entry,
     create_user_message_entry, format_flock_sections, load_flock_contexts,
 };
@@ -30,6 +33,7 @@ use ratatoskr::{ChatEvent, ChatOptions, ModelGateway};
 use serde_json::json;
 use std::io::{self, ErrorKind};
 use std::path::Path;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Options for controlling prompt execution behaviour.
@@ -40,6 +44,11 @@ pub struct PromptOptions<'a> {
     pub force_render: bool,
     /// Optional override for the fallback handoff target.
     pub fallback_override: Option<crate::tools::HandoffTarget>,
+    /// Attachment sources (file paths or image URLs) for the initial prompt.
+    /// Relative paths resolve against the project root.
+    pub attachments: &'a [String],
+    /// Record/replay tap for model responses and tool results.
+    pub cassette: Option<&'a Cassette>,
 }
 
 impl<'a> PromptOptions<'a> {
@@ -49,6 +58,8 @@ impl<'a> PromptOptions<'a> {
             debug,
             force_render,
             fallback_override: None,
+            attachments: &[],
+            cassette: None,
         }
     }
 
@@ -57,11 +68,26 @@ impl<'a> PromptOptions<'a> {
         self.fallback_override = Some(fallback);
         self
     }
+
+    /// Attach images/files to the initial prompt.
+    pub fn with_attachments(mut self, attachments: &'a [String]) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Record (or replay) this run through a cassette.
+    pub fn with_cassette(mut self, cassette: &'a Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
 }
 
 /// Maximum number of simultaneous tool calls allowed (prevents memory exhaustion from malicious responses)
 const MAX_TOOL_CALLS: usize = 100;
 
+/// Upper bound on a single provider retry delay, including `Retry-After` hints.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 // ============================================================================
 // Permission Checking
 // ============================================================================
@@ -557,6 +583,58 @@ struct StreamingResponse {
     response_meta: Option<serde_json::Value>,
 }
 
+impl StreamingResponse {
+    fn to_record(&self) -> CassetteRecord {
+        CassetteRecord::Response {
+            text: self.full_response.clone(),
+            tool_calls: self
+                .tool_calls
+                .iter()
+                .map(|tc| RecordedToolCall {
+                    id: tc.id.clone(),
+                    name: tc.name.clone(),
+                    arguments: tc.arguments.clone(),
+                })
+                .collect(),
+            usage: self.response_meta.clone(),
+        }
+    }
+}
+
+/// Serve a recorded response in place of the gateway, streaming its text to
+/// the sink as a single chunk.
+fn replay_streaming_response<S: ResponseSink>(
+    recorded: CassetteRecord,
+    sink: &mut S,
+) -> io::Result<StreamingResponse> {
+    let CassetteRecord::Response {
+        text,
+        tool_calls,
+        usage,
+    } = recorded
+    else {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "cassette record is not a model response",
+        ));
+    };
+    if !text.is_empty() {
+        sink.handle(ResponseEvent::TextChunk(&text))?;
+    }
+    Ok(StreamingResponse {
+        full_response: text,
+        tool_calls: tool_calls
+            .into_iter()
+            .map(|tc| ratatoskr::ToolCall {
+                id: tc.id,
+                name: tc.name,
+                arguments: tc.arguments,
+            })
+            .collect(),
+        response_meta: usage,
+    })
+}
+
 /// Collect a streaming response from the LLM API via ratatoskr.
 ///
 /// Handles: SSE parsing, content accumulation, tool call reconstruction.
@@ -594,7 +672,7 @@ async fn collect_streaming_response<S: ResponseSink>(
     let mut stream = gateway
         .chat_stream(&ratatoskr_messages, tools_opt, options)
         .await
-        .map_err(|e| io::Error::other(format!("Gateway error: {}", e)))?;
+        .map_err(|e| ChibiError::provider(format!("Gateway error: {}", e)))?;
 
     let mut full_response = String::new();
     let mut tool_calls: Vec<ratatoskr::ToolCall> = Vec::new();
@@ -602,7 +680,8 @@ async fn collect_streaming_response<S: ResponseSink>(
     let mut is_first_content = true;
 
     while let Some(event_result) = stream.next().await {
-        let event = event_result.map_err(|e| io::Error::other(format!("Stream error: {}", e)))?;
+        let event =
+            event_result.map_err(|e| ChibiError::provider(format!("Stream error: {}", e)))?;
 
         match event {
             ChatEvent::Content(chunk) => {
@@ -675,6 +754,122 @@ async fn collect_streaming_response<S: ResponseSink>(
     })
 }
 
+/// Delay before provider retry `attempt` (1-based).
+///
+/// Exponential backoff from `base_ms`, unless the provider sent a
+/// `Retry-After` hint; either way capped at [`MAX_RETRY_DELAY`].
+fn retry_delay(attempt: usize, base_ms: u64, retry_after_secs: Option<u64>) -> Duration {
+    let delay = match retry_after_secs {
+        Some(secs) => Duration::from_secs(secs),
+        None => {
+            let shift = attempt.saturating_sub(1).min(16) as u32;
+            Duration::from_millis(base_ms.saturating_mul(1 << shift))
+        }
+    };
+    delay.min(MAX_RETRY_DELAY)
+}
+
+/// Next entry in `fallbacks` (from `*index`) that differs from `current`,
+/// advancing `*index` past it.
+fn next_fallback_model<'a>(
+    fallbacks: &'a [String],
+    index: &mut usize,
+    current: &str,
+) -> Option<&'a str> {
+    while let Some(model) = fallbacks.get(*index) {
+        *index += 1;
+        if model != current {
+            return Some(model);
+        }
+    }
+    None
+}
+
+/// Collect a streaming response, retrying transient provider failures and
+/// walking `model_fallbacks` when a model keeps failing.
+///
+/// Retryable errors (rate limits, 5xx, dropped streams) are retried up to
+/// `provider_max_retries` times with backoff. Context-length errors, exhausted
+/// retries and other provider rejections switch `options.model` to the next
+/// fallback; authentication failures (401/403) and non-provider errors are
+/// returned as-is. Every attempt streams a fresh response, so the partial text
+/// of a failed attempt never reaches the transcript; `ProviderRetry` and
+/// `ModelFallback` tell sinks to discard what they already showed.
+///
+/// `fallback_index` persists across the turn, so a model that was abandoned
+/// is not retried on the next iteration.
+async fn collect_with_retries<S: ResponseSink>(
+    resolved_config: &ResolvedConfig,
+    options: &mut ChatOptions,
+    fallback_index: &mut usize,
+    messages: &[serde_json::Value],
+    all_tools: &[serde_json::Value],
+    sink: &mut S,
+) -> io::Result<StreamingResponse> {
+    let mut attempt = 0;
+    loop {
+        let err =
+            match collect_streaming_response(resolved_config, options, messages, all_tools, sink)
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+        // Only provider failures are recoverable here; a bad API key fails
+        // the same way on every model.
+        let (retryable, overflow, retry_after_secs) = match ChibiError::find(&err) {
+            Some(ChibiError::Provider {
+                status: Some(401 | 403),
+                ..
+            }) => return Err(err),
+            Some(e @ ChibiError::Provider { .. }) => {
+                (e.is_retryable(), e.is_context_overflow(), None)
+            }
+            Some(ChibiError::RateLimited {
+                retry_after_secs, ..
+            }) => (true, false, *retry_after_secs),
+            _ => return Err(err),
+        };
+
+        if !overflow && retryable && attempt < resolved_config.provider_max_retries {
+            attempt += 1;
+            let delay = retry_delay(
+                attempt,
+                resolved_config.provider_retry_base_ms,
+                retry_after_secs,
+            );
+            sink.handle(ResponseEvent::ProviderRetry {
+                attempt,
+                max_retries: resolved_config.provider_max_retries,
+                delay_ms: delay.as_millis() as u64,
+                error: err.to_string(),
+            })?;
+            tokio::time::sleep(delay).await;
+            continue;
+        }
+
+        let Some(next) = next_fallback_model(
+            &resolved_config.model_fallbacks,
+            fallback_index,
+            &options.model,
+        ) else {
+            return Err(err);
+        };
+        let reason = if overflow {
+            "context length exceeded".to_string()
+        } else {
+            err.to_string()
+        };
+        sink.handle(ResponseEvent::ModelFallback {
+            from: options.model.clone(),
+            to: next.to_string(),
+            reason,
+        })?;
+        options.model = next.to_string();
+        attempt = 0;
+    }
+}
+
 /// Result of executing a single tool.
 struct ToolExecutionResult {
     /// The final result to send back to the LLM (may be truncated if cached).
@@ -685,157 +880,20 @@ struct ToolExecutionResult {
     was_cached: bool,
     /// Verbose diagnostic messages collected during execution.
     diagnostics: Vec<String>,
+    /// Attachments produced by the tool (`view_image`), recorded on the tool_result entry.
+    attachments: Vec<crate::attachments::AttachmentRef>,
 }
 
 /// Result of processing PreTool hook results.
-struct PreToolResult {
-    blocked: bool,
-    block_message: String,
-    args: serde_json::Value,
+pub(crate) struct PreToolResult {
+    pub(crate) blocked: bool,
+    pub(crate) block_message: String,
+    pub(crate) args: serde_json::Value,
     diagnostics: Vec<String>,
 }
 
-/// Detects when the same tool call produces the same result consecutively.
-/// Used to inject a warning and charge fuel to break infinite loops.
-struct LoopDetector {
-    last_tool_name: String,
-    last_args: String,
-    last_result: String,
-    /// Whether the last recorded call had a cached result.
-    last_was_cached: bool,
-    /// How many times this call has repeated consecutively.
-    /// Starts at 0 (no previous call). Becomes 1 on first call, 2 on first repeat, etc.
-    pub consecutive_count: u32,
-}
-
-impl LoopDetector {
-    fn new() -> Self {
-        Self {
-            last_tool_name: String::new(),
-            last_args: String::new(),
-            last_result: String::new(),
-            last_was_cached: false,
-            consecutive_count: 0,
-        }
-    }
-
-    /// Record a tool call result. Returns `true` if this is a duplicate (loop detected).
-    ///
-    /// When both the current and previous calls were cached, compares `(tool_name, args)`
-    /// only — cached stubs contain timestamp-based VFS URIs that differ every time,
-    /// defeating result-based comparison (#207).
-    fn check_and_update(
-        &mut self,
-        tool_name: &str,
-        args: &str,
-        result: &str,
-        was_cached: bool,
-    ) -> bool {
-        let is_repeat = tool_name == self.last_tool_name
-            && args == self.last_args
-            && if was_cached && self.last_was_cached {
-                true // both cached: (tool, args) match is sufficient
-            } else {
-                result == self.last_result
-            };
-
-        if is_repeat {
-            self.consecutive_count += 1;
-            true
-        } else {
-            self.last_tool_name = tool_name.to_string();
-            self.last_args = args.to_string();
-            self.last_result = result.to_string();
-            self.last_was_cached = was_cached;
-            self.consecutive_count = 1;
-            false
-        }
-    }
-}
-
-#[cfg(test)]
-mod loop_detector_tests {
-    use super::*;
-
-    #[test]
-    fn test_no_loop_on_first_call() {
-        let mut d = LoopDetector::new();
-        assert!(!d.check_and_update("grep", r#"{"path":"x"}"#, "result", false));
-    }
-
-    #[test]
-    fn test_no_loop_on_different_args() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", r#"{"path":"a"}"#, "result", false);
-        assert!(!d.check_and_update("grep", r#"{"path":"b"}"#, "result", false));
-    }
-
-    #[test]
-    fn test_no_loop_on_different_result() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", r#"{"path":"a"}"#, "result1", false);
-        assert!(!d.check_and_update("grep", r#"{"path":"a"}"#, "result2", false));
-    }
-
-    #[test]
-    fn test_loop_detected_on_second_identical_call() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", r#"{"path":"a"}"#, "result", false);
-        assert!(d.check_and_update("grep", r#"{"path":"a"}"#, "result", false));
-    }
-
-    #[test]
-    fn test_loop_count_increments() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", "args", "res", false);
-        d.check_and_update("grep", "args", "res", false); // count=2, loop
-        d.check_and_update("grep", "args", "res", false); // count=3, loop
-        assert_eq!(d.consecutive_count, 3);
-    }
-
-    #[test]
-    fn test_loop_resets_on_different_call() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", "args", "res", false);
-        d.check_and_update("grep", "args", "res", false); // loop detected
-        d.check_and_update("ls", "{}", "files", false); // resets
-        assert_eq!(d.consecutive_count, 1);
-        assert!(!d.check_and_update("ls", "{}", "files2", false)); // different result → no loop
-    }
-
-    #[test]
-    fn test_cached_loop_detected_despite_different_stubs() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("web_fetch", r#"{"url":"x"}"#, "stub_ts1", true);
-        assert!(d.check_and_update("web_fetch", r#"{"url":"x"}"#, "stub_ts2", true));
-    }
-
-    #[test]
-    fn test_cached_then_uncached_no_loop() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", "args", "stub_cached", true);
-        assert!(!d.check_and_update("grep", "args", "real_result", false));
-    }
-
-    #[test]
-    fn test_uncached_then_cached_no_loop() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("grep", "args", "real_result", false);
-        assert!(!d.check_and_update("grep", "args", "stub_cached", true));
-    }
-
-    #[test]
-    fn test_cached_loop_count_increments() {
-        let mut d = LoopDetector::new();
-        d.check_and_update("fetch", "args", "stub1", true);
-        d.check_and_update("fetch", "args", "stub2", true);
-        d.check_and_update("fetch", "args", "stub3", true);
-        assert_eq!(d.consecutive_count, 3);
-    }
-}
-
 /// Process PreTool hook results: check for block signals and argument modifications.
-fn apply_pre_tool_results(
+pub(crate) fn apply_pre_tool_results(
     hook_results: Vec<(String, serde_json::Value)>,
     tool_name: &str,
     args: serde_json::Value,
@@ -932,6 +990,7 @@ async fn execute_tool_pure(
     let mut args: serde_json::Value =
         serde_json::from_str(&tool_call.arguments).unwrap_or(serde_json::json!({}));
     let mut diagnostics = Vec::new();
+    let mut attachments = Vec::new();
 
     // Look up tool category and metadata once. Drop the lock before any .await.
     let (tool_category, tool_metadata) = {
@@ -1192,6 +1251,7 @@ async fn execute_tool_pure(
                                 original_result: msg,
                                 was_cached: false,
                                 diagnostics,
+                                attachments: Vec::new(),
                             });
                         }
                     } else if let tools::UrlSafety::Sensitive(category) = &safety {
@@ -1216,6 +1276,7 @@ async fn execute_tool_pure(
                                     original_result: msg,
                                     was_cached: false,
                                     diagnostics,
+                                    attachments: Vec::new(),
                                 });
                             }
                         }
@@ -1228,6 +1289,18 @@ async fn execute_tool_pure(
 
         if let Some(reason) = permission_denied {
             reason
+        } else if tool_call.name == tools::VIEW_IMAGE_TOOL_NAME {
+            // view_image is intercepted after the FsRead permission gate so the
+            // stored AttachmentRef can be recorded on the tool_result entry.
+            match tools::execute_view_image(app, context_name, &args, resolved_config, project_root)
+            {
+                Ok(attachment) => {
+                    let description = tools::describe_view_image(&attachment);
+                    attachments.push(attachment);
+                    description
+                }
+                Err(e) => format!("Error: {}", e),
+            }
         } else {
             // Dispatch via registry.
             // Clone ToolImpl while holding the read lock, then drop the guard
@@ -1299,42 +1372,51 @@ async fn execute_tool_pure(
                 tool_call.name
             ));
             (tool_result.clone(), false)
+        } else if crate::vfs_cache::reads_private_path(&app.vfs, &args).await {
+            diagnostics.push(format!(
+                "[Not caching {}: it read a private path]",
+                tool_call.name
+            ));
+            (tool_result.clone(), false)
         } else {
             let cache_id = crate::vfs_cache::generate_cache_id(&tool_call.name, &args);
-            let vfs_path_str = crate::vfs_cache::vfs_path_for(context_name, &cache_id);
-            let vfs_uri = crate::vfs_cache::vfs_uri_for(context_name, &cache_id);
-            let vfs_path = crate::vfs::VfsPath::new(&vfs_path_str).map_err(|e| {
-                std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
-            })?;
 
-            match app
-                .vfs
-                .write(
-                    crate::vfs::VfsCaller::System,
-                    &vfs_path,
-                    tool_result.as_bytes(),
-                )
-                .await
+            match crate::vfs_cache::store_blob(
+                &app.vfs,
+                context_name,
+                &cache_id,
+                &tool_call.name,
+                Some(&tool_call.id),
+                tool_result.as_bytes(),
+            )
+            .await
             {
-                Ok(()) => {
+                Ok(stored) => {
                     let truncated = crate::vfs_cache::truncated_message(
-                        &vfs_uri,
+                        &stored.uri,
                         &tool_call.name,
                         &tool_result,
                         resolved_config.tool_cache_preview_chars,
                     );
 
                     diagnostics.push(format!(
-                        "[Cached {} chars from {} at {}]",
+                        "[Cached {} chars from {} at {}{}]",
                         tool_result.len(),
                         tool_call.name,
-                        vfs_uri,
+                        stored.uri,
+                        if stored.deduplicated {
+                            " (deduplicated)"
+                        } else {
+                            ""
+                        },
                     ));
 
                     // Fire post_cache_output hook (notification only)
                     let post_cache_data = serde_json::json!({
                         "tool_name": tool_call.name,
                         "cache_id": cache_id,
+                        "blob_uri": stored.uri,
+                        "deduplicated": stored.deduplicated,
                         "output_size": tool_result.len(),
                         "preview_size": truncated.len(),
                     });
@@ -1377,6 +1459,7 @@ async fn execute_tool_pure(
         original_result: tool_result,
         was_cached,
         diagnostics,
+        attachments,
     })
 }
 
@@ -1546,8 +1629,9 @@ async fn process_tool_calls<S: ResponseSink>(
     sink: &mut S,
     permission_handler: Option<&PermissionHandler>,
     project_root: &Path,
-    loop_detector: &mut LoopDetector,
+    progress: &mut ProgressMonitor,
     tein_ctx: Option<&tools::TeinHookContext<'_>>,
+    cassette: Option<&Cassette>,
 ) -> io::Result<()> {
     // Convert tool calls to JSON format for the assistant message
     let tool_calls_json: Vec<serde_json::Value> = tool_calls
@@ -1574,19 +1658,32 @@ async fn process_tool_calls<S: ResponseSink>(
     let mut parallel_batch: Vec<(usize, &ratatoskr::ToolCall)> = Vec::new();
     let mut sequential_batch: Vec<(usize, &ratatoskr::ToolCall)> = Vec::new();
 
+    // Results indexed by original position
+    let mut results: Vec<Option<ToolExecutionResult>> =
+        (0..tool_calls.len()).map(|_| None).collect();
+
     for (i, tc) in tool_calls.iter().enumerate() {
         let metadata = tools::get_tool_metadata(&registry.read().unwrap(), &tc.name);
-        if metadata.parallel && !metadata.flow_control {
+        // Replayed tool results skip execution entirely. Flow control tools
+        // always run: they drive the handoff, not the outside world.
+        if !metadata.flow_control
+            && let Some(result) =
+                cassette.and_then(|c| c.recorded_tool_result(&tc.name, &tc.arguments))
+        {
+            results[i] = Some(ToolExecutionResult {
+                final_result: result.clone(),
+                original_result: result,
+                was_cached: false,
+                diagnostics: Vec::new(),
+                attachments: Vec::new(),
+            });
+        } else if metadata.parallel && !metadata.flow_control {
             parallel_batch.push((i, tc));
         } else {
             sequential_batch.push((i, tc));
         }
     }
 
-    // Results indexed by original position
-    let mut results: Vec<Option<ToolExecutionResult>> =
-        (0..tool_calls.len()).map(|_| None).collect();
-
     // Execute parallel batch concurrently via join_all.
     // These futures run on the current task (no spawn), interleaving at .await
     // points — safe with !Send types like AppState's RefCell.
@@ -1668,6 +1765,10 @@ async fn process_tool_calls<S: ResponseSink>(
         }
     }
 
+    // Set when a progress signal ends the turn; applied after the batch so
+    // every tool call still gets its result.
+    let mut stop_message: Option<String> = None;
+
     // Emit sink events and write tool_result entries in original order
     for (i, tc) in tool_calls.iter().enumerate() {
         let result = results[i]
@@ -1735,10 +1836,18 @@ async fn process_tool_calls<S: ResponseSink>(
         } else {
             &result.original_result
         };
-        let tool_result_entry =
+        let mut tool_result_entry =
             create_tool_result_entry(context_name, &tc.name, logged_result, &tc.id);
+        tool_result_entry.attachments = result.attachments.clone();
         app.append_to_transcript_and_context(context_name, &tool_result_entry)?;
         sink.handle(ResponseEvent::TranscriptEntry(tool_result_entry))?;
+        if let Some(cassette) = cassette {
+            cassette.push(CassetteRecord::ToolResult {
+                tool: tc.name.clone(),
+                arguments: tc.arguments.clone(),
+                result: result.original_result.clone(),
+            })?;
+        }
 
         sink.handle(ResponseEvent::ToolResult {
             name: tc.name.clone(),
@@ -1773,19 +1882,18 @@ async fn process_tool_calls<S: ResponseSink>(
             tein_ctx,
         );
 
+        let failed = result.original_result.starts_with("Error");
+        let is_fs_write = {
+            let reg = registry.read().unwrap();
+            reg.get(&tc.name)
+                .map(|t| t.category == tools::ToolCategory::FsWrite)
+                .unwrap_or(false)
+        };
+
         // Fire PostVfsWrite for successful VFS writes (advisory, non-blocking).
         {
             let raw_path = args.get_str("path").unwrap_or("");
-            let is_vfs_write = {
-                let reg = registry.read().unwrap();
-                reg.get(&tc.name)
-                    .map(|t| t.category == tools::ToolCategory::FsWrite)
-                    .unwrap_or(false)
-            };
-            if is_vfs_write
-                && VfsPath::is_vfs_uri(raw_path)
-                && !result.original_result.starts_with("Error")
-            {
+            if is_fs_write && VfsPath::is_vfs_uri(raw_path) && !failed {
                 let vfs_post_hook_data = serde_json::json!({
                     "tool_name": tc.name,
                     "path": raw_path,
@@ -1812,46 +1920,71 @@ async fn process_tool_calls<S: ResponseSink>(
             }
         }
 
-        // Loop detection: warn and charge fuel if same (tool, args, result) repeats.
-        let is_loop = loop_detector.check_and_update(
-            &tc.name,
-            &tc.arguments,
-            &result.final_result,
-            result.was_cached,
-        );
+        // Progress monitoring: repeats, cycles, error streaks and stalls.
+        let signal = progress.observe(&ToolObservation {
+            tool: &tc.name,
+            args: &tc.arguments,
+            result: &result.final_result,
+            was_cached: result.was_cached,
+            failed,
+            progressed: is_fs_write && !failed,
+        });
 
-        messages.push(serde_json::json!({
+        let mut tool_message = serde_json::json!({
             "role": "tool",
             "tool_call_id": tc.id,
             "content": result.final_result,
-        }));
+        });
+        if !result.attachments.is_empty() {
+            tool_message["attachments"] = serde_json::json!(result.attachments);
+        }
+        messages.push(tool_message);
 
-        if is_loop {
-            if !fuel_unlimited {
-                *fuel_remaining =
-                    fuel_remaining.saturating_sub(resolved_config.fuel_empty_response_cost);
-                sink.handle(ResponseEvent::FuelStatus {
-                    event: crate::api::sink::FuelEvent::EmptyResponse,
-                    remaining: *fuel_remaining,
-                    total: fuel_total,
-                })?;
-                if *fuel_remaining == 0 {
-                    sink.handle(ResponseEvent::FuelExhausted { total: fuel_total })?;
-                    return Ok(());
-                }
+        let Some(signal) = signal else {
+            continue;
+        };
+        let Some((action, message)) = resolve_progress_action(
+            context_name,
+            &signal,
+            progress.action,
+            plugin_tools,
+            tein_ctx,
+            sink,
+        )?
+        else {
+            continue;
+        };
+        sink.handle(ResponseEvent::ToolDiagnostic {
+            tool: tc.name.clone(),
+            message: format!(
+                "[progress: {} on {} ({})]",
+                signal.kind.as_str(),
+                signal.tool,
+                action.as_str()
+            ),
+        })?;
+        if action == ProgressAction::CallUser {
+            stop_message.get_or_insert(message);
+            continue;
+        }
+        if action == ProgressAction::Fuel && !fuel_unlimited {
+            *fuel_remaining =
+                fuel_remaining.saturating_sub(resolved_config.fuel_empty_response_cost);
+            sink.handle(ResponseEvent::FuelStatus {
+                event: crate::api::sink::FuelEvent::EmptyResponse,
+                remaining: *fuel_remaining,
+                total: fuel_total,
+            })?;
+            if *fuel_remaining == 0 {
+                sink.handle(ResponseEvent::FuelExhausted { total: fuel_total })?;
+                return Ok(());
             }
-            let warning = format!(
-                "[Loop detected] You have called {}({}) {} time(s) in a row and received the same result. \
-                 This is not making progress. Try a different approach, use a different tool, \
-                 or ask the user for help.",
-                tc.name, tc.arguments, loop_detector.consecutive_count,
-            );
-            messages.push(serde_json::json!({
-                "role": "tool",
-                "tool_call_id": tc.id,
-                "content": warning,
-            }));
         }
+        messages.push(serde_json::json!({
+            "role": "tool",
+            "tool_call_id": tc.id,
+            "content": message,
+        }));
     }
 
     // Execute post_tool_batch hook — allows plugins to override fallback after seeing tool results
@@ -1881,9 +2014,88 @@ async fn process_tool_calls<S: ResponseSink>(
     )?;
     apply_hook_overrides(handoff, fuel_remaining, fuel_unlimited, &hook_results, sink)?;
 
+    if let Some(message) = stop_message {
+        // Same transcript shape as an agent-initiated call_user.
+        let msg_entry =
+            create_flow_control_message_entry(context_name, username, &message, "agent");
+        app.append_to_transcript_and_context(context_name, &msg_entry)?;
+        sink.handle(ResponseEvent::TranscriptEntry(msg_entry))?;
+        let ct_entry = create_control_transfer_entry(context_name, username);
+        app.append_to_transcript_and_context(context_name, &ct_entry)?;
+        sink.handle(ResponseEvent::TranscriptEntry(ct_entry))?;
+        sink.handle(ResponseEvent::StartResponse)?;
+        sink.handle(ResponseEvent::TextChunk(&message))?;
+        sink.handle(ResponseEvent::Newline)?;
+        sink.handle(ResponseEvent::Finished)?;
+        handoff.set_user(message);
+    }
+
     Ok(())
 }
 
+/// Decide how to respond to a progress signal.
+///
+/// `on_no_progress` hooks may return `{"action": ..., "message": ...}` to
+/// override the configured action and the text injected or shown; an action
+/// of `"ignore"` drops the signal. Returns `None` when the signal is ignored.
+fn resolve_progress_action<S: ResponseSink>(
+    context_name: &str,
+    signal: &ProgressSignal,
+    configured: ProgressAction,
+    plugin_tools: &[Tool],
+    tein_ctx: Option<&tools::TeinHookContext<'_>>,
+    sink: &mut S,
+) -> io::Result<Option<(ProgressAction, String)>> {
+    let hook_data = json!({
+        "context_name": context_name,
+        "kind": signal.kind.as_str(),
+        "tool_name": signal.tool,
+        "count": signal.count,
+        "detail": signal.detail,
+        "action": configured.as_str(),
+    });
+    let hook_results = tools::execute_hook(
+        plugin_tools,
+        tools::HookPoint::OnNoProgress,
+        &hook_data,
+        tein_ctx,
+    )?;
+    apply_progress_hook_results(signal, configured, &hook_results, sink)
+}
+
+/// Apply `on_no_progress` hook results to the configured action.
+fn apply_progress_hook_results<S: ResponseSink>(
+    signal: &ProgressSignal,
+    configured: ProgressAction,
+    hook_results: &[(String, serde_json::Value)],
+    sink: &mut S,
+) -> io::Result<Option<(ProgressAction, String)>> {
+    let mut action = configured;
+    let mut message = None;
+    for (hook_tool_name, result) in hook_results {
+        match result.get_str("action") {
+            Some("ignore") => return Ok(None),
+            Some(name) => match ProgressAction::parse(name) {
+                Ok(parsed) => action = parsed,
+                Err(e) => sink.handle(ResponseEvent::HookDebug {
+                    hook: hook_tool_name.clone(),
+                    message: format!("[Hook on_no_progress: {} {}]", hook_tool_name, e),
+                })?,
+            },
+            None => {}
+        }
+        if let Some(text) = result.get_str("message") {
+            message = Some(text.to_string());
+        }
+    }
+
+    let message = message.unwrap_or_else(|| match action {
+        ProgressAction::CallUser => signal.call_user_message(),
+        ProgressAction::Nudge | ProgressAction::Fuel => signal.nudge_message(),
+    });
+    Ok(Some((action, message)))
+}
+
 /// Result of handling a final (non-tool-call) response.
 enum FinalResponseAction {
     /// Return to the user (turn ended).
@@ -2012,6 +2224,21 @@ pub async fn send_prompt<S: ResponseSink>(
     let fuel_unlimited = fuel_total == 0;
     let mut current_prompt = initial_prompt;
 
+    // Attachments belong to the user's turn only; resolve (and store) them up
+    // front so a bad path fails before anything is written to the transcript.
+    let attachments_dir = app.attachments_dir(context_name);
+    let mut prompt_attachments = options
+        .attachments
+        .iter()
+        .map(|source| {
+            crate::attachments::attach_source(&attachments_dir, source, Some(project_root))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    if let Some(cassette) = options.cassette {
+        cassette.begin_prompt(context_name, &current_prompt, &resolved_config.model)?;
+    }
+
     // Tools eligible for hook dispatch: see Tool::is_hook_eligible.
     let plugin_tools: Vec<Tool> = registry
         .read()
@@ -2088,9 +2315,16 @@ pub async fn send_prompt<S: ResponseSink>(
         let prefixed_prompt = format!("[{}] {}", datetime_prefix, final_prompt);
 
         // Add user message to context and transcript
-        app.add_message(&mut context, "user".to_string(), prefixed_prompt.clone());
-        let user_entry =
+        let attachments = std::mem::take(&mut prompt_attachments);
+        app.add_message_with_attachments(
+            &mut context,
+            "user".to_string(),
+            prefixed_prompt.clone(),
+            &attachments,
+        );
+        let mut user_entry =
             create_user_message_entry(context_name, &prefixed_prompt, &resolved_config.username);
+        user_entry.attachments = attachments;
         app.append_to_transcript_and_context(context_name, &user_entry)?;
         sink.handle(ResponseEvent::TranscriptEntry(user_entry))?;
 
@@ -2267,8 +2501,10 @@ pub async fn send_prompt<S: ResponseSink>(
 
         // Deserialise ChatOptions from the (potentially hook-modified) request body
         // so API parameter overrides from hooks are honoured in the actual call.
-        let chat_options: ChatOptions =
+        let mut chat_options: ChatOptions =
             serde_json::from_value(request_body.clone()).unwrap_or(base_options);
+        // Index into `model_fallbacks`; advances as models are abandoned this turn.
+        let mut fallback_index = 0;
 
         // === Initialize Handoff ===
         let fallback = options.fallback_override.clone().unwrap_or_else(|| {
@@ -2310,22 +2546,40 @@ pub async fn send_prompt<S: ResponseSink>(
             sink,
         )?;
 
-        // Loop detector resets per user-message turn (each outer loop iteration).
-        let mut loop_detector = LoopDetector::new();
+        // Progress monitor resets per user-message turn (each outer loop iteration).
+        let mut progress = ProgressMonitor::from_config(&resolved_config)?;
 
         // === Inner Loop: stream responses and process tool calls ===
         loop {
             sink.handle(ResponseEvent::StartResponse)?;
             log_request_if_enabled(app, context_name, debug, &request_body);
 
-            let response = collect_streaming_response(
-                &resolved_config,
-                &chat_options,
-                &messages,
-                &all_tools,
-                sink,
-            )
-            .await?;
+            // Expand attachment refs into multipart content for this request only;
+            // `messages` keeps the compact refs.
+            let mut api_messages = messages.clone();
+            crate::attachments::inline_attachments(&mut api_messages, &attachments_dir)?;
+
+            let recorded = match options.cassette {
+                Some(cassette) => cassette.next_response()?,
+                None => None,
+            };
+            let response = match recorded {
+                Some(recorded) => replay_streaming_response(recorded, sink)?,
+                None => {
+                    collect_with_retries(
+                        &resolved_config,
+                        &mut chat_options,
+                        &mut fallback_index,
+                        &api_messages,
+                        &all_tools,
+                        sink,
+                    )
+                    .await?
+                }
+            };
+            if let Some(cassette) = options.cassette {
+                cassette.push(response.to_record())?;
+            }
 
             // Log response metadata
             if let Some(ref meta) = response.response_meta {
@@ -2354,8 +2608,9 @@ pub async fn send_prompt<S: ResponseSink>(
                     sink,
                     permission_handler,
                     project_root,
-                    &mut loop_detector,
+                    &mut progress,
                     tein_hook_ctx_ref,
+                    options.cassette,
                 )
                 .await?;
 
@@ -2479,6 +2734,30 @@ mod tests {
         Arc::new(RwLock::new(reg))
     }
 
+    #[test]
+    fn test_retry_delay_exponential_and_capped() {
+        assert_eq!(retry_delay(1, 1000, None), Duration::from_millis(1000));
+        assert_eq!(retry_delay(2, 1000, None), Duration::from_millis(2000));
+        assert_eq!(retry_delay(4, 1000, None), Duration::from_millis(8000));
+        assert_eq!(retry_delay(30, 1000, None), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_retry_delay_honours_retry_after() {
+        assert_eq!(retry_delay(1, 1000, Some(7)), Duration::from_secs(7));
+        assert_eq!(retry_delay(1, 1000, Some(3600)), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_next_fallback_model_skips_current() {
+        let fallbacks = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut index = 0;
+        assert_eq!(next_fallback_model(&fallbacks, &mut index, "a"), Some("b"));
+        assert_eq!(index, 2);
+        assert_eq!(next_fallback_model(&fallbacks, &mut index, "b"), Some("c"));
+        assert_eq!(next_fallback_model(&fallbacks, &mut index, "c"), None);
+    }
+
     #[test]
     fn test_filter_tools_by_config_no_filters() {
         let tools = vec![
@@ -3130,6 +3409,72 @@ mod tests {
             .collect()
     }
 
+    fn cycle_signal() -> ProgressSignal {
+        ProgressSignal {
+            kind: crate::api::progress::ProgressKind::Cycle,
+            tool: "file_edit".to_string(),
+            count: 3,
+            detail: "You have repeated the same 2 calls (file_head → file_edit) 3 times in a row."
+                .to_string(),
+        }
+    }
+
+    #[test]
+    fn test_progress_no_hooks_uses_configured_action() {
+        let mut sink = CollectingSink::default();
+        let (action, message) =
+            apply_progress_hook_results(&cycle_signal(), ProgressAction::Fuel, &[], &mut sink)
+                .unwrap()
+                .unwrap();
+        assert_eq!(action, ProgressAction::Fuel);
+        assert!(message.starts_with("[Cycle detected]"));
+    }
+
+    #[test]
+    fn test_progress_hook_overrides_action_and_message() {
+        let results = vec![(
+            "guard".to_string(),
+            json!({"action": "call_user", "message": "stuck on edits"}),
+        )];
+        let mut sink = CollectingSink::default();
+        let (action, message) = apply_progress_hook_results(
+            &cycle_signal(),
+            ProgressAction::Nudge,
+            &results,
+            &mut sink,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(action, ProgressAction::CallUser);
+        assert_eq!(message, "stuck on edits");
+    }
+
+    #[test]
+    fn test_progress_hook_ignore_drops_signal() {
+        let results = vec![("guard".to_string(), json!({"action": "ignore"}))];
+        let mut sink = CollectingSink::default();
+        let resolved =
+            apply_progress_hook_results(&cycle_signal(), ProgressAction::Fuel, &results, &mut sink)
+                .unwrap();
+        assert!(resolved.is_none());
+    }
+
+    #[test]
+    fn test_progress_hook_invalid_action_keeps_configured() {
+        let results = vec![("guard".to_string(), json!({"action": "explode"}))];
+        let mut sink = CollectingSink::default();
+        let (action, message) = apply_progress_hook_results(
+            &cycle_signal(),
+            ProgressAction::CallUser,
+            &results,
+            &mut sink,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(action, ProgressAction::CallUser);
+        assert!(message.ends_with("returning control to the user."));
+    }
+
     #[test]
     fn test_filter_tools_include() {
         let tools = make_api_tools(&["read_file", "write_file", "shell_exec"]);
@@ -3406,6 +3751,17 @@ mod tests {
             reflection_enabled: false,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 10000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: false,
@@ -79,6 +79,23 @@ pub enum ResponseEvent<'a> {
 
     /// Inbox messages injected into prompt (verbose-tier in CLI).
     InboxInjected { count: usize },
+
+    /// A provider request failed and will be retried after `delay_ms` —
+    /// always shown in CLI. Text streamed by the failed attempt is discarded.
+    ProviderRetry {
+        attempt: usize,
+        max_retries: usize,
+        delay_ms: u64,
+        error: String,
+    },
+
+    /// Switched to the next entry in `model_fallbacks` — always shown in CLI.
+    /// Text streamed by the failed attempt is discarded.
+    ModelFallback {
+        from: String,
+        to: String,
+        reason: String,
+    },
 }
 
 /// Trait for handling response events during prompt processing.
@@ -130,6 +147,9 @@ pub struct CollectingSink {
     pub reasoning: String,
     /// Transcript entries emitted during the interaction.
     pub entries: Vec<TranscriptEntry>,
+    /// Lengths of `text`/`reasoning` when the current response started, so a
+    /// retried attempt can drop what it streamed.
+    marks: (usize, usize),
 }
 
 impl CollectingSink {
@@ -151,9 +171,15 @@ impl ResponseSink for CollectingSink {
             ResponseEvent::TranscriptEntry(entry) => {
                 self.entries.push(entry);
             }
+            ResponseEvent::StartResponse => {
+                self.marks = (self.text.len(), self.reasoning.len());
+            }
+            ResponseEvent::ProviderRetry { .. } | ResponseEvent::ModelFallback { .. } => {
+                self.text.truncate(self.marks.0);
+                self.reasoning.truncate(self.marks.1);
+            }
             ResponseEvent::Finished
             | ResponseEvent::Newline
-            | ResponseEvent::StartResponse
             | ResponseEvent::ToolStart { .. }
             | ResponseEvent::ToolResult { .. }
             | ResponseEvent::HookDebug { .. }
@@ -188,4 +214,22 @@ mod tests {
         assert_eq!(sink.reasoning, "");
         assert!(sink.entries.is_empty());
     }
+
+    #[test]
+    fn test_collecting_sink_discards_retried_attempt() {
+        let mut sink = CollectingSink::new();
+        sink.handle(ResponseEvent::TextChunk("kept ")).unwrap();
+        sink.handle(ResponseEvent::StartResponse).unwrap();
+        sink.handle(ResponseEvent::TextChunk("partial")).unwrap();
+        sink.handle(ResponseEvent::ProviderRetry {
+            attempt: 1,
+            max_retries: 3,
+            delay_ms: 1000,
+            error: "Stream error: connection reset".to_string(),
+        })
+        .unwrap();
+        sink.handle(ResponseEvent::TextChunk("full answer"))
+            .unwrap();
+        assert_eq!(sink.text, "kept full answer");
+    }
 }
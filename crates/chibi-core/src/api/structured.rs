@@ -0,0 +1,608 @@
+//! Structured output: check a final reply against a JSON Schema.
+//!
+//! `Command::SendStructured` asks the provider for schema-shaped output via
+//! `response_format`, but providers differ in how strictly they honour it. The
+//! final reply is therefore parsed ([`parse_reply`]) and validated
+//! ([`validate`]); on failure the model is re-prompted with the errors
+//! ([`repair_prompt`]) for a bounded number of attempts.
+//!
+//! The validator covers the keywords structured-output providers accept:
+//! `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`,
+//! `items`, `minItems`/`maxItems`, `minLength`/`maxLength`, `pattern`,
+//! `minimum`/`maximum` (and their exclusive forms), `allOf`/`anyOf`/`oneOf`,
+//! and local `$ref`s (`#/$defs/...`). Other keywords are ignored.
+
+use super::sink::{ResponseEvent, ResponseSink};
+use crate::context::ENTRY_TYPE_MESSAGE;
+use regex::Regex;
+use serde_json::Value;
+use std::io;
+
+/// Attempts (the first reply plus repairs) when the command doesn't say.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+/// Errors listed in a repair prompt; the rest are summarised as a count.
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Guards against `$ref` cycles that never consume any of the instance.
+const MAX_DEPTH: usize = 64;
+
+/// Parse a reply as JSON, tolerating a surrounding code fence or prose.
+///
+/// Tries the whole reply, then the body of a ```` ``` ```` fence, then the
+/// span from the first `{`/`[` to the last `}`/`]`.
+pub fn parse_reply(reply: &str) -> Result<Value, String> {
+    let trimmed = reply.trim();
+    let first_error = match serde_json::from_str(trimmed) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    if let Some(rest) = trimmed.strip_prefix("```") {
+        // Drop the info string (e.g. "json") on the opening line
+        let body = rest.split_once('\n').map_or("", |(_, body)| body);
+        let body = body.trim_end().trim_end_matches("```");
+        if let Ok(value) = serde_json::from_str(body.trim()) {
+            return Ok(value);
+        }
+    }
+
+    let start = trimmed.find(['{', '[']);
+    let end = trimmed.rfind(['}', ']']);
+    if let (Some(start), Some(end)) = (start, end)
+        && start < end
+        && let Ok(value) = serde_json::from_str(&trimmed[start..=end])
+    {
+        return Ok(value);
+    }
+
+    if trimmed.is_empty() {
+        return Err("reply is empty; expected a JSON value".to_string());
+    }
+    Err(format!("reply is not valid JSON: {}", first_error))
+}
+
+/// Validate `instance` against `schema`, returning one message per violation
+/// (empty when valid). Messages are prefixed with the offending path
+/// (`$`, `$.items[2].name`).
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut validator = Validator {
+        root: schema,
+        errors: Vec::new(),
+    };
+    validator.check(schema, instance, "$", 0);
+    validator.errors
+}
+
+/// Parse and validate a reply: the JSON value, or every problem found.
+pub fn check_reply(schema: &Value, reply: &str) -> Result<Value, Vec<String>> {
+    let value = parse_reply(reply).map_err(|e| vec![e])?;
+    let errors = validate(schema, &value);
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Follow-up prompt asking the model to fix a reply that failed validation.
+pub fn repair_prompt(errors: &[String]) -> String {
+    let mut prompt = String::from("Your previous reply did not match the required JSON schema:\n");
+    for error in errors.iter().take(MAX_REPORTED_ERRORS) {
+        prompt.push_str("- ");
+        prompt.push_str(error);
+        prompt.push('\n');
+    }
+    if errors.len() > MAX_REPORTED_ERRORS {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            errors.len() - MAX_REPORTED_ERRORS
+        ));
+    }
+    prompt.push_str(
+        "\nReply again with only a JSON value that satisfies the schema: \
+         no prose, no code fences.",
+    );
+    prompt
+}
+
+/// A sink that forwards every event and remembers the last agent reply.
+pub struct ReplyCapture<'a, S: ResponseSink> {
+    inner: &'a mut S,
+    /// Content of the last assistant message written to the transcript.
+    pub reply: Option<String>,
+}
+
+impl<'a, S: ResponseSink> ReplyCapture<'a, S> {
+    pub fn new(inner: &'a mut S) -> Self {
+        Self { inner, reply: None }
+    }
+}
+
+impl<S: ResponseSink> ResponseSink for ReplyCapture<'_, S> {
+    fn handle(&mut self, event: ResponseEvent<'_>) -> io::Result<()> {
+        if let ResponseEvent::TranscriptEntry(entry) = &event
+            && entry.entry_type == ENTRY_TYPE_MESSAGE
+            && entry.role.as_deref() == Some("agent")
+        {
+            self.reply = Some(entry.content.clone());
+        }
+        self.inner.handle(event)
+    }
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    errors: Vec<String>,
+}
+
+impl<'a> Validator<'a> {
+    fn check(&mut self, schema: &'a Value, value: &Value, path: &str, depth: usize) {
+        if depth > MAX_DEPTH {
+            self.errors
+                .push(format!("{}: schema nesting too deep to check", path));
+            return;
+        }
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                self.errors
+                    .push(format!("{}: no value is allowed here", path));
+                return;
+            }
+            Value::Object(map) => map,
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path, depth + 1),
+                None => self
+                    .errors
+                    .push(format!("{}: cannot resolve $ref {}", path, reference)),
+            }
+        }
+
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| type_matches(t, value)) {
+                self.errors.push(format!(
+                    "{}: expected {}, got {}",
+                    path,
+                    allowed.join(" or "),
+                    type_name(value)
+                ));
+                // Further keywords would only repeat the mismatch
+                return;
+            }
+        }
+
+        if let Some(Value::Array(options)) = schema.get("enum")
+            && !options.contains(value)
+        {
+            let options: Vec<String> = options.iter().map(Value::to_string).collect();
+            self.errors.push(format!(
+                "{}: {} is not one of {}",
+                path,
+                value,
+                options.join(", ")
+            ));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            self.errors
+                .push(format!("{}: expected {}, got {}", path, expected, value));
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, depth),
+            Value::Array(items) => self.check_array(schema, items, path, depth),
+            Value::String(text) => self.check_string(schema, text, path),
+            Value::Number(_) => self.check_number(schema, value, path),
+            _ => {}
+        }
+
+        self.check_combinators(schema, value, path, depth);
+    }
+
+    fn check_object(
+        &mut self,
+        schema: &'a serde_json::Map<String, Value>,
+        object: &serde_json::Map<String, Value>,
+        path: &str,
+        depth: usize,
+    ) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    self.errors
+                        .push(format!("{}: missing required property \"{}\"", path, name));
+                }
+            }
+        }
+        for (key, item) in object {
+            let item_path = format!("{}.{}", path, key);
+            match properties.and_then(|p| p.get(key)) {
+                Some(sub) => self.check(sub, item, &item_path, depth + 1),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => self
+                        .errors
+                        .push(format!("{}: unexpected property \"{}\"", path, key)),
+                    Some(sub @ Value::Object(_)) => self.check(sub, item, &item_path, depth + 1),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &mut self,
+        schema: &'a serde_json::Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        depth: usize,
+    ) {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+            && (items.len() as u64) < min
+        {
+            self.errors.push(format!(
+                "{}: expected at least {} item(s), got {}",
+                path,
+                min,
+                items.len()
+            ));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+            && (items.len() as u64) > max
+        {
+            self.errors.push(format!(
+                "{}: expected at most {} item(s), got {}",
+                path,
+                max,
+                items.len()
+            ));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                self.check(item_schema, item, &format!("{}[{}]", path, i), depth + 1);
+            }
+        }
+    }
+
+    fn check_string(&mut self, schema: &serde_json::Map<String, Value>, text: &str, path: &str) {
+        let len = text.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+            && len < min
+        {
+            self.errors.push(format!(
+                "{}: expected at least {} character(s), got {}",
+                path, min, len
+            ));
+        }
+        if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+            && len > max
+        {
+            self.errors.push(format!(
+                "{}: expected at most {} character(s), got {}",
+                path, max, len
+            ));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+            match Regex::new(pattern) {
+                Ok(re) if !re.is_match(text) => self
+                    .errors
+                    .push(format!("{}: does not match pattern {}", path, pattern)),
+                Ok(_) => {}
+                Err(_) => self
+                    .errors
+                    .push(format!("{}: schema pattern {} is invalid", path, pattern)),
+            }
+        }
+    }
+
+    fn check_number(&mut self, schema: &serde_json::Map<String, Value>, value: &Value, path: &str) {
+        let Some(n) = value.as_f64() else {
+            return;
+        };
+        let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+        if let Some(min) = bound("minimum")
+            && n < min
+        {
+            self.errors.push(format!(
+                "{}: {} is less than the minimum {}",
+                path, value, min
+            ));
+        }
+        if let Some(max) = bound("maximum")
+            && n > max
+        {
+            self.errors.push(format!(
+                "{}: {} is greater than the maximum {}",
+                path, value, max
+            ));
+        }
+        if let Some(min) = bound("exclusiveMinimum")
+            && n <= min
+        {
+            self.errors
+                .push(format!("{}: {} must be greater than {}", path, value, min));
+        }
+        if let Some(max) = bound("exclusiveMaximum")
+            && n >= max
+        {
+            self.errors
+                .push(format!("{}: {} must be less than {}", path, value, max));
+        }
+    }
+
+    fn check_combinators(
+        &mut self,
+        schema: &'a serde_json::Map<String, Value>,
+        value: &Value,
+        path: &str,
+        depth: usize,
+    ) {
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for sub in all {
+                self.check(sub, value, path, depth + 1);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf")
+            && !any
+                .iter()
+                .any(|sub| self.errors_for(sub, value, path, depth).is_empty())
+        {
+            self.errors.push(format!(
+                "{}: does not match any of the {} allowed shapes",
+                path,
+                any.len()
+            ));
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            let matching = one
+                .iter()
+                .filter(|sub| self.errors_for(sub, value, path, depth).is_empty())
+                .count();
+            if matching != 1 {
+                self.errors.push(format!(
+                    "{}: matches {} of the oneOf shapes, expected exactly 1",
+                    path, matching
+                ));
+            }
+        }
+    }
+
+    /// Errors `schema` alone reports for `value`, without recording them.
+    fn errors_for(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        path: &str,
+        depth: usize,
+    ) -> Vec<String> {
+        let mut sub = Validator {
+            root: self.root,
+            errors: Vec::new(),
+        };
+        sub.check(schema, value, path, depth + 1);
+        sub.errors
+    }
+
+    /// Resolve a local `$ref` (`#`, `#/$defs/name`, `#/definitions/name`).
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        // Unknown type names are not ours to reject
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CollectingSink;
+    use crate::context::TranscriptEntry;
+    use serde_json::json;
+
+    fn review_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "verdict": { "enum": ["approve", "reject"] },
+                "score": { "type": "integer", "minimum": 0, "maximum": 10 },
+                "issues": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/issue" },
+                    "maxItems": 2
+                }
+            },
+            "required": ["verdict", "score"],
+            "additionalProperties": false,
+            "$defs": {
+                "issue": {
+                    "type": "object",
+                    "properties": { "line": { "type": "integer" }, "note": { "type": "string", "minLength": 1 } },
+                    "required": ["note"]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_valid_instance_has_no_errors() {
+        let value = json!({
+            "verdict": "approve",
+            "score": 8,
+            "issues": [{ "line": 3, "note": "typo" }]
+        });
+        assert!(validate(&review_schema(), &value).is_empty());
+    }
+
+    #[test]
+    fn test_reports_each_violation_with_path() {
+        let value = json!({
+            "verdict": "maybe",
+            "score": 11,
+            "issues": [{ "line": "x", "note": "" }],
+            "extra": true
+        });
+        // Property order depends on serde_json's map ordering
+        let mut errors = validate(&review_schema(), &value);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "$.issues[0].line: expected integer, got string",
+                "$.issues[0].note: expected at least 1 character(s), got 0",
+                "$.score: 11 is greater than the maximum 10",
+                "$.verdict: \"maybe\" is not one of \"approve\", \"reject\"",
+                "$: unexpected property \"extra\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_required_and_type_mismatch() {
+        assert_eq!(
+            validate(&review_schema(), &json!({ "verdict": "approve" })),
+            vec!["$: missing required property \"score\""]
+        );
+        assert_eq!(
+            validate(&review_schema(), &json!([1, 2])),
+            vec!["$: expected object, got array"]
+        );
+    }
+
+    #[test]
+    fn test_integer_accepts_whole_floats() {
+        let schema = json!({ "type": "integer" });
+        assert!(validate(&schema, &json!(3.0)).is_empty());
+        assert_eq!(
+            validate(&schema, &json!(3.5)),
+            vec!["$: expected integer, got number"]
+        );
+    }
+
+    #[test]
+    fn test_any_of_and_one_of() {
+        let schema = json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] });
+        assert!(validate(&schema, &json!("x")).is_empty());
+        assert_eq!(validate(&schema, &json!(true)).len(), 1);
+
+        let schema = json!({ "oneOf": [{ "type": "number" }, { "type": "integer" }] });
+        assert_eq!(
+            validate(&schema, &json!(2)),
+            vec!["$: matches 2 of the oneOf shapes, expected exactly 1"]
+        );
+    }
+
+    #[test]
+    fn test_self_referencing_ref_terminates() {
+        let errors = validate(&json!({ "$ref": "#" }), &json!(1));
+        assert_eq!(errors, vec!["$: schema nesting too deep to check"]);
+    }
+
+    #[test]
+    fn test_parse_reply_tolerates_fences_and_prose() {
+        assert_eq!(parse_reply(" {\"a\": 1} ").unwrap(), json!({ "a": 1 }));
+        assert_eq!(
+            parse_reply("```json\n{\"a\": 1}\n```").unwrap(),
+            json!({ "a": 1 })
+        );
+        assert_eq!(
+            parse_reply("Here you go:\n[1, 2]\nHope that helps.").unwrap(),
+            json!([1, 2])
+        );
+        assert!(parse_reply("").unwrap_err().contains("empty"));
+        assert!(
+            parse_reply("no json here")
+                .unwrap_err()
+                .starts_with("reply is not valid JSON")
+        );
+    }
+
+    #[test]
+    fn test_check_reply() {
+        let schema = json!({ "type": "object", "required": ["ok"] });
+        assert_eq!(
+            check_reply(&schema, "{\"ok\": true}").unwrap(),
+            json!({ "ok": true })
+        );
+        assert_eq!(
+            check_reply(&schema, "{}").unwrap_err(),
+            vec!["$: missing required property \"ok\""]
+        );
+    }
+
+    #[test]
+    fn test_repair_prompt_lists_and_truncates_errors() {
+        let errors: Vec<String> = (0..25).map(|i| format!("error {}", i)).collect();
+        let prompt = repair_prompt(&errors);
+        assert!(prompt.contains("- error 0\n"));
+        assert!(prompt.contains("- error 19\n"));
+        assert!(!prompt.contains("error 20\n"));
+        assert!(prompt.contains("- ... and 5 more\n"));
+    }
+
+    #[test]
+    fn test_reply_capture_keeps_last_agent_message() {
+        let mut inner = CollectingSink::new();
+        let mut capture = ReplyCapture::new(&mut inner);
+        let user = TranscriptEntry::builder()
+            .from("user")
+            .to("ctx")
+            .content("question")
+            .role("user")
+            .build();
+        let agent = |content: &str| {
+            TranscriptEntry::builder()
+                .from("ctx")
+                .to("user")
+                .content(content)
+                .role("agent")
+                .build()
+        };
+        capture.handle(ResponseEvent::StartResponse).unwrap();
+        capture
+            .handle(ResponseEvent::TranscriptEntry(user))
+            .unwrap();
+        capture
+            .handle(ResponseEvent::TranscriptEntry(agent("draft")))
+            .unwrap();
+        capture
+            .handle(ResponseEvent::TranscriptEntry(agent("{}")))
+            .unwrap();
+        assert_eq!(capture.reply.as_deref(), Some("{}"));
+        assert_eq!(inner.entries.len(), 3);
+    }
+}
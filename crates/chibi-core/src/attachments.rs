@@ -0,0 +1,393 @@
+//! Multimodal prompt attachments (images and text files).
+//!
+//! Attachments are recorded on transcript entries as [`AttachmentRef`]s. Local
+//! files are stored content-addressed under `contexts/<name>/attachments/`
+//! (`<sha256>.<ext>`) so the transcript stays small and replayable; remote
+//! images are referenced by URL and never downloaded.
+//!
+//! At request time [`inline_attachments`] expands the refs into OpenAI-style
+//! content arrays (`text` + `image_url` parts), which the gateway converts to
+//! ratatoskr multipart messages.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Maximum size of a single attachment blob (20 MiB).
+pub const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// What an attachment is, from the model's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    /// Sent as an `image_url` content part.
+    Image,
+    /// UTF-8 text file, inlined as a `text` content part.
+    File,
+}
+
+/// Reference to an attachment, stored on [`crate::context::TranscriptEntry`].
+///
+/// Exactly one of `sha256` (local blob) or `url` (remote image) is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttachmentRef {
+    pub kind: AttachmentKind,
+    pub mime: String,
+    /// Original file name or URL, for display.
+    pub name: String,
+    /// Hex sha256 of the blob in the context's `attachments/` directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Remote URL (images only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Blob size in bytes (local attachments only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl AttachmentRef {
+    /// Short human-readable label, e.g. `image/png diagram.png (12345 bytes)`.
+    pub fn describe(&self) -> String {
+        match self.size {
+            Some(size) => format!("{} {} ({} bytes)", self.mime, self.name, size),
+            None => format!("{} {}", self.mime, self.name),
+        }
+    }
+}
+
+/// Guess an image MIME type from a file extension.
+pub fn image_mime_for(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit('.').next()?.to_ascii_lowercase();
+    // strip query strings from URLs (`foo.png?size=2`)
+    let ext = ext.split(['?', '#']).next().unwrap_or_default();
+    match ext {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn extension_for_mime(mime: &str) -> &'static str {
+    match mime {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => "txt",
+    }
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Path of a stored blob inside `dir`.
+pub fn blob_path(dir: &Path, attachment: &AttachmentRef) -> Option<PathBuf> {
+    let sha = attachment.sha256.as_deref()?;
+    Some(dir.join(format!("{}.{}", sha, extension_for_mime(&attachment.mime))))
+}
+
+/// Reject an attachment of `size` bytes over `MAX_ATTACHMENT_BYTES`. Check
+/// before reading a file so an oversized one is never loaded.
+pub fn check_size(name: &str, size: u64) -> io::Result<()> {
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "attachment '{}' is {} bytes (max {})",
+                name, size, MAX_ATTACHMENT_BYTES
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Store `bytes` content-addressed in `dir`. Identical content is written once.
+pub fn store_bytes(
+    dir: &Path,
+    name: &str,
+    kind: AttachmentKind,
+    mime: &str,
+    bytes: &[u8],
+) -> io::Result<AttachmentRef> {
+    check_size(name, bytes.len() as u64)?;
+    let sha256 = format!("{:x}", Sha256::digest(bytes));
+    let attachment = AttachmentRef {
+        kind,
+        mime: mime.to_string(),
+        name: name.to_string(),
+        sha256: Some(sha256),
+        url: None,
+        size: Some(bytes.len() as u64),
+    };
+    let path = blob_path(dir, &attachment).expect("sha256 is set");
+    if !path.exists() {
+        fs::create_dir_all(dir)?;
+        crate::safe_io::atomic_write(&path, bytes)?;
+    }
+    Ok(attachment)
+}
+
+/// Resolve an attachment source (local path or http(s) URL) into a stored ref.
+///
+/// Images (png/jpg/gif/webp) become [`AttachmentKind::Image`]; any other file
+/// must be valid UTF-8 and becomes [`AttachmentKind::File`]. Remote URLs must
+/// point at an image.
+pub fn attach_source(
+    dir: &Path,
+    source: &str,
+    base_dir: Option<&Path>,
+) -> io::Result<AttachmentRef> {
+    if is_url(source) {
+        let mime = image_mime_for(source).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "URL attachments must be images (png, jpg, gif, webp): {}",
+                    source
+                ),
+            )
+        })?;
+        return Ok(AttachmentRef {
+            kind: AttachmentKind::Image,
+            mime: mime.to_string(),
+            name: source.to_string(),
+            sha256: None,
+            url: Some(source.to_string()),
+            size: None,
+        });
+    }
+
+    let path = match base_dir {
+        Some(base) if Path::new(source).is_relative() => base.join(source),
+        _ => PathBuf::from(source),
+    };
+    let meta = fs::metadata(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot attach '{}': {}", source, e)))?;
+    if !meta.is_file() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("cannot attach '{}': not a regular file", source),
+        ));
+    }
+    check_size(source, meta.len())?;
+    let bytes = fs::read(&path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| source.to_string());
+
+    if let Some(mime) = image_mime_for(source) {
+        return store_bytes(dir, &name, AttachmentKind::Image, mime, &bytes);
+    }
+    if std::str::from_utf8(&bytes).is_err() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "cannot attach '{}': unsupported binary file (images: png, jpg, gif, webp; other files must be UTF-8 text)",
+                source
+            ),
+        ));
+    }
+    store_bytes(dir, &name, AttachmentKind::File, "text/plain", &bytes)
+}
+
+/// Build the content part for one attachment.
+fn content_part(dir: &Path, attachment: &AttachmentRef) -> io::Result<Value> {
+    if let Some(ref url) = attachment.url {
+        return Ok(json!({"type": "image_url", "image_url": {"url": url}}));
+    }
+    let path = blob_path(dir, attachment).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "attachment '{}' has neither sha256 nor url",
+                attachment.name
+            ),
+        )
+    })?;
+    let bytes = fs::read(&path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("attachment blob for '{}' missing: {}", attachment.name, e),
+        )
+    })?;
+    match attachment.kind {
+        AttachmentKind::Image => {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+            let data_uri = format!("data:{};base64,{}", attachment.mime, encoded);
+            Ok(json!({"type": "image_url", "image_url": {"url": data_uri}}))
+        }
+        AttachmentKind::File => {
+            let text = String::from_utf8_lossy(&bytes);
+            Ok(json!({
+                "type": "text",
+                "text": format!("[attachment: {}]\n```\n{}\n```", attachment.name, text),
+            }))
+        }
+    }
+}
+
+/// Expand `attachments` arrays on API messages into multipart content.
+///
+/// - user/assistant messages: content becomes `[text, ...parts]`
+/// - tool messages: the tool content stays a string (providers don't accept
+///   images in tool results); image parts are delivered in a synthetic user
+///   message placed after the run of consecutive tool messages.
+///
+/// The `attachments` key is removed from every message.
+pub fn inline_attachments(messages: &mut Vec<Value>, dir: &Path) -> io::Result<()> {
+    let mut out = Vec::with_capacity(messages.len());
+    let mut pending_tool_parts: Vec<Value> = Vec::new();
+
+    for mut msg in messages.drain(..) {
+        let is_tool = msg["role"].as_str() == Some("tool");
+        if !is_tool && !pending_tool_parts.is_empty() {
+            out.push(tool_attachment_message(std::mem::take(
+                &mut pending_tool_parts,
+            )));
+        }
+
+        let attachments: Vec<AttachmentRef> =
+            match msg.as_object_mut().and_then(|o| o.remove("attachments")) {
+                Some(v) => serde_json::from_value(v).map_err(|e| {
+                    io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid attachments: {}", e),
+                    )
+                })?,
+                None => Vec::new(),
+            };
+
+        if !attachments.is_empty() {
+            let parts = attachments
+                .iter()
+                .map(|a| content_part(dir, a))
+                .collect::<io::Result<Vec<_>>>()?;
+            if is_tool {
+                pending_tool_parts.extend(parts);
+            } else {
+                let text = msg["content"].as_str().unwrap_or_default().to_string();
+                let mut content = vec![json!({"type": "text", "text": text})];
+                content.extend(parts);
+                msg["content"] = Value::Array(content);
+            }
+        }
+        out.push(msg);
+    }
+    if !pending_tool_parts.is_empty() {
+        out.push(tool_attachment_message(pending_tool_parts));
+    }
+
+    *messages = out;
+    Ok(())
+}
+
+fn tool_attachment_message(parts: Vec<Value>) -> Value {
+    let mut content =
+        vec![json!({"type": "text", "text": "[attachments returned by tools above]"})];
+    content.extend(parts);
+    json!({"role": "user", "content": content})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_image_mime_for() {
+        assert_eq!(image_mime_for("a.PNG"), Some("image/png"));
+        assert_eq!(image_mime_for("https://x/y.jpeg?w=2"), Some("image/jpeg"));
+        assert_eq!(image_mime_for("notes.md"), None);
+    }
+
+    #[test]
+    fn test_attach_image_dedups_blob() {
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("pic.png");
+        fs::write(&src, b"\x89PNG fake").unwrap();
+        let dir = tmp.path().join("attachments");
+
+        let a = attach_source(&dir, src.to_str().unwrap(), None).unwrap();
+        let b = attach_source(&dir, "pic.png", Some(tmp.path())).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.kind, AttachmentKind::Image);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(blob_path(&dir, &a).unwrap().exists());
+    }
+
+    #[test]
+    fn test_attach_rejects_binary_and_non_image_urls() {
+        let tmp = TempDir::new().unwrap();
+        let src = tmp.path().join("blob.bin");
+        fs::write(&src, [0xff, 0xfe, 0x00]).unwrap();
+        let dir = tmp.path().join("attachments");
+        assert!(attach_source(&dir, src.to_str().unwrap(), None).is_err());
+        assert!(attach_source(&dir, "https://example.com/page.html", None).is_err());
+    }
+
+    #[test]
+    fn test_inline_user_message() {
+        let tmp = TempDir::new().unwrap();
+        let file = store_bytes(
+            tmp.path(),
+            "notes.txt",
+            AttachmentKind::File,
+            "text/plain",
+            b"hi",
+        )
+        .unwrap();
+        let url = attach_source(tmp.path(), "https://example.com/cat.png", None).unwrap();
+        let mut messages = vec![json!({
+            "role": "user",
+            "content": "look",
+            "attachments": [file, url],
+        })];
+        inline_attachments(&mut messages, tmp.path()).unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].get("attachments").is_none());
+        let parts = messages[0]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["text"], "look");
+        assert!(parts[1]["text"].as_str().unwrap().contains("notes.txt"));
+        assert_eq!(parts[2]["image_url"]["url"], "https://example.com/cat.png");
+    }
+
+    #[test]
+    fn test_inline_tool_images_follow_tool_run() {
+        let tmp = TempDir::new().unwrap();
+        let img = store_bytes(
+            tmp.path(),
+            "p.png",
+            AttachmentKind::Image,
+            "image/png",
+            b"png",
+        )
+        .unwrap();
+        let mut messages = vec![
+            json!({"role": "assistant", "content": "", "tool_calls": []}),
+            json!({"role": "tool", "tool_call_id": "a", "content": "ok", "attachments": [img]}),
+            json!({"role": "tool", "tool_call_id": "b", "content": "ok"}),
+            json!({"role": "assistant", "content": "done"}),
+        ];
+        inline_attachments(&mut messages, tmp.path()).unwrap();
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[1]["content"], "ok");
+        assert_eq!(messages[3]["role"], "user");
+        let url = messages[3]["content"][1]["image_url"]["url"]
+            .as_str()
+            .unwrap();
+        assert!(url.starts_with("data:image/png;base64,"));
+        assert_eq!(messages[4]["content"], "done");
+    }
+}
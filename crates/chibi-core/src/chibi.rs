@@ -37,6 +37,7 @@ use std::sync::{Arc, RwLock};
 use crate::api::sink::ResponseSink;
 use crate::api::{PromptOptions, send_prompt};
 use crate::config::ResolvedConfig;
+use crate::error::ChibiError;
 use crate::output::{CommandEvent, NoopSink, OutputSink};
 use crate::state::AppState;
 use crate::tools::{self, Tool, ToolCategory, ToolRegistry};
@@ -226,21 +227,25 @@ impl Chibi {
         let registry = Arc::new(RwLock::new(reg));
 
         // Mount /tools/sys/ as a virtual read-only backend backed by the registry.
-        // The local backend (already constructed in AppState) stays at root.
-        // We rebuild the VFS using the builder, transferring the existing local backend.
+        // The root backend (already constructed in AppState) stays at root.
+        // We rebuild the VFS using the builder, reopening the same root storage.
         let site_id = app.vfs.site_id().to_string();
-        let vfs_root = app.chibi_dir.join("vfs");
-        let local_backend = crate::vfs::LocalBackend::new(vfs_root);
+        let root_backend = crate::vfs::open_root_backend(&app.config.vfs, &app.chibi_dir)?;
         let tools_backend = crate::vfs::ToolsBackend::new(Arc::clone(&registry));
-        let contexts_backend = crate::vfs::ContextsBackend::new(
+        let mut contexts_backend = crate::vfs::ContextsBackend::new(
             Arc::clone(&app.state),
             app.chibi_dir.clone(),
             app.site_id.clone(),
         );
+        if let crate::vfs::RootBackend::Sqlite(store) = &root_backend {
+            contexts_backend = contexts_backend.with_registry_store(store.clone());
+        }
         app.vfs = crate::vfs::Vfs::builder(site_id)
-            .mount("/", Box::new(local_backend))
+            .mount("/", root_backend.boxed())
             .mount("/tools/sys", Box::new(tools_backend))
             .mount("/sys/contexts", Box::new(contexts_backend))
+            .versioning(app.config.vfs.versioning.clone())
+            .quotas(app.config.vfs.quotas.clone())
             .build();
 
         #[cfg(feature = "synthesised-tools")]
@@ -259,6 +264,9 @@ impl Chibi {
             let tools_cfg = app.config.tools.clone();
             app.vfs
                 .set_scm_change_callback(Arc::new(move |path, kind, content| match kind {
+                    _ if crate::tools::scheme_lib::is_library_path(path) => {
+                        crate::tools::synthesised::reload_library(&reg, path, content, &tools_cfg);
+                    }
                     crate::vfs::ScmChangeKind::Write => {
                         if let Some(bytes) = content {
                             crate::tools::synthesised::reload_tool_from_content(
@@ -272,6 +280,52 @@ impl Chibi {
                 }));
         }
 
+        // Deliver VFS changes to watching contexts' inboxes and to
+        // `vfs_changed` hooks.
+        {
+            let reg = Arc::clone(&registry);
+            let contexts_dir = app.chibi_dir.join("contexts");
+            app.vfs
+                .set_change_callback(Arc::new(move |change, watchers| {
+                    for watcher in watchers {
+                        let dir = contexts_dir.join(watcher);
+                        if !dir.is_dir() {
+                            continue;
+                        }
+                        let entry = crate::context::InboxEntry {
+                            id: uuid::Uuid::new_v4().to_string(),
+                            timestamp: crate::context::now_timestamp(),
+                            from: change.caller.clone(),
+                            to: watcher.clone(),
+                            content: format!("[vfs] {change}"),
+                        };
+                        if let Err(e) = crate::inbox::append_inbox_entry(&dir, &entry) {
+                            eprintln!("[WARN] vfs watch notification for '{watcher}' failed: {e}");
+                        }
+                    }
+                    let plugin_tools: Vec<Tool> = reg
+                        .read()
+                        .unwrap()
+                        .filter(|t| t.is_hook_eligible())
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                    let hook_data = serde_json::json!({
+                        "path": change.path.as_str(),
+                        "kind": change.kind.as_str(),
+                        "caller": change.caller,
+                        "from": change.from.as_ref().map(|f| f.as_str()),
+                        "watchers": watchers,
+                    });
+                    let _ = tools::execute_hook(
+                        &plugin_tools,
+                        tools::HookPoint::VfsChanged,
+                        &hook_data,
+                        None,
+                    );
+                }));
+        }
+
         Ok(Self {
             app,
             registry,
@@ -325,8 +379,8 @@ impl Chibi {
 
     /// Shutdown the session.
     ///
-    /// Executes `OnEnd` hooks. Call this once at the end of a session,
-    /// after all prompts are complete.
+    /// Executes `OnEnd` hooks, then tells persistent plugins to shut down.
+    /// Call this once at the end of a session, after all prompts are complete.
     pub fn shutdown(&self) -> io::Result<Vec<(String, serde_json::Value)>> {
         let reg = self.registry.read().unwrap();
         let hook_data = serde_json::json!({
@@ -340,7 +394,9 @@ impl Chibi {
             .cloned()
             .collect();
         drop(reg);
-        tools::execute_hook(&plugin_tools, tools::HookPoint::OnEnd, &hook_data, None)
+        let results = tools::execute_hook(&plugin_tools, tools::HookPoint::OnEnd, &hook_data, None);
+        tools::shutdown_persistent_plugins(&plugin_tools);
+        results
     }
 
     /// Clear a context, executing PreClear/PostClear hooks.
@@ -489,7 +545,29 @@ impl Chibi {
                 format!("unknown tool: {name}"),
             ));
         };
-        tools::ToolRegistry::dispatch_impl(tool_impl, name, &args, &call_ctx).await
+
+        // pre_tool hooks apply to direct calls too. There is no model to show
+        // a block message to, so a block is reported to the caller.
+        let plugin_tools: Vec<Tool> = self
+            .registry
+            .read()
+            .unwrap()
+            .filter(|t| t.is_hook_eligible())
+            .into_iter()
+            .cloned()
+            .collect();
+        let hook_data = serde_json::json!({ "tool_name": name, "arguments": args });
+        let hook_results =
+            tools::execute_hook(&plugin_tools, tools::HookPoint::PreTool, &hook_data, None)?;
+        let pre_tool = crate::api::send::apply_pre_tool_results(hook_results, name, args);
+        if pre_tool.blocked {
+            return Err(ChibiError::HookDenied {
+                hook: "pre_tool".to_string(),
+                message: pre_tool.block_message,
+            }
+            .into());
+        }
+        tools::ToolRegistry::dispatch_impl(tool_impl, name, &pre_tool.args, &call_ctx).await
     }
 
     // NOTE: The following methods were removed in the stateless-core refactor:
@@ -846,4 +924,45 @@ mod tests {
         let output = result.unwrap();
         assert!(output.contains("Reflection updated"));
     }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_execute_tool_pre_tool_block_is_hook_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (chibi, tmp) = create_test_chibi();
+        let script = tmp.path().join("gate.sh");
+        std::fs::write(
+            &script,
+            "#!/bin/bash\necho '{\"block\": true, \"message\": \"not today\"}'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        chibi.registry.write().unwrap().register(Tool {
+            name: "gate".to_string(),
+            description: String::new(),
+            parameters: serde_json::json!({}),
+            hooks: vec![tools::HookPoint::PreTool],
+            hook_priorities: Default::default(),
+            metadata: tools::ToolMetadata::new(),
+            summary_params: vec![],
+            r#impl: tools::ToolImpl::Plugin(script),
+            category: ToolCategory::Plugin,
+        });
+
+        let err = chibi
+            .execute_tool(
+                "default",
+                "update_reflection",
+                serde_json::json!({"content": "x"}),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            ChibiError::find(&err),
+            Some(ChibiError::HookDenied { hook, message })
+                if hook == "pre_tool" && message == "not today"
+        ));
+        assert!(chibi.app.load_reflection().unwrap().is_empty());
+    }
 }
@@ -420,8 +420,9 @@ impl ApiParams {
 /// - `Unsandboxed`: full R7RS access, no step limit. Use with trusted tools only.
 ///
 /// Configured via `[tools.tiers]` in `chibi.toml`. Most specific path wins.
+/// Ordered from least to most trusted.
 #[cfg(feature = "synthesised-tools")]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum SandboxTier {
     /// Tier 1: sandboxed, safe modules only, step limit. Default.
     #[default]
@@ -514,9 +515,108 @@ pub struct ToolsConfig {
 /// If introspection is needed in future, add it to `ResolvedConfig::list_fields`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VfsConfig {
-    /// Backend name. Currently only "local" is built in.
+    /// Root storage backend: "local" (files under `vfs/`) or "sqlite"
+    /// (single `vfs.db` database).
     #[serde(default = "default_vfs_backend")]
     pub backend: String,
+    /// File version history kept by the VFS router (`[vfs.versioning]`).
+    #[serde(default)]
+    pub versioning: VersioningConfig,
+    /// Per-zone storage limits (`[vfs.quotas]`).
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+}
+
+/// VFS version history settings (`[vfs.versioning]`).
+///
+/// Every write, append, delete, copy and rename of a file outside `/sys/` is
+/// recorded under `/sys/versions/`. Retention is a per-file version count,
+/// resolved by longest matching path prefix in `retention`; `0` disables
+/// versioning below that prefix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersioningConfig {
+    /// Master switch (default: true).
+    #[serde(default = "default_versioning_enabled")]
+    pub enabled: bool,
+    /// Versions kept per file when no `retention` prefix matches (default: 20).
+    #[serde(default = "default_max_versions")]
+    pub max_versions: usize,
+    /// Per-zone overrides: VFS path prefix → versions kept per file.
+    #[serde(default)]
+    pub retention: BTreeMap<String, usize>,
+}
+
+fn default_versioning_enabled() -> bool {
+    true
+}
+
+fn default_max_versions() -> usize {
+    20
+}
+
+impl Default for VersioningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_versions: default_max_versions(),
+            retention: BTreeMap::new(),
+        }
+    }
+}
+
+impl VersioningConfig {
+    /// Number of versions to keep for `path` (longest matching prefix wins).
+    /// `0` means the path is not versioned.
+    pub fn retention_for(&self, path: &str) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        self.retention
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.max_versions, |(_, keep)| *keep)
+    }
+}
+
+/// VFS storage quotas (`[vfs.quotas]`).
+///
+/// Limits apply to each zone instance separately: every context's
+/// `/home/<ctx>/` gets the full `home` budget, every flock (including
+/// `/site/`) the `flock` budget. Unset limits are unlimited. `/sys/` has no
+/// quota; the tool cache is bounded by `tool_cache_max_age_days` instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    /// Each context's home directory.
+    #[serde(default)]
+    pub home: ZoneQuota,
+    /// Each flock directory, and `/site/`.
+    #[serde(default)]
+    pub flock: ZoneQuota,
+    /// `/shared/`.
+    #[serde(default)]
+    pub shared: ZoneQuota,
+    /// The writable `/tools/` tree (tools and scheme libraries).
+    #[serde(default)]
+    pub tools: ZoneQuota,
+}
+
+/// Byte and file-count limits for one VFS zone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZoneQuota {
+    /// Total size of all files in the zone.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Number of files in the zone.
+    #[serde(default)]
+    pub max_files: Option<u64>,
+}
+
+impl ZoneQuota {
+    /// Whether neither limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_bytes.is_none() && self.max_files.is_none()
+    }
 }
 
 /// Site identity configuration.
@@ -537,6 +637,8 @@ impl Default for VfsConfig {
     fn default() -> Self {
         Self {
             backend: default_vfs_backend(),
+            versioning: VersioningConfig::default(),
+            quotas: QuotaConfig::default(),
         }
     }
 }
@@ -782,8 +884,17 @@ impl ConfigDefaults {
     pub const FUEL_EMPTY_RESPONSE_COST: usize = 15;
     pub const LOCK_HEARTBEAT_SECONDS: u64 = 30;
     pub const ROLLING_COMPACT_DROP_PERCENTAGE: f32 = 50.0;
+    pub const COMPACT_KEEP_TURNS: usize = 8;
+    pub const LOCK_WAIT_TIMEOUT_SECONDS: u64 = 300;
+    pub const PROGRESS_WINDOW: usize = 12;
+    pub const PROGRESS_ERROR_STREAK: usize = 3;
+    pub const PROGRESS_STALL_CALLS: usize = 40;
+    pub const PROVIDER_MAX_RETRIES: usize = 3;
+    pub const PROVIDER_RETRY_BASE_MS: u64 = 1000;
     pub const TOOL_OUTPUT_CACHE_THRESHOLD: usize = 4_000;
     pub const TOOL_CACHE_MAX_AGE_DAYS: u64 = 7;
+    /// 1 GiB
+    pub const TOOL_CACHE_MAX_BYTES: u64 = 1024 * 1024 * 1024;
     pub const TOOL_CACHE_PREVIEW_CHARS: usize = 500;
 
     // String defaults
@@ -793,6 +904,12 @@ impl ConfigDefaults {
     pub const MODEL: &'static str = "ratatoskr:free/agentic";
     /// Default cost tier for resolving subagent presets
     pub const SUBAGENT_COST_TIER: &'static str = "free";
+    /// Default auto-compaction strategy (see `api::compact_strategy`)
+    pub const COMPACTION_STRATEGY: &'static str = "rolling";
+    /// Default policy when a context is locked (see `lock::LockPolicy`)
+    pub const LOCK_POLICY: &'static str = "fail";
+    /// Default response to a stuck agentic loop (see `api::progress`)
+    pub const PROGRESS_ACTION: &'static str = "fuel";
 }
 
 // Thin wrappers for serde's #[serde(default = "...")] requirement
@@ -826,12 +943,45 @@ fn default_username() -> String {
 fn default_rolling_compact_drop_percentage() -> f32 {
     ConfigDefaults::ROLLING_COMPACT_DROP_PERCENTAGE
 }
+fn default_compaction_strategy() -> String {
+    ConfigDefaults::COMPACTION_STRATEGY.to_string()
+}
+fn default_compact_keep_turns() -> usize {
+    ConfigDefaults::COMPACT_KEEP_TURNS
+}
+fn default_lock_policy() -> String {
+    ConfigDefaults::LOCK_POLICY.to_string()
+}
+fn default_lock_wait_timeout_seconds() -> u64 {
+    ConfigDefaults::LOCK_WAIT_TIMEOUT_SECONDS
+}
+fn default_progress_action() -> String {
+    ConfigDefaults::PROGRESS_ACTION.to_string()
+}
+fn default_progress_window() -> usize {
+    ConfigDefaults::PROGRESS_WINDOW
+}
+fn default_progress_error_streak() -> usize {
+    ConfigDefaults::PROGRESS_ERROR_STREAK
+}
+fn default_progress_stall_calls() -> usize {
+    ConfigDefaults::PROGRESS_STALL_CALLS
+}
+fn default_provider_max_retries() -> usize {
+    ConfigDefaults::PROVIDER_MAX_RETRIES
+}
+fn default_provider_retry_base_ms() -> u64 {
+    ConfigDefaults::PROVIDER_RETRY_BASE_MS
+}
 fn default_tool_output_cache_threshold() -> usize {
     ConfigDefaults::TOOL_OUTPUT_CACHE_THRESHOLD
 }
 fn default_tool_cache_max_age_days() -> u64 {
     ConfigDefaults::TOOL_CACHE_MAX_AGE_DAYS
 }
+fn default_tool_cache_max_bytes() -> u64 {
+    ConfigDefaults::TOOL_CACHE_MAX_BYTES
+}
 fn default_auto_cleanup_cache() -> bool {
     ConfigDefaults::AUTO_CLEANUP_CACHE
 }
@@ -903,12 +1053,55 @@ pub struct Config {
     #[serde(default = "default_rolling_compact_drop_percentage")]
     /// Percentage of oldest messages to drop during rolling compaction.
     pub rolling_compact_drop_percentage: f32,
+    /// Strategy used by auto-compaction: `rolling`, `summarize`, `keep_last_turns`,
+    /// `elide_tool_results`, `structured`, or `custom:<name>` for a `custom_compact` hook.
+    #[serde(default = "default_compaction_strategy")]
+    pub compaction_strategy: String,
+    /// Recent user turns left untouched by `keep_last_turns` and `elide_tool_results`.
+    #[serde(default = "default_compact_keep_turns")]
+    pub compact_keep_turns: usize,
+    /// What a prompt does when its context is locked by another process:
+    /// `fail`, `wait` (up to `lock_wait_timeout_seconds`) or `queue` (into the inbox).
+    #[serde(default = "default_lock_policy")]
+    pub lock_policy: String,
+    /// How long the `wait` lock policy waits before giving up.
+    #[serde(default = "default_lock_wait_timeout_seconds")]
+    pub lock_wait_timeout_seconds: u64,
+    /// Response when the agentic loop stops making progress:
+    /// `nudge`, `fuel` (nudge and charge `fuel_empty_response_cost`) or `call_user`.
+    #[serde(default = "default_progress_action")]
+    pub progress_action: String,
+    /// Recent tool calls searched for cycles (0 disables cycle detection).
+    #[serde(default = "default_progress_window")]
+    pub progress_window: usize,
+    /// Consecutive failures of one tool that count as no progress (0 disables).
+    #[serde(default = "default_progress_error_streak")]
+    pub progress_error_streak: usize,
+    /// Tool calls without a successful file write that count as a stall (0 disables).
+    #[serde(default = "default_progress_stall_calls")]
+    pub progress_stall_calls: usize,
+    /// Retries of a failed provider request (rate limit, 5xx, dropped stream)
+    /// before moving to the next model in `model_fallbacks`.
+    #[serde(default = "default_provider_max_retries")]
+    pub provider_max_retries: usize,
+    /// Base delay for exponential retry backoff; a `Retry-After` hint wins.
+    #[serde(default = "default_provider_retry_base_ms")]
+    pub provider_retry_base_ms: u64,
+    /// Models tried in order when the current one keeps failing or the
+    /// request exceeds its context window.
+    #[serde(default)]
+    pub model_fallbacks: Vec<String>,
     /// Threshold (in chars) above which tool output is cached
     #[serde(default = "default_tool_output_cache_threshold")]
     pub tool_output_cache_threshold: usize,
     /// Maximum age in days for cached tool outputs
     #[serde(default = "default_tool_cache_max_age_days")]
     pub tool_cache_max_age_days: u64,
+    /// Size budget in bytes for the shared tool output blob store; least
+    /// recently referenced blobs are evicted past it (0 = unlimited).
+    /// Global-only: the store is shared by all contexts.
+    #[serde(default = "default_tool_cache_max_bytes")]
+    pub tool_cache_max_bytes: u64,
     /// Automatically cleanup old cache entries on exit
     #[serde(default = "default_auto_cleanup_cache")]
     pub auto_cleanup_cache: bool,
@@ -980,6 +1173,28 @@ pub struct LocalConfig {
     pub reflection_character_limit: Option<usize>,
     /// Rolling compaction drop percentage override for this context. `None` = use global config.
     pub rolling_compact_drop_percentage: Option<f32>,
+    /// Auto-compaction strategy override for this context. `None` = use global config.
+    pub compaction_strategy: Option<String>,
+    /// Turns kept by `keep_last_turns`/`elide_tool_results`. `None` = use global config.
+    pub compact_keep_turns: Option<usize>,
+    /// Lock policy override for this context. `None` = use global config.
+    pub lock_policy: Option<String>,
+    /// Lock wait timeout override for this context. `None` = use global config.
+    pub lock_wait_timeout_seconds: Option<u64>,
+    /// Progress action override for this context. `None` = use global config.
+    pub progress_action: Option<String>,
+    /// Cycle window override for this context. `None` = use global config.
+    pub progress_window: Option<usize>,
+    /// Error streak override for this context. `None` = use global config.
+    pub progress_error_streak: Option<usize>,
+    /// Stall threshold override for this context. `None` = use global config.
+    pub progress_stall_calls: Option<usize>,
+    /// Provider retry count override for this context. `None` = use global config.
+    pub provider_max_retries: Option<usize>,
+    /// Retry backoff base override for this context. `None` = use global config.
+    pub provider_retry_base_ms: Option<u64>,
+    /// Model fallback chain override for this context. `None` = use global config.
+    pub model_fallbacks: Option<Vec<String>>,
     /// Threshold (in chars) above which tool output is cached
     pub tool_output_cache_threshold: Option<usize>,
     /// Maximum age in days for cached tool outputs
@@ -1049,6 +1264,17 @@ impl LocalConfig {
             reflection_enabled,
             reflection_character_limit,
             rolling_compact_drop_percentage,
+            compaction_strategy,
+            compact_keep_turns,
+            lock_policy,
+            lock_wait_timeout_seconds,
+            progress_action,
+            progress_window,
+            progress_error_streak,
+            progress_stall_calls,
+            provider_max_retries,
+            provider_retry_base_ms,
+            model_fallbacks,
             tool_output_cache_threshold,
             tool_cache_max_age_days,
             auto_cleanup_cache,
@@ -1097,6 +1323,28 @@ pub struct ResolvedConfig {
     pub reflection_character_limit: usize,
     /// Percentage of messages to drop during rolling compaction
     pub rolling_compact_drop_percentage: f32,
+    /// Auto-compaction strategy name
+    pub compaction_strategy: String,
+    /// Recent user turns kept by `keep_last_turns` and `elide_tool_results`
+    pub compact_keep_turns: usize,
+    /// Policy when the context is locked: `fail`, `wait` or `queue`
+    pub lock_policy: String,
+    /// Seconds the `wait` lock policy waits before giving up
+    pub lock_wait_timeout_seconds: u64,
+    /// Response to a stuck agentic loop: `nudge`, `fuel` or `call_user`
+    pub progress_action: String,
+    /// Recent tool calls searched for cycles
+    pub progress_window: usize,
+    /// Consecutive failures of one tool before a progress signal
+    pub progress_error_streak: usize,
+    /// Tool calls without a file write before a progress signal
+    pub progress_stall_calls: usize,
+    /// Retries of a failed provider request before falling back
+    pub provider_max_retries: usize,
+    /// Base delay (ms) for exponential retry backoff
+    pub provider_retry_base_ms: u64,
+    /// Models tried in order after the current one fails
+    pub model_fallbacks: Vec<String>,
     /// Threshold (in chars) above which tool output is cached
     pub tool_output_cache_threshold: usize,
     /// Maximum age in days for cached tool outputs
@@ -1137,8 +1385,12 @@ impl ResolvedConfig {
                      context_window_limit, reflection_character_limit,
                      fuel, fuel_empty_response_cost,
                      tool_output_cache_threshold, tool_cache_preview_chars,
-                     tool_cache_max_age_days;
-            clone: model, username, fallback_tool, subagent_cost_tier;
+                     tool_cache_max_age_days, compact_keep_turns,
+                     lock_wait_timeout_seconds, progress_window,
+                     progress_error_streak, progress_stall_calls,
+                     provider_max_retries, provider_retry_base_ms;
+            clone: model, username, fallback_tool, subagent_cost_tier,
+                   compaction_strategy, lock_policy, progress_action;
             int: warn_threshold_percent, auto_compact_threshold;
             fmt: rolling_compact_drop_percentage;
         );
@@ -1160,6 +1412,13 @@ impl ResolvedConfig {
                     Some(self.file_tools_allowed_paths.join(", "))
                 }
             }
+            "model_fallbacks" => {
+                if self.model_fallbacks.is_empty() {
+                    Some("(empty)".to_string())
+                } else {
+                    Some(self.model_fallbacks.join(", "))
+                }
+            }
 
             // API params (api.*)
             "api.temperature" => self.api.temperature.map(|v| format!("{}", v)),
@@ -1227,6 +1486,17 @@ impl ResolvedConfig {
             "reflection_enabled",
             "reflection_character_limit",
             "rolling_compact_drop_percentage",
+            "compaction_strategy",
+            "compact_keep_turns",
+            "lock_policy",
+            "lock_wait_timeout_seconds",
+            "progress_action",
+            "progress_window",
+            "progress_error_streak",
+            "progress_stall_calls",
+            "provider_max_retries",
+            "provider_retry_base_ms",
+            "model_fallbacks",
             "fallback_tool",
             "tool_output_cache_threshold",
             "tool_cache_max_age_days",
@@ -1271,15 +1541,32 @@ impl ResolvedConfig {
                   reflection_enabled, auto_cleanup_cache;
             usize: context_window_limit, reflection_character_limit,
                    fuel, fuel_empty_response_cost,
-                   tool_output_cache_threshold, tool_cache_preview_chars;
-            u64: tool_cache_max_age_days;
+                   tool_output_cache_threshold, tool_cache_preview_chars,
+                   compact_keep_turns, progress_window, progress_error_streak,
+                   progress_stall_calls, provider_max_retries;
+            u64: tool_cache_max_age_days, lock_wait_timeout_seconds,
+                 provider_retry_base_ms;
             f32: warn_threshold_percent, auto_compact_threshold,
                  rolling_compact_drop_percentage;
-            string: model, username, fallback_tool;
+            string: model, username, fallback_tool,
+                    lock_policy, progress_action;
         );
 
         // Fields with custom parsing
         match path {
+            "compaction_strategy" => {
+                crate::api::compact_strategy::CompactionStrategy::parse(value)
+                    .map_err(|e| e.to_string())?;
+                self.compaction_strategy = value.to_string();
+            }
+            "model_fallbacks" => {
+                // Comma-separated model IDs, tried in order; empty clears the chain.
+                self.model_fallbacks = value
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+            }
             // API params (api.*)
             "api.temperature" => {
                 self.api.temperature = Some(
@@ -1534,6 +1821,17 @@ mod tests {
             reflection_enabled: true,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: true,
@@ -1758,6 +2056,17 @@ mod tests {
             reflection_enabled: false,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: true,
@@ -1835,6 +2144,25 @@ mod tests {
         assert_eq!(config.storage.partition_max_entries, Some(1000));
     }
 
+    #[test]
+    fn test_set_field_model_fallbacks() {
+        let mut config = test_resolved_config();
+        assert_eq!(
+            config.get_field("model_fallbacks"),
+            Some("(empty)".to_string())
+        );
+        config
+            .set_field("model_fallbacks", "gpt-4o-mini, ,claude-haiku")
+            .unwrap();
+        assert_eq!(config.model_fallbacks, vec!["gpt-4o-mini", "claude-haiku"]);
+        assert_eq!(
+            config.get_field("model_fallbacks"),
+            Some("gpt-4o-mini, claude-haiku".to_string())
+        );
+        config.set_field("model_fallbacks", "").unwrap();
+        assert!(config.model_fallbacks.is_empty());
+    }
+
     #[test]
     fn test_set_field_unknown_goes_to_extra() {
         let mut config = test_resolved_config();
@@ -1924,6 +2252,29 @@ mod tests {
         assert_eq!(config.vfs.backend, "fossil");
     }
 
+    #[test]
+    fn test_vfs_versioning_retention_longest_prefix() {
+        let config: Config = toml::from_str(
+            "[vfs.versioning]\nmax_versions = 5\n[vfs.versioning.retention]\n\"/flocks/\" = 50\n\"/flocks/scratch/\" = 0\n",
+        )
+        .unwrap();
+        let v = &config.vfs.versioning;
+        assert!(v.enabled);
+        assert_eq!(v.retention_for("/shared/notes.md"), 5);
+        assert_eq!(v.retention_for("/flocks/dev/plan.md"), 50);
+        assert_eq!(v.retention_for("/flocks/scratch/tmp"), 0);
+    }
+
+    #[test]
+    fn test_vfs_quotas_default_unlimited() {
+        let config: Config = toml::from_str("[vfs.quotas]\nhome = { max_bytes = 1024 }\n").unwrap();
+        let q = &config.vfs.quotas;
+        assert_eq!(q.home.max_bytes, Some(1024));
+        assert_eq!(q.home.max_files, None);
+        assert!(!q.home.is_unlimited());
+        assert!(q.shared.is_unlimited());
+    }
+
     #[cfg(feature = "synthesised-tools")]
     #[test]
     fn test_resolve_env_present() {
@@ -1,3 +1,4 @@
+use crate::attachments::AttachmentRef;
 use serde::{Deserialize, Serialize};
 use std::io::{self, ErrorKind};
 use std::path::Path;
@@ -213,6 +214,10 @@ pub struct TranscriptEntry {
     /// NOT used as a filter criterion — purely informational.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub flow_control: bool,
+    /// Images and files attached to this entry (user prompts, `view_image` results).
+    /// Blobs live in the context's `attachments/` directory; see [`crate::attachments`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachmentRef>,
 }
 
 impl TranscriptEntry {
@@ -235,6 +240,7 @@ pub struct TranscriptEntryBuilder {
     tool_call_id: Option<String>,
     role: Option<String>,
     flow_control: bool,
+    attachments: Vec<AttachmentRef>,
 }
 
 impl TranscriptEntryBuilder {
@@ -286,6 +292,12 @@ impl TranscriptEntryBuilder {
         self
     }
 
+    /// Attach images/files to the entry
+    pub fn attachments(mut self, attachments: Vec<AttachmentRef>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
     /// Build the TranscriptEntry with auto-generated ID and current timestamp
     pub fn build(self) -> TranscriptEntry {
         TranscriptEntry {
@@ -301,6 +313,7 @@ impl TranscriptEntryBuilder {
             tool_call_id: self.tool_call_id,
             role: self.role,
             flow_control: self.flow_control,
+            attachments: self.attachments,
         }
     }
 }
@@ -0,0 +1,340 @@
+//! Typed errors for failures callers need to tell apart.
+//!
+//! Core APIs return `io::Result` throughout. A `ChibiError` travels inside the
+//! `io::Error` (with a matching `ErrorKind`), so `?` chains and `kind()` checks
+//! keep working while frontends recover the variant with [`ChibiError::find`]
+//! and report its stable [`code`](ChibiError::code).
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Explicit status mentions: `status 429`, `status code: 503`, `HTTP/1.1 502`.
+static STATUS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(?:status(?:\s+code)?|http(?:/[\d.]+)?)\s*[:=]?\s*(\d{3})\b")
+        .expect("valid regex")
+});
+/// Status followed by its reason phrase: `429 Too Many Requests`.
+static REASON_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(\d{3})\s+(?:bad request|unauthorized|payment required|forbidden|not found|request timeout|too many requests|internal server error|bad gateway|service unavailable|gateway timeout)\b",
+    )
+    .expect("valid regex")
+});
+static RETRY_AFTER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)retry[- _]after\D{0,3}(\d+)").expect("valid regex"));
+
+/// A failure with a stable machine-readable code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChibiError {
+    /// The named context has no directory or transcript.
+    ContextNotFound { context: String },
+    /// Another process holds the context lock.
+    ContextLocked { context: String, lock_file: PathBuf },
+    /// A config file or value could not be used.
+    ConfigInvalid { message: String },
+    /// The agentic loop ran out of fuel before finishing.
+    FuelExhausted { total: usize },
+    /// The LLM provider or gateway failed (`status` when an HTTP status is known).
+    Provider {
+        status: Option<u16>,
+        message: String,
+    },
+    /// The provider rejected the request for rate limiting.
+    RateLimited {
+        retry_after_secs: Option<u64>,
+        message: String,
+    },
+    /// A hook refused the operation.
+    HookDenied { hook: String, message: String },
+    /// A directly invoked tool failed.
+    ToolFailed { tool: String, message: String },
+    /// Structured output still failed schema validation after every attempt.
+    SchemaMismatch {
+        attempts: usize,
+        errors: Vec<String>,
+    },
+}
+
+impl ChibiError {
+    /// Stable code reported to programmatic callers (e.g. the chibi-json `done` signal).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ContextNotFound { .. } => "context_not_found",
+            Self::ContextLocked { .. } => "context_locked",
+            Self::ConfigInvalid { .. } => "config_invalid",
+            Self::FuelExhausted { .. } => "fuel_exhausted",
+            Self::Provider { .. } => "provider_error",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::HookDenied { .. } => "hook_denied",
+            Self::ToolFailed { .. } => "tool_failed",
+            Self::SchemaMismatch { .. } => "schema_mismatch",
+        }
+    }
+
+    /// `io::ErrorKind` used when this error is converted into an `io::Error`.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::ContextNotFound { .. } => ErrorKind::NotFound,
+            Self::ContextLocked { .. } => ErrorKind::AlreadyExists,
+            Self::ConfigInvalid { .. } => ErrorKind::InvalidInput,
+            Self::FuelExhausted { .. } => ErrorKind::QuotaExceeded,
+            Self::HookDenied { .. } => ErrorKind::PermissionDenied,
+            Self::SchemaMismatch { .. } => ErrorKind::InvalidData,
+            Self::Provider { .. } | Self::RateLimited { .. } | Self::ToolFailed { .. } => {
+                ErrorKind::Other
+            }
+        }
+    }
+
+    /// Whether repeating the same request later may succeed.
+    ///
+    /// True for rate limits, locked contexts, and provider failures that are
+    /// not the request's fault (5xx, 408, or no status at all, e.g. a dropped
+    /// connection). Bad input, missing contexts and denials are final.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::ContextLocked { .. } => true,
+            Self::Provider { status, .. } => match status {
+                Some(code) => *code >= 500 || *code == 408,
+                None => true,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the provider rejected the request for exceeding the model's
+    /// context window. Retrying the same model cannot help; a fallback with a
+    /// larger window might.
+    pub fn is_context_overflow(&self) -> bool {
+        let Self::Provider { message, .. } = self else {
+            return false;
+        };
+        let lower = message.to_lowercase();
+        [
+            "context length",
+            "context_length",
+            "maximum context",
+            "context window",
+            "too many tokens",
+            "prompt is too long",
+        ]
+        .iter()
+        .any(|needle| lower.contains(needle))
+    }
+
+    /// Classify a provider/gateway failure from its message.
+    ///
+    /// The gateway reports failures as text, so the HTTP status (and any
+    /// retry-after hint) is recovered from it; 429s and "rate limit" wording
+    /// become `RateLimited`.
+    pub fn provider(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let status = STATUS_RE
+            .captures(&message)
+            .or_else(|| REASON_RE.captures(&message))
+            .and_then(|c| c[1].parse::<u16>().ok());
+        let lower = message.to_lowercase();
+        if status == Some(429)
+            || lower.contains("rate limit")
+            || lower.contains("too many requests")
+        {
+            let retry_after_secs = RETRY_AFTER_RE
+                .captures(&message)
+                .and_then(|c| c[1].parse().ok());
+            return Self::RateLimited {
+                retry_after_secs,
+                message,
+            };
+        }
+        Self::Provider { status, message }
+    }
+
+    /// The `ChibiError` carried by `e`, if any.
+    pub fn find(e: &io::Error) -> Option<&ChibiError> {
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<ChibiError>())
+    }
+
+    /// Wrap in an `io::Error` with an explicit kind, for call sites whose
+    /// callers already match on a different `ErrorKind`.
+    pub fn with_kind(self, kind: ErrorKind) -> io::Error {
+        io::Error::new(kind, self)
+    }
+}
+
+impl fmt::Display for ChibiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContextNotFound { context } => write!(f, "Context '{}' does not exist", context),
+            Self::ContextLocked { context, lock_file } => write!(
+                f,
+                "Context '{}' is locked by another process. Lock file: {}",
+                context,
+                lock_file.display()
+            ),
+            Self::ConfigInvalid { message } => write!(f, "{}", message),
+            Self::FuelExhausted { total } => {
+                write!(f, "Fuel exhausted (0/{}) before the task finished", total)
+            }
+            Self::Provider { message, .. } | Self::RateLimited { message, .. } => {
+                write!(f, "{}", message)
+            }
+            Self::HookDenied { hook, message } => {
+                write!(f, "Blocked by {} hook: {}", hook, message)
+            }
+            Self::ToolFailed { tool, message } => write!(f, "Tool '{}' failed: {}", tool, message),
+            Self::SchemaMismatch { attempts, errors } => write!(
+                f,
+                "Reply did not match the schema after {} attempt(s): {}",
+                attempts,
+                errors.join("; ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChibiError {}
+
+impl From<ChibiError> for io::Error {
+    fn from(e: ChibiError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_round_trips_through_io_error() {
+        let err: io::Error = ChibiError::ContextNotFound {
+            context: "nope".to_string(),
+        }
+        .into();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "Context 'nope' does not exist");
+        assert_eq!(
+            ChibiError::find(&err).map(ChibiError::code),
+            Some("context_not_found")
+        );
+    }
+
+    #[test]
+    fn test_find_none_for_plain_io_error() {
+        let err = io::Error::new(ErrorKind::NotFound, "x");
+        assert!(ChibiError::find(&err).is_none());
+    }
+
+    #[test]
+    fn test_with_kind_keeps_variant() {
+        let err = ChibiError::ConfigInvalid {
+            message: "bad".to_string(),
+        }
+        .with_kind(ErrorKind::InvalidData);
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(ChibiError::find(&err).unwrap().code(), "config_invalid");
+    }
+
+    #[test]
+    fn test_provider_rate_limited_by_status() {
+        let e = ChibiError::provider("Gateway error: HTTP 429: slow down, retry-after: 12");
+        assert_eq!(
+            e,
+            ChibiError::RateLimited {
+                retry_after_secs: Some(12),
+                message: "Gateway error: HTTP 429: slow down, retry-after: 12".to_string(),
+            }
+        );
+        assert!(e.is_retryable());
+    }
+
+    #[test]
+    fn test_provider_rate_limited_by_wording() {
+        let e = ChibiError::provider("Stream error: Rate limit reached for requests");
+        assert_eq!(e.code(), "rate_limited");
+    }
+
+    #[test]
+    fn test_provider_status_from_reason_phrase() {
+        let e = ChibiError::provider("Gateway error: 401 Unauthorized");
+        assert!(matches!(
+            e,
+            ChibiError::Provider {
+                status: Some(401),
+                ..
+            }
+        ));
+        assert!(!e.is_retryable());
+    }
+
+    #[test]
+    fn test_provider_server_error_retryable() {
+        let e = ChibiError::provider("Gateway error: status code: 503");
+        assert!(matches!(
+            e,
+            ChibiError::Provider {
+                status: Some(503),
+                ..
+            }
+        ));
+        assert!(e.is_retryable());
+    }
+
+    #[test]
+    fn test_provider_ignores_unrelated_numbers() {
+        let e = ChibiError::provider(
+            "Gateway error: maximum context length is 8192 tokens, you requested 450 tokens",
+        );
+        assert!(matches!(e, ChibiError::Provider { status: None, .. }));
+        assert!(e.is_context_overflow());
+    }
+
+    #[test]
+    fn test_context_overflow_only_for_provider_errors() {
+        assert!(!ChibiError::provider("Gateway error: status code: 503").is_context_overflow());
+        assert!(
+            !ChibiError::ToolFailed {
+                tool: "shell".to_string(),
+                message: "context window".to_string(),
+            }
+            .is_context_overflow()
+        );
+        assert!(
+            ChibiError::provider("Gateway error: 400 Bad Request: prompt is too long")
+                .is_context_overflow()
+        );
+    }
+
+    #[test]
+    fn test_schema_mismatch_display() {
+        let err: io::Error = ChibiError::SchemaMismatch {
+            attempts: 3,
+            errors: vec!["$: missing required property \"a\"".to_string()],
+        }
+        .into();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "Reply did not match the schema after 3 attempt(s): $: missing required property \"a\""
+        );
+    }
+
+    #[test]
+    fn test_bad_input_not_retryable() {
+        let e = ChibiError::ConfigInvalid {
+            message: "x".to_string(),
+        };
+        assert!(!e.is_retryable());
+        assert!(
+            !ChibiError::HookDenied {
+                hook: "pre_message".to_string(),
+                message: "no".to_string(),
+            }
+            .is_retryable()
+        );
+    }
+}
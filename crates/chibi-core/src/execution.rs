@@ -10,12 +10,20 @@ use std::io;
 use crate::Chibi;
 use crate::api::PromptOptions;
 use crate::api::sink::ResponseSink;
-use crate::config::ResolvedConfig;
+use crate::api::structured;
+use crate::config::{ResolvedConfig, ResponseFormat};
 use crate::context;
+use crate::error::ChibiError;
 use crate::input::{Command, ExecutionFlags, Inspectable};
+use crate::lock::{ContextLock, LockPolicy};
 use crate::output::{CommandEvent, OutputSink};
-use crate::state::{StatePaths, format_flock_sections, load_flock_contexts};
-use crate::vfs::flock::site_flock_name;
+use crate::state::{
+    ContextArchive, ExportFormat, MAX_TEMPLATE_CONTEXTS, PROMPT_LIBRARY_DIR, StatePaths,
+    TemplateVars, expand_name_pattern, format_flock_sections, list_prompt_templates,
+    load_flock_contexts, render_prompt_template,
+};
+use crate::vfs::flock::{resolve_flock_vfs_root, site_flock_name, validate_flock_name};
+use crate::vfs::{VfsCaller, VfsPath};
 
 /// Side effects of command execution that binaries may need to act on.
 ///
@@ -40,7 +48,14 @@ pub enum CommandEffect {
 /// These are context-specific files that can be inspected via `-i` but are not
 /// config fields. Binaries combine this list with `ResolvedConfig::list_fields()`
 /// to produce the full inspectable items list.
-pub const INSPECTABLE_ITEMS: &[&str] = &["system_prompt", "reflection", "tasks", "goals", "home"];
+pub const INSPECTABLE_ITEMS: &[&str] = &[
+    "system_prompt",
+    "reflection",
+    "tasks",
+    "goals",
+    "home",
+    "hooks",
+];
 
 /// Execute a command with full lifecycle management.
 ///
@@ -123,8 +138,8 @@ pub async fn execute_command<S: ResponseSink>(
 
 /// Dispatch a command to the appropriate handler.
 ///
-/// Send-path commands (SendPrompt, CallTool, CheckInbox, CheckAllInboxes)
-/// use the provided `ResponseSink`. Non-send commands use `OutputSink` only.
+/// Send-path commands (SendPrompt, SendStructured, RunPromptTemplate, CallTool, CheckInbox,
+/// CheckAllInboxes) use the provided `ResponseSink`. Non-send commands use `OutputSink` only.
 async fn dispatch_command<S: ResponseSink>(
     chibi: &mut Chibi,
     context: &str,
@@ -144,10 +159,8 @@ async fn dispatch_command<S: ResponseSink>(
             let contexts = chibi.list_contexts();
             for name in contexts {
                 let context_dir = chibi.app.context_dir(&name);
-                let status = crate::lock::ContextLock::get_status(
-                    &context_dir,
-                    chibi.app.config.lock_heartbeat_seconds,
-                );
+                let status =
+                    ContextLock::get_status(&context_dir, chibi.app.config.lock_heartbeat_seconds);
                 let marker = if name == context { "* " } else { "  " };
                 let status_str = status.map(|s| format!(" {}", s)).unwrap_or_default();
                 let explicit_flocks = chibi
@@ -168,10 +181,8 @@ async fn dispatch_command<S: ResponseSink>(
         Command::ListCurrentContext => {
             let ctx = chibi.app.get_or_create_context(context)?;
             let context_dir = chibi.app.context_dir(context);
-            let status = crate::lock::ContextLock::get_status(
-                &context_dir,
-                chibi.app.config.lock_heartbeat_seconds,
-            );
+            let status =
+                ContextLock::get_status(&context_dir, chibi.app.config.lock_heartbeat_seconds);
             let status_str = status.map(|s| format!(" {}", s)).unwrap_or_default();
             output.emit_result(&format!("Context: {}{}", context, status_str));
             let prompt_count = chibi.app.prompt_count(context).unwrap_or(0);
@@ -240,6 +251,43 @@ async fn dispatch_command<S: ResponseSink>(
                 new: new.clone(),
             })
         }
+        Command::ExportContext { name, path, format } => {
+            let ctx_name = name.as_deref().unwrap_or(context);
+            let format =
+                format.unwrap_or_else(|| ExportFormat::from_path(std::path::Path::new(path)));
+            let content = match format {
+                ExportFormat::Archive => chibi.app.export_context(ctx_name).await?.to_json()?,
+                _ => chibi.app.render_transcript(ctx_name, format)?,
+            };
+            crate::safe_io::atomic_write_text(std::path::Path::new(path), &content)?;
+            output.emit_result(&format!("Exported context '{}' to {}", ctx_name, path));
+            Ok(CommandEffect::None)
+        }
+        Command::ImportContext { path, name } => {
+            let archive = ContextArchive::from_json(&std::fs::read_to_string(path)?)?;
+            let report = chibi.app.import_context(&archive, name.as_deref()).await?;
+            if report.renamed {
+                output.emit_result(&format!(
+                    "Context '{}' already exists; imported as '{}'",
+                    archive.context, report.name
+                ));
+            } else {
+                output.emit_result(&format!("Imported context '{}'", report.name));
+            }
+            if !report.flocks.is_empty() {
+                output.emit_result(&format!("Joined flocks: {}", report.flocks.join(", ")));
+            }
+            if !report.created_flocks.is_empty() {
+                output.emit_result(&format!(
+                    "Created flocks: {}",
+                    report.created_flocks.join(", ")
+                ));
+            }
+            if report.reflection_imported {
+                output.emit_result("Installed archived reflection");
+            }
+            Ok(CommandEffect::None)
+        }
         Command::ShowLog {
             context: ctx,
             count,
@@ -274,6 +322,58 @@ async fn dispatch_command<S: ResponseSink>(
             });
             Ok(CommandEffect::None)
         }
+        Command::ListPromptTemplates => {
+            let names = list_prompt_templates(&chibi.app.vfs)?;
+            if names.is_empty() {
+                output.emit_result(&format!("(no prompt templates in {})", PROMPT_LIBRARY_DIR));
+            } else {
+                output.emit_result(&names.join("\n"));
+            }
+            Ok(CommandEffect::None)
+        }
+        Command::ComposeSystemPrompt {
+            context: ctx,
+            fragments,
+            args,
+        } => {
+            let ctx_name = ctx.as_deref().unwrap_or(context);
+            if fragments.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Composing a system prompt needs at least one template",
+                ));
+            }
+            let content = compose_prompt(chibi, ctx_name, None, fragments, args)?;
+            chibi.app.set_system_prompt_for(ctx_name, &content)?;
+            output.emit_event(CommandEvent::SystemPromptSet {
+                context: ctx_name.to_string(),
+            });
+            Ok(CommandEffect::None)
+        }
+        Command::CreateFromTemplate {
+            template,
+            names,
+            flock,
+        } => {
+            create_from_template(chibi, template, names, flock.as_deref(), output).await?;
+            Ok(CommandEffect::None)
+        }
+        Command::ListContextTemplates => {
+            let templates = chibi.app.list_context_templates()?;
+            if templates.is_empty() {
+                output.emit_result(&format!(
+                    "(no context templates in {})",
+                    chibi.app.context_templates_dir().display()
+                ));
+            }
+            for (name, description) in templates {
+                match description {
+                    Some(d) => output.emit_result(&format!("{}: {}", name, d)),
+                    None => output.emit_result(&name),
+                }
+            }
+            Ok(CommandEffect::None)
+        }
         Command::SetModel {
             context: ctx,
             model,
@@ -380,14 +480,126 @@ async fn dispatch_command<S: ResponseSink>(
             }
             Ok(CommandEffect::None)
         }
+        Command::VfsUsage => {
+            let usage = chibi.app.vfs.usage().await?;
+            output.emit_result(crate::vfs::quota::render_usage(&usage).trim_end());
+            Ok(CommandEffect::None)
+        }
+        Command::Replay {
+            cassette,
+            model,
+            replay_tools,
+        } => {
+            let recorded = crate::api::Cassette::load(std::path::Path::new(cassette))?;
+            let options = crate::api::ReplayOptions {
+                model: model.clone(),
+                replay_tools: *replay_tools,
+            };
+            let report = crate::api::run_replay(chibi, &recorded, &options, sink).await?;
+            output.emit_result(report.render().trim_end());
+            if report.diverged() > 0 {
+                return Err(io::Error::other(format!(
+                    "replay diverged: {} of {} prompts differ from the recording",
+                    report.diverged(),
+                    report.prompts.len()
+                )));
+            }
+            Ok(CommandEffect::None)
+        }
+        #[cfg(feature = "synthesised-tools")]
+        Command::TestTool { path, format } => {
+            let report = crate::tools::tool_test::run_tool_tests(chibi, context, path).await?;
+            output.emit_result(report.render(format.unwrap_or_default()).trim_end());
+            if report.failed() > 0 {
+                return Err(io::Error::other(format!(
+                    "{} of {} tool tests failed",
+                    report.failed(),
+                    report.results.len()
+                )));
+            }
+            Ok(CommandEffect::None)
+        }
+        #[cfg(not(feature = "synthesised-tools"))]
+        Command::TestTool { .. } => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--test-tool requires the synthesised-tools feature",
+        )),
 
         // --- send-path commands ---
-        Command::SendPrompt { prompt } => {
+        Command::SendPrompt {
+            prompt,
+            attachments,
+        } => {
+            if !chibi.app.context_dir(context).exists() {
+                let new_context = context::Context::new(context.to_string());
+                chibi.app.save_and_register_context(&new_context)?;
+            }
+            send_prompt_inner(
+                chibi,
+                context,
+                prompt,
+                attachments,
+                config,
+                flags,
+                None,
+                output,
+                sink,
+            )
+            .await?;
+            Ok(CommandEffect::None)
+        }
+        Command::SendStructured {
+            prompt,
+            schema,
+            max_attempts,
+            attachments,
+        } => {
+            if !chibi.app.context_dir(context).exists() {
+                let new_context = context::Context::new(context.to_string());
+                chibi.app.save_and_register_context(&new_context)?;
+            }
+            send_structured(
+                chibi,
+                context,
+                prompt,
+                schema,
+                max_attempts.unwrap_or(structured::DEFAULT_MAX_ATTEMPTS),
+                attachments,
+                config,
+                flags,
+                output,
+                sink,
+            )
+            .await?;
+            Ok(CommandEffect::None)
+        }
+        Command::RunPromptTemplate {
+            name,
+            args,
+            attachments,
+        } => {
+            let vars = TemplateVars {
+                project_root: &chibi.project_root,
+                context,
+                args,
+            };
+            let prompt = render_prompt_template(&chibi.app.vfs, name, &vars)?;
             if !chibi.app.context_dir(context).exists() {
                 let new_context = context::Context::new(context.to_string());
                 chibi.app.save_and_register_context(&new_context)?;
             }
-            send_prompt_inner(chibi, context, prompt, config, flags, None, sink).await?;
+            send_prompt_inner(
+                chibi,
+                context,
+                &prompt,
+                attachments,
+                config,
+                flags,
+                None,
+                output,
+                sink,
+            )
+            .await?;
             Ok(CommandEffect::None)
         }
         Command::CallTool { name, args } => {
@@ -403,7 +615,20 @@ async fn dispatch_command<S: ResponseSink>(
                 })?
             };
 
-            let result = chibi.execute_tool(context, name, args_json.clone()).await?;
+            let result = chibi
+                .execute_tool(context, name, args_json.clone())
+                .await
+                .map_err(|e| {
+                    // Keep typed errors raised inside the tool; tag the rest
+                    if ChibiError::find(&e).is_some() {
+                        return e;
+                    }
+                    ChibiError::ToolFailed {
+                        tool: name.clone(),
+                        message: e.to_string(),
+                    }
+                    .with_kind(e.kind())
+                })?;
 
             if flags.force_call_agent {
                 let tool_context = format!(
@@ -417,9 +642,11 @@ async fn dispatch_command<S: ResponseSink>(
                     chibi,
                     context,
                     &tool_context,
+                    &[],
                     config,
                     flags,
                     Some(fallback),
+                    output,
                     sink,
                 )
                 .await?;
@@ -448,9 +675,11 @@ async fn dispatch_command<S: ResponseSink>(
                     chibi,
                     ctx,
                     crate::INBOX_CHECK_PROMPT,
+                    &[],
                     &inbox_config,
                     flags,
                     None,
+                    output,
                     sink,
                 )
                 .await?;
@@ -474,9 +703,11 @@ async fn dispatch_command<S: ResponseSink>(
                     chibi,
                     &ctx_name,
                     crate::INBOX_CHECK_PROMPT,
+                    &[],
                     &inbox_config,
                     flags,
                     None,
+                    output,
                     sink,
                 )
                 .await?;
@@ -497,23 +728,32 @@ async fn dispatch_command<S: ResponseSink>(
 /// Resolve config, acquire context lock, and send a prompt through the agentic loop.
 ///
 /// Shared by SendPrompt, CallTool (with continuation), CheckInbox, CheckAllInboxes.
+/// A locked context is handled per `lock_policy`. Prompts other callers queued
+/// meanwhile are run before the lock is released, and the inbox is checked
+/// again afterwards for any queued while the lock was being let go.
+#[allow(clippy::too_many_arguments)]
 async fn send_prompt_inner<S: ResponseSink>(
     chibi: &Chibi,
     context: &str,
     prompt: &str,
+    attachments: &[String],
     config: &ResolvedConfig,
     flags: &ExecutionFlags,
     fallback: Option<crate::tools::HandoffTarget>,
+    output: &dyn OutputSink,
     sink: &mut S,
 ) -> io::Result<()> {
     let mut resolved = config.clone();
     crate::gateway::ensure_context_window(&mut resolved);
     let use_reflection = resolved.reflection_enabled;
 
-    let context_dir = chibi.app.context_dir(context);
-    let _lock =
-        crate::lock::ContextLock::acquire(&context_dir, chibi.app.config.lock_heartbeat_seconds)?;
+    let Some((lock, run_prompt)) =
+        acquire_context_lock(chibi, context, prompt, attachments, &resolved, output).await?
+    else {
+        return Ok(());
+    };
 
+    let cassette = flags.record.as_ref().map(crate::api::Cassette::record);
     let mut options = PromptOptions::new(
         use_reflection,
         &flags.debug,
@@ -522,10 +762,464 @@ async fn send_prompt_inner<S: ResponseSink>(
     if let Some(fb) = fallback {
         options = options.with_fallback(fb);
     }
+    if let Some(cassette) = &cassette {
+        options = options.with_cassette(cassette);
+    }
+
+    let result = if run_prompt {
+        let prompt_options = options.clone().with_attachments(attachments);
+        chibi
+            .send_prompt_streaming(context, prompt, &resolved, &prompt_options, sink)
+            .await
+    } else {
+        Ok(())
+    };
+
+    run_queue_and_release(
+        chibi, context, lock, &resolved, &options, output, sink, result,
+    )
+    .await
+}
+
+/// Run the prompts other callers queued while we held the lock, release it,
+/// and return `result`, the outcome of the holder's own turns.
+///
+/// Queued prompts run even if our own turn failed: they are independent.
+#[allow(clippy::too_many_arguments)]
+async fn run_queue_and_release<S: ResponseSink>(
+    chibi: &Chibi,
+    context: &str,
+    mut lock: ContextLock,
+    resolved: &ResolvedConfig,
+    options: &PromptOptions<'_>,
+    output: &dyn OutputSink,
+    sink: &mut S,
+    result: io::Result<()>,
+) -> io::Result<()> {
+    let context_dir = chibi.app.context_dir(context);
+    loop {
+        let drained = run_queued_prompts(chibi, context, resolved, options, output, sink).await;
+        drop(lock);
+        if drained.is_err() {
+            return result.and(drained);
+        }
+        // A prompt queued as we finished saw the lock still held and left it
+        // to us. Take the lock back and run it, unless another process got
+        // there first (that process runs the queue itself).
+        if queued_prompt_count(chibi, context)? == 0 {
+            return result;
+        }
+        match ContextLock::acquire(&context_dir, chibi.app.config.lock_heartbeat_seconds) {
+            Ok(next) => lock = next,
+            Err(_) => return result,
+        }
+    }
+}
+
+/// Prompts from `lock_policy = "queue"` waiting in a context's inbox.
+fn queued_prompt_count(chibi: &Chibi, context: &str) -> io::Result<usize> {
+    Ok(chibi
+        .app
+        .peek_inbox(context)?
+        .iter()
+        .filter(|m| m.from == crate::QUEUED_PROMPT_SENDER)
+        .count())
+}
+
+/// Run turns until no queued prompts are left. The caller holds the lock.
+async fn run_queued_prompts<S: ResponseSink>(
+    chibi: &Chibi,
+    context: &str,
+    resolved: &ResolvedConfig,
+    options: &PromptOptions<'_>,
+    output: &dyn OutputSink,
+    sink: &mut S,
+) -> io::Result<()> {
+    // Each turn drains the inbox, so this stops once nobody queues more
+    loop {
+        let queued = queued_prompt_count(chibi, context)?;
+        if queued == 0 {
+            return Ok(());
+        }
+        output.emit_event(CommandEvent::QueuedPromptsRunning {
+            context: context.to_string(),
+            count: queued,
+        });
+        chibi
+            .send_prompt_streaming(context, crate::INBOX_CHECK_PROMPT, resolved, options, sink)
+            .await?;
+    }
+}
+
+/// Send a prompt whose final reply must be JSON matching `schema`.
+///
+/// The schema is also passed to the provider as `response_format`. A reply
+/// that fails to parse or validate is answered with a repair prompt listing
+/// the errors, up to `max_attempts` replies in total; the valid value is
+/// emitted via `OutputSink::emit_structured`.
+///
+/// The lock is held across every attempt. Prompts other callers queued
+/// meanwhile run afterwards under `config`, without the schema.
+#[allow(clippy::too_many_arguments)]
+async fn send_structured<S: ResponseSink>(
+    chibi: &Chibi,
+    context: &str,
+    prompt: &str,
+    schema: &serde_json::Value,
+    max_attempts: usize,
+    attachments: &[String],
+    config: &ResolvedConfig,
+    flags: &ExecutionFlags,
+    output: &dyn OutputSink,
+    sink: &mut S,
+) -> io::Result<()> {
+    let cassette = flags.record.as_ref().map(crate::api::Cassette::record);
+    let mut options = PromptOptions::new(config.reflection_enabled, &flags.debug, false);
+    if let Some(cassette) = &cassette {
+        options = options.with_cassette(cassette);
+    }
+    send_structured_with_options(
+        chibi,
+        context,
+        prompt,
+        schema,
+        max_attempts,
+        attachments,
+        config,
+        &options,
+        output,
+        sink,
+    )
+    .await
+}
+
+/// `send_structured` with the prompt options already built.
+#[allow(clippy::too_many_arguments)]
+async fn send_structured_with_options<S: ResponseSink>(
+    chibi: &Chibi,
+    context: &str,
+    prompt: &str,
+    schema: &serde_json::Value,
+    max_attempts: usize,
+    attachments: &[String],
+    config: &ResolvedConfig,
+    options: &PromptOptions<'_>,
+    output: &dyn OutputSink,
+    sink: &mut S,
+) -> io::Result<()> {
+    let mut resolved = config.clone();
+    crate::gateway::ensure_context_window(&mut resolved);
+
+    // A queued prompt runs later with no schema to enforce, so no validated
+    // value will ever come back from this call
+    let queued = || -> io::Error {
+        ChibiError::ContextLocked {
+            context: context.to_string(),
+            lock_file: chibi.app.context_dir(context).join(".lock"),
+        }
+        .into()
+    };
+    let Some((lock, run_prompt)) =
+        acquire_context_lock(chibi, context, prompt, attachments, &resolved, output).await?
+    else {
+        return Err(queued());
+    };
 
-    chibi
-        .send_prompt_streaming(context, prompt, &resolved, &options, sink)
+    let result = if run_prompt {
+        structured_turns(
+            chibi,
+            context,
+            prompt,
+            schema,
+            max_attempts,
+            attachments,
+            &resolved,
+            options,
+            output,
+            sink,
+        )
         .await
+    } else {
+        Err(queued())
+    };
+    run_queue_and_release(
+        chibi, context, lock, &resolved, options, output, sink, result,
+    )
+    .await
+}
+
+/// The turns of a structured send: the prompt, then repair prompts until the
+/// reply validates or `max_attempts` replies have failed. The caller holds
+/// the lock.
+#[allow(clippy::too_many_arguments)]
+async fn structured_turns<S: ResponseSink>(
+    chibi: &Chibi,
+    context: &str,
+    prompt: &str,
+    schema: &serde_json::Value,
+    max_attempts: usize,
+    attachments: &[String],
+    resolved: &ResolvedConfig,
+    options: &PromptOptions<'_>,
+    output: &dyn OutputSink,
+    sink: &mut S,
+) -> io::Result<()> {
+    let mut structured_config = resolved.clone();
+    structured_config.api.response_format = Some(ResponseFormat::JsonSchema {
+        json_schema: Some(schema.clone()),
+    });
+
+    let max_attempts = max_attempts.max(1);
+    let mut current_prompt = prompt.to_string();
+    let mut current_attachments = attachments;
+    for attempt in 1..=max_attempts {
+        let mut capture = structured::ReplyCapture::new(&mut *sink);
+        let prompt_options = options.clone().with_attachments(current_attachments);
+        chibi
+            .send_prompt_streaming(
+                context,
+                &current_prompt,
+                &structured_config,
+                &prompt_options,
+                &mut capture,
+            )
+            .await?;
+
+        let reply = capture.reply.unwrap_or_default();
+        let errors = match structured::check_reply(schema, &reply) {
+            Ok(value) => {
+                output.emit_structured(&value);
+                return Ok(());
+            }
+            Err(errors) => errors,
+        };
+        if attempt == max_attempts {
+            return Err(ChibiError::SchemaMismatch {
+                attempts: max_attempts,
+                errors,
+            }
+            .into());
+        }
+        output.emit_event(CommandEvent::StructuredOutputRetry {
+            attempt,
+            max_attempts,
+            errors: errors.clone(),
+        });
+        current_prompt = structured::repair_prompt(&errors);
+        current_attachments = &[];
+    }
+    Ok(())
+}
+
+/// Take the context lock, applying `lock_policy` if another process holds it.
+///
+/// Returns the lock and whether the caller should still send `prompt`
+/// (false when it was queued and the lock freed up straight after), or
+/// `None` when the prompt was queued for the current holder.
+async fn acquire_context_lock(
+    chibi: &Chibi,
+    context: &str,
+    prompt: &str,
+    attachments: &[String],
+    config: &ResolvedConfig,
+    output: &dyn OutputSink,
+) -> io::Result<Option<(ContextLock, bool)>> {
+    let context_dir = chibi.app.context_dir(context);
+    let heartbeat = chibi.app.config.lock_heartbeat_seconds;
+    let busy = match ContextLock::acquire(&context_dir, heartbeat) {
+        Ok(lock) => return Ok(Some((lock, true))),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => e,
+        Err(e) => return Err(e),
+    };
+
+    match LockPolicy::parse(&config.lock_policy)? {
+        LockPolicy::Fail => Err(busy),
+        LockPolicy::Wait => {
+            let timeout_secs = config.lock_wait_timeout_seconds;
+            let started = std::time::Instant::now();
+            let lock = ContextLock::acquire_waiting(
+                &context_dir,
+                heartbeat,
+                std::time::Duration::from_secs(timeout_secs),
+                |waited| {
+                    output.emit_event(CommandEvent::LockWaiting {
+                        context: context.to_string(),
+                        waited_secs: waited.as_secs(),
+                        timeout_secs,
+                    })
+                },
+            )
+            .await?;
+            output.emit_event(CommandEvent::LockAcquired {
+                context: context.to_string(),
+                waited_secs: started.elapsed().as_secs(),
+            });
+            Ok(Some((lock, true)))
+        }
+        LockPolicy::Queue => {
+            if !attachments.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} (prompts with attachments can't be queued)", busy),
+                ));
+            }
+            chibi
+                .app
+                .send_inbox_message_from(crate::QUEUED_PROMPT_SENDER, context, prompt)?;
+            output.emit_event(CommandEvent::PromptQueued {
+                context: context.to_string(),
+            });
+            // The holder may have released between our attempts; if so,
+            // run the queue ourselves rather than leave the prompt waiting
+            match ContextLock::acquire(&context_dir, heartbeat) {
+                Ok(lock) => Ok(Some((lock, false))),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+    }
+}
+
+/// Join `lead` and the rendered library `fragments` into one prompt, separated
+/// by blank lines.
+fn compose_prompt(
+    chibi: &Chibi,
+    context: &str,
+    lead: Option<&str>,
+    fragments: &[String],
+    args: &[String],
+) -> io::Result<String> {
+    let vars = TemplateVars {
+        project_root: &chibi.project_root,
+        context,
+        args,
+    };
+    let mut parts: Vec<String> = lead.map(|l| l.trim().to_string()).into_iter().collect();
+    for name in fragments {
+        parts.push(
+            render_prompt_template(&chibi.app.vfs, name, &vars)?
+                .trim()
+                .to_string(),
+        );
+    }
+    Ok(parts.join("\n\n"))
+}
+
+/// Create the contexts named by a `CreateFromTemplate` command.
+///
+/// Every template is loaded, every name expanded and checked, every system
+/// prompt rendered and every flock name validated before anything is
+/// created, so a typo or a taken name creates nothing.
+async fn create_from_template(
+    chibi: &Chibi,
+    template_pattern: &str,
+    name_patterns: &[String],
+    flock: Option<&str>,
+    output: &dyn OutputSink,
+) -> io::Result<()> {
+    let default_pattern = ["{template}".to_string()];
+    let name_patterns = if name_patterns.is_empty() {
+        &default_pattern[..]
+    } else {
+        name_patterns
+    };
+
+    let mut templates = Vec::new();
+    let mut plan: Vec<(usize, String, Option<String>)> = Vec::new();
+    for template_name in expand_name_pattern(template_pattern)? {
+        let template = chibi.app.load_context_template(&template_name)?;
+        for flock_name in template.flocks.iter().map(String::as_str).chain(flock) {
+            validate_flock_name(flock_name)?;
+        }
+        if template.goals.is_some() && template.flocks.is_empty() && flock.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Context template '{}' sets goals but joins no flock to hold them",
+                    template_name
+                ),
+            ));
+        }
+        for pattern in name_patterns {
+            for name in expand_name_pattern(&pattern.replace("{template}", &template_name))? {
+                context::validate_context_name(&name)?;
+                if chibi.app.context_dir(&name).exists() || plan.iter().any(|(_, n, _)| *n == name)
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("Context '{}' already exists", name),
+                    ));
+                }
+                let prompt =
+                    if template.system_prompt.is_some() || !template.prompt_templates.is_empty() {
+                        Some(compose_prompt(
+                            chibi,
+                            &name,
+                            template.system_prompt.as_deref(),
+                            &template.prompt_templates,
+                            &template.prompt_args,
+                        )?)
+                    } else {
+                        None
+                    };
+                plan.push((templates.len(), name, prompt));
+            }
+        }
+        templates.push((template_name, template));
+    }
+    if plan.len() > MAX_TEMPLATE_CONTEXTS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Templates would create {} contexts (at most {})",
+                plan.len(),
+                MAX_TEMPLATE_CONTEXTS
+            ),
+        ));
+    }
+
+    for (index, name, prompt) in plan {
+        let (template_name, template) = &templates[index];
+        chibi
+            .app
+            .save_and_register_context(&context::Context::new(name.clone()))?;
+        chibi.app.save_local_config(&name, &template.local)?;
+        if let Some(prompt) = prompt {
+            chibi.app.set_system_prompt_for(&name, &prompt)?;
+        }
+
+        let flocks: Vec<&str> = template
+            .flocks
+            .iter()
+            .map(String::as_str)
+            .chain(flock)
+            .collect();
+        for flock_name in &flocks {
+            chibi.app.vfs.flock_join(flock_name, &name).await?;
+            if let Some(goals) = &template.goals {
+                seed_flock_goals(chibi, flock_name, goals).await?;
+            }
+        }
+
+        output.emit_result(&format!(
+            "Created context '{}' from template '{}'",
+            name, template_name
+        ));
+    }
+    Ok(())
+}
+
+/// Write `goals` to a flock's `goals.md` unless it already has some.
+async fn seed_flock_goals(chibi: &Chibi, flock: &str, goals: &str) -> io::Result<()> {
+    let vfs = &chibi.app.vfs;
+    let root = resolve_flock_vfs_root(flock, vfs.site_id())?;
+    let path = VfsPath::new(&format!("{}/goals.md", root.as_str()))?;
+    let existing = vfs.read(VfsCaller::System, &path).await.unwrap_or_default();
+    if String::from_utf8_lossy(&existing).trim().is_empty() {
+        vfs.write(VfsCaller::System, &path, goals.as_bytes())
+            .await?;
+    }
+    Ok(())
 }
 
 /// Show log entries for a context.
@@ -618,6 +1312,23 @@ fn inspect_context(
             output.emit_result(&chibi.home_dir().display().to_string());
             Ok(None)
         }
+        Inspectable::Hooks => {
+            let hook_tools: Vec<crate::tools::Tool> = chibi
+                .registry
+                .read()
+                .unwrap()
+                .filter(|t| t.is_hook_eligible())
+                .into_iter()
+                .cloned()
+                .collect();
+            let chains = crate::tools::describe_hook_chains(&hook_tools);
+            if chains.is_empty() {
+                output.emit_result("(no hooks registered)");
+            } else {
+                output.emit_result(chains.trim_end());
+            }
+            Ok(None)
+        }
         Inspectable::ConfigField(field_path) => Ok(Some(CommandEffect::InspectConfigField {
             context: context.to_string(),
             field: field_path.clone(),
@@ -628,29 +1339,237 @@ fn inspect_context(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::CollectingSink;
+    use crate::api::{CassetteRecord, CollectingSink};
     use crate::context::{Context, ContextEntry, now_timestamp};
     use crate::output::CaptureSink;
     use crate::test_support::create_test_chibi;
-    use crate::vfs::{VfsCaller, VfsPath};
 
-    // === pre-command lifecycle ===
+    // === lock policy ===
+
+    fn hold_lock(chibi: &Chibi, context: &str) -> ContextLock {
+        chibi.app.ensure_context_dir(context).unwrap();
+        ContextLock::acquire(&chibi.app.context_dir(context), 30).unwrap()
+    }
 
     #[tokio::test]
-    async fn execute_command_registers_new_context_in_state() {
-        let (mut chibi, _dir) = create_test_chibi();
-        let config = chibi.resolve_config("myctx", None).unwrap();
-        let flags = ExecutionFlags::default();
+    async fn lock_policy_fail_errors_when_busy() {
+        let (chibi, _dir) = create_test_chibi();
+        let _held = hold_lock(&chibi, "busy");
+        let config = chibi.resolve_config("busy", None).unwrap();
         let sink = CaptureSink::new();
-        let mut response = CollectingSink::default();
 
-        execute_command(
-            &mut chibi,
-            "myctx",
-            &Command::NoOp,
-            &flags,
-            &config,
-            &sink,
+        let result = acquire_context_lock(&chibi, "busy", "hi", &[], &config, &sink).await;
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn lock_policy_queue_appends_to_inbox() {
+        let (chibi, _dir) = create_test_chibi();
+        let _held = hold_lock(&chibi, "busy");
+        let mut config = chibi.resolve_config("busy", None).unwrap();
+        config.lock_policy = "queue".to_string();
+        let sink = CaptureSink::new();
+
+        let result = acquire_context_lock(&chibi, "busy", "run the tests", &[], &config, &sink)
+            .await
+            .unwrap();
+        assert!(result.is_none());
+
+        let inbox = chibi.app.peek_inbox("busy").unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].from, crate::QUEUED_PROMPT_SENDER);
+        assert_eq!(inbox[0].content, "run the tests");
+        assert!(
+            sink.events
+                .borrow()
+                .iter()
+                .any(|e| matches!(e, CommandEvent::PromptQueued { context } if context == "busy"))
+        );
+
+        let with_attachment = acquire_context_lock(
+            &chibi,
+            "busy",
+            "look",
+            &["shot.png".to_string()],
+            &config,
+            &sink,
+        )
+        .await;
+        assert!(with_attachment.is_err());
+        assert_eq!(chibi.app.peek_inbox("busy").unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn structured_prompt_queued_for_holder_is_an_error() {
+        let (chibi, _dir) = create_test_chibi();
+        let _held = hold_lock(&chibi, "busy");
+        let mut config = chibi.resolve_config("busy", None).unwrap();
+        config.lock_policy = "queue".to_string();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+
+        let err = send_structured(
+            &chibi,
+            "busy",
+            "classify",
+            &serde_json::json!({"type": "object"}),
+            3,
+            &[],
+            &config,
+            &ExecutionFlags::default(),
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            ChibiError::find(&err),
+            Some(ChibiError::ContextLocked { .. })
+        ));
+        assert_eq!(chibi.app.peek_inbox("busy").unwrap().len(), 1);
+    }
+
+    /// Queues a prompt for `context` when the first response starts, as
+    /// another process would while this one holds the lock.
+    struct QueueOnFirstResponse<'a> {
+        app: &'a crate::state::AppState,
+        context: &'a str,
+        queued: bool,
+        inner: CollectingSink,
+    }
+
+    impl ResponseSink for QueueOnFirstResponse<'_> {
+        fn handle(&mut self, event: crate::api::ResponseEvent<'_>) -> io::Result<()> {
+            if matches!(event, crate::api::ResponseEvent::StartResponse) && !self.queued {
+                self.queued = true;
+                self.app.send_inbox_message_from(
+                    crate::QUEUED_PROMPT_SENDER,
+                    self.context,
+                    "summarise",
+                )?;
+            }
+            self.inner.handle(event)
+        }
+    }
+
+    #[tokio::test]
+    async fn structured_prompt_runs_queued_prompts_after_validating() {
+        let (chibi, _dir) = create_test_chibi();
+        chibi.app.ensure_context_dir("ctx").unwrap();
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let turn = |prompt: &str, reply: &str| {
+            [
+                CassetteRecord::Prompt {
+                    context: "ctx".to_string(),
+                    prompt: prompt.to_string(),
+                    model: "test-model".to_string(),
+                    timestamp: 0,
+                },
+                CassetteRecord::Response {
+                    text: reply.to_string(),
+                    tool_calls: vec![],
+                    usage: None,
+                },
+            ]
+        };
+        let recorded: Vec<CassetteRecord> = turn("classify", r#"{"label": "a"}"#)
+            .into_iter()
+            .chain(turn(crate::INBOX_CHECK_PROMPT, "a summary, not JSON"))
+            .collect();
+        let cassette = crate::api::Cassette::replay(&recorded, false);
+        let options = PromptOptions::new(false, &[], false).with_cassette(&cassette);
+        let sink = CaptureSink::new();
+        let mut response = QueueOnFirstResponse {
+            app: &chibi.app,
+            context: "ctx",
+            queued: false,
+            inner: CollectingSink::default(),
+        };
+
+        send_structured_with_options(
+            &chibi,
+            "ctx",
+            "classify",
+            &serde_json::json!({"type": "object", "required": ["label"]}),
+            3,
+            &[],
+            &config,
+            &options,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap();
+
+        // Our reply is the one validated; the queued prompt's reply is not
+        assert_eq!(*sink.results.borrow(), vec![r#"{"label":"a"}"#.to_string()]);
+        let events = sink.events.borrow();
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, CommandEvent::QueuedPromptsRunning { count: 1, .. }))
+        );
+        assert!(
+            !events
+                .iter()
+                .any(|e| matches!(e, CommandEvent::StructuredOutputRetry { .. }))
+        );
+        assert!(chibi.app.peek_inbox("ctx").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn lock_policy_wait_reports_and_times_out() {
+        let (chibi, _dir) = create_test_chibi();
+        let _held = hold_lock(&chibi, "busy");
+        let mut config = chibi.resolve_config("busy", None).unwrap();
+        config.lock_policy = "wait".to_string();
+        config.lock_wait_timeout_seconds = 1;
+        let sink = CaptureSink::new();
+
+        let result = acquire_context_lock(&chibi, "busy", "hi", &[], &config, &sink).await;
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::TimedOut);
+        assert!(sink.events.borrow().iter().any(|e| matches!(
+            e,
+            CommandEvent::LockWaiting {
+                timeout_secs: 1,
+                ..
+            }
+        )));
+    }
+
+    #[tokio::test]
+    async fn lock_policy_unknown_is_rejected_only_when_busy() {
+        let (chibi, _dir) = create_test_chibi();
+        chibi.app.ensure_context_dir("free").unwrap();
+        let mut config = chibi.resolve_config("free", None).unwrap();
+        config.lock_policy = "bogus".to_string();
+        let sink = CaptureSink::new();
+
+        let free = acquire_context_lock(&chibi, "free", "hi", &[], &config, &sink)
+            .await
+            .unwrap();
+        assert!(matches!(free, Some((_, true))));
+        let busy = acquire_context_lock(&chibi, "free", "hi", &[], &config, &sink).await;
+        assert_eq!(busy.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    // === pre-command lifecycle ===
+
+    #[tokio::test]
+    async fn execute_command_registers_new_context_in_state() {
+        let (mut chibi, _dir) = create_test_chibi();
+        let config = chibi.resolve_config("myctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+
+        execute_command(
+            &mut chibi,
+            "myctx",
+            &Command::NoOp,
+            &flags,
+            &config,
+            &sink,
             &mut response,
         )
         .await
@@ -836,6 +1755,279 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "synthesised-tools")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn dispatch_test_tool_reports_failures() {
+        let (mut chibi, _dir) = create_test_chibi();
+        chibi.app.ensure_context_dir("ctx").unwrap();
+        let source = r#"
+(import (scheme base))
+(import (harness test))
+(define tool-name "double")
+(define tool-description "doubles n")
+(define tool-parameters '((n . ((type . "integer")))))
+(define (tool-execute args) (* 2 (cdr (assoc "n" args))))
+(define-test "doubles" (assert-equal 4 (tool-execute '(("n" . 2)))))
+(define-test "broken" (assert-equal 5 (tool-execute '(("n" . 2)))))
+"#;
+        chibi
+            .app
+            .vfs
+            .write(
+                crate::vfs::VfsCaller::System,
+                &crate::vfs::VfsPath::new("/tools/shared/double.scm").unwrap(),
+                source.as_bytes(),
+            )
+            .await
+            .unwrap();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+        let err = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::TestTool {
+                path: "/tools/shared/double.scm".to_string(),
+                format: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("1 of 2 tool tests failed"));
+        let results = sink.results.borrow();
+        assert!(results.iter().any(|r| r.contains("ok 1 - ")));
+        assert!(results.iter().any(|r| r.contains("not ok 2 - ")));
+    }
+
+    #[tokio::test]
+    async fn dispatch_export_then_import_context() {
+        let (mut chibi, dir) = create_test_chibi();
+        chibi.app.ensure_context_dir("ctx").unwrap();
+        chibi
+            .app
+            .append_to_transcript(
+                "ctx",
+                &crate::state::create_user_message_entry("ctx", "hi there", "testuser"),
+            )
+            .unwrap();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+        let archive_path = dir.path().join("ctx.json");
+        let md_path = dir.path().join("ctx.md");
+
+        for path in [&archive_path, &md_path] {
+            execute_command(
+                &mut chibi,
+                "ctx",
+                &Command::ExportContext {
+                    name: None,
+                    path: path.to_string_lossy().into_owned(),
+                    format: None,
+                },
+                &flags,
+                &config,
+                &sink,
+                &mut response,
+            )
+            .await
+            .unwrap();
+        }
+        assert!(
+            std::fs::read_to_string(&md_path)
+                .unwrap()
+                .contains("hi there")
+        );
+
+        execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::ImportContext {
+                path: archive_path.to_string_lossy().into_owned(),
+                name: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap();
+
+        let results = sink.results.borrow();
+        assert!(
+            results.iter().any(|r| r.contains("imported as 'ctx-2'")),
+            "collision should be reported: {results:?}"
+        );
+        assert!(chibi.app.list_contexts().contains(&"ctx-2".to_string()));
+    }
+
+    /// Write a cassette with one prompt that calls an unregistered tool, then answers.
+    fn write_tool_cassette(path: &std::path::Path) {
+        use crate::api::replay::RecordedToolCall;
+        let records = [
+            CassetteRecord::Prompt {
+                context: "ctx".to_string(),
+                prompt: "check the thing".to_string(),
+                model: "test-model".to_string(),
+                timestamp: 0,
+            },
+            CassetteRecord::Response {
+                text: String::new(),
+                tool_calls: vec![RecordedToolCall {
+                    id: "c1".to_string(),
+                    name: "no_such_tool".to_string(),
+                    arguments: "{}".to_string(),
+                }],
+                usage: None,
+            },
+            CassetteRecord::ToolResult {
+                tool: "no_such_tool".to_string(),
+                arguments: "{}".to_string(),
+                result: "recorded output".to_string(),
+            },
+            CassetteRecord::Response {
+                text: "all done".to_string(),
+                tool_calls: vec![],
+                usage: None,
+            },
+        ];
+        let lines: Vec<String> = records
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap())
+            .collect();
+        std::fs::write(path, lines.join("\n")).unwrap();
+    }
+
+    #[tokio::test]
+    async fn dispatch_replay_reports_tool_result_divergence() {
+        let (mut chibi, dir) = create_test_chibi();
+        let cassette = dir.path().join("session.jsonl");
+        write_tool_cassette(&cassette);
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+
+        // Served tool results: the workflow reproduces exactly.
+        execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::Replay {
+                cassette: cassette.to_string_lossy().into_owned(),
+                model: None,
+                replay_tools: true,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap();
+        assert!(
+            sink.results
+                .borrow()
+                .iter()
+                .any(|r| r.contains("0 of 1 prompts diverged"))
+        );
+        assert!(
+            !chibi
+                .list_contexts()
+                .iter()
+                .any(|c| c.starts_with("replay-")),
+            "scratch contexts should be destroyed"
+        );
+
+        // Live tools: the unregistered tool no longer produces the recorded output.
+        let err = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::Replay {
+                cassette: cassette.to_string_lossy().into_owned(),
+                model: None,
+                replay_tools: false,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("replay diverged"));
+        assert!(
+            sink.results
+                .borrow()
+                .iter()
+                .any(|r| r.contains("- no_such_tool: recorded output"))
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatch_replay_cleans_up_scratch_contexts_on_seed_failure() {
+        let (mut chibi, dir) = create_test_chibi();
+        let path = dir.path().join("two.jsonl");
+        let records: Vec<String> = ["a", "b"]
+            .iter()
+            .flat_map(|ctx| {
+                [
+                    CassetteRecord::Prompt {
+                        context: ctx.to_string(),
+                        prompt: "hi".to_string(),
+                        model: "test-model".to_string(),
+                        timestamp: 0,
+                    },
+                    CassetteRecord::Response {
+                        text: "hello".to_string(),
+                        tool_calls: vec![],
+                        usage: None,
+                    },
+                ]
+            })
+            .map(|r| serde_json::to_string(&r).unwrap())
+            .collect();
+        std::fs::write(&path, records.join("\n")).unwrap();
+        // A directory where b's local.toml should be makes seeding b fail.
+        std::fs::create_dir_all(chibi.app.local_config_file("b")).unwrap();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+        execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::Replay {
+                cassette: path.to_string_lossy().into_owned(),
+                model: None,
+                replay_tools: false,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+
+        let leftovers: Vec<String> = std::fs::read_dir(&chibi.app.contexts_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|n| n.starts_with("replay-"))
+            .collect();
+        assert!(leftovers.is_empty(), "scratch contexts left: {leftovers:?}");
+    }
+
     #[tokio::test]
     async fn dispatch_destroy_context_confirmed_returns_destroyed_effect() {
         let (mut chibi, _dir) = create_test_chibi();
@@ -979,6 +2171,187 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn dispatch_compose_system_prompt_renders_fragments() {
+        let (mut chibi, _dir) = create_test_chibi();
+        for (name, body) in [
+            ("persona", "You review {{lang|Rust}} code.\n"),
+            ("checklist", "Check {{1}} for {{context}}."),
+        ] {
+            chibi
+                .app
+                .vfs
+                .write(
+                    crate::vfs::VfsCaller::System,
+                    &crate::vfs::VfsPath::new(&format!("/site/prompts/{}.md", name)).unwrap(),
+                    body.as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+
+        execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::ComposeSystemPrompt {
+                context: Some("reviewer".to_string()),
+                fragments: vec!["persona".to_string(), "checklist".to_string()],
+                args: vec!["unsafe blocks".to_string()],
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            chibi.app.load_system_prompt_for("reviewer").unwrap(),
+            "You review Rust code.\n\nCheck unsafe blocks for reviewer."
+        );
+        assert!(sink.events.borrow().iter().any(
+            |e| matches!(e, CommandEvent::SystemPromptSet { context } if context == "reviewer")
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn dispatch_create_from_template_provisions_contexts() {
+        let (mut chibi, _dir) = create_test_chibi();
+        let templates_dir = chibi.app.context_templates_dir();
+        std::fs::create_dir_all(&templates_dir).unwrap();
+        std::fs::write(
+            templates_dir.join("worker.toml"),
+            "system_prompt = \"You build things.\"\nflocks = [\"crew\"]\ngoals = \"Finish\"\nmodel = \"test/worker\"\n",
+        )
+        .unwrap();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+        execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::CreateFromTemplate {
+                template: "worker".to_string(),
+                names: vec!["w-{1..2}".to_string()],
+                flock: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap();
+
+        for name in ["w-1", "w-2"] {
+            assert!(chibi.app.context_dir(name).exists());
+            assert_eq!(
+                chibi.app.load_local_config(name).unwrap().model.as_deref(),
+                Some("test/worker")
+            );
+            assert_eq!(
+                chibi.app.load_system_prompt_for(name).unwrap(),
+                "You build things."
+            );
+            let flocks = chibi.app.vfs.flock_list_for(name).await.unwrap();
+            assert_eq!(flocks, vec!["crew".to_string()]);
+        }
+        let goals = chibi
+            .app
+            .vfs
+            .read(
+                VfsCaller::System,
+                &VfsPath::new("/flocks/crew/goals.md").unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(goals, b"Finish");
+        assert_eq!(sink.results.borrow().len(), 2);
+
+        // A taken name aborts the whole batch before anything is created
+        let err = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::CreateFromTemplate {
+                template: "worker".to_string(),
+                names: vec!["w-{3..1}".to_string(), "w-{2..3}".to_string()],
+                flock: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::CreateFromTemplate {
+                template: "worker".to_string(),
+                names: vec!["w-{2..3}".to_string()],
+                flock: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(!chibi.app.context_dir("w-3").exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn dispatch_create_from_template_renders_prompts_before_creating() {
+        let (mut chibi, _dir) = create_test_chibi();
+        let templates_dir = chibi.app.context_templates_dir();
+        std::fs::create_dir_all(&templates_dir).unwrap();
+        std::fs::write(
+            templates_dir.join("lead.toml"),
+            "system_prompt = \"Lead.\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            templates_dir.join("helper.toml"),
+            "prompt_templates = [\"missing\"]\n",
+        )
+        .unwrap();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+        let result = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::CreateFromTemplate {
+                template: "{lead,helper}".to_string(),
+                names: vec![],
+                flock: None,
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(!chibi.app.context_dir("lead").exists());
+        assert!(!chibi.app.context_dir("helper").exists());
+        assert!(sink.results.borrow().is_empty());
+    }
+
     #[tokio::test]
     async fn dispatch_call_tool_invalid_json_returns_error() {
         let (mut chibi, _dir) = create_test_chibi();
@@ -1007,6 +2380,37 @@ mod tests {
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     }
 
+    #[tokio::test]
+    async fn dispatch_call_tool_failure_is_typed() {
+        let (mut chibi, _dir) = create_test_chibi();
+
+        let config = chibi.resolve_config("ctx", None).unwrap();
+        let flags = ExecutionFlags::default();
+        let sink = CaptureSink::new();
+        let mut response = CollectingSink::default();
+
+        let err = execute_command(
+            &mut chibi,
+            "ctx",
+            &Command::CallTool {
+                name: "no_such_tool".to_string(),
+                args: vec![],
+            },
+            &flags,
+            &config,
+            &sink,
+            &mut response,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(matches!(
+            ChibiError::find(&err),
+            Some(ChibiError::ToolFailed { tool, .. }) if tool == "no_such_tool"
+        ));
+    }
+
     #[tokio::test]
     async fn dispatch_show_log_on_empty_context_succeeds() {
         let (mut chibi, _dir) = create_test_chibi();
@@ -4,8 +4,9 @@
 //! and ratatoskr's ModelGateway types.
 
 use crate::config::{self, ResolvedConfig};
+use crate::error::ChibiError;
 use ratatoskr::{
-    ChatOptions, EmbeddedGateway, Message, ModelGateway, Ratatoskr,
+    ChatOptions, ContentPart, EmbeddedGateway, Message, MessageContent, ModelGateway, Ratatoskr,
     ReasoningConfig as RatatoskrReasoningConfig, ReasoningEffort as RatatoskrReasoningEffort,
     ResponseFormat as RatatoskrResponseFormat, ToolCall, ToolChoice as RatatoskrToolChoice,
     ToolDefinition,
@@ -18,6 +19,11 @@ pub fn to_ratatoskr_message(json: &serde_json::Value) -> io::Result<Message> {
         .as_str()
         .ok_or_else(|| io::Error::other("missing role"))?;
 
+    // Multipart content (attachments expanded by `attachments::inline_attachments`)
+    if let Some(parts) = json["content"].as_array() {
+        return to_multipart_message(role_str, parts);
+    }
+
     let content = json["content"]
         .as_str()
         .map(|s| s.to_string())
@@ -59,6 +65,42 @@ pub fn to_ratatoskr_message(json: &serde_json::Value) -> io::Result<Message> {
     }
 }
 
+/// Convert an OpenAI-style content array (`text` / `image_url` parts) into a
+/// multipart ratatoskr message. Only user and assistant messages carry parts.
+fn to_multipart_message(role_str: &str, parts: &[serde_json::Value]) -> io::Result<Message> {
+    let parts = parts
+        .iter()
+        .map(|part| match part["type"].as_str() {
+            Some("text") => Ok(ContentPart::Text(
+                part["text"].as_str().unwrap_or_default().to_string(),
+            )),
+            Some("image_url") => part["image_url"]["url"]
+                .as_str()
+                .map(|url| ContentPart::ImageUrl {
+                    url: url.to_string(),
+                })
+                .ok_or_else(|| io::Error::other("image_url part missing url")),
+            other => Err(io::Error::other(format!(
+                "unsupported content part type: {:?}",
+                other
+            ))),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut msg = match role_str {
+        "user" => Message::user(String::new()),
+        "assistant" => Message::assistant(String::new()),
+        other => {
+            return Err(io::Error::other(format!(
+                "multipart content not supported for role: {}",
+                other
+            )));
+        }
+    };
+    msg.content = MessageContent::Parts(parts);
+    Ok(msg)
+}
+
 /// Convert OpenAI-format tool JSON to ratatoskr ToolDefinition.
 ///
 /// Expects the format: `{"type": "function", "function": {"name": ..., "description": ..., "parameters": ...}}`
@@ -186,9 +228,12 @@ pub fn build_gateway(config: &ResolvedConfig) -> io::Result<EmbeddedGateway> {
         Ratatoskr::builder().openrouter(config.api_key.as_deref())
     };
 
-    builder
-        .build()
-        .map_err(|e| io::Error::other(format!("Failed to build gateway: {}", e)))
+    builder.build().map_err(|e| {
+        ChibiError::ConfigInvalid {
+            message: format!("Failed to build gateway: {}", e),
+        }
+        .into()
+    })
 }
 
 /// Resolve `context_window_limit` from ratatoskr's model registry.
@@ -235,7 +280,7 @@ pub async fn chat(config: &ResolvedConfig, messages: &[serde_json::Value]) -> io
     let response = gateway
         .chat(&ratatoskr_messages, None, &options)
         .await
-        .map_err(|e| io::Error::other(format!("Chat request failed: {}", e)))?;
+        .map_err(|e| ChibiError::provider(format!("Chat request failed: {}", e)))?;
 
     Ok(response.content)
 }
@@ -291,6 +336,39 @@ mod tests {
         assert!(matches!(msg.role, Role::Tool { tool_call_id } if tool_call_id == "call_123"));
     }
 
+    #[test]
+    fn test_to_ratatoskr_message_multipart_user() {
+        let json = json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "what is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}
+            ]
+        });
+        let msg = to_ratatoskr_message(&json).unwrap();
+        assert_eq!(msg.role, Role::User);
+        match msg.content {
+            MessageContent::Parts(ref parts) => {
+                assert_eq!(parts.len(), 2);
+                assert!(matches!(&parts[0], ContentPart::Text(t) if t == "what is this?"));
+                assert!(
+                    matches!(&parts[1], ContentPart::ImageUrl { url } if url.starts_with("data:image/png"))
+                );
+            }
+            _ => panic!("expected multipart content"),
+        }
+    }
+
+    #[test]
+    fn test_to_ratatoskr_message_multipart_rejects_tool_role() {
+        let json = json!({
+            "role": "tool",
+            "tool_call_id": "call_1",
+            "content": [{"type": "text", "text": "x"}]
+        });
+        assert!(to_ratatoskr_message(&json).is_err());
+    }
+
     #[test]
     fn test_to_chat_options_includes_parallel_tool_calls() {
         let config = test_config(|api| {
@@ -378,6 +456,17 @@ mod tests {
             reflection_enabled: false,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 10000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: false,
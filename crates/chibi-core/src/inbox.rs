@@ -8,7 +8,7 @@ use crate::safe_io::FileLock;
 use crate::state::{AppState, StatePaths};
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 impl AppState {
@@ -41,26 +41,7 @@ impl AppState {
 
     /// Append a message to a context's inbox with exclusive locking
     pub fn append_to_inbox(&self, context_name: &str, entry: &InboxEntry) -> io::Result<()> {
-        // Ensure context directory exists
-        let context_dir = self.context_dir(context_name);
-        std::fs::create_dir_all(&context_dir)?;
-
-        let lock_path = self.inbox_lock_file(context_name);
-        let inbox_path = self.inbox_file(context_name);
-
-        // Acquire RAII lock - released automatically on drop
-        let _lock = FileLock::acquire(&lock_path)?;
-
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&inbox_path)?;
-
-        let json = serde_json::to_string(entry)
-            .map_err(|e| io::Error::other(format!("JSON serialize error: {}", e)))?;
-        writeln!(file, "{}", json)?;
-
-        Ok(())
+        append_inbox_entry(&self.context_dir(context_name), entry)
     }
 
     /// Load and clear a context's inbox atomically.
@@ -141,3 +122,23 @@ impl AppState {
         Ok(entries)
     }
 }
+
+/// Append `entry` to the inbox in `context_dir`, creating the directory if
+/// needed. Used where no `AppState` is at hand (VFS change notifications).
+pub(crate) fn append_inbox_entry(context_dir: &Path, entry: &InboxEntry) -> io::Result<()> {
+    std::fs::create_dir_all(context_dir)?;
+
+    // Acquire RAII lock - released automatically on drop
+    let _lock = FileLock::acquire(&context_dir.join(".inbox.lock"))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(context_dir.join("inbox.jsonl"))?;
+
+    let json = serde_json::to_string(entry)
+        .map_err(|e| io::Error::other(format!("JSON serialize error: {}", e)))?;
+    writeln!(file, "{}", json)?;
+
+    Ok(())
+}
@@ -7,6 +7,9 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::ExportFormat;
+use crate::tools::tool_test::TestReportFormat;
+
 /// Inspectable things via -n/-N
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -18,6 +21,8 @@ pub enum Inspectable {
     Goals,
     // Global items
     Home,
+    /// Resolved hook chains (order, priority, composition per hook point)
+    Hooks,
     // Lists all inspectable items
     List,
     // Config field (dynamic path like "model", "api.temperature", etc.)
@@ -31,7 +36,24 @@ pub enum Inspectable {
 #[serde(rename_all = "snake_case")]
 pub enum Command {
     /// Send a prompt to the LLM
-    SendPrompt { prompt: String },
+    SendPrompt {
+        prompt: String,
+        /// Images/files to attach (local paths or image URLs)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<String>,
+    },
+    /// Send a prompt and return its reply as JSON validated against `schema` (--schema)
+    SendStructured {
+        prompt: String,
+        /// JSON Schema the final reply must satisfy
+        schema: serde_json::Value,
+        /// Attempts including repairs; `api::structured::DEFAULT_MAX_ATTEMPTS` when absent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_attempts: Option<usize>,
+        /// Images/files to attach (local paths or image URLs)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<String>,
+    },
     /// List all contexts (-L)
     ListContexts,
     /// Show current context info (-l)
@@ -44,6 +66,16 @@ pub enum Command {
     CompactContext { name: Option<String> },
     /// Rename a context (-r/-R)
     RenameContext { old: Option<String>, new: String },
+    /// Export a context to a file (--export/--export-context)
+    ExportContext {
+        name: Option<String>,
+        path: String,
+        /// Inferred from the path's extension when absent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<ExportFormat>,
+    },
+    /// Import a context archive (--import/--import-as)
+    ImportContext { path: String, name: Option<String> },
     /// Show log entries (-g/-G)
     ShowLog {
         context: Option<String>,
@@ -59,6 +91,40 @@ pub enum Command {
         context: Option<String>,
         prompt: String,
     },
+    /// Render a template from the prompt library and send it (--prompt-template)
+    RunPromptTemplate {
+        name: String,
+        /// Positional and `KEY=VALUE` template arguments
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        /// Images/files to attach (local paths or image URLs)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        attachments: Vec<String>,
+    },
+    /// List the templates in the prompt library (--list-prompts)
+    ListPromptTemplates,
+    /// Set a system prompt composed of rendered library templates (--compose-system-prompt)
+    ComposeSystemPrompt {
+        context: Option<String>,
+        /// Template names, joined in order with blank lines
+        fragments: Vec<String>,
+        /// Positional and `KEY=VALUE` template arguments
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+    },
+    /// Create contexts from context templates (--from-template)
+    CreateFromTemplate {
+        /// Template name, or a brace pattern naming several (`{reviewer,tester}`)
+        template: String,
+        /// Context name patterns (`worker-{1..3}`, `{template}-b`); the template name when empty
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        names: Vec<String>,
+        /// Flock every created context joins, on top of the template's own
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flock: Option<String>,
+    },
+    /// List context templates (--list-templates)
+    ListContextTemplates,
     /// Set model for a context (-m/--set-model, -M/--set-model-for-context)
     SetModel {
         context: Option<String>,
@@ -94,6 +160,26 @@ pub enum Command {
     FlockLeave { flock: String, context: String },
     /// List all flocks (--flock-list)
     FlockList,
+    /// Report VFS usage per quota zone (--vfs-usage)
+    VfsUsage,
+    /// Re-run a recorded session and diff it against the recording (--replay)
+    Replay {
+        cassette: String,
+        /// Run live against this model instead of the recorded responses
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        /// Serve recorded tool results instead of executing tools
+        #[serde(default)]
+        replay_tools: bool,
+    },
+    /// Run the define-test tests in a synthesised tool file or directory (--test-tool)
+    TestTool {
+        /// VFS path of a `.scm` file, or a directory of them
+        path: String,
+        /// TAP when absent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        format: Option<TestReportFormat>,
+    },
 }
 
 /// Debug feature keys
@@ -149,6 +235,9 @@ pub struct ExecutionFlags {
     /// Auto-destroy this context after N seconds of inactivity (0/None = disabled).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub destroy_after_seconds_inactive: Option<u64>,
+    /// Record model responses and tool results to this cassette (JSONL) for replay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<String>,
 }
 
 // CLI-specific types (ContextSelection, UsernameOverride, ChibiInput) have been
@@ -292,10 +381,26 @@ mod tests {
     fn test_command_send_prompt_serialization() {
         let cmd = Command::SendPrompt {
             prompt: "hello".to_string(),
+            attachments: vec![],
         };
         let json = serde_json::to_string(&cmd).unwrap();
         assert!(json.contains("send_prompt"));
         assert!(json.contains("hello"));
+        assert!(!json.contains("attachments"));
+    }
+
+    #[test]
+    fn test_command_send_prompt_attachments_default() {
+        let cmd: Command = serde_json::from_str(r#"{"send_prompt":{"prompt":"hi"}}"#).unwrap();
+        assert!(
+            matches!(cmd, Command::SendPrompt { ref attachments, .. } if attachments.is_empty())
+        );
+        let cmd: Command =
+            serde_json::from_str(r#"{"send_prompt":{"prompt":"hi","attachments":["a.png"]}}"#)
+                .unwrap();
+        assert!(
+            matches!(cmd, Command::SendPrompt { ref attachments, .. } if attachments == &["a.png"])
+        );
     }
 
     #[test]
@@ -323,6 +428,23 @@ mod tests {
         assert!(json.contains("null"));
     }
 
+    #[test]
+    fn test_command_export_context_format_optional() {
+        let cmd: Command =
+            serde_json::from_str(r#"{"export_context":{"name":null,"path":"out.md"}}"#).unwrap();
+        assert!(matches!(cmd, Command::ExportContext { format: None, .. }));
+        let cmd: Command =
+            serde_json::from_str(r#"{"export_context":{"name":"a","path":"out","format":"html"}}"#)
+                .unwrap();
+        assert!(matches!(
+            cmd,
+            Command::ExportContext {
+                format: Some(ExportFormat::Html),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_command_rename_context() {
         let cmd = Command::RenameContext {
@@ -439,6 +561,68 @@ mod tests {
         assert_eq!(deser.debug.len(), 1);
     }
 
+    #[test]
+    fn test_command_replay_defaults() {
+        let cmd: Command =
+            serde_json::from_str(r#"{"replay":{"cassette":"session.jsonl"}}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Replay {
+                model: None,
+                replay_tools: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_command_send_structured_defaults() {
+        let cmd: Command = serde_json::from_str(
+            r#"{"send_structured":{"prompt":"triage","schema":{"type":"object"}}}"#,
+        )
+        .unwrap();
+        let Command::SendStructured {
+            schema,
+            max_attempts,
+            attachments,
+            ..
+        } = cmd
+        else {
+            panic!("expected SendStructured");
+        };
+        assert_eq!(schema, serde_json::json!({"type": "object"}));
+        assert_eq!(max_attempts, None);
+        assert!(attachments.is_empty());
+    }
+
+    #[test]
+    fn test_command_run_prompt_template_defaults() {
+        let cmd: Command =
+            serde_json::from_str(r#"{"run_prompt_template":{"name":"review"}}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::RunPromptTemplate { ref name, ref args, ref attachments }
+                if name == "review" && args.is_empty() && attachments.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_command_test_tool_format() {
+        let cmd: Command =
+            serde_json::from_str(r#"{"test_tool":{"path":"/tools/shared"}}"#).unwrap();
+        assert!(matches!(cmd, Command::TestTool { format: None, .. }));
+        let cmd: Command =
+            serde_json::from_str(r#"{"test_tool":{"path":"/tools/shared/a.scm","format":"json"}}"#)
+                .unwrap();
+        assert!(matches!(
+            cmd,
+            Command::TestTool {
+                format: Some(TestReportFormat::Json),
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn test_execution_flags_destroy_fields_round_trip() {
         let flags = ExecutionFlags {
@@ -29,9 +29,11 @@
 
 pub mod agents_md;
 pub mod api;
+pub mod attachments;
 mod chibi;
 pub mod config;
 pub mod context;
+pub mod error;
 pub mod execution;
 pub mod gateway;
 mod inbox;
@@ -54,6 +56,10 @@ pub mod vfs_cache;
 /// System prompt used when processing inbox messages via -b/-B flags.
 pub const INBOX_CHECK_PROMPT: &str = "[System: You have received new message(s) above. Review and take appropriate action now — you may not be reactivated soon, so handle anything urgent immediately.]";
 
+/// Inbox sender for prompts queued by the `queue` lock policy. The lock
+/// holder runs these before releasing the context.
+pub const QUEUED_PROMPT_SENDER: &str = "queued_prompt";
+
 // Re-export the facade
 pub use chibi::{Chibi, LoadOptions, PermissionHandler, project_chibi_dir, project_index_db_path};
 
@@ -61,6 +67,7 @@ pub use chibi::{Chibi, LoadOptions, PermissionHandler, project_chibi_dir, projec
 pub use api::{CollectingSink, PromptOptions, ResponseEvent, ResponseSink};
 pub use config::{ApiParams, Config, LocalConfig, ResolvedConfig, ToolsConfig};
 pub use context::{Context, ContextEntry, TranscriptEntry};
+pub use error::ChibiError;
 pub use execution::{CommandEffect, INSPECTABLE_ITEMS, execute_command};
 pub use input::{Command, ExecutionFlags, Inspectable};
 pub use output::{CommandEvent, OutputSink};
@@ -102,8 +109,20 @@ pub(crate) mod test_support {
             username: "testuser".to_string(),
             lock_heartbeat_seconds: 30,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
+            tool_cache_max_bytes: 0,
             auto_cleanup_cache: false,
             tool_cache_preview_chars: 500,
             file_tools_allowed_paths: vec![],
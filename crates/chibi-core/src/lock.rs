@@ -5,6 +5,43 @@ use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::error::ChibiError;
+
+/// How often `acquire_waiting` retries a held lock.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often `acquire_waiting` reports progress while waiting.
+const WAIT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a prompt does when its context is locked by another process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Fail immediately with `AlreadyExists`
+    Fail,
+    /// Retry until the lock is released or `lock_wait_timeout_seconds` passes
+    Wait,
+    /// Append the prompt to the context's inbox; the holder runs it when done
+    Queue,
+}
+
+impl LockPolicy {
+    /// Parse a `lock_policy` config value.
+    pub fn parse(s: &str) -> io::Result<Self> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "wait" => Ok(Self::Wait),
+            "queue" => Ok(Self::Queue),
+            other => Err(ChibiError::ConfigInvalid {
+                message: format!(
+                    "Unknown lock_policy '{}' (expected fail, wait or queue)",
+                    other
+                ),
+            }
+            .into()),
+        }
+    }
+}
+
 /// Manages a lockfile for a context directory.
 /// The lock is acquired on creation and released on drop.
 /// A heartbeat thread keeps the lock fresh by updating the timestamp.
@@ -49,13 +86,14 @@ impl ContextLock {
                         }
                     }
                     // Lock is held by another active process
-                    return Err(io::Error::new(
-                        ErrorKind::AlreadyExists,
-                        format!(
-                            "Context is locked by another process. Lock file: {}",
-                            lock_path.display()
-                        ),
-                    ));
+                    return Err(ChibiError::ContextLocked {
+                        context: context_dir
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default(),
+                        lock_file: lock_path,
+                    }
+                    .into());
                 }
                 Err(e) => return Err(e),
             }
@@ -91,6 +129,46 @@ impl ContextLock {
         })
     }
 
+    /// Acquire a lock, retrying while another process holds it.
+    ///
+    /// `on_wait` is called with the time waited so far when the first attempt
+    /// fails and every few seconds after. Gives up with `TimedOut` once
+    /// `timeout` has passed; errors other than `AlreadyExists` are returned
+    /// immediately.
+    pub async fn acquire_waiting(
+        context_dir: &Path,
+        heartbeat_secs: u64,
+        timeout: Duration,
+        mut on_wait: impl FnMut(Duration),
+    ) -> io::Result<Self> {
+        let started = std::time::Instant::now();
+        let mut next_report = Duration::ZERO;
+        loop {
+            match Self::acquire(context_dir, heartbeat_secs) {
+                Ok(lock) => return Ok(lock),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let waited = started.elapsed();
+                    if waited >= timeout {
+                        return Err(io::Error::new(
+                            ErrorKind::TimedOut,
+                            format!(
+                                "Timed out after {}s waiting for context lock. Lock file: {}",
+                                timeout.as_secs(),
+                                context_dir.join(".lock").display()
+                            ),
+                        ));
+                    }
+                    if waited >= next_report {
+                        on_wait(waited);
+                        next_report = waited + WAIT_REPORT_INTERVAL;
+                    }
+                    tokio::time::sleep(WAIT_POLL_INTERVAL.min(timeout - waited)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     /// Atomically create lock file with timestamp using O_CREAT | O_EXCL.
     /// Returns `AlreadyExists` error if lock file already exists.
     fn try_create_lock(path: &Path) -> io::Result<()> {
@@ -240,6 +318,10 @@ mod tests {
         let err = result.err().unwrap();
         assert_eq!(err.kind(), ErrorKind::AlreadyExists);
         assert!(err.to_string().contains("locked by another process"));
+        assert!(matches!(
+            ChibiError::find(&err),
+            Some(ChibiError::ContextLocked { .. })
+        ));
     }
 
     #[test]
@@ -428,4 +510,48 @@ mod tests {
 
         drop(lock);
     }
+
+    #[test]
+    fn test_lock_policy_parse() {
+        assert_eq!(LockPolicy::parse("fail").unwrap(), LockPolicy::Fail);
+        assert_eq!(LockPolicy::parse("wait").unwrap(), LockPolicy::Wait);
+        assert_eq!(LockPolicy::parse("queue").unwrap(), LockPolicy::Queue);
+        let err = LockPolicy::parse("block").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waiting_gets_lock_when_released() {
+        let temp_dir = create_test_dir();
+        let holder = ContextLock::acquire(temp_dir.path(), 30).unwrap();
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            drop(holder);
+        });
+
+        let mut reports = Vec::new();
+        let lock =
+            ContextLock::acquire_waiting(temp_dir.path(), 30, Duration::from_secs(10), |waited| {
+                reports.push(waited)
+            })
+            .await
+            .unwrap();
+        release.join().unwrap();
+
+        // Reported once at the start, then acquired well before the next report
+        assert_eq!(reports.len(), 1);
+        assert!(temp_dir.path().join(".lock").exists());
+        drop(lock);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waiting_times_out() {
+        let temp_dir = create_test_dir();
+        let _holder = ContextLock::acquire(temp_dir.path(), 30).unwrap();
+
+        let result =
+            ContextLock::acquire_waiting(temp_dir.path(), 30, Duration::from_millis(300), |_| {})
+                .await;
+        assert_eq!(result.err().unwrap().kind(), ErrorKind::TimedOut);
+    }
 }
@@ -54,6 +54,27 @@ pub enum CommandEvent {
     RollingCompactionFallback { drop_percentage: f64 },
     /// Rolling compaction completed (verbose-tier).
     RollingCompactionComplete { archived: usize, remaining: usize },
+    /// No `custom_compact` hook returned well-formed messages for a strategy;
+    /// rolling ran instead (verbose-tier).
+    CompactionStrategyFallback { strategy: String },
+    /// Context locked by another process; still waiting (always shown).
+    LockWaiting {
+        context: String,
+        waited_secs: u64,
+        timeout_secs: u64,
+    },
+    /// Context lock acquired after waiting (verbose-tier).
+    LockAcquired { context: String, waited_secs: u64 },
+    /// Context locked by another process; prompt queued in its inbox (always shown).
+    PromptQueued { context: String },
+    /// Running prompts other callers queued while this one held the lock (always shown).
+    QueuedPromptsRunning { context: String, count: usize },
+    /// A structured reply failed schema validation; re-prompting (always shown).
+    StructuredOutputRetry {
+        attempt: usize,
+        max_attempts: usize,
+        errors: Vec<String>,
+    },
 }
 
 /// Abstraction over how command results and diagnostics are presented.
@@ -89,6 +110,13 @@ pub trait OutputSink {
         Ok(())
     }
 
+    /// Emit a JSON value as the result of a structured-output command.
+    ///
+    /// The default implementation emits it as compact JSON via `emit_result()`.
+    fn emit_structured(&self, value: &serde_json::Value) {
+        self.emit_result(&value.to_string());
+    }
+
     /// Signal command completion. Called once, after all output has been emitted.
     ///
     /// Default: no-op — chibi-cli handles completion via its own UX.
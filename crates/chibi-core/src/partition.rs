@@ -1042,6 +1042,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         }
     }
 
@@ -1057,6 +1058,7 @@ mod tests {
             tool_call_id: None,
             role: None,
             flow_control: false,
+            attachments: Vec::new(),
         }
     }
 
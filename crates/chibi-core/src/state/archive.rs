@@ -0,0 +1,1076 @@
+//! Context export and import.
+//!
+//! A context archive is a single JSON document bundling everything needed to
+//! recreate a context elsewhere: the context directory (transcript partitions
+//! and manifest, `context.jsonl`, `context_meta.json`, `local.toml`,
+//! `system_prompt.md`, `summary.md`, attachment blobs), the context's VFS home
+//! (`/home/<ctx>/`, including tasks, and the sharing grants inside it), the
+//! goals and prompts of its explicit flocks, and the global reflection. UTF-8
+//! files are stored verbatim so archives stay readable and diffable; anything
+//! else is base64-encoded.
+//!
+//! Transcripts can also be rendered as Markdown or HTML for sharing. Those
+//! renderings are one-way — only archives can be imported.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path};
+
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::context::{
+    ContextEntry, ENTRY_TYPE_COMPACTION, ENTRY_TYPE_CONTROL_TRANSFER, ENTRY_TYPE_MESSAGE,
+    ENTRY_TYPE_TOOL_CALL, ENTRY_TYPE_TOOL_RESULT, TranscriptEntry, now_timestamp,
+    validate_context_name,
+};
+use crate::error::ChibiError;
+use crate::safe_io::{atomic_write, atomic_write_text};
+use crate::vfs::flock::{resolve_flock_vfs_root, validate_flock_name};
+use crate::vfs::{AclAccess, VfsCaller, VfsEntryKind, VfsPath, is_reserved_caller_name};
+
+use super::{AppState, StatePaths};
+
+/// Value of [`ContextArchive::format`]; identifies a file as a chibi archive.
+pub const ARCHIVE_FORMAT: &str = "chibi-context";
+
+/// Current archive schema version. Imports reject newer versions.
+///
+/// Version 2 added `grants`; older builds would drop them and expose private
+/// files, so they must refuse these archives.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+/// Output format for context export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Full, importable JSON archive
+    #[default]
+    Archive,
+    /// Read-only Markdown rendering of the transcript
+    Markdown,
+    /// Read-only standalone HTML rendering of the transcript
+    Html,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "archive" | "json" => Some(ExportFormat::Archive),
+            "markdown" | "md" => Some(ExportFormat::Markdown),
+            "html" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// Infer the format from a file extension (`.md`, `.html`); anything else
+    /// is an archive.
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("md" | "markdown") => ExportFormat::Markdown,
+            Some("html" | "htm") => ExportFormat::Html,
+            _ => ExportFormat::Archive,
+        }
+    }
+}
+
+/// A single file's contents inside an archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "data", rename_all = "snake_case")]
+pub enum ArchiveData {
+    Text(String),
+    Base64(String),
+}
+
+impl ArchiveData {
+    fn encode(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => ArchiveData::Text(text),
+            Err(e) => ArchiveData::Base64(
+                base64::engine::general_purpose::STANDARD.encode(e.into_bytes()),
+            ),
+        }
+    }
+
+    fn decode(&self) -> io::Result<Vec<u8>> {
+        match self {
+            ArchiveData::Text(text) => Ok(text.clone().into_bytes()),
+            ArchiveData::Base64(data) => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
+/// An explicit flock the context belonged to, with its shared files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedFlock {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goals: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// A sharing grant inside the archived home (see `vfs::acl`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedGrant {
+    /// Path relative to `/home/<ctx>/`; empty for the home itself.
+    pub path: String,
+    /// Grantee context name, or `*`.
+    pub context: String,
+    pub access: AclAccess,
+}
+
+/// A portable snapshot of one context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextArchive {
+    pub format: String,
+    pub version: u32,
+    /// Context name at export time (the default name on import).
+    pub context: String,
+    pub created_at: u64,
+    pub exported_at: u64,
+    /// Site the archive was exported from (informational).
+    pub site_id: String,
+    /// Context directory files, keyed by `/`-separated relative path.
+    #[serde(default)]
+    pub files: BTreeMap<String, ArchiveData>,
+    /// VFS home files, keyed by path relative to `/home/<ctx>/`.
+    #[serde(default)]
+    pub home: BTreeMap<String, ArchiveData>,
+    /// Sharing grants inside the VFS home, re-created under the new name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grants: Vec<ArchivedGrant>,
+    /// Explicit flock memberships (the site flock is implicit and not recorded).
+    #[serde(default)]
+    pub flocks: Vec<ArchivedFlock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reflection: Option<String>,
+}
+
+impl ContextArchive {
+    /// Parse and validate an archive document.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let archive: ContextArchive = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("invalid archive: {e}")))?;
+        if archive.format != ARCHIVE_FORMAT {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("not a chibi context archive (format '{}')", archive.format),
+            ));
+        }
+        if archive.version > ARCHIVE_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "archive version {} is newer than supported version {}",
+                    archive.version, ARCHIVE_VERSION
+                ),
+            ));
+        }
+        Ok(archive)
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+}
+
+/// What an import did, for reporting back to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    /// Name the context was imported under.
+    pub name: String,
+    /// True when the archived name was taken and a free one was chosen.
+    pub renamed: bool,
+    /// Flocks the imported context was joined to.
+    pub flocks: Vec<String>,
+    /// Flocks that did not exist locally and were created from the archive.
+    pub created_flocks: Vec<String>,
+    /// True when the archived reflection was installed (only if none existed).
+    pub reflection_imported: bool,
+}
+
+impl AppState {
+    /// Snapshot a context into a [`ContextArchive`].
+    pub async fn export_context(&self, name: &str) -> io::Result<ContextArchive> {
+        let dir = self.context_dir(name);
+        if !dir.exists() {
+            return Err(ChibiError::ContextNotFound {
+                context: name.to_string(),
+            }
+            .into());
+        }
+        self.migrate_transcript_if_needed(name)?;
+
+        let mut files = BTreeMap::new();
+        collect_dir_files(&dir, &dir, &mut files)?;
+
+        let mut home = BTreeMap::new();
+        let home_root = VfsPath::new(&format!("/home/{}", name))?;
+        self.collect_vfs_files(&home_root, "", &mut home).await?;
+        let home_prefix = format!("{}/", home_root);
+        let grants = self
+            .vfs
+            .grants_under(&home_root)
+            .await?
+            .into_iter()
+            .map(|g| ArchivedGrant {
+                path: g
+                    .prefix
+                    .strip_prefix(&home_prefix)
+                    .unwrap_or_default()
+                    .to_string(),
+                context: g.context,
+                access: g.access,
+            })
+            .collect();
+
+        let mut flocks = Vec::new();
+        for flock in self.vfs.flock_list_for(name).await? {
+            let root = resolve_flock_vfs_root(&flock, self.vfs.site_id())?;
+            flocks.push(ArchivedFlock {
+                goals: self.read_vfs_text(&root.join("goals.md")?).await,
+                prompt: self.read_vfs_text(&root.join("prompt.md")?).await,
+                name: flock,
+            });
+        }
+
+        let reflection = self.load_reflection()?;
+        let created_at = {
+            let state = self.state.read().unwrap();
+            state
+                .contexts
+                .iter()
+                .find(|e| e.name == name)
+                .map(|e| e.created_at)
+        }
+        .unwrap_or_else(|| self.get_context_created_at(name));
+
+        Ok(ContextArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            context: name.to_string(),
+            created_at,
+            exported_at: now_timestamp(),
+            site_id: self.vfs.site_id().to_string(),
+            files,
+            home,
+            grants,
+            flocks,
+            reflection: (!reflection.is_empty()).then_some(reflection),
+        })
+    }
+
+    /// Recreate a context from an archive.
+    ///
+    /// With an explicit `name`, importing over an existing context is an
+    /// error. Otherwise the archived name is used, suffixed (`-2`, `-3`, ...)
+    /// if it is taken. Flock memberships are restored; flocks that do not
+    /// exist locally are created with the archived goals and prompt, while
+    /// existing flocks keep their own. Sharing grants are re-created on the
+    /// new home before any file is written to it. The archived reflection is
+    /// installed only when there is no local reflection.
+    pub async fn import_context(
+        &mut self,
+        archive: &ContextArchive,
+        name: Option<&str>,
+    ) -> io::Result<ImportReport> {
+        let (target, renamed) = match name {
+            Some(n) => {
+                validate_context_name(n)?;
+                if self.context_taken(n) {
+                    return Err(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("Context '{}' already exists", n),
+                    ));
+                }
+                (n.to_string(), false)
+            }
+            None => {
+                validate_context_name(&archive.context)?;
+                let mut candidate = archive.context.clone();
+                let mut n = 2;
+                while self.context_taken(&candidate) {
+                    candidate = format!("{}-{}", archive.context, n);
+                    n += 1;
+                }
+                let renamed = candidate != archive.context;
+                (candidate, renamed)
+            }
+        };
+
+        // Validate and decode everything before touching the filesystem, so
+        // a malformed archive fails without writing anything.
+        let home_root = VfsPath::new(&format!("/home/{}", target))?;
+        let mut files = Vec::new();
+        for (rel, data) in &archive.files {
+            check_relative_path(rel)?;
+            files.push((rel, data.decode()?));
+        }
+        let mut home = Vec::new();
+        for (rel, data) in &archive.home {
+            check_relative_path(rel)?;
+            home.push((home_root.join(rel)?, data.decode()?));
+        }
+        let mut grants = Vec::new();
+        for grant in &archive.grants {
+            let path = if grant.path.is_empty() {
+                home_root.clone()
+            } else {
+                check_relative_path(&grant.path)?;
+                home_root.join(&grant.path)?
+            };
+            if grant.context != crate::vfs::acl::EVERYONE {
+                validate_context_name(&grant.context)?;
+                if is_reserved_caller_name(&grant.context) {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("archive grants access to reserved name '{}'", grant.context),
+                    ));
+                }
+            }
+            // The owner is never restricted, so a grant naming it is moot.
+            if grant.context != target {
+                grants.push((path, grant));
+            }
+        }
+        let mut flock_roots = Vec::new();
+        for flock in &archive.flocks {
+            validate_flock_name(&flock.name)?;
+            let root = resolve_flock_vfs_root(&flock.name, self.vfs.site_id())?;
+            if !root.as_str().starts_with("/flocks/") {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("archive names the site flock '{}'", flock.name),
+                ));
+            }
+            let root_existed = self.vfs.exists(VfsCaller::System, &root).await?;
+            flock_roots.push((flock, root, root_existed));
+        }
+        let existing: Vec<String> = self
+            .vfs
+            .flock_list_all()
+            .await?
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+
+        // A write can still fail (quota, permissions); remove what was
+        // written, leave the joined flocks and delete the created ones, so
+        // the context is either fully imported or absent.
+        let dir = self.context_dir(&target);
+        let home_existed = self.vfs.exists(VfsCaller::System, &home_root).await?;
+        let mut shared = Vec::new();
+        let mut flocks = Vec::new();
+        let mut created_flocks = Vec::new();
+        let written: io::Result<()> = async {
+            fs::create_dir_all(&dir)?;
+            for (rel, data) in &files {
+                atomic_write(&dir.join(rel), data)?;
+            }
+            // Grants first, so a private file is never readable by others.
+            for (path, grant) in &grants {
+                self.vfs
+                    .share(VfsCaller::System, path, &grant.context, grant.access)
+                    .await?;
+                shared.push((path, &grant.context));
+            }
+            for (path, data) in &home {
+                self.vfs.write(VfsCaller::System, path, data).await?;
+            }
+            for (flock, root, _) in &flock_roots {
+                self.vfs.flock_join(&flock.name, &target).await?;
+                flocks.push(flock.name.clone());
+                if !existing.contains(&flock.name) {
+                    created_flocks.push(flock.name.clone());
+                    for (file, content) in
+                        [("goals.md", &flock.goals), ("prompt.md", &flock.prompt)]
+                    {
+                        if let Some(content) = content {
+                            self.vfs
+                                .write(VfsCaller::System, &root.join(file)?, content.as_bytes())
+                                .await?;
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = written {
+            let _ = fs::remove_dir_all(&dir);
+            if !home_existed {
+                let _ = self.vfs.delete(VfsCaller::System, &home_root).await;
+            }
+            for (path, context) in shared {
+                let _ = self.vfs.unshare(VfsCaller::System, path, context).await;
+            }
+            for (flock, root, root_existed) in &flock_roots {
+                if created_flocks.contains(&flock.name) {
+                    let _ = self.vfs.flock_delete(&flock.name).await;
+                    if !root_existed {
+                        let _ = self.vfs.delete(VfsCaller::System, root).await;
+                    }
+                } else if flocks.contains(&flock.name) {
+                    let _ = self.vfs.flock_leave(&flock.name, &target).await;
+                }
+            }
+            return Err(e);
+        }
+
+        let mut reflection_imported = false;
+        if let Some(reflection) = &archive.reflection
+            && self.load_reflection()?.is_empty()
+        {
+            fs::create_dir_all(&self.prompts_dir)?;
+            atomic_write_text(&self.prompts_dir.join("reflection.md"), reflection)?;
+            reflection_imported = true;
+        }
+
+        // The prefix was built on another machine; rebuild it on next load.
+        self.mark_context_dirty(&target)?;
+        self.active_state_cache.borrow_mut().remove(&target);
+        {
+            let mut state = self.state.write().unwrap();
+            if !state.contexts.iter().any(|e| e.name == target) {
+                state
+                    .contexts
+                    .push(ContextEntry::with_created_at(&target, archive.created_at));
+            }
+        }
+        self.save()?;
+
+        Ok(ImportReport {
+            name: target,
+            renamed,
+            flocks,
+            created_flocks,
+            reflection_imported,
+        })
+    }
+
+    /// Render a context's full transcript as Markdown or HTML.
+    ///
+    /// Errors for [`ExportFormat::Archive`], which is not a rendering.
+    pub fn render_transcript(&self, name: &str, format: ExportFormat) -> io::Result<String> {
+        if !self.context_dir(name).exists() {
+            return Err(ChibiError::ContextNotFound {
+                context: name.to_string(),
+            }
+            .into());
+        }
+        let entries = self.read_transcript_entries(name)?;
+        match format {
+            ExportFormat::Markdown => Ok(render_transcript_markdown(name, &entries)),
+            ExportFormat::Html => Ok(render_transcript_html(name, &entries)),
+            ExportFormat::Archive => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "archives are produced by export_context, not render_transcript",
+            )),
+        }
+    }
+
+    fn context_taken(&self, name: &str) -> bool {
+        self.context_dir(name).exists()
+            || self
+                .state
+                .read()
+                .unwrap()
+                .contexts
+                .iter()
+                .any(|e| e.name == name)
+    }
+
+    async fn read_vfs_text(&self, path: &VfsPath) -> Option<String> {
+        self.vfs
+            .read(VfsCaller::System, path)
+            .await
+            .ok()
+            .map(|data| String::from_utf8_lossy(&data).into_owned())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Recursively collect files under `dir` into `out`, keyed by `prefix`-relative path.
+    async fn collect_vfs_files(
+        &self,
+        dir: &VfsPath,
+        prefix: &str,
+        out: &mut BTreeMap<String, ArchiveData>,
+    ) -> io::Result<()> {
+        let entries = match self.vfs.list(VfsCaller::System, dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let path = dir.join(&entry.name)?;
+            let rel = format!("{}{}", prefix, entry.name);
+            match entry.kind {
+                VfsEntryKind::Directory => {
+                    Box::pin(self.collect_vfs_files(&path, &format!("{rel}/"), out)).await?
+                }
+                VfsEntryKind::File => {
+                    let data = self.vfs.read(VfsCaller::System, &path).await?;
+                    out.insert(rel, ArchiveData::encode(data));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recursively collect a context directory's files, skipping dotfiles
+/// (locks and the dirty marker are machine-local).
+fn collect_dir_files(
+    root: &Path,
+    dir: &Path,
+    out: &mut BTreeMap<String, ArchiveData>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_dir_files(root, &path, out)?;
+        } else {
+            let rel = path
+                .strip_prefix(root)
+                .map_err(io::Error::other)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.insert(rel, ArchiveData::encode(fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+/// Reject archive paths that could escape the target directory.
+fn check_relative_path(rel: &str) -> io::Result<()> {
+    let path = Path::new(rel);
+    if rel.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unsafe path in archive: '{}'", rel),
+        ));
+    }
+    Ok(())
+}
+
+fn format_timestamp(ts: u64) -> String {
+    chrono::DateTime::from_timestamp(ts as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+/// Wrap `content` in a code fence longer than any backtick run inside it.
+fn fenced(content: &str, lang: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{lang}\n{}\n{fence}\n", content.trim_end())
+}
+
+/// Pretty-print JSON tool arguments when possible.
+fn pretty_json(content: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(content)
+        .and_then(|v| serde_json::to_string_pretty(&v))
+        .unwrap_or_else(|_| content.to_string())
+}
+
+/// Render a transcript as Markdown.
+pub fn render_transcript_markdown(context: &str, entries: &[TranscriptEntry]) -> String {
+    let mut out = format!("# {}\n\n", context);
+    out.push_str(&format!(
+        "*Exported {} · {} entries*\n",
+        format_timestamp(now_timestamp()),
+        entries.len()
+    ));
+    for entry in entries {
+        let time = format_timestamp(entry.timestamp);
+        match entry.entry_type.as_str() {
+            ENTRY_TYPE_CONTROL_TRANSFER => continue,
+            ENTRY_TYPE_MESSAGE => {
+                out.push_str(&format!(
+                    "\n## {} → {}\n\n*{}*\n\n",
+                    entry.from, entry.to, time
+                ));
+                out.push_str(entry.content.trim_end());
+                out.push('\n');
+            }
+            ENTRY_TYPE_TOOL_CALL => {
+                out.push_str(&format!(
+                    "\n### Tool call: `{}`\n\n*{}*\n\n",
+                    entry.to, time
+                ));
+                out.push_str(&fenced(&pretty_json(&entry.content), "json"));
+            }
+            ENTRY_TYPE_TOOL_RESULT => {
+                out.push_str(&format!(
+                    "\n### Tool result: `{}`\n\n*{}*\n\n",
+                    entry.from, time
+                ));
+                out.push_str(&fenced(&entry.content, ""));
+            }
+            _ => {
+                out.push_str(&format!("\n---\n\n*{} — {}*\n", entry.content, time));
+                if entry.entry_type == ENTRY_TYPE_COMPACTION
+                    && let Some(summary) = entry.metadata.as_ref().and_then(|m| m.summary.as_ref())
+                {
+                    out.push('\n');
+                    for line in summary.lines() {
+                        out.push_str(&format!("> {}\n", line));
+                    }
+                }
+            }
+        }
+        for attachment in &entry.attachments {
+            out.push_str(&format!(
+                "\n- attachment: {} ({})\n",
+                attachment.name, attachment.mime
+            ));
+        }
+    }
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;padding:0 1rem;color:#222}\
+header p{color:#666}\
+.entry{border-left:3px solid #ccc;margin:1rem 0;padding:.25rem .75rem}\
+.entry.user{border-color:#4a7bd0}.entry.agent{border-color:#3a9a5b}\
+.entry.tool{border-color:#c08a2e;background:#fafafa}\
+.entry.anchor{border-color:#999;color:#666;font-style:italic}\
+.meta{font-size:.85em;color:#666}\
+pre{white-space:pre-wrap;word-wrap:break-word;margin:.5rem 0}";
+
+/// Render a transcript as a standalone HTML document.
+pub fn render_transcript_html(context: &str, entries: &[TranscriptEntry]) -> String {
+    let title = escape_html(context);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <header><h1>{title}</h1><p>Exported {} · {} entries</p></header>\n",
+        format_timestamp(now_timestamp()),
+        entries.len()
+    );
+    for entry in entries {
+        let time = escape_html(&format_timestamp(entry.timestamp));
+        let (class, heading, body) = match entry.entry_type.as_str() {
+            ENTRY_TYPE_CONTROL_TRANSFER => continue,
+            ENTRY_TYPE_MESSAGE => (
+                entry.role.as_deref().unwrap_or("message"),
+                format!("{} → {}", escape_html(&entry.from), escape_html(&entry.to)),
+                entry.content.clone(),
+            ),
+            ENTRY_TYPE_TOOL_CALL => (
+                "tool",
+                format!("Tool call: <code>{}</code>", escape_html(&entry.to)),
+                pretty_json(&entry.content),
+            ),
+            ENTRY_TYPE_TOOL_RESULT => (
+                "tool",
+                format!("Tool result: <code>{}</code>", escape_html(&entry.from)),
+                entry.content.clone(),
+            ),
+            _ => (
+                "anchor",
+                escape_html(&entry.content),
+                entry
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.summary.clone())
+                    .unwrap_or_default(),
+            ),
+        };
+        out.push_str(&format!(
+            "<section class=\"entry {}\">\n<div class=\"meta\">{} · {}</div>\n",
+            escape_html(class),
+            heading,
+            time
+        ));
+        if !body.is_empty() {
+            out.push_str(&format!("<pre>{}</pre>\n", escape_html(body.trim_end())));
+        }
+        for attachment in &entry.attachments {
+            out.push_str(&format!(
+                "<div class=\"meta\">attachment: {} ({})</div>\n",
+                escape_html(&attachment.name),
+                escape_html(&attachment.mime)
+            ));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{create_assistant_message_entry, create_tool_call_entry};
+    use crate::test_support::create_test_chibi;
+
+    fn seed_context(app: &AppState, name: &str) {
+        app.append_to_transcript(
+            name,
+            &create_assistant_message_entry(name, "hello <world>", "u"),
+        )
+        .unwrap();
+        app.append_to_transcript(
+            name,
+            &create_tool_call_entry(name, "shell", "{\"cmd\":\"ls\"}", "t1"),
+        )
+        .unwrap();
+        fs::write(app.summary_file(name), "a summary").unwrap();
+        fs::write(app.context_dir(name).join(".lock"), "pid").unwrap();
+        fs::create_dir_all(app.attachments_dir(name)).unwrap();
+        fs::write(app.attachments_dir(name).join("blob"), [0xff, 0x00, 0xfe]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_import_roundtrip() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        app.ensure_context_dir("src").unwrap();
+        seed_context(app, "src");
+        let note = VfsPath::new("/home/src/notes/todo.md").unwrap();
+        app.vfs
+            .write(VfsCaller::System, &note, b"remember")
+            .await
+            .unwrap();
+        app.vfs.flock_join("team", "src").await.unwrap();
+        let goals = VfsPath::new("/flocks/team/goals.md").unwrap();
+        app.vfs
+            .write(VfsCaller::System, &goals, b"ship it")
+            .await
+            .unwrap();
+
+        let archive = app.export_context("src").await.unwrap();
+        assert!(archive.files.contains_key("summary.md"));
+        assert!(!archive.files.contains_key(".lock"));
+        assert!(matches!(
+            archive.files.get("attachments/blob"),
+            Some(ArchiveData::Base64(_))
+        ));
+        assert_eq!(
+            archive.home.get("notes/todo.md"),
+            Some(&ArchiveData::Text("remember".to_string()))
+        );
+        assert_eq!(archive.flocks[0].goals.as_deref(), Some("ship it"));
+
+        let parsed = ContextArchive::from_json(&archive.to_json().unwrap()).unwrap();
+        let report = app.import_context(&parsed, Some("copy")).await.unwrap();
+        assert_eq!(report.name, "copy");
+        assert_eq!(report.flocks, vec!["team".to_string()]);
+        assert!(report.created_flocks.is_empty());
+        assert_eq!(
+            fs::read(app.attachments_dir("copy").join("blob")).unwrap(),
+            vec![0xff, 0x00, 0xfe]
+        );
+        assert_eq!(app.read_transcript_entries("copy").unwrap().len(), 2);
+        let copied = VfsPath::new("/home/copy/notes/todo.md").unwrap();
+        assert_eq!(
+            app.vfs.read(VfsCaller::System, &copied).await.unwrap(),
+            b"remember"
+        );
+        assert!(
+            app.vfs
+                .flock_list_for("copy")
+                .await
+                .unwrap()
+                .contains(&"team".to_string())
+        );
+        assert!(app.list_contexts().contains(&"copy".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_private_files_stay_private_after_import() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        app.ensure_context_dir("src").unwrap();
+        for (path, data) in [
+            ("/home/src/secret/key.md", "hunter2"),
+            ("/home/src/notes.md", "public"),
+        ] {
+            app.vfs
+                .write(
+                    VfsCaller::System,
+                    &VfsPath::new(path).unwrap(),
+                    data.as_bytes(),
+                )
+                .await
+                .unwrap();
+        }
+        let secret = VfsPath::new("/home/src/secret").unwrap();
+        app.vfs
+            .share(VfsCaller::System, &secret, "*", AclAccess::None)
+            .await
+            .unwrap();
+        app.vfs
+            .share(VfsCaller::System, &secret, "friend", AclAccess::Read)
+            .await
+            .unwrap();
+
+        let archive = app.export_context("src").await.unwrap();
+        assert_eq!(archive.grants.len(), 2);
+        assert!(archive.grants.iter().all(|g| g.path == "secret"));
+
+        let parsed = ContextArchive::from_json(&archive.to_json().unwrap()).unwrap();
+        app.import_context(&parsed, Some("copy")).await.unwrap();
+        let key = VfsPath::new("/home/copy/secret/key.md").unwrap();
+        let err = app
+            .vfs
+            .read(VfsCaller::Context("bob"), &key)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(
+            app.vfs
+                .read(VfsCaller::Context("friend"), &key)
+                .await
+                .unwrap(),
+            b"hunter2"
+        );
+        let notes = VfsPath::new("/home/copy/notes.md").unwrap();
+        assert!(
+            app.vfs
+                .read(VfsCaller::Context("bob"), &notes)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_name_collisions() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        app.ensure_context_dir("src").unwrap();
+        seed_context(app, "src");
+        let archive = app.export_context("src").await.unwrap();
+
+        let err = app.import_context(&archive, Some("src")).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+        let first = app.import_context(&archive, None).await.unwrap();
+        assert_eq!(first.name, "src-2");
+        assert!(first.renamed);
+        let second = app.import_context(&archive, None).await.unwrap();
+        assert_eq!(second.name, "src-3");
+    }
+
+    #[tokio::test]
+    async fn test_import_creates_missing_flock_and_reflection() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        let mut archive = empty_archive("remote");
+        archive.flocks.push(ArchivedFlock {
+            name: "crew".to_string(),
+            goals: Some("crew goals".to_string()),
+            prompt: None,
+        });
+        archive.reflection = Some("learned things".to_string());
+        let report = app.import_context(&archive, None).await.unwrap();
+        assert_eq!(report.created_flocks, vec!["crew".to_string()]);
+        assert!(report.reflection_imported);
+        assert_eq!(app.load_reflection().unwrap(), "learned things");
+        let goals = VfsPath::new("/flocks/crew/goals.md").unwrap();
+        assert_eq!(
+            app.vfs.read(VfsCaller::System, &goals).await.unwrap(),
+            b"crew goals"
+        );
+
+        // An existing reflection is never overwritten.
+        let again = app.import_context(&archive, None).await.unwrap();
+        assert!(!again.reflection_imported);
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_unsafe_paths() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        let mut archive = empty_archive("evil");
+        archive.files.insert(
+            "../escape.txt".to_string(),
+            ArchiveData::Text("x".to_string()),
+        );
+        let err = app.import_context(&archive, None).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!app.context_dir("evil").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_import_leaves_nothing_behind() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+
+        // Bad base64 in a later file is caught before anything is written.
+        let mut archive = empty_archive("broken");
+        archive
+            .files
+            .insert("summary.md".to_string(), ArchiveData::Text("s".to_string()));
+        archive
+            .files
+            .insert("zz".to_string(), ArchiveData::Base64("!!!".to_string()));
+        let err = app.import_context(&archive, None).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(!app.context_dir("broken").exists());
+
+        // A home file nested under another file can't be written.
+        let mut archive = empty_archive("broken");
+        archive
+            .files
+            .insert("summary.md".to_string(), ArchiveData::Text("s".to_string()));
+        for rel in ["a.md", "a.md/b.md"] {
+            archive
+                .home
+                .insert(rel.to_string(), ArchiveData::Text("x".to_string()));
+        }
+        assert!(app.import_context(&archive, None).await.is_err());
+        assert!(!app.context_dir("broken").exists());
+        let home = VfsPath::new("/home/broken").unwrap();
+        assert!(!app.vfs.exists(VfsCaller::System, &home).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_flock_import_leaves_nothing_behind() {
+        let (mut chibi, _tmp) = create_test_chibi();
+        let app = &mut chibi.app;
+        app.vfs.flock_join("team", "other").await.unwrap();
+        let crew = |name: &str| ArchivedFlock {
+            name: name.to_string(),
+            goals: Some("crew goals".to_string()),
+            prompt: None,
+        };
+
+        // An invalid flock name is caught before anything is written.
+        let mut archive = empty_archive("broken");
+        archive
+            .files
+            .insert("summary.md".to_string(), ArchiveData::Text("s".to_string()));
+        archive.flocks = vec![crew("crew"), crew("Not A Flock")];
+        assert!(app.import_context(&archive, None).await.is_err());
+        assert!(!app.context_dir("broken").exists());
+        assert!(app.vfs.flock_list_for("broken").await.unwrap().is_empty());
+
+        // A file where the new flock's directory should be makes its goals
+        // unwritable, after the existing flock was joined.
+        let blocker = VfsPath::new("/flocks/crew").unwrap();
+        app.vfs
+            .write(VfsCaller::System, &blocker, b"in the way")
+            .await
+            .unwrap();
+        archive.flocks = vec![
+            ArchivedFlock {
+                name: "team".to_string(),
+                goals: None,
+                prompt: None,
+            },
+            crew("crew"),
+        ];
+        assert!(app.import_context(&archive, None).await.is_err());
+        assert!(!app.context_dir("broken").exists());
+        assert!(app.vfs.flock_list_for("broken").await.unwrap().is_empty());
+        let names: Vec<String> = app
+            .vfs
+            .flock_list_all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert!(!names.contains(&"crew".to_string()));
+        assert_eq!(
+            app.vfs.read(VfsCaller::System, &blocker).await.unwrap(),
+            b"in the way"
+        );
+    }
+
+    fn empty_archive(name: &str) -> ContextArchive {
+        ContextArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            context: name.to_string(),
+            created_at: 0,
+            exported_at: 0,
+            site_id: String::new(),
+            files: BTreeMap::new(),
+            home: BTreeMap::new(),
+            grants: vec![],
+            flocks: vec![],
+            reflection: None,
+        }
+    }
+
+    #[test]
+    fn test_from_json_rejects_foreign_documents() {
+        let err = ContextArchive::from_json("{\"format\":\"other\",\"version\":1,\"context\":\"x\",\"created_at\":0,\"exported_at\":0,\"site_id\":\"\"}").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let mut newer = empty_archive("x");
+        newer.version = ARCHIVE_VERSION + 1;
+        assert!(ContextArchive::from_json(&newer.to_json().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_export_format_from_path() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.md")),
+            ExportFormat::Markdown
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.HTML")),
+            ExportFormat::Html
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.json")),
+            ExportFormat::Archive
+        );
+        assert_eq!(ExportFormat::parse("md"), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::parse("pdf"), None);
+    }
+
+    #[test]
+    fn test_render_markdown_and_html() {
+        let entries = vec![
+            create_assistant_message_entry("ctx", "hello <b>", "u"),
+            create_tool_call_entry("ctx", "shell", "{\"cmd\":\"```\"}", "t1"),
+        ];
+        let md = render_transcript_markdown("ctx", &entries);
+        assert!(md.starts_with("# ctx\n"));
+        assert!(md.contains("## ctx → u"));
+        assert!(md.contains("hello <b>"));
+        // Fence must outgrow the backticks inside the arguments.
+        assert!(md.contains("````json"));
+
+        let html = render_transcript_html("ctx", &entries);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("hello &lt;b&gt;"));
+        assert!(html.contains("Tool call: <code>shell</code>"));
+        assert!(!html.contains("<b>"));
+    }
+}
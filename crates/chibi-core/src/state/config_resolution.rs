@@ -3,6 +3,7 @@
 //! Methods for loading, saving, and resolving local configs and model names.
 
 use crate::config::{ApiParams, ConfigDefaults, LocalConfig, ResolvedConfig};
+use crate::error::ChibiError;
 use std::collections::BTreeMap;
 use std::env;
 use std::fs;
@@ -17,10 +18,10 @@ impl AppState {
         if path.exists() {
             let content = fs::read_to_string(&path)?;
             toml::from_str(&content).map_err(|e| {
-                io::Error::new(
-                    ErrorKind::InvalidData,
-                    format!("Failed to parse local.toml: {}", e),
-                )
+                ChibiError::ConfigInvalid {
+                    message: format!("Failed to parse local.toml: {}", e),
+                }
+                .with_kind(ErrorKind::InvalidData)
             })
         } else {
             Ok(LocalConfig::default())
@@ -80,6 +81,17 @@ impl AppState {
             reflection_enabled: self.config.reflection_enabled,
             reflection_character_limit: self.config.reflection_character_limit,
             rolling_compact_drop_percentage: self.config.rolling_compact_drop_percentage,
+            compaction_strategy: self.config.compaction_strategy.clone(),
+            compact_keep_turns: self.config.compact_keep_turns,
+            lock_policy: self.config.lock_policy.clone(),
+            lock_wait_timeout_seconds: self.config.lock_wait_timeout_seconds,
+            progress_action: self.config.progress_action.clone(),
+            progress_window: self.config.progress_window,
+            progress_error_streak: self.config.progress_error_streak,
+            progress_stall_calls: self.config.progress_stall_calls,
+            provider_max_retries: self.config.provider_max_retries,
+            provider_retry_base_ms: self.config.provider_retry_base_ms,
+            model_fallbacks: self.config.model_fallbacks.clone(),
             tool_output_cache_threshold: self.config.tool_output_cache_threshold,
             tool_cache_max_age_days: self.config.tool_cache_max_age_days,
             auto_cleanup_cache: self.config.auto_cleanup_cache,
@@ -113,6 +125,9 @@ impl AppState {
             resolved.tools = resolved.tools.merge_local(local_tools);
         }
 
+        // Reject unknown strategy names here rather than at compaction time
+        crate::api::compact_strategy::CompactionStrategy::parse(&resolved.compaction_strategy)?;
+
         // Apply runtime username override (highest priority)
         if let Some(username) = username_override {
             resolved.username = username.to_string();
@@ -182,10 +197,10 @@ impl AppState {
         let in_registry = registry.get(fallback).is_some();
 
         if !is_builtin && !in_registry {
-            return Err(io::Error::new(
-                ErrorKind::InvalidInput,
-                format!("fallback_tool '{}' not found", fallback),
-            ));
+            return Err(ChibiError::ConfigInvalid {
+                message: format!("fallback_tool '{}' not found", fallback),
+            }
+            .into());
         }
 
         // Enforce: fallback must be a flow_control tool
@@ -2,7 +2,9 @@
 //!
 //! Methods for creating, loading, saving, clearing, destroying, renaming, and listing contexts.
 
+use crate::attachments::AttachmentRef;
 use crate::context::{Context, ContextEntry, now_timestamp, validate_context_name};
+use crate::error::ChibiError;
 use std::fs;
 use std::io::{self, ErrorKind};
 
@@ -62,11 +64,26 @@ impl AppState {
     }
 
     pub fn add_message(&self, context: &mut Context, role: String, content: String) {
-        context.messages.push(serde_json::json!({
+        self.add_message_with_attachments(context, role, content, &[]);
+    }
+
+    /// Like [`add_message`](Self::add_message), recording attachment refs on the message.
+    pub fn add_message_with_attachments(
+        &self,
+        context: &mut Context,
+        role: String,
+        content: String,
+        attachments: &[AttachmentRef],
+    ) {
+        let mut message = serde_json::json!({
             "_id": uuid::Uuid::new_v4().to_string(),
             "role": role,
             "content": content,
-        }));
+        });
+        if !attachments.is_empty() {
+            message["attachments"] = serde_json::json!(attachments);
+        }
+        context.messages.push(message);
         context.updated_at = now_timestamp();
     }
 
@@ -148,10 +165,10 @@ impl AppState {
         let new_dir = self.context_dir(new_name);
 
         if !old_dir.exists() {
-            return Err(io::Error::new(
-                ErrorKind::NotFound,
-                format!("Context '{}' does not exist", old_name),
-            ));
+            return Err(ChibiError::ContextNotFound {
+                context: old_name.to_string(),
+            }
+            .into());
         }
 
         if new_dir.exists() {
@@ -0,0 +1,219 @@
+//! Context templates for provisioning specialised contexts in one step.
+//!
+//! A template lives at `~/.chibi/templates/<name>.toml`. It is a `local.toml`
+//! (model, tool filters, fuel, ...) plus a few provisioning keys:
+//!
+//! ```toml
+//! description = "Reviews diffs before they land"
+//! system_prompt = "You are a meticulous code reviewer."
+//! prompt_templates = ["house-style"]   # appended from /site/prompts
+//! flocks = ["team"]
+//! goals = "Ship the parser rewrite"     # seeds each flock's goals.md if empty
+//! model = "anthropic/claude-sonnet-4"
+//!
+//! [tools]
+//! exclude = ["shell_exec"]
+//! ```
+//!
+//! Templates live in the chibi home rather than the VFS: they carry tool
+//! permissions and API settings, which agents must not be able to rewrite.
+
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::config::LocalConfig;
+use crate::error::ChibiError;
+
+use super::AppState;
+
+/// Most contexts a single `CreateFromTemplate` may expand to.
+pub const MAX_TEMPLATE_CONTEXTS: usize = 64;
+
+/// A named bundle of settings applied to newly created contexts.
+#[derive(Debug, Default, Deserialize)]
+pub struct ContextTemplate {
+    /// One-line summary, for listings.
+    pub description: Option<String>,
+    /// System prompt text.
+    pub system_prompt: Option<String>,
+    /// Prompt library templates appended to `system_prompt`, in order.
+    #[serde(default)]
+    pub prompt_templates: Vec<String>,
+    /// Arguments for `prompt_templates` (positional or `KEY=VALUE`).
+    #[serde(default)]
+    pub prompt_args: Vec<String>,
+    /// Flocks the context joins.
+    #[serde(default)]
+    pub flocks: Vec<String>,
+    /// Goals written to each joined flock that has none yet.
+    pub goals: Option<String>,
+    /// Everything else is written to the context's `local.toml`.
+    #[serde(flatten)]
+    pub local: LocalConfig,
+}
+
+impl AppState {
+    /// Directory holding context templates (`~/.chibi/templates/`).
+    pub fn context_templates_dir(&self) -> PathBuf {
+        self.chibi_dir.join("templates")
+    }
+
+    /// List template names with their descriptions, sorted by name.
+    pub fn list_context_templates(&self) -> io::Result<Vec<(String, Option<String>)>> {
+        let dir = self.context_templates_dir();
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut templates = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let description = self.load_context_template(name)?.description;
+            templates.push((name.to_string(), description));
+        }
+        templates.sort();
+        Ok(templates)
+    }
+
+    /// Load `~/.chibi/templates/<name>.toml`.
+    pub fn load_context_template(&self, name: &str) -> io::Result<ContextTemplate> {
+        crate::context::validate_context_name(name).map_err(|_| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid context template name '{}'", name),
+            )
+        })?;
+        let path = self.context_templates_dir().join(format!("{}.toml", name));
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            if e.kind() == ErrorKind::NotFound {
+                io::Error::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "Context template '{}' not found (expected {})",
+                        name,
+                        path.display()
+                    ),
+                )
+            } else {
+                e
+            }
+        })?;
+        toml::from_str(&content).map_err(|e| {
+            ChibiError::ConfigInvalid {
+                message: format!("Failed to parse context template '{}': {}", name, e),
+            }
+            .with_kind(ErrorKind::InvalidData)
+        })
+    }
+}
+
+/// Expand brace patterns: `{1..3}` counts, `{a,b}` lists alternatives, and
+/// several groups multiply out (`{a,b}-{1..2}` gives four names). Braces with
+/// neither `..` nor `,` are kept as written.
+pub fn expand_name_pattern(pattern: &str) -> io::Result<Vec<String>> {
+    let mut out = Vec::new();
+    expand_into(pattern, 0, &mut out)?;
+    Ok(out)
+}
+
+fn expand_into(pattern: &str, from: usize, out: &mut Vec<String>) -> io::Result<()> {
+    let Some((start, end, alternatives)) = next_group(pattern, from)? else {
+        if out.len() >= MAX_TEMPLATE_CONTEXTS {
+            return Err(too_many());
+        }
+        out.push(pattern.to_string());
+        return Ok(());
+    };
+    for alt in alternatives {
+        let expanded = format!("{}{}{}", &pattern[..start], alt, &pattern[end + 1..]);
+        expand_into(&expanded, start + alt.len(), out)?;
+    }
+    Ok(())
+}
+
+/// Find the next expandable `{...}` at or after `from`: (open, close, alternatives).
+fn next_group(pattern: &str, from: usize) -> io::Result<Option<(usize, usize, Vec<String>)>> {
+    let mut search = from;
+    while let Some(offset) = pattern[search..].find('{') {
+        let start = search + offset;
+        let Some(len) = pattern[start..].find('}') else {
+            return Ok(None);
+        };
+        let end = start + len;
+        let inner = &pattern[start + 1..end];
+        if let Some((lo, hi)) = inner.split_once("..") {
+            let invalid = || {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Invalid range '{{{}}}' in '{}'", inner, pattern),
+                )
+            };
+            let parse = |s: &str| s.trim().parse::<u64>().map_err(|_| invalid());
+            let (lo, hi) = (parse(lo)?, parse(hi)?);
+            if hi < lo {
+                return Err(invalid());
+            }
+            if hi - lo >= MAX_TEMPLATE_CONTEXTS as u64 {
+                return Err(too_many());
+            }
+            return Ok(Some((
+                start,
+                end,
+                (lo..=hi).map(|n| n.to_string()).collect(),
+            )));
+        }
+        if inner.contains(',') {
+            let alternatives = inner.split(',').map(|s| s.trim().to_string()).collect();
+            return Ok(Some((start, end, alternatives)));
+        }
+        search = end + 1;
+    }
+    Ok(None)
+}
+
+fn too_many() -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "Name pattern expands to more than {} contexts",
+            MAX_TEMPLATE_CONTEXTS
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_name_pattern() {
+        assert_eq!(expand_name_pattern("solo").unwrap(), vec!["solo"]);
+        assert_eq!(
+            expand_name_pattern("worker-{1..3}").unwrap(),
+            vec!["worker-1", "worker-2", "worker-3"]
+        );
+        assert_eq!(
+            expand_name_pattern("{a, b}-{1..2}").unwrap(),
+            vec!["a-1", "a-2", "b-1", "b-2"]
+        );
+        assert_eq!(
+            expand_name_pattern("team-{template}").unwrap(),
+            vec!["team-{template}"]
+        );
+    }
+
+    #[test]
+    fn test_expand_name_pattern_limits() {
+        assert!(expand_name_pattern("w-{1..1000}").is_err());
+        assert!(expand_name_pattern("w-{3..1}").is_err());
+        assert!(expand_name_pattern("w-{a..z}").is_err());
+        assert!(expand_name_pattern("{1..8}-{1..8}-{1..2}").is_err());
+    }
+}
@@ -5,14 +5,21 @@
 //! - Configuration loading and resolution
 //! - Transcript and inbox operations
 
+pub mod archive;
 mod config_resolution;
 mod context_ops;
+pub mod context_templates;
 mod entries;
 pub mod flocks;
 mod paths;
+pub mod prompt_templates;
 mod prompts;
 pub mod tasks;
 
+pub use archive::{
+    ContextArchive, ExportFormat, ImportReport, render_transcript_html, render_transcript_markdown,
+};
+pub use context_templates::{ContextTemplate, MAX_TEMPLATE_CONTEXTS, expand_name_pattern};
 pub use entries::{
     create_archival_anchor, create_assistant_message_entry, create_compaction_anchor,
     create_context_created_anchor, create_control_transfer_entry,
@@ -21,10 +28,15 @@ pub use entries::{
 };
 pub use flocks::{FlockContext, format_flock_sections, load_flock_contexts};
 pub use paths::StatePaths;
+pub use prompt_templates::{
+    PROMPT_LIBRARY_DIR, TemplateVars, list_prompt_templates, load_prompt_template,
+    render_prompt_template,
+};
 
 use crate::jsonl::read_jsonl_file;
 
 use crate::config::{Config, ConfigDefaults, ResolvedConfig};
+use crate::error::ChibiError;
 // Note: ImageConfig, MarkdownStyle removed - these are CLI presentation concerns
 use crate::context::{
     Context, ContextEntry, ContextMeta, ContextState, TranscriptEntry, is_valid_context_name,
@@ -98,25 +110,12 @@ impl AppState {
             contexts: Vec::new(),
         }));
 
-        let vfs_root = chibi_dir.join("vfs");
-        if config.vfs.backend != "local" {
-            return Err(io::Error::new(
-                ErrorKind::InvalidInput,
-                format!(
-                    "unsupported VFS backend '{}' (only 'local' is built in)",
-                    config.vfs.backend
-                ),
-            ));
-        }
-        fs::create_dir_all(&vfs_root)?;
-        // Bootstrap /site/ VFS directory (sync, directly on filesystem).
-        let _ = fs::create_dir_all(vfs_root.join("site"));
-        let vfs_backend = crate::vfs::LocalBackend::new(vfs_root);
+        let vfs_backend = crate::vfs::open_root_backend(&config.vfs, &chibi_dir)?.boxed();
 
         let hostname_override = config.site.as_ref().and_then(|s| s.hostname.as_deref());
         let site = crate::site::load_or_create(&chibi_dir, hostname_override)?;
 
-        let vfs = crate::vfs::Vfs::new(Box::new(vfs_backend), &site.site_id);
+        let vfs = crate::vfs::Vfs::new(vfs_backend, &site.site_id);
 
         Ok(AppState {
             config,
@@ -169,10 +168,10 @@ impl AppState {
                 String::new()
             };
             toml::from_str(&content).map_err(|e| {
-                io::Error::new(
-                    ErrorKind::InvalidData,
-                    format!("Failed to parse config: {}", e),
-                )
+                ChibiError::ConfigInvalid {
+                    message: format!("Failed to parse config: {}", e),
+                }
+                .with_kind(ErrorKind::InvalidData)
             })?
         };
 
@@ -198,24 +197,11 @@ impl AppState {
         };
         let state = Arc::new(RwLock::new(loaded_state));
 
-        let vfs_root = chibi_dir.join("vfs");
-        if config.vfs.backend != "local" {
-            return Err(io::Error::new(
-                ErrorKind::InvalidInput,
-                format!(
-                    "unsupported VFS backend '{}' (only 'local' is built in)",
-                    config.vfs.backend
-                ),
-            ));
-        }
-        fs::create_dir_all(&vfs_root)?;
-        // Bootstrap /site/ VFS directory (sync, directly on filesystem).
-        let _ = fs::create_dir_all(vfs_root.join("site"));
-        let vfs_backend = crate::vfs::LocalBackend::new(vfs_root.clone());
+        let vfs_backend = crate::vfs::open_root_backend(&config.vfs, &chibi_dir)?.boxed();
 
         let hostname_override = config.site.as_ref().and_then(|s| s.hostname.as_deref());
         let site = crate::site::load_or_create(&chibi_dir, hostname_override)?;
-        let vfs = crate::vfs::Vfs::new(Box::new(vfs_backend), &site.site_id);
+        let vfs = crate::vfs::Vfs::new(vfs_backend, &site.site_id);
 
         let mut app = AppState {
             config,
@@ -367,6 +353,9 @@ impl AppState {
     }
 
     /// Clear the tool cache for a context (deletes all entries from VFS).
+    ///
+    /// Blobs shared with other contexts stay; the next cleanup collects
+    /// those no longer referenced.
     pub async fn clear_tool_cache(&self, name: &str) -> io::Result<()> {
         let dir_str = format!("/sys/tool_cache/{}", name);
         let dir = crate::vfs::VfsPath::new(&dir_str)
@@ -430,7 +419,9 @@ impl AppState {
         Ok(removed)
     }
 
-    /// Clean up tool cache entries for all contexts. Returns total entries removed.
+    /// Clean up tool cache entries for all contexts, then garbage-collect the
+    /// blob store down to `tool_cache_max_bytes`. Returns total entries and
+    /// blobs removed.
     pub async fn cleanup_all_tool_caches(&self, max_age_days: u64) -> io::Result<usize> {
         use crate::vfs::VfsEntryKind;
 
@@ -439,7 +430,7 @@ impl AppState {
 
         let ctx_dirs = match self.vfs.list(crate::vfs::VfsCaller::System, &root).await {
             Ok(e) => e,
-            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
             Err(e) => return Err(e),
         };
 
@@ -449,7 +440,8 @@ impl AppState {
                 total += self.cleanup_tool_cache(&dir.name, max_age_days).await?;
             }
         }
-        Ok(total)
+        let gc = crate::vfs_cache::gc_blobs(&self.vfs, self.config.tool_cache_max_bytes).await?;
+        Ok(total + gc.removed())
     }
 
     // === Context Dirty/Clean State ===
@@ -503,10 +495,10 @@ impl AppState {
         }
 
         // Neither exists - return not found error
-        Err(io::Error::new(
-            ErrorKind::NotFound,
-            format!("Context '{}' not found", name),
-        ))
+        Err(ChibiError::ContextNotFound {
+            context: name.to_string(),
+        }
+        .into())
     }
 
     /// Load context from the new JSONL format
@@ -597,6 +589,7 @@ impl AppState {
                     tool_call_id: None,
                     role: None,
                     flow_control: false,
+                    attachments: Vec::new(),
                 };
                 // Include all transcript entries that belong in context
                 let entries: Vec<_> = transcript_entries
@@ -822,11 +815,14 @@ impl AppState {
                             }
                         }
                     };
-                    messages.push(serde_json::json!({
-                        "_id": entry.id,
-                        "role": role,
-                        "content": entry.content,
-                    }));
+                    messages.push(with_attachments(
+                        serde_json::json!({
+                            "_id": entry.id,
+                            "role": role,
+                            "content": entry.content,
+                        }),
+                        entry,
+                    ));
                     i += 1;
                 }
                 crate::context::ENTRY_TYPE_TOOL_CALL => {
@@ -891,12 +887,15 @@ impl AppState {
                         } else {
                             format!("synth_{}", tr.id)
                         };
-                        messages.push(serde_json::json!({
-                            "_id": tr.id,
-                            "role": "tool",
-                            "tool_call_id": tc_id,
-                            "content": tr.content,
-                        }));
+                        messages.push(with_attachments(
+                            serde_json::json!({
+                                "_id": tr.id,
+                                "role": "tool",
+                                "tool_call_id": tc_id,
+                                "content": tr.content,
+                            }),
+                            tr,
+                        ));
                     }
                 }
                 crate::context::ENTRY_TYPE_TOOL_RESULT => {
@@ -905,12 +904,15 @@ impl AppState {
                         .tool_call_id
                         .clone()
                         .unwrap_or_else(|| format!("synth_{}", entry.id));
-                    messages.push(serde_json::json!({
-                        "_id": entry.id,
-                        "role": "tool",
-                        "tool_call_id": tc_id,
-                        "content": entry.content,
-                    }));
+                    messages.push(with_attachments(
+                        serde_json::json!({
+                            "_id": entry.id,
+                            "role": "tool",
+                            "tool_call_id": tc_id,
+                            "content": entry.content,
+                        }),
+                        entry,
+                    ));
                     i += 1;
                 }
                 _ => {
@@ -980,7 +982,8 @@ impl AppState {
                         .from(tool_name)
                         .to(context_name)
                         .content(content)
-                        .entry_type(crate::context::ENTRY_TYPE_TOOL_RESULT);
+                        .entry_type(crate::context::ENTRY_TYPE_TOOL_RESULT)
+                        .attachments(attachments_of(m));
                     if !tc_id.is_empty() {
                         builder = builder.tool_call_id(tc_id);
                     }
@@ -995,6 +998,7 @@ impl AppState {
                             .to(context_name)
                             .content(content)
                             .entry_type(crate::context::ENTRY_TYPE_MESSAGE)
+                            .attachments(attachments_of(m))
                             .build(),
                     );
                 }
@@ -1190,6 +1194,23 @@ fn is_context_entry(entry: &TranscriptEntry) -> bool {
     )
 }
 
+/// Attach an entry's attachment refs to its API message (omitted when empty).
+/// Expanded into content parts by `attachments::inline_attachments` at send time.
+fn with_attachments(mut message: serde_json::Value, entry: &TranscriptEntry) -> serde_json::Value {
+    if !entry.attachments.is_empty() {
+        message["attachments"] = serde_json::json!(entry.attachments);
+    }
+    message
+}
+
+/// Parse the `attachments` array back off an API message (inverse of `with_attachments`).
+fn attachments_of(message: &serde_json::Value) -> Vec<crate::attachments::AttachmentRef> {
+    message
+        .get("attachments")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default()
+}
+
 /// check whether a cache entry's modification timestamp is older than `max_age_days`.
 /// the `+1` offset means `max_age_days=0` tolerates entries less than 1 day old.
 pub(crate) fn is_cache_entry_expired(
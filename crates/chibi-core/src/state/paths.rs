@@ -50,11 +50,21 @@ pub trait StatePaths {
         self.context_dir(name).join(".dirty")
     }
 
+    /// Path to a context's content-addressed attachment blobs
+    fn attachments_dir(&self, name: &str) -> PathBuf {
+        self.context_dir(name).join("attachments")
+    }
+
     /// Path to summary file
     fn summary_file(&self, name: &str) -> PathBuf {
         self.context_dir(name).join("summary.md")
     }
 
+    /// Path to the structured compaction record (decisions, open questions, touched files)
+    fn compaction_record_file(&self, name: &str) -> PathBuf {
+        self.context_dir(name).join("compaction.json")
+    }
+
     /// Get the path to a context's system prompt file
     fn context_prompt_file(&self, context_name: &str) -> PathBuf {
         self.context_dir(context_name).join("system_prompt.md")
@@ -0,0 +1,408 @@
+//! Shared prompt template library.
+//!
+//! Templates are markdown files under `/site/prompts/` in the VFS, so every
+//! context on the site (and every user of the site) sees the same library.
+//! A template is plain text with `{{...}}` placeholders:
+//!
+//! - `{{project_root}}`, `{{context}}`, `{{date}}` (YYYY-MM-DD)
+//! - `{{git_diff}}` — `git diff HEAD` in the project root (empty outside a repo)
+//! - `{{file:PATH}}` — contents of a file inside the project root
+//! - `{{include:NAME}}` — another template from the library, rendered in place
+//! - `{{args}}` — all positional arguments joined by spaces
+//! - `{{1}}`, `{{2}}`, ... — positional arguments
+//! - `{{KEY}}` — a `KEY=VALUE` argument; `{{KEY|DEFAULT}}` supplies a fallback
+//!
+//! `\{{` produces a literal `{{`. An unknown placeholder is an error rather
+//! than being sent to the model verbatim.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::tools::vfs_block_on;
+use crate::vfs::{Vfs, VfsCaller, VfsEntryKind, VfsPath};
+
+/// VFS directory holding the template library.
+pub const PROMPT_LIBRARY_DIR: &str = "/site/prompts";
+
+/// Maximum `{{include:...}}` nesting depth.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Values available to a template while it renders.
+pub struct TemplateVars<'a> {
+    pub project_root: &'a Path,
+    pub context: &'a str,
+    /// Raw invocation arguments; `KEY=VALUE` entries are named, the rest positional.
+    pub args: &'a [String],
+}
+
+/// List the template names in the library, sorted. An absent library is empty.
+pub fn list_prompt_templates(vfs: &Vfs) -> io::Result<Vec<String>> {
+    let dir = VfsPath::new(PROMPT_LIBRARY_DIR)?;
+    if !vfs_block_on(vfs.exists(VfsCaller::System, &dir))? {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = vfs_block_on(vfs.list(VfsCaller::System, &dir))?
+        .into_iter()
+        .filter(|e| e.kind == VfsEntryKind::File)
+        .filter_map(|e| e.name.strip_suffix(".md").map(String::from))
+        .filter(|name| validate_name(name).is_ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Read a template's source from the library.
+pub fn load_prompt_template(vfs: &Vfs, name: &str) -> io::Result<String> {
+    validate_name(name)?;
+    let path = VfsPath::new(&format!("{}/{}.md", PROMPT_LIBRARY_DIR, name))?;
+    match vfs_block_on(vfs.read(VfsCaller::System, &path)) {
+        Ok(data) => Ok(String::from_utf8_lossy(&data).into_owned()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Prompt template '{}' not found (expected {}/{}.md)",
+                name, PROMPT_LIBRARY_DIR, name
+            ),
+        )),
+        Err(e) => Err(e),
+    }
+}
+
+/// Load a template from the library and render it.
+pub fn render_prompt_template(vfs: &Vfs, name: &str, vars: &TemplateVars) -> io::Result<String> {
+    let mut renderer = Renderer::new(vars, |include: &str| load_prompt_template(vfs, include));
+    renderer.render_named(name, 0)
+}
+
+/// Template names are single path segments: no separators, no leading dot.
+fn validate_name(name: &str) -> io::Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid prompt template name '{}'", name),
+        ))
+    }
+}
+
+/// Split invocation arguments into positional and `KEY=VALUE` named values.
+fn split_args(args: &[String]) -> (Vec<&str>, HashMap<&str, &str>) {
+    let mut positional = Vec::new();
+    let mut named = HashMap::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((key, value)) if is_identifier(key) => {
+                named.insert(key, value);
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    (positional, named)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+struct Renderer<'a, L> {
+    vars: &'a TemplateVars<'a>,
+    positional: Vec<&'a str>,
+    named: HashMap<&'a str, &'a str>,
+    load: L,
+    /// Names currently being rendered, to reject include cycles.
+    stack: Vec<String>,
+    git_diff: Option<String>,
+}
+
+impl<'a, L: FnMut(&str) -> io::Result<String>> Renderer<'a, L> {
+    fn new(vars: &'a TemplateVars<'a>, load: L) -> Self {
+        let (positional, named) = split_args(vars.args);
+        Self {
+            vars,
+            positional,
+            named,
+            load,
+            stack: Vec::new(),
+            git_diff: None,
+        }
+    }
+
+    fn render_named(&mut self, name: &str, depth: usize) -> io::Result<String> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Prompt template includes nest deeper than {} levels",
+                    MAX_INCLUDE_DEPTH
+                ),
+            ));
+        }
+        if self.stack.iter().any(|n| n == name) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Prompt template include cycle: {} -> {}",
+                    self.stack.join(" -> "),
+                    name
+                ),
+            ));
+        }
+        let source = (self.load)(name)?;
+        self.stack.push(name.to_string());
+        let rendered = self.render_source(&source, depth);
+        self.stack.pop();
+        rendered
+    }
+
+    fn render_source(&mut self, source: &str, depth: usize) -> io::Result<String> {
+        let mut out = String::with_capacity(source.len());
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let placeholder = rest[start + 2..start + 2 + len].trim();
+            out.push_str(&self.resolve(placeholder, depth)?);
+            rest = &rest[start + 2 + len + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn resolve(&mut self, placeholder: &str, depth: usize) -> io::Result<String> {
+        if let Some(name) = placeholder.strip_prefix("include:") {
+            let name = name.trim();
+            validate_name(name)?;
+            return self.render_named(name, depth + 1);
+        }
+        if let Some(path) = placeholder.strip_prefix("file:") {
+            return read_project_file(self.vars.project_root, path.trim());
+        }
+
+        let (key, default) = match placeholder.split_once('|') {
+            Some((key, default)) => (key.trim(), Some(default.trim())),
+            None => (placeholder, None),
+        };
+        if let Some(value) = self.lookup(key) {
+            return Ok(value);
+        }
+        default.map(String::from).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Prompt template '{}' needs a value for '{{{{{}}}}}'",
+                    self.stack.last().map(String::as_str).unwrap_or("?"),
+                    key
+                ),
+            )
+        })
+    }
+
+    /// Arguments shadow the built-in variables, so `date=...` pins the date.
+    fn lookup(&mut self, key: &str) -> Option<String> {
+        if let Some(value) = self.named.get(key) {
+            return Some(value.to_string());
+        }
+        if let Ok(index) = key.parse::<usize>() {
+            return index
+                .checked_sub(1)
+                .and_then(|i| self.positional.get(i))
+                .map(|s| s.to_string());
+        }
+        match key {
+            "args" => Some(self.positional.join(" ")),
+            "project_root" => Some(self.vars.project_root.to_string_lossy().into_owned()),
+            "context" => Some(self.vars.context.to_string()),
+            "date" => Some(chrono::Local::now().format("%Y-%m-%d").to_string()),
+            "git_diff" => {
+                let root = self.vars.project_root;
+                Some(self.git_diff.get_or_insert_with(|| git_diff(root)).clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Read a file for `{{file:PATH}}`. Paths resolve against the project root and
+/// must stay inside it: templates are site-writable, so they must not be able
+/// to pull arbitrary files into a prompt.
+fn read_project_file(project_root: &Path, path: &str) -> io::Result<String> {
+    let root = project_root.canonicalize()?;
+    let full = root.join(path).canonicalize().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Prompt template file '{}' could not be read: {}", path, e),
+        )
+    })?;
+    if !full.starts_with(&root) {
+        return Err(io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "Prompt template file '{}' is outside the project root",
+                path
+            ),
+        ));
+    }
+    std::fs::read_to_string(&full)
+}
+
+/// Staged and unstaged changes against HEAD; empty when git is unavailable.
+fn git_diff(project_root: &Path) -> String {
+    std::process::Command::new("git")
+        .args(["diff", "HEAD"])
+        .current_dir(project_root)
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::LocalBackend;
+    use tempfile::TempDir;
+
+    fn render_with(
+        templates: &[(&str, &str)],
+        name: &str,
+        project_root: &Path,
+        args: &[&str],
+    ) -> io::Result<String> {
+        let library: HashMap<String, String> = templates
+            .iter()
+            .map(|(n, s)| (n.to_string(), s.to_string()))
+            .collect();
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let vars = TemplateVars {
+            project_root,
+            context: "reviewer",
+            args: &args,
+        };
+        let mut renderer = Renderer::new(&vars, |n: &str| {
+            library
+                .get(n)
+                .cloned()
+                .ok_or_else(|| io::Error::new(ErrorKind::NotFound, n.to_string()))
+        });
+        renderer.render_named(name, 0)
+    }
+
+    #[test]
+    fn test_render_builtins_and_args() {
+        let dir = TempDir::new().unwrap();
+        let out = render_with(
+            &[(
+                "review",
+                "Review {{1}} for {{context}} ({{focus|correctness}}, {{args}}) on {{ date }}",
+            )],
+            "review",
+            dir.path(),
+            &["src/lib.rs", "strict", "date=2026-01-02"],
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "Review src/lib.rs for reviewer (correctness, src/lib.rs strict) on 2026-01-02"
+        );
+    }
+
+    #[test]
+    fn test_render_missing_arg_is_error() {
+        let dir = TempDir::new().unwrap();
+        let err = render_with(&[("t", "Fix {{issue}}")], "t", dir.path(), &[]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("{{issue}}"));
+    }
+
+    #[test]
+    fn test_render_escape_and_unclosed() {
+        let dir = TempDir::new().unwrap();
+        let out =
+            render_with(&[("t", r"Use \{{name}} and {{ open")], "t", dir.path(), &[]).unwrap();
+        assert_eq!(out, "Use {{name}} and {{ open");
+    }
+
+    #[test]
+    fn test_render_includes_and_cycles() {
+        let dir = TempDir::new().unwrap();
+        let templates = [
+            ("base", "You are careful."),
+            ("review", "{{include:base}}\nReview {{1}}."),
+            ("a", "{{include:b}}"),
+            ("b", "{{include:a}}"),
+        ];
+        let out = render_with(&templates, "review", dir.path(), &["x"]).unwrap();
+        assert_eq!(out, "You are careful.\nReview x.");
+
+        let err = render_with(&templates, "a", dir.path(), &[]).unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+    }
+
+    #[test]
+    fn test_render_file_stays_in_project_root() {
+        let outer = TempDir::new().unwrap();
+        let root = outer.path().join("project");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("notes.txt"), "hello").unwrap();
+        std::fs::write(outer.path().join("secret.txt"), "nope").unwrap();
+
+        let out = render_with(&[("t", "{{file:notes.txt}}")], "t", &root, &[]).unwrap();
+        assert_eq!(out, "hello");
+
+        let err = render_with(&[("t", "{{file:../secret.txt}}")], "t", &root, &[]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_library_list_and_load() {
+        let dir = TempDir::new().unwrap();
+        let vfs = Vfs::new(
+            Box::new(LocalBackend::new(dir.path().to_path_buf())),
+            "test-site",
+        );
+        assert!(list_prompt_templates(&vfs).unwrap().is_empty());
+
+        for path in ["/site", PROMPT_LIBRARY_DIR] {
+            vfs_block_on(vfs.mkdir(VfsCaller::System, &VfsPath::new(path).unwrap())).unwrap();
+        }
+        for (name, body) in [("triage.md", "Triage {{1}}"), ("notes.txt", "skip")] {
+            let p = VfsPath::new(&format!("{}/{}", PROMPT_LIBRARY_DIR, name)).unwrap();
+            vfs_block_on(vfs.write(VfsCaller::System, &p, body.as_bytes())).unwrap();
+        }
+
+        assert_eq!(list_prompt_templates(&vfs).unwrap(), vec!["triage"]);
+        let args = vec!["#42".to_string()];
+        let vars = TemplateVars {
+            project_root: dir.path(),
+            context: "default",
+            args: &args,
+        };
+        assert_eq!(
+            render_prompt_template(&vfs, "triage", &vars).unwrap(),
+            "Triage #42"
+        );
+        let err = load_prompt_template(&vfs, "missing").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(load_prompt_template(&vfs, "../etc").is_err());
+    }
+}
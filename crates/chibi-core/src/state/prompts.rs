@@ -80,6 +80,7 @@ impl AppState {
                 tool_call_id: None,
                 role: None,
                 flow_control: false,
+                attachments: Vec::new(),
             };
             self.append_to_transcript(context_name, &entry)?;
 
@@ -144,6 +145,7 @@ impl AppState {
                     tool_call_id: None,
                     role: None,
                     flow_control: false,
+                    attachments: Vec::new(),
                 };
                 self.append_to_transcript(context_name, &entry)?;
             }
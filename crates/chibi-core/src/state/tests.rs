@@ -25,8 +25,20 @@ fn create_test_app() -> (AppState, TempDir) {
         username: "testuser".to_string(),
         lock_heartbeat_seconds: 30,
         rolling_compact_drop_percentage: 50.0,
+        compaction_strategy: "rolling".to_string(),
+        compact_keep_turns: 8,
+        lock_policy: "fail".to_string(),
+        lock_wait_timeout_seconds: 300,
+        progress_action: "fuel".to_string(),
+        progress_window: 12,
+        progress_error_streak: 3,
+        progress_stall_calls: 40,
+        provider_max_retries: 3,
+        provider_retry_base_ms: 1000,
+        model_fallbacks: vec![],
         tool_output_cache_threshold: 4000,
         tool_cache_max_age_days: 7,
+        tool_cache_max_bytes: 0,
         auto_cleanup_cache: true,
         tool_cache_preview_chars: 500,
         file_tools_allowed_paths: vec![],
@@ -120,6 +132,7 @@ fn test_add_message() {
         "Test message"
     );
     assert!(context.updated_at > 0);
+    assert!(context.messages[0].get("attachments").is_none());
 }
 
 #[test]
@@ -187,7 +200,12 @@ fn test_rename_nonexistent_context() {
     let (mut app, _temp) = create_test_app();
     let result = app.rename_context("nonexistent", "new-name");
     assert!(result.is_err());
-    assert!(result.unwrap_err().to_string().contains("does not exist"));
+    let err = result.unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+    assert_eq!(
+        ChibiError::find(&err).map(ChibiError::code),
+        Some("context_not_found")
+    );
 }
 
 #[test]
@@ -443,6 +461,23 @@ fn test_resolve_config_local_override() {
     assert!(resolved.auto_compact);
 }
 
+#[test]
+fn test_resolve_config_rejects_unknown_compaction_strategy() {
+    let (app, _temp) = create_test_app();
+
+    let mut local = LocalConfig {
+        compaction_strategy: Some("structed".to_string()),
+        ..Default::default()
+    };
+    app.save_local_config("default", &local).unwrap();
+    assert!(app.resolve_config("default", None).is_err());
+
+    local.compaction_strategy = Some("custom:structed".to_string());
+    app.save_local_config("default", &local).unwrap();
+    let resolved = app.resolve_config("default", None).unwrap();
+    assert_eq!(resolved.compaction_strategy, "custom:structed");
+}
+
 #[test]
 fn test_resolve_config_username_override() {
     let (app, _temp) = create_test_app();
@@ -517,8 +552,20 @@ fn test_resolve_config_model_level_api_params() {
         username: "testuser".to_string(),
         lock_heartbeat_seconds: 30,
         rolling_compact_drop_percentage: 50.0,
+        compaction_strategy: "rolling".to_string(),
+        compact_keep_turns: 8,
+        lock_policy: "fail".to_string(),
+        lock_wait_timeout_seconds: 300,
+        progress_action: "fuel".to_string(),
+        progress_window: 12,
+        progress_error_streak: 3,
+        progress_stall_calls: 40,
+        provider_max_retries: 3,
+        provider_retry_base_ms: 1000,
+        model_fallbacks: vec![],
         tool_output_cache_threshold: 4000,
         tool_cache_max_age_days: 7,
+        tool_cache_max_bytes: 0,
         auto_cleanup_cache: true,
         tool_cache_preview_chars: 500,
         file_tools_allowed_paths: vec![],
@@ -580,8 +627,20 @@ fn test_resolve_config_hierarchy_context_over_model() {
         username: "testuser".to_string(),
         lock_heartbeat_seconds: 30,
         rolling_compact_drop_percentage: 50.0,
+        compaction_strategy: "rolling".to_string(),
+        compact_keep_turns: 8,
+        lock_policy: "fail".to_string(),
+        lock_wait_timeout_seconds: 300,
+        progress_action: "fuel".to_string(),
+        progress_window: 12,
+        progress_error_streak: 3,
+        progress_stall_calls: 40,
+        provider_max_retries: 3,
+        provider_retry_base_ms: 1000,
+        model_fallbacks: vec![],
         tool_output_cache_threshold: 4000,
         tool_cache_max_age_days: 7,
+        tool_cache_max_bytes: 0,
         auto_cleanup_cache: true,
         tool_cache_preview_chars: 500,
         file_tools_allowed_paths: vec![],
@@ -694,6 +753,17 @@ fn test_resolve_config_all_local_overrides() {
         reflection_enabled: Some(false),
         reflection_character_limit: None,
         rolling_compact_drop_percentage: None,
+        compaction_strategy: None,
+        compact_keep_turns: None,
+        lock_policy: None,
+        lock_wait_timeout_seconds: None,
+        progress_action: None,
+        progress_window: None,
+        progress_error_streak: None,
+        progress_stall_calls: None,
+        provider_max_retries: None,
+        provider_retry_base_ms: None,
+        model_fallbacks: None,
         tool_output_cache_threshold: None,
         tool_cache_max_age_days: None,
         auto_cleanup_cache: None,
@@ -1375,6 +1445,40 @@ fn test_entries_to_messages_includes_tool_calls() {
     );
 }
 
+#[test]
+fn test_entries_to_messages_carries_attachments() {
+    use crate::attachments::{AttachmentKind, AttachmentRef};
+    let (app, _temp) = create_test_app();
+
+    let image = AttachmentRef {
+        kind: AttachmentKind::Image,
+        mime: "image/png".to_string(),
+        name: "shot.png".to_string(),
+        sha256: Some("abc".to_string()),
+        url: None,
+        size: Some(3),
+    };
+    let mut user = create_user_message_entry("ctx", "look", "testuser");
+    user.attachments = vec![image.clone()];
+    let mut result = create_tool_result_entry("ctx", "view_image", "Attached image", "tc_1");
+    result.attachments = vec![image.clone()];
+    let entries = vec![
+        user,
+        create_tool_call_entry("ctx", "view_image", r#"{"path":"a.png"}"#, "tc_1"),
+        result,
+    ];
+
+    let messages = app.entries_to_messages(&entries);
+    assert_eq!(messages[0]["attachments"][0]["name"], "shot.png");
+    assert!(messages[1].get("attachments").is_none());
+    assert_eq!(messages[2]["attachments"][0]["sha256"], "abc");
+
+    // Round-trips through save_context
+    let round_tripped = app.messages_to_entries(&messages, "ctx");
+    assert_eq!(round_tripped[0].attachments, vec![image.clone()]);
+    assert_eq!(round_tripped[2].attachments, vec![image]);
+}
+
 #[test]
 fn test_entries_to_messages_groups_tool_batch() {
     let (app, _temp) = create_test_app();
@@ -1730,6 +1834,30 @@ fn test_appstate_rejects_unknown_vfs_backend() {
     }
 }
 
+#[tokio::test]
+async fn test_appstate_sqlite_vfs_backend_imports_local_tree() {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir_all(dir.path().join("vfs/shared")).unwrap();
+    std::fs::write(dir.path().join("vfs/shared/notes.md"), b"from disk").unwrap();
+    let mut config = Config::default();
+    config.vfs.backend = "sqlite".to_string();
+    let app = AppState::from_dir(dir.path().to_path_buf(), config).unwrap();
+
+    assert!(dir.path().join("vfs.db").exists());
+    let notes = crate::vfs::VfsPath::new("/shared/notes.md").unwrap();
+    let sys = crate::vfs::VfsCaller::System;
+    assert_eq!(app.vfs.read(sys, &notes).await.unwrap(), b"from disk");
+    let site = crate::vfs::VfsPath::new("/site").unwrap();
+    assert!(app.vfs.exists(sys, &site).await.unwrap());
+
+    // Writes go to the database, not the old tree.
+    app.vfs.write(sys, &notes, b"from db").await.unwrap();
+    assert_eq!(
+        std::fs::read(dir.path().join("vfs/shared/notes.md")).unwrap(),
+        b"from disk"
+    );
+}
+
 // === Tool cache VFS tests ===
 
 #[tokio::test]
@@ -2183,6 +2311,7 @@ fn test_is_context_entry_filters_system_prompt_changed() {
         tool_call_id: None,
         role: None,
         flow_control: false,
+        attachments: Vec::new(),
     };
     assert!(!is_context_entry(&entry));
 }
@@ -2272,3 +2401,48 @@ fn test_resolve_falls_back_to_current_dir_when_no_context_cwd() {
         config.file_tools_allowed_paths
     );
 }
+
+// === Context template tests ===
+
+#[test]
+fn test_load_context_template() {
+    let (app, _dir) = create_test_app();
+    std::fs::create_dir_all(app.context_templates_dir()).unwrap();
+    std::fs::write(
+        app.context_templates_dir().join("reviewer.toml"),
+        r#"
+system_prompt = "Review carefully."
+flocks = ["team"]
+goals = "Ship it"
+model = "test/model"
+fuel = 20
+
+[tools]
+exclude = ["shell_exec"]
+"#,
+    )
+    .unwrap();
+
+    let template = app.load_context_template("reviewer").unwrap();
+    assert_eq!(template.system_prompt.as_deref(), Some("Review carefully."));
+    assert_eq!(template.flocks, vec!["team"]);
+    assert_eq!(template.local.model.as_deref(), Some("test/model"));
+    assert_eq!(template.local.fuel, Some(20));
+    assert_eq!(
+        template.local.tools.and_then(|t| t.exclude),
+        Some(vec!["shell_exec".to_string()])
+    );
+
+    let listed = app.list_context_templates().unwrap();
+    assert_eq!(listed, vec![("reviewer".to_string(), None)]);
+
+    let err = app.load_context_template("missing").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(app.load_context_template("../x").is_err());
+
+    std::fs::write(app.context_templates_dir().join("bad.toml"), "fuel = \"x\"").unwrap();
+    assert_eq!(
+        app.load_context_template("bad").unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+}
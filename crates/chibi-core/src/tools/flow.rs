@@ -4,7 +4,7 @@
 
 use super::{BuiltinToolDef, Tool, ToolMetadata, ToolPropertyDef};
 use super::{HookPoint, execute_hook};
-use crate::config::ResolvedConfig;
+use crate::config::{ResolvedConfig, ResponseFormat};
 use crate::gateway;
 use crate::json_ext::JsonExt;
 use serde_json::json;
@@ -344,6 +344,18 @@ pub fn is_url(source: &str) -> bool {
 
 // === Core Async Functions ===
 
+/// Result of a non-streaming completion run through the sub-agent hooks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// Response text: the model's reply, a `pre_spawn_agent` hook's
+    /// `response`, or the block message when `blocked` is set.
+    pub content: String,
+    /// Model the request resolved to after preset and override resolution.
+    pub model: String,
+    /// True when a `pre_spawn_agent` hook blocked the call.
+    pub blocked: bool,
+}
+
 /// Reusable async primitive — both tool-facing and internal.
 /// Fires pre/post_spawn_agent hooks.
 pub async fn spawn_agent(
@@ -353,8 +365,49 @@ pub async fn spawn_agent(
     options: &SpawnOptions,
     tools: &[Tool],
 ) -> io::Result<String> {
+    let messages = vec![
+        json!({ "role": "system", "content": system_prompt }),
+        json!({ "role": "user", "content": input }),
+    ];
+    complete(config, &messages, options, None, tools)
+        .await
+        .map(|completion| completion.content)
+}
+
+/// Non-streaming chat completion over an arbitrary message list.
+///
+/// Shares `spawn_agent`'s preset resolution (against
+/// `config.subagent_cost_tier`) and its pre/post_spawn_agent hooks. The hook
+/// payload's `system_prompt` and `input` are taken from the first system
+/// message and the last user message; the full list is under `messages`.
+/// `response_format` overrides the config's format for this call only.
+pub async fn complete(
+    config: &ResolvedConfig,
+    messages: &[serde_json::Value],
+    options: &SpawnOptions,
+    response_format: Option<&ResponseFormat>,
+    tools: &[Tool],
+) -> io::Result<Completion> {
     let gateway = gateway::build_gateway(config).ok();
-    let effective_config = apply_spawn_options(config, options, gateway.as_ref());
+    let mut effective_config = apply_spawn_options(config, options, gateway.as_ref());
+    if let Some(format) = response_format {
+        effective_config.api.response_format = Some(format.clone());
+    }
+
+    let content_of = |role: &str, from_end: bool| {
+        let mut matching = messages
+            .iter()
+            .filter(|m| m.get_str("role") == Some(role))
+            .filter_map(|m| m.get_str("content"));
+        let found = if from_end {
+            matching.next_back()
+        } else {
+            matching.next()
+        };
+        found.unwrap_or_default().to_string()
+    };
+    let system_prompt = content_of("system", false);
+    let input = content_of("user", true);
 
     let hook_data = json!({
         "system_prompt": system_prompt,
@@ -362,27 +415,32 @@ pub async fn spawn_agent(
         "model": effective_config.model,
         "temperature": effective_config.api.temperature,
         "max_tokens": effective_config.api.max_tokens,
+        "messages": messages,
+        "response_format": effective_config.api.response_format,
     });
     let hook_results = execute_hook(tools, HookPoint::PreSpawnAgent, &hook_data, None)?;
 
     for (_hook_name, result) in &hook_results {
         if let Some(response) = result.get_str("response") {
-            return Ok(response.to_string());
+            return Ok(Completion {
+                content: response.to_string(),
+                model: effective_config.model.clone(),
+                blocked: false,
+            });
         }
         if result.get_bool_or("block", false) {
             let message = result
                 .get_str_or("message", "Sub-agent call blocked by hook")
                 .to_string();
-            return Ok(message);
+            return Ok(Completion {
+                content: message,
+                model: effective_config.model.clone(),
+                blocked: true,
+            });
         }
     }
 
-    let messages = vec![
-        json!({ "role": "system", "content": system_prompt }),
-        json!({ "role": "user", "content": input }),
-    ];
-
-    let response = gateway::chat(&effective_config, &messages).await?;
+    let response = gateway::chat(&effective_config, messages).await?;
 
     let post_hook_data = json!({
         "system_prompt": system_prompt,
@@ -392,7 +450,11 @@ pub async fn spawn_agent(
     });
     let _ = execute_hook(tools, HookPoint::PostSpawnAgent, &post_hook_data, None);
 
-    Ok(response)
+    Ok(Completion {
+        content: response,
+        model: effective_config.model,
+        blocked: false,
+    })
 }
 
 /// Reads file or fetches URL, then delegates to spawn_agent for processing.
@@ -529,6 +591,7 @@ pub fn register_flow_tools(registry: &mut super::registry::ToolRegistry) {
             description: def.description.to_string(),
             parameters: def.to_json_schema(),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata,
             summary_params: def.summary_params.iter().map(|s| s.to_string()).collect(),
             r#impl: super::registry::ToolImpl::Builtin(handler),
@@ -977,6 +1040,17 @@ mod tests {
             reflection_enabled: false,
             reflection_character_limit: 10000,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 5000,
             tool_cache_max_age_days: 7,
             auto_cleanup_cache: false,
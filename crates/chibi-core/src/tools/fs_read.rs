@@ -1,6 +1,6 @@
 //!
 //! fs_read tools: read-only access to OS and VFS paths.
-//! file_head, file_tail, file_lines, file_grep, dir_list, glob_files, grep_files.
+//! file_head, file_tail, file_lines, file_grep, dir_list, glob_files, grep_files, view_image.
 //! No PreFileRead/PreFileWrite hooks are fired here; gating is in the dispatcher.
 
 use std::io::{self, BufRead, ErrorKind};
@@ -8,9 +8,10 @@ use std::path::Path;
 
 use super::paths::{ResolvedPath, resolve_tool_path};
 use super::{BuiltinToolDef, ToolPropertyDef, require_str_param};
+use crate::attachments::{self, AttachmentKind, AttachmentRef, image_mime_for};
 use crate::config::ResolvedConfig;
 use crate::json_ext::JsonExt;
-use crate::state::AppState;
+use crate::state::{AppState, StatePaths};
 use crate::vfs::VfsCaller;
 
 // === Tool Name Constants ===
@@ -22,6 +23,7 @@ pub const FILE_GREP_TOOL_NAME: &str = "file_grep";
 pub const DIR_LIST_TOOL_NAME: &str = "dir_list";
 pub const GLOB_FILES_TOOL_NAME: &str = "glob_files";
 pub const GREP_FILES_TOOL_NAME: &str = "grep_files";
+pub const VIEW_IMAGE_TOOL_NAME: &str = "view_image";
 
 // === Tool Definition Registry ===
 
@@ -215,6 +217,18 @@ pub static FS_READ_TOOL_DEFS: &[BuiltinToolDef] = &[
         required: &["pattern"],
         summary_params: &["pattern", "path"],
     },
+    BuiltinToolDef {
+        name: VIEW_IMAGE_TOOL_NAME,
+        description: "Look at an image file (png, jpg, gif, webp). The image is attached to the conversation so you can see it on your next turn.",
+        properties: &[ToolPropertyDef {
+            name: "path",
+            prop_type: "string",
+            description: "Absolute or relative path to an image file, or a vfs:/// URI for VFS storage",
+            default: None,
+        }],
+        required: &["path"],
+        summary_params: &["path"],
+    },
 ];
 
 // === Registry Helpers ===
@@ -300,10 +314,65 @@ pub fn execute_fs_read_tool(
         DIR_LIST_TOOL_NAME => Some(execute_dir_list(args, project_root, config)),
         GLOB_FILES_TOOL_NAME => Some(execute_glob_files(args, project_root, config)),
         GREP_FILES_TOOL_NAME => Some(execute_grep_files(args, project_root, config)),
+        VIEW_IMAGE_TOOL_NAME => Some(
+            execute_view_image(app, context_name, args, config, project_root)
+                .map(|attachment| describe_view_image(&attachment)),
+        ),
         _ => None,
     }
 }
 
+// === view_image ===
+
+/// Read an image (OS or VFS path) and store it in the context's attachment store.
+///
+/// The send loop intercepts `view_image` to record the returned ref on the
+/// tool_result entry, which is what makes the image visible to the model.
+pub fn execute_view_image(
+    app: &AppState,
+    context_name: &str,
+    args: &serde_json::Value,
+    config: &ResolvedConfig,
+    project_root: &Path,
+) -> io::Result<AttachmentRef> {
+    let path_str = require_str_param(args, "path")?;
+    let mime = image_mime_for(&path_str).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("not a supported image (png, jpg, gif, webp): {}", path_str),
+        )
+    })?;
+    let (name, bytes) = match resolve_tool_path(&path_str, project_root, config)? {
+        ResolvedPath::Os(p) => {
+            let name = p
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path_str.clone());
+            attachments::check_size(&name, std::fs::metadata(&p)?.len())?;
+            (name, std::fs::read(&p)?)
+        }
+        ResolvedPath::Vfs(vfs_path) => {
+            let caller = VfsCaller::Context(context_name);
+            let meta = vfs_block_on(app.vfs.metadata(caller, &vfs_path))?;
+            attachments::check_size(vfs_path.as_str(), meta.size)?;
+            let data = vfs_block_on(app.vfs.read(caller, &vfs_path))?;
+            (vfs_path.as_str().to_string(), data)
+        }
+    };
+    attachments::store_bytes(
+        &app.attachments_dir(context_name),
+        &name,
+        AttachmentKind::Image,
+        mime,
+        &bytes,
+    )
+}
+
+/// Tool result text for a successful `view_image` call.
+pub fn describe_view_image(attachment: &AttachmentRef) -> String {
+    format!("Attached image: {}", attachment.describe())
+}
+
 // === VFS bridge ===
 /// Bridge an async VFS future into synchronous tool dispatch.
 ///
@@ -925,7 +994,7 @@ mod tests {
 
     #[test]
     fn test_fs_read_tool_registry_count() {
-        assert_eq!(FS_READ_TOOL_DEFS.len(), 7);
+        assert_eq!(FS_READ_TOOL_DEFS.len(), 8);
     }
 
     #[test]
@@ -937,6 +1006,7 @@ mod tests {
         assert_eq!(DIR_LIST_TOOL_NAME, "dir_list");
         assert_eq!(GLOB_FILES_TOOL_NAME, "glob_files");
         assert_eq!(GREP_FILES_TOOL_NAME, "grep_files");
+        assert_eq!(VIEW_IMAGE_TOOL_NAME, "view_image");
     }
 
     #[test]
@@ -991,6 +1061,47 @@ mod tests {
         assert!(!output.contains("line3"));
     }
 
+    #[test]
+    fn test_view_image_stores_attachment() {
+        let project_dir = tempfile::tempdir().unwrap();
+        fs::write(project_dir.path().join("shot.png"), b"\x89PNG fake").unwrap();
+        fs::write(project_dir.path().join("notes.txt"), "text").unwrap();
+
+        let config = make_config_for(&project_dir);
+        let home = tempfile::tempdir().unwrap();
+        let app = AppState::load(Some(home.path().to_path_buf())).unwrap();
+
+        let a = args(&[("path", serde_json::json!("shot.png"))]);
+        let attachment = execute_view_image(&app, "test", &a, &config, project_dir.path()).unwrap();
+        assert_eq!(attachment.mime, "image/png");
+        assert_eq!(attachment.name, "shot.png");
+        let blob = crate::attachments::blob_path(&app.attachments_dir("test"), &attachment);
+        assert!(blob.unwrap().exists());
+
+        let text = execute_fs_read_tool(
+            &app,
+            "test",
+            VIEW_IMAGE_TOOL_NAME,
+            &a,
+            &config,
+            project_dir.path(),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(text.starts_with("Attached image: image/png shot.png"));
+
+        let a = args(&[("path", serde_json::json!("notes.txt"))]);
+        assert!(execute_view_image(&app, "test", &a, &config, project_dir.path()).is_err());
+
+        // Rejected from its size alone; the sparse file is never read
+        let huge = fs::File::create(project_dir.path().join("huge.png")).unwrap();
+        huge.set_len(crate::attachments::MAX_ATTACHMENT_BYTES + 1)
+            .unwrap();
+        let a = args(&[("path", serde_json::json!("huge.png"))]);
+        let err = execute_view_image(&app, "test", &a, &config, project_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("max"), "{err}");
+    }
+
     #[test]
     fn test_execute_file_grep_with_project_root() {
         let project_dir = tempfile::tempdir().unwrap();
@@ -40,6 +40,7 @@ pub enum HookPoint {
     PostCompact,
     PreRollingCompact,
     PostRollingCompact,
+    CustomCompact, // Runs a custom compaction strategy (return {"messages": [...]})
     OnStart,
     OnEnd,
     PreSystemPrompt,  // Can inject content before system prompt sections
@@ -52,6 +53,7 @@ pub enum HookPoint {
     PreApiRequest,    // Before API request is sent (can modify full request body)
     PreAgenticLoop,   // Before entering the tool loop (can override fallback)
     PostToolBatch,    // After processing a batch of tool calls (can override fallback)
+    OnNoProgress,     // When the agentic loop stops making progress (can override the response)
     PreFileRead, // Before reading a file outside allowed paths (can approve/deny, fail-safe deny)
     PreFileWrite, // Before file write/patch (can approve/deny/modify operation)
     PreShellExec, // Before shell command execution (can approve/deny, fail-safe deny)
@@ -61,6 +63,113 @@ pub enum HookPoint {
     PostIndexFile, // After a file is indexed (observe: path, lang, symbol_count, ref_count)
     PreVfsWrite,   // Before a VFS file write (advisory, non-blocking; observe-and-snapshot)
     PostVfsWrite,  // After a successful VFS file write (observe only)
+    VfsChanged,    // After any VFS change outside /sys/ (observe only)
+}
+
+/// How the results of several hooks on the same point combine.
+///
+/// Handlers run in ascending priority order (ties broken by tool name; see
+/// `hook_chain`). The chain decides what each handler sees and whether later
+/// handlers run at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum HookChain {
+    /// Return values are ignored; every handler runs.
+    Observe,
+    /// Every handler runs and every result is used (injections are
+    /// concatenated, tool include filters intersect and excludes union).
+    Collect,
+    /// Every handler runs; results are applied in order, so the last handler
+    /// to set a field wins.
+    LastWins,
+    /// The first handler to return a non-empty result decides; later
+    /// handlers do not run.
+    FirstWins,
+    /// Each handler sees the payload as modified by the one before it; a
+    /// `"block": true` result stops the chain.
+    Pipeline,
+    /// Any handler can veto with `"denied": true` or `"block": true`; the
+    /// first veto stops the chain.
+    VetoAny,
+}
+
+impl HookPoint {
+    /// Composition semantics for this hook point.
+    ///
+    /// Mutation site: keep in sync with the consumers in `api/send.rs`,
+    /// `tools/flow.rs` and the "Hook Ordering" section of `docs/hooks.md`.
+    pub fn chain(self) -> HookChain {
+        match self {
+            HookPoint::PreMessage
+            | HookPoint::PreTool
+            | HookPoint::PreToolOutput
+            | HookPoint::PreApiRequest => HookChain::Pipeline,
+            HookPoint::PreFileRead
+            | HookPoint::PreFileWrite
+            | HookPoint::PreShellExec
+            | HookPoint::PreFetchUrl
+            | HookPoint::PreCacheOutput => HookChain::VetoAny,
+            HookPoint::PreSpawnAgent
+            | HookPoint::PreSendMessage
+            | HookPoint::CustomCompact
+            | HookPoint::OnNoProgress => HookChain::FirstWins,
+            HookPoint::PreAgenticLoop | HookPoint::PostToolBatch => HookChain::LastWins,
+            HookPoint::PreSystemPrompt | HookPoint::PostSystemPrompt | HookPoint::PreApiTools => {
+                HookChain::Collect
+            }
+            _ => HookChain::Observe,
+        }
+    }
+
+    /// Payload field a pipeline hook rewrites, and whether a returned object
+    /// is merged into it (`true`) or replaces it (`false`).
+    fn pipeline_field(self) -> Option<(&'static str, bool)> {
+        match self {
+            HookPoint::PreMessage => Some(("prompt", false)),
+            HookPoint::PreTool => Some(("arguments", false)),
+            HookPoint::PreToolOutput => Some(("output", false)),
+            HookPoint::PreApiRequest => Some(("request_body", true)),
+            _ => None,
+        }
+    }
+}
+
+impl HookChain {
+    /// Fold one handler's result into the running payload. Returns `true`
+    /// when the chain should stop.
+    fn absorb(
+        self,
+        hook: HookPoint,
+        data: &mut serde_json::Value,
+        result: &serde_json::Value,
+    ) -> bool {
+        let flag = |key: &str| result.get(key).and_then(|v| v.as_bool()) == Some(true);
+        match self {
+            HookChain::Observe | HookChain::Collect | HookChain::LastWins => false,
+            HookChain::FirstWins => {
+                !(result.is_null() || result.as_object().is_some_and(|o| o.is_empty()))
+            }
+            HookChain::VetoAny => flag("denied") || flag("block"),
+            HookChain::Pipeline => {
+                if flag("block") {
+                    return true;
+                }
+                if let Some((field, merge)) = hook.pipeline_field()
+                    && let Some(value) = result.get(field)
+                {
+                    match (merge, data.get_mut(field), value.as_object()) {
+                        (true, Some(serde_json::Value::Object(current)), Some(changes)) => {
+                            for (k, v) in changes {
+                                current.insert(k.clone(), v.clone());
+                            }
+                        }
+                        _ => data[field] = value.clone(),
+                    }
+                }
+                false
+            }
+        }
+    }
 }
 
 // --- hook metadata for discoverability ---
@@ -277,7 +386,7 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
                 description: "message shown when blocked",
             },
         ],
-        notes: "",
+        notes: "the block message becomes the tool result the model sees; a directly invoked tool (call_tool, -P) fails with hook_denied instead",
     },
     HookMeta {
         point: HookPoint::PostTool,
@@ -552,6 +661,57 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
         ],
         notes: "post_tool_batch output > pre_agentic_loop output > config fallback; last hook to set fallback wins",
     },
+    HookMeta {
+        point: HookPoint::OnNoProgress,
+        category: "agentic",
+        description: "fires when progress monitoring flags a repeat, cycle, error streak or stall; can override the response",
+        can_modify: true,
+        payload_fields: &[
+            FieldMeta {
+                name: "context_name",
+                typ: "string",
+                description: "active context name",
+            },
+            FieldMeta {
+                name: "kind",
+                typ: "string",
+                description: "repeat, cycle, error_streak, or stall",
+            },
+            FieldMeta {
+                name: "tool_name",
+                typ: "string",
+                description: "tool whose call raised the signal",
+            },
+            FieldMeta {
+                name: "count",
+                typ: "number",
+                description: "repetitions, failures, or calls, depending on kind",
+            },
+            FieldMeta {
+                name: "detail",
+                typ: "string",
+                description: "description of what was detected",
+            },
+            FieldMeta {
+                name: "action",
+                typ: "string",
+                description: "configured progress_action",
+            },
+        ],
+        return_fields: &[
+            FieldMeta {
+                name: "action",
+                typ: "string",
+                description: "nudge, fuel, call_user, or ignore",
+            },
+            FieldMeta {
+                name: "message",
+                typ: "string",
+                description: "replaces the injected warning (or the message shown to the user for call_user)",
+            },
+        ],
+        notes: "return null to keep the configured progress_action",
+    },
     HookMeta {
         point: HookPoint::PreFileRead,
         category: "file_permission",
@@ -727,6 +887,16 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
                 typ: "number",
                 description: "max tokens for response",
             },
+            FieldMeta {
+                name: "messages",
+                typ: "array",
+                description: "full chat message list sent to the model",
+            },
+            FieldMeta {
+                name: "response_format",
+                typ: "object",
+                description: "requested response format, null for free text",
+            },
         ],
         return_fields: &[
             FieldMeta {
@@ -830,7 +1000,17 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
             FieldMeta {
                 name: "cache_id",
                 typ: "string",
-                description: "filename under vfs:///sys/tool_cache/<context>/",
+                description: "reference filename under vfs:///sys/tool_cache/<context>/",
+            },
+            FieldMeta {
+                name: "blob_uri",
+                typ: "string",
+                description: "vfs:///sys/blobs/ URI of the stored content",
+            },
+            FieldMeta {
+                name: "deduplicated",
+                typ: "bool",
+                description: "content was already stored by an earlier call",
             },
             FieldMeta {
                 name: "output_size",
@@ -844,7 +1024,7 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
             },
         ],
         return_fields: &[],
-        notes: "access cached content with file_head/file_tail/file_lines using full vfs:// URI",
+        notes: "access cached content with file_head/file_tail/file_lines using blob_uri",
     },
     HookMeta {
         point: HookPoint::PreSendMessage,
@@ -1007,6 +1187,41 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
         return_fields: &[],
         notes: "same caller restriction as pre_vfs_write",
     },
+    HookMeta {
+        point: HookPoint::VfsChanged,
+        category: "vfs_write",
+        description: "fires after any VFS change outside /sys/, whoever made it; observe only",
+        can_modify: false,
+        payload_fields: &[
+            FieldMeta {
+                name: "path",
+                typ: "string",
+                description: "VFS path that changed",
+            },
+            FieldMeta {
+                name: "kind",
+                typ: "string",
+                description: "write, append, delete, mkdir, copy or rename",
+            },
+            FieldMeta {
+                name: "caller",
+                typ: "string",
+                description: "SYSTEM or the context that made the change",
+            },
+            FieldMeta {
+                name: "from",
+                typ: "string",
+                description: "source path for copy and rename (null otherwise)",
+            },
+            FieldMeta {
+                name: "watchers",
+                typ: "array",
+                description: "contexts watching the path (each also gets an inbox message)",
+            },
+        ],
+        return_fields: &[],
+        notes: "fires for every mutation through the VFS router, including SYSTEM and (harness io) writes; filter on path in the handler",
+    },
     HookMeta {
         point: HookPoint::PreClear,
         category: "context",
@@ -1060,7 +1275,7 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
     HookMeta {
         point: HookPoint::PreCompact,
         category: "context",
-        description: "fires before full compaction; observe only",
+        description: "fires before full or strategy compaction; observe only",
         can_modify: false,
         payload_fields: &[
             FieldMeta {
@@ -1078,6 +1293,11 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
                 typ: "string",
                 description: "conversation summary",
             },
+            FieldMeta {
+                name: "strategy",
+                typ: "string",
+                description: "compaction strategy name",
+            },
         ],
         return_fields: &[],
         notes: "",
@@ -1085,7 +1305,7 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
     HookMeta {
         point: HookPoint::PostCompact,
         category: "context",
-        description: "fires after full compaction; observe only",
+        description: "fires after full or strategy compaction; observe only",
         can_modify: false,
         payload_fields: &[
             FieldMeta {
@@ -1103,6 +1323,11 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
                 typ: "string",
                 description: "conversation summary",
             },
+            FieldMeta {
+                name: "strategy",
+                typ: "string",
+                description: "compaction strategy name",
+            },
         ],
         return_fields: &[],
         notes: "",
@@ -1167,6 +1392,57 @@ pub(crate) const HOOK_METADATA: &[HookMeta] = &[
         return_fields: &[],
         notes: "",
     },
+    HookMeta {
+        point: HookPoint::CustomCompact,
+        category: "context",
+        description: "fires when auto-compaction uses a strategy that is not built in; can supply the compacted context",
+        can_modify: true,
+        payload_fields: &[
+            FieldMeta {
+                name: "context_name",
+                typ: "string",
+                description: "context being compacted",
+            },
+            FieldMeta {
+                name: "strategy",
+                typ: "string",
+                description: "strategy name (the part after custom:)",
+            },
+            FieldMeta {
+                name: "messages",
+                typ: "array",
+                description: "current context messages",
+            },
+            FieldMeta {
+                name: "summary",
+                typ: "string",
+                description: "conversation summary",
+            },
+            FieldMeta {
+                name: "keep_turns",
+                typ: "number",
+                description: "configured compact_keep_turns",
+            },
+            FieldMeta {
+                name: "drop_percentage",
+                typ: "number",
+                description: "configured rolling_compact_drop_percentage",
+            },
+        ],
+        return_fields: &[
+            FieldMeta {
+                name: "messages",
+                typ: "array",
+                description: "messages to keep (replaces the context)",
+            },
+            FieldMeta {
+                name: "summary",
+                typ: "string",
+                description: "new summary (optional; unchanged if absent)",
+            },
+        ],
+        notes: "check strategy and return null for names you do not handle; every message must be an object with a string role, or the result is ignored; with no handler, rolling compaction runs",
+    },
 ];
 
 #[cfg(feature = "synthesised-tools")]
@@ -1276,14 +1552,16 @@ pub fn generate_hooks_markdown() -> String {
         };
         out.push('\n');
         out.push_str(&format!("### {cat_display}\n\n"));
-        out.push_str("| Hook | When | Can Modify |\n");
-        out.push_str("|------|------|------------|\n");
+        out.push_str("| Hook | When | Can Modify | Chain |\n");
+        out.push_str("|------|------|------------|-------|\n");
         for meta in hooks {
             let key = meta.point.as_ref();
             let can_modify = if meta.can_modify { "Yes" } else { "No" };
+            let chain = meta.point.chain();
             out.push_str(&format!(
-                "| `{key}` | {} | {can_modify} |\n",
-                meta.description
+                "| `{key}` | {} | {can_modify} | {} |\n",
+                meta.description,
+                chain.as_ref()
             ));
         }
     }
@@ -1372,9 +1650,116 @@ pub struct TeinHookContext<'a> {
     _phantom: std::marker::PhantomData<&'a ()>,
 }
 
+/// Identity of one hook handler, used to fire each handler once per event.
+///
+/// One-shot plugins are keyed per tool (each tool spawns its executable);
+/// persistent plugins per process; tein hooks per (context, binding) since
+/// tools from the same `.scm` file share a context.
+#[derive(PartialEq, Eq, Hash)]
+enum HandlerKey {
+    Tool(String),
+    Process(usize),
+    #[cfg(feature = "synthesised-tools")]
+    Tein(std::thread::ThreadId, String),
+}
+
+#[cfg_attr(not(feature = "synthesised-tools"), allow(unused_variables))]
+fn handler_key(tool: &Tool, hook: HookPoint) -> Option<HandlerKey> {
+    match &tool.r#impl {
+        super::ToolImpl::Plugin(_) => Some(HandlerKey::Tool(tool.name.clone())),
+        super::ToolImpl::PersistentPlugin(process) => {
+            Some(HandlerKey::Process(std::sync::Arc::as_ptr(process) as usize))
+        }
+        #[cfg(feature = "synthesised-tools")]
+        super::ToolImpl::Synthesised {
+            hook_bindings,
+            worker_thread_id,
+            ..
+        } => hook_bindings
+            .get(&hook)
+            .map(|binding| HandlerKey::Tein(*worker_thread_id, binding.clone())),
+        _ => None,
+    }
+}
+
+/// Resolve the ordered chain of tools that will handle `hook`.
+///
+/// Ascending priority (default 0), ties broken by tool name, so the order
+/// never depends on plugin directory order or registration order. Tools
+/// sharing a handler (persistent process, tein context) appear once.
+fn hook_chain(tools: &[Tool], hook: HookPoint) -> Vec<&Tool> {
+    let mut chain: Vec<&Tool> = tools.iter().filter(|t| t.hooks.contains(&hook)).collect();
+    chain.sort_by(|a, b| {
+        a.hook_priority(hook)
+            .cmp(&b.hook_priority(hook))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut seen = std::collections::HashSet::new();
+    chain.retain(|t| handler_key(t, hook).is_some_and(|key| seen.insert(key)));
+    chain
+}
+
+/// Render the resolved chain for every hook point that has handlers.
+///
+/// Backs `chibi -n hooks`.
+pub fn describe_hook_chains(tools: &[Tool]) -> String {
+    use strum::IntoEnumIterator;
+
+    let mut out = String::new();
+    for hook in HookPoint::iter() {
+        let chain = hook_chain(tools, hook);
+        if chain.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} ({})\n", hook.as_ref(), hook.chain().as_ref()));
+        for (i, tool) in chain.iter().enumerate() {
+            out.push_str(&format!(
+                "  {}. {} [{}, priority {}]\n",
+                i + 1,
+                tool.name,
+                tool.category.as_str(),
+                tool.hook_priority(hook)
+            ));
+        }
+    }
+    out
+}
+
+/// Marks a hook point as being dispatched to tein callbacks for the lifetime
+/// of the guard; `None` when it already was (re-entrant call).
+#[cfg(feature = "synthesised-tools")]
+struct TeinDispatch(HookPoint);
+
+#[cfg(feature = "synthesised-tools")]
+impl TeinDispatch {
+    fn enter(hook: HookPoint) -> Option<Self> {
+        TEIN_HOOK_GUARD
+            .with(|guard| guard.borrow_mut().insert(hook))
+            .then_some(TeinDispatch(hook))
+    }
+}
+
+#[cfg(feature = "synthesised-tools")]
+impl Drop for TeinDispatch {
+    fn drop(&mut self) {
+        TEIN_HOOK_GUARD.with(|guard| {
+            guard.borrow_mut().remove(&self.0);
+        });
+    }
+}
+
 /// Execute a hook on all tools that registered for it
 /// Returns a vector of (tool_name, result) for tools that returned non-empty output
 ///
+/// Handlers run in `hook_chain` order and results are combined according to
+/// `hook.chain()`: pipeline hooks see the payload as modified by earlier
+/// handlers, and first-wins/veto-any chains stop early. Results are returned
+/// in run order, so callers applying them in sequence get the documented
+/// semantics.
+///
 /// Hook data is passed via stdin (JSON). The CHIBI_HOOK env var identifies which hook is firing.
 ///
 /// `tein_ctx` (synthesised-tools feature only): when `Some`, sets `BRIDGE_CALL_CTX` per tein tool
@@ -1386,177 +1771,189 @@ pub fn execute_hook(
     data: &serde_json::Value,
     _tein_ctx: Option<&TeinHookContext<'_>>,
 ) -> io::Result<Vec<(String, serde_json::Value)>> {
+    let chain = hook.chain();
+    let mut data = data.clone();
     let mut results = Vec::new();
-    let data_str = data.to_string();
 
-    for tool in tools {
-        if !tool.hooks.contains(&hook) {
-            continue;
-        }
+    // Tein callbacks are skipped on re-entrant calls for the same hook point.
+    #[cfg(feature = "synthesised-tools")]
+    let tein_dispatch = TeinDispatch::enter(hook);
+
+    for tool in hook_chain(tools, hook) {
+        let value = match &tool.r#impl {
+            super::ToolImpl::Plugin(path) => run_plugin_hook(path, tool, hook, &data)?,
+            super::ToolImpl::PersistentPlugin(process) => {
+                let reply = process.hook(hook, &data).map_err(|e| {
+                    io::Error::other(format!(
+                        "Failed to execute hook {} on {}: {}",
+                        hook.as_ref(),
+                        tool.name,
+                        e
+                    ))
+                })?;
+                // An error reply is the persistent equivalent of a non-zero exit.
+                reply.ok().filter(|v| !v.is_null())
+            }
+            #[cfg(feature = "synthesised-tools")]
+            super::ToolImpl::Synthesised { .. } => {
+                if tein_dispatch.is_none() {
+                    continue;
+                }
+                run_tein_hook(tool, hook, &data, _tein_ctx)
+            }
+            _ => None,
+        };
 
-        // Only plugin tools can register hooks; extract the executable path.
-        let plugin_path = match &tool.r#impl {
-            super::ToolImpl::Plugin(p) => p.clone(),
-            _ => continue, // non-plugin tools cannot spawn hooks
+        let Some(value) = value else {
+            continue;
         };
-        let mut child = Command::new(&plugin_path)
-            .env("CHIBI_HOOK", hook.as_ref())
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(|e| {
-                io::Error::other(format!(
-                    "Failed to spawn hook {} on {}: {}",
-                    hook.as_ref(),
-                    tool.name,
-                    e
-                ))
-            })?;
-
-        // Write hook data to stdin (ignore BrokenPipe — child may exit before reading)
-        if let Some(mut stdin) = child.stdin.take() {
-            match stdin.write_all(data_str.as_bytes()) {
-                Ok(()) => {}
-                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
-                Err(e) => return Err(e),
-            }
-            // stdin is dropped here, closing the pipe and signaling EOF
+        let stop = chain.absorb(hook, &mut data, &value);
+        results.push((tool.name.clone(), value));
+        if stop {
+            break;
         }
+    }
+
+    Ok(results)
+}
 
-        let timeout = std::time::Duration::from_secs(super::PLUGIN_TIMEOUT_SECS);
-        let context = format!("hook {} on {}", hook.as_ref(), tool.name);
-        let output = super::wait_with_timeout(child, timeout, &context).map_err(|e| {
+/// Spawn a one-shot plugin for a hook. `None` when it exits non-zero or
+/// prints nothing.
+fn run_plugin_hook(
+    plugin_path: &std::path::Path,
+    tool: &Tool,
+    hook: HookPoint,
+    data: &serde_json::Value,
+) -> io::Result<Option<serde_json::Value>> {
+    let mut child = Command::new(plugin_path)
+        .env("CHIBI_HOOK", hook.as_ref())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| {
             io::Error::other(format!(
-                "Failed to execute hook {} on {}: {}",
+                "Failed to spawn hook {} on {}: {}",
                 hook.as_ref(),
                 tool.name,
                 e
             ))
         })?;
 
-        if !output.status.success() {
-            continue;
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let trimmed = stdout.trim();
-
-        if trimmed.is_empty() {
-            continue;
+    // Write hook data to stdin (ignore BrokenPipe — child may exit before reading)
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(data.to_string().as_bytes()) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(e) => return Err(e),
         }
-
-        // Try to parse as JSON, otherwise wrap as string
-        let value: serde_json::Value = serde_json::from_str(trimmed)
-            .unwrap_or_else(|_| serde_json::Value::String(trimmed.to_string()));
-
-        results.push((tool.name.clone(), value));
+        // stdin is dropped here, closing the pipe and signaling EOF
     }
 
-    // --- synthesised tein hooks ---
-    #[cfg(feature = "synthesised-tools")]
-    {
-        let should_dispatch = TEIN_HOOK_GUARD.with(|guard| !guard.borrow().contains(&hook));
-
-        if should_dispatch {
-            TEIN_HOOK_GUARD.with(|guard| {
-                guard.borrow_mut().insert(hook);
-            });
+    let timeout = std::time::Duration::from_secs(super::PLUGIN_TIMEOUT_SECS);
+    let context = format!("hook {} on {}", hook.as_ref(), tool.name);
+    let output = super::wait_with_timeout(child, timeout, &context).map_err(|e| {
+        io::Error::other(format!(
+            "Failed to execute hook {} on {}: {}",
+            hook.as_ref(),
+            tool.name,
+            e
+        ))
+    })?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
 
-            // Deduplicate: tools from the same .scm file share a tein context
-            // and produce the same (worker_thread_id, binding) pair. Only fire
-            // each unique (context instance, binding) once per hook event.
-            let mut dispatched: std::collections::HashSet<(std::thread::ThreadId, String)> =
-                std::collections::HashSet::new();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let trimmed = stdout.trim();
 
-            for tool in tools {
-                if !tool.hooks.contains(&hook) {
-                    continue;
-                }
-                let (context, hook_bindings, worker_thread_id) = match &tool.r#impl {
-                    super::ToolImpl::Synthesised {
-                        context,
-                        hook_bindings,
-                        worker_thread_id,
-                        ..
-                    } => (context, hook_bindings, *worker_thread_id),
-                    _ => continue,
-                };
-
-                // Set call context guard if tein_ctx available — enables call-tool
-                // and (harness io) from tein hook callbacks.
-                // Guard drops at end of each loop iteration, clearing the bridge context.
-                let _bridge_guard = _tein_ctx.map(|ctx| {
-                    super::synthesised::CallContextGuard::set_from_hook_ctx(ctx, worker_thread_id)
-                });
-
-                let Some(binding) = hook_bindings.get(&hook) else {
-                    continue;
-                };
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
 
-                // Skip if this (context, binding) was already dispatched this event.
-                if !dispatched.insert((worker_thread_id, binding.clone())) {
-                    continue;
-                }
+    // Try to parse as JSON, otherwise wrap as string
+    Ok(Some(serde_json::from_str(trimmed).unwrap_or_else(|_| {
+        serde_json::Value::String(trimmed.to_string())
+    })))
+}
 
-                let payload = match super::synthesised::json_args_to_scheme_alist(data) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!(
-                            "[WARN] tein hook {}: payload conversion: {e}",
-                            hook.as_ref()
-                        );
-                        continue;
-                    }
-                };
-
-                let hook_fn = match context.evaluate(binding) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!(
-                            "[WARN] tein hook {} on {}: resolve {binding}: {e}",
-                            hook.as_ref(),
-                            tool.name
-                        );
-                        continue;
-                    }
-                };
+/// Call a synthesised tool's tein hook callback. Failures are warned and
+/// skipped, same as subprocess hook failures.
+#[cfg(feature = "synthesised-tools")]
+fn run_tein_hook(
+    tool: &Tool,
+    hook: HookPoint,
+    data: &serde_json::Value,
+    tein_ctx: Option<&TeinHookContext<'_>>,
+) -> Option<serde_json::Value> {
+    let super::ToolImpl::Synthesised {
+        context,
+        hook_bindings,
+        worker_thread_id,
+        ..
+    } = &tool.r#impl
+    else {
+        return None;
+    };
+    let binding = hook_bindings.get(&hook)?;
+
+    // Set call context guard if tein_ctx available — enables call-tool
+    // and (harness io) from tein hook callbacks.
+    // Guard drops on return, clearing the bridge context.
+    let _bridge_guard = tein_ctx
+        .map(|ctx| super::synthesised::CallContextGuard::set_from_hook_ctx(ctx, *worker_thread_id));
+
+    let payload = match super::synthesised::json_args_to_scheme_alist(data) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!(
+                "[WARN] tein hook {}: payload conversion: {e}",
+                hook.as_ref()
+            );
+            return None;
+        }
+    };
 
-                let result = match context.call(&hook_fn, &[payload]) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        eprintln!("[WARN] tein hook {} on {}: {e}", hook.as_ref(), tool.name);
-                        continue;
-                    }
-                };
+    let hook_fn = match context.evaluate(binding) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!(
+                "[WARN] tein hook {} on {}: resolve {binding}: {e}",
+                hook.as_ref(),
+                tool.name
+            );
+            return None;
+        }
+    };
 
-                // empty list or nil → no-op, don't push a result
-                if result.is_nil() {
-                    continue;
-                }
-                if matches!(&result, tein::Value::List(items) if items.is_empty()) {
-                    continue;
-                }
+    let result = match context.call(&hook_fn, &[payload]) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("[WARN] tein hook {} on {}: {e}", hook.as_ref(), tool.name);
+            return None;
+        }
+    };
 
-                match super::synthesised::scheme_value_to_json(&result) {
-                    Ok(value) => results.push((tool.name.clone(), value)),
-                    Err(e) => {
-                        eprintln!(
-                            "[WARN] tein hook {} on {}: result conversion: {e}",
-                            hook.as_ref(),
-                            tool.name
-                        );
-                    }
-                }
-            }
+    // empty list or nil → no-op, don't push a result
+    if result.is_nil() {
+        return None;
+    }
+    if matches!(&result, tein::Value::List(items) if items.is_empty()) {
+        return None;
+    }
 
-            TEIN_HOOK_GUARD.with(|guard| {
-                guard.borrow_mut().remove(&hook);
-            });
+    match super::synthesised::scheme_value_to_json(&result) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!(
+                "[WARN] tein hook {} on {}: result conversion: {e}",
+                hook.as_ref(),
+                tool.name
+            );
+            None
         }
     }
-
-    Ok(results)
 }
 
 #[cfg(test)]
@@ -1568,7 +1965,7 @@ mod tests {
         crate::config::test_helpers::config_with_tier(vfs_path, tier)
     }
 
-    // All 31 hook points for testing
+    // All 36 hook points for testing
     const ALL_HOOKS: &[(&str, HookPoint)] = &[
         ("pre_message", HookPoint::PreMessage),
         ("post_message", HookPoint::PostMessage),
@@ -1582,6 +1979,7 @@ mod tests {
         ("post_compact", HookPoint::PostCompact),
         ("pre_rolling_compact", HookPoint::PreRollingCompact),
         ("post_rolling_compact", HookPoint::PostRollingCompact),
+        ("custom_compact", HookPoint::CustomCompact),
         ("on_start", HookPoint::OnStart),
         ("on_end", HookPoint::OnEnd),
         ("pre_system_prompt", HookPoint::PreSystemPrompt),
@@ -1594,6 +1992,7 @@ mod tests {
         ("pre_api_request", HookPoint::PreApiRequest),
         ("pre_agentic_loop", HookPoint::PreAgenticLoop),
         ("post_tool_batch", HookPoint::PostToolBatch),
+        ("on_no_progress", HookPoint::OnNoProgress),
         ("pre_file_read", HookPoint::PreFileRead),
         ("pre_file_write", HookPoint::PreFileWrite),
         ("pre_shell_exec", HookPoint::PreShellExec),
@@ -1603,6 +2002,7 @@ mod tests {
         ("post_index_file", HookPoint::PostIndexFile),
         ("pre_vfs_write", HookPoint::PreVfsWrite),
         ("post_vfs_write", HookPoint::PostVfsWrite),
+        ("vfs_changed", HookPoint::VfsChanged),
     ];
 
     #[test]
@@ -1806,6 +2206,7 @@ mod tests {
             description: "Hook tester".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::OnStart],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -1835,6 +2236,7 @@ mod tests {
             description: "Env checker".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::PreMessage],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -1871,6 +2273,7 @@ echo 'OK'
             description: "Env verifier".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::OnEnd],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -1895,6 +2298,7 @@ echo 'OK'
             description: "Should be skipped".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::OnStart], // Registered for OnStart only
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -1918,6 +2322,7 @@ echo 'OK'
             description: "Always fails".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::OnStart],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -1951,6 +2356,7 @@ echo 'OK'
                 description: "First".to_string(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::OnStart],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(script1),
@@ -1961,6 +2367,7 @@ echo 'OK'
                 description: "Second".to_string(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::OnStart],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(script2),
@@ -1994,6 +2401,7 @@ echo 'OK'
                 description: String::new(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::PreMessage],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(ok1),
@@ -2004,6 +2412,7 @@ echo 'OK'
                 description: String::new(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::PreMessage],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(fail),
@@ -2014,6 +2423,7 @@ echo 'OK'
                 description: String::new(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::PreMessage],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(ok2),
@@ -2071,7 +2481,7 @@ echo 'OK'
     #[test]
     #[cfg(unix)]
     fn test_execute_hook_ordering() {
-        // Results must arrive in tool registration order
+        // Equal priorities: results arrive in tool name order
         let dir = tempfile::tempdir().unwrap();
 
         let scripts: Vec<_> = (1..=3)
@@ -2092,6 +2502,7 @@ echo 'OK'
                 description: String::new(),
                 parameters: serde_json::json!({}),
                 hooks: vec![HookPoint::PreMessage],
+                hook_priorities: Default::default(),
                 metadata: ToolMetadata::new(),
                 summary_params: vec![],
                 r#impl: crate::tools::ToolImpl::Plugin(path),
@@ -2269,6 +2680,7 @@ echo 'OK'
             description: "Plugin".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![HookPoint::OnStart],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script),
@@ -2299,12 +2711,224 @@ echo 'OK'
         let results =
             execute_hook_with_retry(&tools, HookPoint::OnStart, &serde_json::json!({})).unwrap();
 
-        // plugin first (subprocess loop), then tein (synthesised loop)
+        // equal priorities: ordered by tool name ("plugin-hook" < "tein-hook")
         assert_eq!(results.len(), 2);
         assert_eq!(results[0].1["from"], "plugin");
         assert_eq!(results[1].1["from"], "tein");
     }
 
+    fn plugin_hook_tool(
+        name: &str,
+        path: std::path::PathBuf,
+        hook: HookPoint,
+        priority: i32,
+    ) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            parameters: serde_json::json!({}),
+            hooks: vec![hook],
+            hook_priorities: [(hook, priority)].into_iter().collect(),
+            metadata: ToolMetadata::new(),
+            summary_params: vec![],
+            r#impl: crate::tools::ToolImpl::Plugin(path),
+            category: crate::tools::ToolCategory::Plugin,
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_execute_hook_priority_overrides_name_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = create_test_script(dir.path(), "a.sh", b"#!/bin/bash\necho '\"a\"'");
+        let z = create_test_script(dir.path(), "z.sh", b"#!/bin/bash\necho '\"z\"'");
+        let tools = vec![
+            plugin_hook_tool("a_hook", a, HookPoint::OnStart, 10),
+            plugin_hook_tool("z_hook", z, HookPoint::OnStart, -10),
+        ];
+
+        let results =
+            execute_hook_with_retry(&tools, HookPoint::OnStart, &serde_json::json!({})).unwrap();
+
+        let names: Vec<_> = results.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["z_hook", "a_hook"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pipeline_hook_sees_previous_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let rewrite = create_test_script(
+            dir.path(),
+            "rewrite.sh",
+            b"#!/bin/bash\ncat > /dev/null\necho '{\"prompt\": \"rewritten\"}'",
+        );
+        let echo = create_test_script(dir.path(), "echo.sh", b"#!/bin/bash\ncat\n");
+        let tools = vec![
+            plugin_hook_tool("echo", echo, HookPoint::PreMessage, 0),
+            plugin_hook_tool("rewrite", rewrite, HookPoint::PreMessage, -1),
+        ];
+
+        let data = serde_json::json!({"prompt": "original", "context_name": "default"});
+        let results = execute_hook_with_retry(&tools, HookPoint::PreMessage, &data).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "rewrite");
+        assert_eq!(results[1].0, "echo");
+        assert_eq!(results[1].1["prompt"], "rewritten");
+        assert_eq!(results[1].1["context_name"], "default");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pipeline_block_stops_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let block = create_test_script(
+            dir.path(),
+            "block.sh",
+            b"#!/bin/bash\ncat > /dev/null\necho '{\"block\": true}'",
+        );
+        let later = create_test_script(dir.path(), "later.sh", b"#!/bin/bash\necho '{}'");
+        let tools = vec![
+            plugin_hook_tool("block", block, HookPoint::PreTool, 0),
+            plugin_hook_tool("later", later, HookPoint::PreTool, 5),
+        ];
+
+        let results = execute_hook_with_retry(
+            &tools,
+            HookPoint::PreTool,
+            &serde_json::json!({"tool_name": "x", "arguments": {}}),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "block");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_first_wins_skips_empty_and_stops_at_first_claim() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = create_test_script(dir.path(), "empty.sh", b"#!/bin/bash\necho '{}'");
+        let claim = create_test_script(
+            dir.path(),
+            "claim.sh",
+            b"#!/bin/bash\necho '{\"delivered\": true, \"via\": \"claim\"}'",
+        );
+        let never = create_test_script(
+            dir.path(),
+            "never.sh",
+            b"#!/bin/bash\necho '{\"delivered\": true, \"via\": \"never\"}'",
+        );
+        let tools = vec![
+            plugin_hook_tool("a_empty", empty, HookPoint::PreSendMessage, 0),
+            plugin_hook_tool("b_claim", claim, HookPoint::PreSendMessage, 0),
+            plugin_hook_tool("c_never", never, HookPoint::PreSendMessage, 0),
+        ];
+
+        let results =
+            execute_hook_with_retry(&tools, HookPoint::PreSendMessage, &serde_json::json!({}))
+                .unwrap();
+
+        let names: Vec<_> = results.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["a_empty", "b_claim"]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_veto_any_stops_at_first_denial() {
+        let dir = tempfile::tempdir().unwrap();
+        let deny = create_test_script(
+            dir.path(),
+            "deny.sh",
+            b"#!/bin/bash\necho '{\"denied\": true, \"reason\": \"no\"}'",
+        );
+        let allow = create_test_script(dir.path(), "allow.sh", b"#!/bin/bash\necho '{}'");
+        let tools = vec![
+            plugin_hook_tool("allow", allow, HookPoint::PreShellExec, 1),
+            plugin_hook_tool("deny", deny, HookPoint::PreShellExec, 0),
+        ];
+
+        let results =
+            execute_hook_with_retry(&tools, HookPoint::PreShellExec, &serde_json::json!({}))
+                .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1["denied"], true);
+    }
+
+    #[test]
+    fn test_pipeline_merges_request_body() {
+        let mut data = serde_json::json!({"request_body": {"model": "m", "temperature": 1.0}});
+        let stop = HookChain::Pipeline.absorb(
+            HookPoint::PreApiRequest,
+            &mut data,
+            &serde_json::json!({"request_body": {"temperature": 0.2}}),
+        );
+        assert!(!stop);
+        assert_eq!(data["request_body"]["model"], "m");
+        assert_eq!(data["request_body"]["temperature"], 0.2);
+    }
+
+    #[test]
+    fn test_hook_chain_assignments() {
+        assert_eq!(HookPoint::PreMessage.chain(), HookChain::Pipeline);
+        assert_eq!(HookPoint::PreApiRequest.chain(), HookChain::Pipeline);
+        assert_eq!(HookPoint::PreFileWrite.chain(), HookChain::VetoAny);
+        assert_eq!(HookPoint::PreSpawnAgent.chain(), HookChain::FirstWins);
+        assert_eq!(HookPoint::PostToolBatch.chain(), HookChain::LastWins);
+        assert_eq!(HookPoint::PreApiTools.chain(), HookChain::Collect);
+        assert_eq!(HookPoint::OnStart.chain(), HookChain::Observe);
+    }
+
+    #[test]
+    fn test_describe_hook_chains() {
+        let tools = vec![
+            plugin_hook_tool("late", "/bin/late".into(), HookPoint::PreApiRequest, 10),
+            plugin_hook_tool("early", "/bin/early".into(), HookPoint::PreApiRequest, -5),
+            plugin_hook_tool("starter", "/bin/starter".into(), HookPoint::OnStart, 0),
+        ];
+
+        let out = describe_hook_chains(&tools);
+
+        assert_eq!(
+            out,
+            "on_start (observe)\n  1. starter [plugin, priority 0]\n\n\
+             pre_api_request (pipeline)\n  1. early [plugin, priority -5]\n  2. late [plugin, priority 10]\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "synthesised-tools")]
+    fn test_register_hook_priority() {
+        use crate::tools::registry::ToolRegistry;
+        use crate::tools::synthesised::load_tools_from_source;
+        use crate::vfs::VfsPath;
+        use std::sync::{Arc, RwLock};
+
+        let source = r#"
+(import (harness hooks))
+(register-hook 'on_start (lambda (payload) '()) -20)
+(register-hook 'pre_message (lambda (payload) '()))
+(define tool-name "prio-hook")
+(define tool-description "Hook priorities")
+(define tool-parameters '())
+(define (tool-execute args) "ok")
+"#;
+        let registry = Arc::new(RwLock::new(ToolRegistry::new()));
+        let path = VfsPath::new("/tools/shared/prio.scm").unwrap();
+        let tools = load_tools_from_source(
+            source,
+            &path,
+            &registry,
+            &crate::config::ToolsConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(tools[0].hook_priority(HookPoint::OnStart), -20);
+        assert_eq!(tools[0].hook_priority(HookPoint::PreMessage), 0);
+    }
+
     // --- re-entrancy guard tests ---
 
     /// Verify that when a hook point is already in TEIN_HOOK_GUARD (simulating
@@ -2422,8 +3046,20 @@ echo 'OK'
             username: "test".to_string(),
             lock_heartbeat_seconds: 30,
             rolling_compact_drop_percentage: 50.0,
+            compaction_strategy: "rolling".to_string(),
+            compact_keep_turns: 8,
+            lock_policy: "fail".to_string(),
+            lock_wait_timeout_seconds: 300,
+            progress_action: "fuel".to_string(),
+            progress_window: 12,
+            progress_error_streak: 3,
+            progress_stall_calls: 40,
+            provider_max_retries: 3,
+            provider_retry_base_ms: 1000,
+            model_fallbacks: vec![],
             tool_output_cache_threshold: 4000,
             tool_cache_max_age_days: 7,
+            tool_cache_max_bytes: 0,
             auto_cleanup_cache: false,
             tool_cache_preview_chars: 500,
             file_tools_allowed_paths: vec![],
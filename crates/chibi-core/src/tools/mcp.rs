@@ -29,6 +29,7 @@ pub fn mcp_tool_from_info(
         description: description.to_string(),
         parameters,
         hooks: vec![],
+        hook_priorities: Default::default(),
         metadata: ToolMetadata::new(),
         summary_params: vec![],
         r#impl: crate::tools::ToolImpl::Mcp {
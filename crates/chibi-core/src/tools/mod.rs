@@ -12,6 +12,8 @@
 //!   - `index`: codebase index management
 //!   - `flow`: control flow, spawning, coordination, model introspection
 //!   - `vfs_tools`: virtual filesystem operations
+//! - `scheme_lib`: shared scheme libraries (`/tools/lib/`) for synthesised tools
+//! - `tool_test`: test runner for synthesised scheme tools
 //! - URL and file path security policies
 //! - Hook system for plugin lifecycle events
 
@@ -25,13 +27,17 @@ pub mod mcp;
 mod memory;
 mod network;
 pub(crate) mod paths;
+mod plugin_host;
 mod plugins;
 pub mod registry;
+pub mod scheme_lib;
 pub mod security;
 mod shell;
 pub mod synthesised;
+pub mod tool_test;
 pub mod vfs_tools;
 
+use std::collections::HashMap;
 use std::io::{self, ErrorKind};
 
 pub use hooks::HookPoint;
@@ -124,9 +130,10 @@ pub fn require_str_param(args: &serde_json::Value, name: &str) -> io::Result<Str
 // Re-export hook execution and docs generation
 #[cfg(feature = "synthesised-tools")]
 pub use hooks::generate_hooks_markdown;
-pub use hooks::{TeinHookContext, execute_hook};
+pub use hooks::{HookChain, TeinHookContext, describe_hook_chains, execute_hook};
 
 // Re-export plugin functions
+pub use plugin_host::{PluginProcess, shutdown_persistent_plugins};
 pub use plugins::{execute_tool, execute_tool_by_path, load_tools};
 
 // Re-export memory tool constants and functions
@@ -137,17 +144,18 @@ pub use memory::{
 
 // Re-export flow tool constants, types and functions
 pub use flow::{
-    CALL_AGENT_TOOL_NAME, CALL_USER_TOOL_NAME, FLOW_TOOL_DEFS, Handoff, HandoffTarget,
+    CALL_AGENT_TOOL_NAME, CALL_USER_TOOL_NAME, Completion, FLOW_TOOL_DEFS, Handoff, HandoffTarget,
     MODEL_INFO_TOOL_NAME, SEND_MESSAGE_TOOL_NAME, SPAWN_AGENT_TOOL_NAME,
-    SUMMARIZE_CONTENT_TOOL_NAME, SpawnOptions, execute_flow_tool, flow_tool_metadata, is_url,
-    register_flow_tools, spawn_agent, spawn_agent_preset_description,
+    SUMMARIZE_CONTENT_TOOL_NAME, SpawnOptions, complete, execute_flow_tool, flow_tool_metadata,
+    is_url, register_flow_tools, spawn_agent, spawn_agent_preset_description,
 };
 
 // Re-export fs_read tool registry functions and execution
 pub use fs_read::{
     DIR_LIST_TOOL_NAME, FILE_GREP_TOOL_NAME, FILE_HEAD_TOOL_NAME, FILE_LINES_TOOL_NAME,
     FILE_TAIL_TOOL_NAME, FS_READ_TOOL_DEFS, GLOB_FILES_TOOL_NAME, GREP_FILES_TOOL_NAME,
-    execute_fs_read_tool, register_fs_read_tools,
+    VIEW_IMAGE_TOOL_NAME, describe_view_image, execute_fs_read_tool, execute_view_image,
+    register_fs_read_tools,
 };
 
 // Bridge for blocking async VFS calls from synchronous contexts.
@@ -223,6 +231,9 @@ pub struct Tool {
     pub description: String,
     pub parameters: serde_json::Value,
     pub hooks: Vec<HookPoint>,
+    /// Per-hook ordering priority; hooks run in ascending order. Missing
+    /// entries default to 0.
+    pub hook_priorities: HashMap<HookPoint, i32>,
     pub metadata: ToolMetadata,
     /// Parameter names whose values should appear in tool-call notices.
     pub summary_params: Vec<String>,
@@ -269,6 +280,11 @@ impl Tool {
             || (self.category == ToolCategory::Synthesised && !self.hooks.is_empty())
     }
 
+    /// Ordering priority of this tool's handler for `hook` (default 0).
+    pub fn hook_priority(&self, hook: HookPoint) -> i32 {
+        self.hook_priorities.get(&hook).copied().unwrap_or(0)
+    }
+
     /// Construct a Tool from a `BuiltinToolDef`, a shared handler, and a category.
     ///
     /// Reduces boilerplate in the `register_*_tools()` functions across all modules.
@@ -282,6 +298,7 @@ impl Tool {
             description: def.description.to_string(),
             parameters: def.to_json_schema(),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: def.summary_params.iter().map(|s| s.to_string()).collect(),
             r#impl: registry::ToolImpl::Builtin(handler),
@@ -548,6 +565,7 @@ mod tests {
             description: "A custom flow control tool".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata {
                 parallel: false,
                 flow_control: true,
@@ -633,6 +651,7 @@ mod tests {
             description: "test".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec!["path".to_string(), "pattern".to_string()],
             r#impl: registry::ToolImpl::Plugin(PathBuf::from("/bin/test")),
@@ -0,0 +1,502 @@
+//! Persistent plugin processes.
+//!
+//! Plugins that declare `"persistent": true` in their `--schema` output are
+//! spawned once (lazily, on first use) and kept alive for the rest of the
+//! session instead of being re-spawned for every tool call and hook.
+//!
+//! The protocol is newline-delimited JSON-RPC 2.0 over the plugin's
+//! stdin/stdout, one message per line:
+//!
+//! - tool call: `{"jsonrpc":"2.0","id":1,"method":"call_tool","params":{"name":..,"arguments":{..}}}`
+//! - hook: `{"jsonrpc":"2.0","id":2,"method":"hook","params":{"hook":"pre_tool","data":{..}}}`
+//! - shutdown (notification, no reply): `{"jsonrpc":"2.0","method":"shutdown"}`
+//!
+//! Replies carry the request `id` and either a `result` or an `error` object.
+//! Stdout lines that are not JSON or do not match the pending `id` are
+//! ignored, so stray prints do not desynchronise the stream. A plugin that
+//! crashes is restarted on the next request (up to `MAX_PLUGIN_RESTARTS`
+//! times in a row without a successful reply).
+
+use super::HookPoint;
+use super::{Tool, ToolImpl};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// How many times in a row a crashed plugin is respawned, with no successful
+/// reply in between, before chibi gives up on it.
+pub(crate) const MAX_PLUGIN_RESTARTS: u32 = 3;
+
+/// How long a plugin gets to exit on its own after the shutdown notification.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Live plugin processes keyed by canonical executable path.
+///
+/// Weak so that every `load_tools` call during a session (the main registry,
+/// compaction hooks, ...) shares one process, while dropping the last tool
+/// still reaps it.
+static PROCESSES: LazyLock<Mutex<HashMap<PathBuf, Weak<PluginProcess>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Handle to a long-lived plugin executable, shared by all tools it provides.
+pub struct PluginProcess {
+    path: PathBuf,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    running: Option<Running>,
+    /// Spawns since the last successful reply, the current process included.
+    spawns: u32,
+}
+
+struct Running {
+    child: Child,
+    stdin: ChildStdin,
+    /// Stdout lines, fed by a reader thread so reads can time out.
+    lines: Receiver<String>,
+    next_id: u64,
+}
+
+impl std::fmt::Debug for PluginProcess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginProcess")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginProcess {
+    /// Get the shared handle for `path`, creating it if no live one exists.
+    ///
+    /// The process itself is not spawned until the first request.
+    pub fn shared(path: &Path) -> Arc<PluginProcess> {
+        let mut map = PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = map.get(path).and_then(Weak::upgrade) {
+            return existing;
+        }
+        map.retain(|_, weak| weak.strong_count() > 0);
+        let process = Arc::new(PluginProcess {
+            path: path.to_path_buf(),
+            inner: Mutex::new(Inner {
+                running: None,
+                spawns: 0,
+            }),
+        });
+        map.insert(path.to_path_buf(), Arc::downgrade(&process));
+        process
+    }
+
+    /// Path of the plugin executable.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the plugin process is currently running.
+    pub fn is_running(&self) -> bool {
+        self.lock().running.is_some()
+    }
+
+    /// Call one of the plugin's tools. A string `result` is returned as-is;
+    /// any other JSON value is returned serialised.
+    pub fn call_tool(&self, tool_name: &str, arguments: &Value) -> io::Result<String> {
+        let params = json!({ "name": tool_name, "arguments": arguments });
+        let context = format!("plugin tool '{tool_name}'");
+        match self.request("call_tool", params, &context)?? {
+            Value::String(s) => Ok(s),
+            other => Ok(other.to_string()),
+        }
+    }
+
+    /// Deliver a hook event.
+    ///
+    /// The outer `Result` is a transport failure (spawn, crash, timeout); the
+    /// inner one is an `error` reply from the plugin, which hook dispatch
+    /// treats like a one-shot plugin exiting non-zero.
+    pub fn hook(&self, hook: HookPoint, data: &Value) -> io::Result<io::Result<Value>> {
+        let params = json!({ "hook": hook.as_ref(), "data": data });
+        let context = format!("hook {} on {}", hook.as_ref(), self.display_name());
+        self.request("hook", params, &context)
+    }
+
+    /// Send the shutdown notification and wait briefly for the process to
+    /// exit, killing it if it does not. No-op if the process is not running.
+    pub fn shutdown(&self) {
+        let Some(running) = self.lock().running.take() else {
+            return;
+        };
+        let Running {
+            mut child,
+            mut stdin,
+            ..
+        } = running;
+        let _ = writeln!(
+            stdin,
+            "{}",
+            json!({ "jsonrpc": "2.0", "method": "shutdown" })
+        );
+        let _ = stdin.flush();
+        drop(stdin);
+
+        let deadline = Instant::now() + SHUTDOWN_GRACE;
+        while Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(Some(_)) => return,
+                Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+                Err(_) => break,
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn display_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// Send a request and wait for its reply, (re)spawning the process as
+    /// needed. A request that could not be written because the process had
+    /// already died is retried once against a fresh process. Once written it
+    /// is never retried: the plugin may have acted on it before failing.
+    fn request(&self, method: &str, params: Value, context: &str) -> io::Result<io::Result<Value>> {
+        let mut inner = self.lock();
+        let mut retried = false;
+        loop {
+            if inner.running.is_none() {
+                if inner.spawns > MAX_PLUGIN_RESTARTS {
+                    return Err(io::Error::other(format!(
+                        "persistent plugin {} exited {} times; not restarting",
+                        self.display_name(),
+                        inner.spawns,
+                    )));
+                }
+                if inner.spawns > 0 {
+                    eprintln!(
+                        "[WARN] Restarting persistent plugin {}",
+                        self.display_name()
+                    );
+                }
+                inner.running = Some(Running::spawn(&self.path)?);
+                inner.spawns += 1;
+            }
+
+            let running = inner.running.as_mut().expect("spawned above");
+            let outcome = match running.send(method, &params) {
+                Ok(id) => running.receive(id, context).map_err(|e| (e, true)),
+                Err(e) => Err((e, false)),
+            };
+            match outcome {
+                Ok(reply) => {
+                    inner.spawns = 1;
+                    return Ok(reply);
+                }
+                Err((e, delivered)) => {
+                    // The process is dead or wedged: reap it so the next
+                    // request starts a fresh one.
+                    if let Some(dead) = inner.running.take() {
+                        dead.kill();
+                    }
+                    if delivered || retried {
+                        return Err(e);
+                    }
+                    retried = true;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        if let Some(running) = self.lock().running.take() {
+            running.kill();
+        }
+    }
+}
+
+impl Running {
+    fn spawn(path: &Path) -> io::Result<Running> {
+        let mut child = Command::new(path)
+            .env("CHIBI_PLUGIN_MODE", "persistent")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| io::Error::other(format!("Failed to spawn persistent plugin: {e}")))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+            // Dropping `tx` disconnects the channel, which the waiting
+            // request sees as the process having exited.
+        });
+
+        Ok(Running {
+            child,
+            stdin,
+            lines,
+            next_id: 1,
+        })
+    }
+
+    /// Write a request, returning its id. An error means the plugin never
+    /// received it.
+    fn send(&mut self, method: &str, params: &Value) -> io::Result<u64> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        writeln!(self.stdin, "{message}")?;
+        self.stdin.flush()?;
+        Ok(id)
+    }
+
+    /// Wait for the reply to request `id`. Errors in the outer `Result` mean
+    /// the transport is broken and the process should be discarded.
+    fn receive(&mut self, id: u64, context: &str) -> io::Result<io::Result<Value>> {
+        let timeout = Duration::from_secs(super::PLUGIN_TIMEOUT_SECS);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(remaining) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        format!("Timed out after {}s: {context}", timeout.as_secs()),
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("persistent plugin exited during {context}"),
+                    ));
+                }
+            };
+
+            let Ok(reply) = serde_json::from_str::<Value>(line.trim()) else {
+                continue;
+            };
+            if reply["id"].as_u64() != Some(id) {
+                continue;
+            }
+            if let Some(error) = reply.get("error") {
+                let message = error["message"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| error.to_string());
+                return Ok(Err(io::Error::other(format!("{context}: {message}"))));
+            }
+            return Ok(Ok(reply.get("result").cloned().unwrap_or(Value::Null)));
+        }
+    }
+
+    fn kill(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Send the shutdown notification to every persistent plugin among `tools`.
+///
+/// Called at session end, after `OnEnd` hooks have run. Processes shared by
+/// several tools are shut down once.
+pub fn shutdown_persistent_plugins(tools: &[Tool]) {
+    let mut seen = HashSet::new();
+    for tool in tools {
+        if let ToolImpl::PersistentPlugin(process) = &tool.r#impl
+            && seen.insert(Arc::as_ptr(process))
+        {
+            process.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_helpers::create_test_script;
+    use super::*;
+
+    /// A persistent plugin that answers `call_tool` with its pid and the
+    /// tool name, and `hook` with the hook name. Exits on shutdown.
+    const ECHO_PLUGIN: &[u8] = br#"#!/bin/bash
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"shutdown"'*) echo shutdown >> "$0.log"; exit 0 ;;
+    *'"method":"call_tool"'*)
+      name=$(printf '%s' "$line" | sed -n 's/.*"name":"\([^"]*\)".*/\1/p')
+      case "$name" in
+        crash) echo crash >> "$0.log"; exit 1 ;;
+        fail) echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"error\":{\"code\":1,\"message\":\"nope\"}}" ;;
+        *) echo "not json"; echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":\"$name:$$\"}" ;;
+      esac ;;
+    *'"method":"hook"'*)
+      hook=$(printf '%s' "$line" | sed -n 's/.*"hook":"\([^"]*\)".*/\1/p')
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"seen\":\"$hook\"}}" ;;
+  esac
+done
+"#;
+
+    /// Retry on ETXTBSY: the kernel may not have released the fresh script.
+    fn call_with_retry(process: &PluginProcess, name: &str) -> io::Result<String> {
+        for attempt in 0..5 {
+            match process.call_tool(name, &json!({})) {
+                Err(e) if e.to_string().contains("Text file busy") && attempt < 4 => {
+                    std::thread::sleep(Duration::from_millis(10 * (attempt + 1) as u64));
+                }
+                other => return other,
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_process_is_reused_across_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "echo.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        assert!(!process.is_running());
+
+        let first = call_with_retry(&process, "alpha").unwrap();
+        let second = process.call_tool("beta", &json!({})).unwrap();
+        let pid = |s: &str| s.split(':').nth(1).unwrap().to_string();
+        assert!(first.starts_with("alpha:"));
+        assert!(second.starts_with("beta:"));
+        assert_eq!(pid(&first), pid(&second));
+        assert!(process.is_running());
+    }
+
+    #[test]
+    fn test_shared_returns_same_handle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "same.sh", ECHO_PLUGIN);
+        let a = PluginProcess::shared(&path);
+        let b = PluginProcess::shared(&path);
+        assert!(Arc::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn test_error_reply_keeps_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "err.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "warmup").unwrap();
+
+        let err = process.call_tool("fail", &json!({})).unwrap_err();
+        assert!(err.to_string().contains("nope"));
+        assert!(process.is_running());
+    }
+
+    #[test]
+    fn test_restart_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "crash.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        let before = call_with_retry(&process, "a").unwrap();
+
+        // The plugin received the request before crashing, so it is not
+        // re-sent: the tool may already have had its side effects.
+        assert!(process.call_tool("crash", &json!({})).is_err());
+        let log = std::fs::read_to_string(dir.path().join("crash.sh.log")).unwrap();
+        assert_eq!(log.lines().count(), 1);
+
+        let after = process.call_tool("b", &json!({})).unwrap();
+        assert_ne!(before.split(':').nth(1), after.split(':').nth(1));
+    }
+
+    #[test]
+    fn test_undelivered_request_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "idle.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "a").unwrap();
+
+        // The process dies between requests, so the next write fails and the
+        // request goes to a fresh process instead.
+        {
+            let mut inner = process.lock();
+            let running = inner.running.as_mut().unwrap();
+            running.child.kill().unwrap();
+            running.child.wait().unwrap();
+        }
+        assert!(
+            process
+                .call_tool("b", &json!({}))
+                .unwrap()
+                .starts_with("b:")
+        );
+    }
+
+    #[test]
+    fn test_gives_up_after_max_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "flaky.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "a").unwrap();
+
+        for _ in 0..=MAX_PLUGIN_RESTARTS {
+            let _ = process.call_tool("crash", &json!({}));
+        }
+        let err = process.call_tool("a", &json!({})).unwrap_err();
+        assert!(err.to_string().contains("not restarting"), "{err}");
+    }
+
+    #[test]
+    fn test_successful_reply_resets_restart_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "resets.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "a").unwrap();
+
+        for _ in 0..MAX_PLUGIN_RESTARTS + 2 {
+            let _ = process.call_tool("crash", &json!({}));
+            process.call_tool("a", &json!({})).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_hook_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "hook.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "warmup").unwrap();
+
+        let value = process
+            .hook(HookPoint::PreMessage, &json!({"prompt": "hi"}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(value["seen"], "pre_message");
+    }
+
+    #[test]
+    fn test_shutdown_notifies_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_test_script(dir.path(), "stop.sh", ECHO_PLUGIN);
+        let process = PluginProcess::shared(&path);
+        call_with_retry(&process, "warmup").unwrap();
+
+        process.shutdown();
+        assert!(!process.is_running());
+        let log = std::fs::read_to_string(dir.path().join("stop.sh.log")).unwrap();
+        assert_eq!(log.trim(), "shutdown");
+    }
+}
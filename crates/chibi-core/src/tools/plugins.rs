@@ -2,12 +2,16 @@
 //!
 //! Plugins are executable scripts in the plugins directory that provide tools for the LLM.
 //! They output JSON schema when called with --schema and receive arguments via stdin (JSON).
+//! Plugins whose schema sets `"persistent": true` are instead kept running and
+//! spoken to over JSON-RPC (see `plugin_host`).
 
 use super::hooks::HookPoint;
-use super::{Tool, ToolMetadata};
+use super::plugin_host::PluginProcess;
+use super::{Tool, ToolImpl, ToolMetadata};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, ErrorKind, Write};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
 /// Load all tools from the plugins directory by calling each with --schema.
@@ -132,9 +136,20 @@ fn get_tool_schemas(path: &PathBuf) -> io::Result<Vec<Tool>> {
         vec![&schema]
     };
 
+    // One process serves every tool in the executable, so a single
+    // `"persistent": true` makes the whole plugin persistent.
+    let tool_impl = if schemas
+        .iter()
+        .any(|s| s["persistent"].as_bool() == Some(true))
+    {
+        ToolImpl::PersistentPlugin(PluginProcess::shared(path))
+    } else {
+        ToolImpl::Plugin(path.to_path_buf())
+    };
+
     let mut tools = Vec::new();
     for s in schemas {
-        match parse_single_tool_schema(s, path) {
+        match parse_single_tool_schema(s, tool_impl.clone()) {
             Ok(tool) => tools.push(tool),
             Err(e) => {
                 eprintln!(
@@ -156,7 +171,7 @@ fn get_tool_schemas(path: &PathBuf) -> io::Result<Vec<Tool>> {
     Ok(tools)
 }
 
-fn parse_single_tool_schema(schema: &serde_json::Value, path: &Path) -> io::Result<Tool> {
+fn parse_single_tool_schema(schema: &serde_json::Value, tool_impl: ToolImpl) -> io::Result<Tool> {
     let name = schema["name"]
         .as_str()
         .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Schema missing 'name' field"))?
@@ -171,24 +186,41 @@ fn parse_single_tool_schema(schema: &serde_json::Value, path: &Path) -> io::Resu
 
     let parameters = schema["parameters"].clone();
 
-    // Parse hooks array (optional)
-    let hooks = if let Some(hooks_array) = schema["hooks"].as_array() {
-        hooks_array
-            .iter()
-            .filter_map(|v| {
-                let hook_str = v.as_str()?;
-                match hook_str.parse::<HookPoint>() {
-                    Ok(hook) => Some(hook),
-                    Err(_) => {
-                        eprintln!("[WARN] Unknown hook '{}' in tool '{}'", hook_str, name);
-                        None
-                    }
+    // Parse hooks array (optional). Entries are hook names, or
+    // `{"hook": "...", "priority": N}` objects to override the tool-wide
+    // `hook_priority` for one hook point.
+    let default_priority = schema["hook_priority"].as_i64().unwrap_or(0) as i32;
+    let mut hooks = Vec::new();
+    let mut hook_priorities = HashMap::new();
+    for entry in schema["hooks"].as_array().into_iter().flatten() {
+        let (hook_str, priority) = match entry {
+            serde_json::Value::String(s) => (s.as_str(), default_priority),
+            serde_json::Value::Object(obj) => match obj.get("hook").and_then(|v| v.as_str()) {
+                Some(s) => (
+                    s,
+                    obj.get("priority")
+                        .and_then(|v| v.as_i64())
+                        .map_or(default_priority, |p| p as i32),
+                ),
+                None => {
+                    eprintln!("[WARN] Hook entry without 'hook' field in tool '{}'", name);
+                    continue;
                 }
-            })
-            .collect()
-    } else {
-        Vec::new()
-    };
+            },
+            _ => continue,
+        };
+        match hook_str.parse::<HookPoint>() {
+            Ok(hook) => {
+                hooks.push(hook);
+                if priority != 0 {
+                    hook_priorities.insert(hook, priority);
+                }
+            }
+            Err(_) => {
+                eprintln!("[WARN] Unknown hook '{}' in tool '{}'", hook_str, name);
+            }
+        }
+    }
 
     // Parse metadata fields (all optional with defaults)
     let metadata = ToolMetadata {
@@ -212,9 +244,10 @@ fn parse_single_tool_schema(schema: &serde_json::Value, path: &Path) -> io::Resu
         description,
         parameters,
         hooks,
+        hook_priorities,
         metadata,
         summary_params,
-        r#impl: crate::tools::ToolImpl::Plugin(path.to_path_buf()),
+        r#impl: tool_impl,
         category: crate::tools::ToolCategory::Plugin,
     })
 }
@@ -268,11 +301,13 @@ pub fn execute_tool_by_path(
 /// Execute a tool with the given arguments (as JSON)
 ///
 /// Tools receive arguments via stdin (JSON), leaving stdout for results.
-/// The executable path is extracted from `ToolImpl::Plugin`; returns an error
-/// for non-plugin tools.
+/// The executable path is extracted from `ToolImpl::Plugin`; persistent
+/// plugins are called over their running process. Returns an error for
+/// non-plugin tools.
 pub fn execute_tool(tool: &Tool, arguments: &serde_json::Value) -> io::Result<String> {
     let path = match &tool.r#impl {
-        crate::tools::ToolImpl::Plugin(p) => p.clone(),
+        ToolImpl::Plugin(p) => p.clone(),
+        ToolImpl::PersistentPlugin(process) => return process.call_tool(&tool.name, arguments),
         _ => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -333,6 +368,7 @@ mod tests {
             description: "A test tool".to_string(),
             parameters: serde_json::json!({"type": "object", "properties": {}}),
             hooks: vec![HookPoint::OnStart, HookPoint::OnEnd],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(PathBuf::from("/usr/bin/test")),
@@ -377,6 +413,7 @@ mod tests {
             description: "Echoes params".to_string(),
             parameters: serde_json::json!({"type": "object"}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -406,6 +443,7 @@ mod tests {
             description: "Checks env vars".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -426,6 +464,7 @@ mod tests {
             description: "Always fails".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -469,6 +508,7 @@ echo 'OK'
             description: "Verifies env".to_string(),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: crate::tools::ToolImpl::Plugin(script_path),
@@ -490,4 +530,65 @@ echo 'OK'
         assert!(meta.flow_control);
         assert!(meta.ends_turn);
     }
+
+    #[test]
+    fn test_persistent_schema_shares_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let script_path = create_test_script(
+            dir.path(),
+            "multi.sh",
+            br#"#!/bin/bash
+echo '[{"name":"a","description":"A","parameters":{},"persistent":true},{"name":"b","description":"B","parameters":{}}]'
+"#,
+        );
+
+        let mut tools = None;
+        for attempt in 0..5 {
+            match get_tool_schemas(&script_path) {
+                Err(e) if e.to_string().contains("Text file busy") && attempt < 4 => {
+                    std::thread::sleep(std::time::Duration::from_millis(10 * (attempt + 1) as u64));
+                }
+                result => {
+                    tools = Some(result.unwrap());
+                    break;
+                }
+            }
+        }
+        let tools = tools.unwrap();
+
+        assert_eq!(tools.len(), 2);
+        match (&tools[0].r#impl, &tools[1].r#impl) {
+            (ToolImpl::PersistentPlugin(a), ToolImpl::PersistentPlugin(b)) => {
+                assert!(std::sync::Arc::ptr_eq(a, b));
+                assert!(!a.is_running(), "process should spawn lazily");
+            }
+            _ => panic!("expected persistent plugin tools"),
+        }
+    }
+
+    #[test]
+    fn test_parse_hook_priorities() {
+        let schema = serde_json::json!({
+            "name": "prio",
+            "description": "Priorities",
+            "parameters": {},
+            "hook_priority": 5,
+            "hooks": ["on_start", {"hook": "pre_api_request", "priority": -10}, {"hook": "pre_tool"}]
+        });
+        let tool = parse_single_tool_schema(&schema, ToolImpl::Plugin(PathBuf::from("/bin/prio")))
+            .unwrap();
+
+        assert_eq!(
+            tool.hooks,
+            vec![
+                HookPoint::OnStart,
+                HookPoint::PreApiRequest,
+                HookPoint::PreTool
+            ]
+        );
+        assert_eq!(tool.hook_priority(HookPoint::OnStart), 5);
+        assert_eq!(tool.hook_priority(HookPoint::PreApiRequest), -10);
+        assert_eq!(tool.hook_priority(HookPoint::PreTool), 5);
+        assert_eq!(tool.hook_priority(HookPoint::OnEnd), 0);
+    }
 }
@@ -20,6 +20,7 @@ use crate::vfs::caller::VfsCaller;
 use super::Tool;
 #[cfg(test)]
 use super::ToolMetadata;
+use super::scheme_lib::SchemeLibraries;
 
 /// Async future type for tool handlers.
 ///
@@ -62,6 +63,10 @@ pub enum ToolImpl {
     Builtin(ToolHandler),
     /// OS-path plugin executable (spawned as subprocess).
     Plugin(PathBuf),
+    /// Plugin that opted into `"persistent": true`: one long-lived process,
+    /// shared (Arc) by every tool the executable provides, spoken to over
+    /// newline-delimited JSON-RPC.
+    PersistentPlugin(Arc<super::plugin_host::PluginProcess>),
     /// MCP bridge tool (JSON-over-TCP to mcp-bridge daemon).
     Mcp { server: String, tool_name: String },
     /// Scheme tool loaded from VFS source via tein. Context is shared across
@@ -94,6 +99,7 @@ impl Clone for ToolImpl {
         match self {
             ToolImpl::Builtin(h) => ToolImpl::Builtin(h.clone()),
             ToolImpl::Plugin(p) => ToolImpl::Plugin(p.clone()),
+            ToolImpl::PersistentPlugin(process) => ToolImpl::PersistentPlugin(Arc::clone(process)),
             ToolImpl::Mcp { server, tool_name } => ToolImpl::Mcp {
                 server: server.clone(),
                 tool_name: tool_name.clone(),
@@ -187,15 +193,28 @@ impl ToolCategory {
 ///   Hooks, permissions, and caching stay in `send.rs` as middleware.
 pub struct ToolRegistry {
     tools: IndexMap<String, Tool>,
+    /// Scheme libraries under `/tools/lib/` and the tool files importing them.
+    scheme_libs: SchemeLibraries,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
             tools: IndexMap::new(),
+            scheme_libs: SchemeLibraries::default(),
         }
     }
 
+    /// Scheme libraries available to synthesised tools.
+    pub fn scheme_libs(&self) -> &SchemeLibraries {
+        &self.scheme_libs
+    }
+
+    /// Mutable access to the scheme library store (startup scan, hot-reload).
+    pub fn scheme_libs_mut(&mut self) -> &mut SchemeLibraries {
+        &mut self.scheme_libs
+    }
+
     /// Register a tool. Replaces any existing tool with the same name (hot-reload).
     pub fn register(&mut self, tool: Tool) {
         self.tools.insert(tool.name.clone(), tool);
@@ -300,6 +319,7 @@ impl ToolRegistry {
         match tool_impl {
             ToolImpl::Builtin(handler) => handler(call).await,
             ToolImpl::Plugin(path) => super::plugins::execute_tool_by_path(&path, name, args),
+            ToolImpl::PersistentPlugin(process) => process.call_tool(name, args),
             ToolImpl::Mcp { server, tool_name } => {
                 let home = ctx.app.chibi_dir.clone();
                 super::mcp::execute_mcp_call(&server, &tool_name, args, &home)
@@ -393,6 +413,7 @@ mod tests {
             description: format!("test tool {name}"),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl,
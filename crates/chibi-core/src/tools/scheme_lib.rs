@@ -0,0 +1,323 @@
+//! Shared scheme libraries for synthesised tools.
+//!
+//! Libraries live in the VFS at `/tools/lib/<zone>/<name>.sld`, where `<zone>`
+//! mirrors the tool zones: `shared`, `home/<context>` or `flocks/<name>`. Each
+//! file holds one `(define-library (lib <name>) ...)` form, and tools pull it in
+//! with `(import (lib <name>))`.
+//!
+//! A tool sees the `shared` libraries plus those of its own zone; when both
+//! define the same name, the zone library wins. Libraries are registered into
+//! the tool's own tein context, so they run under the tool's sandbox tier. An
+//! unsandboxed tool refuses to load if it imports a library whose own path
+//! resolves to a weaker tier (see `synthesised::build_tool_session`).
+//!
+//! `SchemeLibraries` also remembers which tool files import a library, so a
+//! write or delete under `/tools/lib/` can reload every dependent tool through
+//! the VFS `on_scm_change` callback.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::vfs::VfsPath;
+
+/// VFS root for scheme library files.
+pub const LIB_ROOT: &str = "/tools/lib/";
+
+/// Returns `true` if `path` is a `.sld` library in a writable library zone.
+pub fn is_library_path(path: &VfsPath) -> bool {
+    path.as_str().ends_with(".sld") && library_zone(path.as_str()).is_some()
+}
+
+/// Zone of a tools-tree path relative to `root`: `shared`, `home/<ctx>` or
+/// `flocks/<name>`. `None` for paths outside the writable zones.
+fn zone_under<'a>(path: &'a str, root: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(root)?;
+    if rest.starts_with("shared/") {
+        return Some("shared");
+    }
+    let owner_zone = |prefix: &str| {
+        let owner = rest.strip_prefix(prefix)?.split('/').next()?;
+        (!owner.is_empty()).then(|| &rest[..prefix.len() + owner.len()])
+    };
+    owner_zone("home/").or_else(|| owner_zone("flocks/"))
+}
+
+/// Zone of a library path (`/tools/lib/home/alice/x.sld` → `home/alice`).
+fn library_zone(path: &str) -> Option<&str> {
+    zone_under(path, LIB_ROOT)
+}
+
+/// Zone of a tool path (`/tools/home/alice/x.scm` → `home/alice`).
+fn tool_zone(path: &str) -> Option<&str> {
+    if path.starts_with(LIB_ROOT) {
+        return None;
+    }
+    zone_under(path, "/tools/")
+}
+
+/// Library name from its file name (`/tools/lib/shared/strings.sld` → `strings`).
+fn library_name(path: &str) -> Option<&str> {
+    path.rsplit('/').next()?.strip_suffix(".sld")
+}
+
+/// Names of all `(lib <name>)` libraries referenced in `source`.
+///
+/// A textual scan rather than a parse: it may over-report (e.g. inside a
+/// comment), which at worst causes an unnecessary reload.
+pub fn imported_libraries(source: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut rest = source;
+    while let Some(idx) = rest.find("(lib") {
+        rest = &rest[idx + 4..];
+        let Some(after_ws) = rest.strip_prefix(|c: char| c.is_whitespace()) else {
+            continue;
+        };
+        let after_ws = after_ws.trim_start();
+        let end = after_ws
+            .find(|c: char| c.is_whitespace() || c == ')')
+            .unwrap_or(after_ws.len());
+        if end > 0 {
+            names.insert(after_ws[..end].to_string());
+        }
+    }
+    names
+}
+
+/// Library sources and the tool files that depend on them.
+///
+/// Held by `ToolRegistry`. Populated by the startup scan and kept current by
+/// the hot-reload callbacks in `synthesised`.
+#[derive(Debug, Default)]
+pub struct SchemeLibraries {
+    /// Library VFS path → source.
+    libraries: BTreeMap<String, String>,
+    /// Tool VFS path → source, for tool files that import at least one library.
+    /// Kept even when the tool failed to load, so a library written after the
+    /// tool can bring it up.
+    dependents: BTreeMap<String, String>,
+}
+
+impl SchemeLibraries {
+    /// Add or replace the library at `path`.
+    pub fn insert(&mut self, path: &VfsPath, source: String) {
+        self.libraries.insert(path.as_str().to_string(), source);
+    }
+
+    /// Remove the library at `path`, returning its source.
+    pub fn remove(&mut self, path: &VfsPath) -> Option<String> {
+        self.libraries.remove(path.as_str())
+    }
+
+    /// Number of known libraries.
+    pub fn len(&self) -> usize {
+        self.libraries.len()
+    }
+
+    /// Whether no libraries are known.
+    pub fn is_empty(&self) -> bool {
+        self.libraries.is_empty()
+    }
+
+    /// Libraries (path, source) that `source`, a tool file at `tool_path`,
+    /// imports directly or through other libraries, ordered by library name.
+    /// Zone libraries shadow `shared` libraries of the same name; libraries
+    /// the tool doesn't use are left out, so a broken one only affects its
+    /// importers.
+    pub fn resolve_imports(&self, tool_path: &VfsPath, source: &str) -> Vec<(String, String)> {
+        let zone = tool_zone(tool_path.as_str());
+        let mut by_name: BTreeMap<&str, (bool, &String, &String)> = BTreeMap::new();
+        for (path, library) in &self.libraries {
+            let (Some(lib_zone), Some(name)) = (library_zone(path), library_name(path)) else {
+                continue;
+            };
+            let specific = Some(lib_zone) == zone && lib_zone != "shared";
+            if lib_zone != "shared" && !specific {
+                continue;
+            }
+            match by_name.get(name) {
+                Some((true, _, _)) if !specific => {}
+                _ => {
+                    by_name.insert(name, (specific, path, library));
+                }
+            }
+        }
+
+        let mut wanted = BTreeSet::new();
+        let mut pending: Vec<String> = imported_libraries(source).into_iter().collect();
+        while let Some(name) = pending.pop() {
+            if let Some((_, _, library)) = by_name.get(name.as_str())
+                && wanted.insert(name)
+            {
+                pending.extend(imported_libraries(library));
+            }
+        }
+        wanted
+            .iter()
+            .filter_map(|name| by_name.get(name.as_str()))
+            .map(|(_, path, library)| ((*path).clone(), (*library).clone()))
+            .collect()
+    }
+
+    /// Record `source` for the tool file at `tool_path` if it imports any
+    /// library; otherwise forget it.
+    pub fn track_dependent(&mut self, tool_path: &VfsPath, source: &str) {
+        if imported_libraries(source).is_empty() {
+            self.dependents.remove(tool_path.as_str());
+        } else {
+            self.dependents
+                .insert(tool_path.as_str().to_string(), source.to_string());
+        }
+    }
+
+    /// Forget the tool file at `tool_path` (it was deleted).
+    pub fn forget_dependent(&mut self, tool_path: &VfsPath) {
+        self.dependents.remove(tool_path.as_str());
+    }
+
+    /// Tool files (path, source) affected by a change to the library at
+    /// `lib_path`, including those importing it indirectly through another
+    /// library in scope.
+    pub fn dependents_of(&self, lib_path: &VfsPath) -> Vec<(VfsPath, String)> {
+        let (Some(lib_zone), Some(name)) = (
+            library_zone(lib_path.as_str()),
+            library_name(lib_path.as_str()),
+        ) else {
+            return Vec::new();
+        };
+        let in_scope = |zone: Option<&str>| lib_zone == "shared" || zone == Some(lib_zone);
+
+        // Close over libraries that (transitively) import the changed one.
+        let mut affected = BTreeSet::from([name.to_string()]);
+        loop {
+            let before = affected.len();
+            for (path, source) in &self.libraries {
+                if let Some(other) = library_name(path)
+                    && in_scope(library_zone(path))
+                    && !affected.contains(other)
+                    && !imported_libraries(source).is_disjoint(&affected)
+                {
+                    affected.insert(other.to_string());
+                }
+            }
+            if affected.len() == before {
+                break;
+            }
+        }
+
+        self.dependents
+            .iter()
+            .filter(|(path, source)| {
+                in_scope(tool_zone(path)) && !imported_libraries(source).is_disjoint(&affected)
+            })
+            .filter_map(|(path, source)| Some((VfsPath::new(path).ok()?, source.clone())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(s: &str) -> VfsPath {
+        VfsPath::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_is_library_path() {
+        assert!(is_library_path(&p("/tools/lib/shared/strings.sld")));
+        assert!(is_library_path(&p("/tools/lib/home/alice/util.sld")));
+        assert!(is_library_path(&p("/tools/lib/flocks/dev/util.sld")));
+        assert!(!is_library_path(&p("/tools/lib/shared/strings.scm")));
+        assert!(!is_library_path(&p("/tools/lib/sys/strings.sld")));
+        assert!(!is_library_path(&p("/tools/shared/strings.sld")));
+    }
+
+    #[test]
+    fn test_imported_libraries() {
+        let src = "(import (scheme base) (lib strings))\n(import (lib  json-util ))";
+        let names: Vec<_> = imported_libraries(src).into_iter().collect();
+        assert_eq!(names, vec!["json-util", "strings"]);
+        assert!(imported_libraries("(define (library x) x)").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_imports_scopes_and_shadows() {
+        let mut libs = SchemeLibraries::default();
+        libs.insert(&p("/tools/lib/shared/strings.sld"), "shared-strings".into());
+        libs.insert(&p("/tools/lib/shared/json.sld"), "shared-json".into());
+        libs.insert(&p("/tools/lib/shared/unused.sld"), "shared-unused".into());
+        libs.insert(
+            &p("/tools/lib/home/alice/strings.sld"),
+            "alice-strings".into(),
+        );
+        libs.insert(
+            &p("/tools/lib/home/bob/extra.sld"),
+            "bob-extra (import (lib strings))".into(),
+        );
+
+        let src = "(import (lib json) (lib strings) (lib extra) (lib missing))";
+        let sources = |tool: &str| -> Vec<String> {
+            libs.resolve_imports(&p(tool), src)
+                .into_iter()
+                .map(|(_, s)| s)
+                .collect()
+        };
+        assert_eq!(
+            sources("/tools/home/alice/t.scm"),
+            vec!["shared-json", "alice-strings"]
+        );
+        assert_eq!(
+            sources("/tools/shared/t.scm"),
+            vec!["shared-json", "shared-strings"]
+        );
+        assert_eq!(
+            sources("/tools/home/bob/t.scm"),
+            vec![
+                "bob-extra (import (lib strings))",
+                "shared-json",
+                "shared-strings"
+            ]
+        );
+        assert_eq!(
+            libs.resolve_imports(&p("/tools/home/bob/t.scm"), "(import (lib extra))")
+                .len(),
+            2,
+            "extra pulls in strings transitively"
+        );
+    }
+
+    #[test]
+    fn test_dependents_of_follows_scope_and_transitive_imports() {
+        let mut libs = SchemeLibraries::default();
+        libs.insert(&p("/tools/lib/shared/strings.sld"), "(begin)".into());
+        libs.insert(
+            &p("/tools/lib/shared/text.sld"),
+            "(import (lib strings))".into(),
+        );
+        libs.track_dependent(&p("/tools/shared/a.scm"), "(import (lib text))");
+        libs.track_dependent(&p("/tools/home/alice/b.scm"), "(import (lib strings))");
+        libs.track_dependent(&p("/tools/home/bob/c.scm"), "(import (lib strings))");
+        libs.track_dependent(&p("/tools/shared/plain.scm"), "(define x 1)");
+
+        let paths = |lib: &str| -> Vec<String> {
+            libs.dependents_of(&p(lib))
+                .into_iter()
+                .map(|(path, _)| path.as_str().to_string())
+                .collect()
+        };
+        assert_eq!(
+            paths("/tools/lib/shared/strings.sld"),
+            vec![
+                "/tools/home/alice/b.scm",
+                "/tools/home/bob/c.scm",
+                "/tools/shared/a.scm"
+            ]
+        );
+        assert_eq!(
+            paths("/tools/lib/home/alice/strings.sld"),
+            vec!["/tools/home/alice/b.scm"]
+        );
+
+        libs.forget_dependent(&p("/tools/home/alice/b.scm"));
+        assert!(paths("/tools/lib/home/alice/strings.sld").is_empty());
+    }
+}
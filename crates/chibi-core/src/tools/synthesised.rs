@@ -46,6 +46,21 @@
 //! - `call-tool` — invoke another tool by name: `(call-tool "name" args-alist)`
 //! - `define-tool` — macro for declaring multiple tools in one file
 //!
+//! ## `(harness llm)` module
+//!
+//! Non-streaming model calls from scheme, in both tiers:
+//!
+//! - `llm-chat` — `(llm-chat messages [options])` → reply alist
+//! - `llm-complete` — `(llm-complete prompt [options])` → reply content
+//!
+//! Both go through `flow::complete`, the same path as `spawn_agent`.
+//!
+//! ## `(harness test)` module
+//!
+//! Assertions (`assert-equal`, `assert-true`, `assert-raises`) and mocks
+//! (`mock-tool`, `mock-file`, `mock-llm`, `tool-calls`) for tests declared
+//! with `define-test` and run by `chibi --test-tool`; see `tool_test.rs`.
+//!
 //! ## `call-tool` bridge
 //!
 //! `call-tool` bridges sync tein → async tokio dispatch via
@@ -277,6 +292,60 @@ const HARNESS_IO_MODULE: &str = r#"
   (begin #t))
 "#;
 
+/// Scheme source for the `(harness llm)` module (non-streaming model calls).
+///
+/// `%llm-request` is registered as a foreign function before this module is
+/// evaluated; `llm-chat` and `llm-complete` are thin scheme wrappers over it.
+/// Calls go through `flow::complete`, so presets resolve against
+/// `subagent_cost_tier` and the pre/post_spawn_agent hooks fire.
+///
+/// Mutation site: if the option keys change, update `LlmRequest::from_json`,
+/// the `harness-tools-docs` entries and `docs/plugins.md`.
+#[cfg(feature = "synthesised-tools")]
+const HARNESS_LLM_MODULE: &str = r#"
+(define-library (harness llm)
+  (import (scheme base))
+  (export llm-chat llm-complete)
+  (begin
+    (define (llm-chat messages . opts)
+      (%llm-request messages (if (pair? opts) (car opts) '())))
+    (define (llm-complete prompt . opts)
+      (let ((reply (llm-chat (list (list (cons "role" "user") (cons "content" prompt)))
+                             (if (pair? opts) (car opts) '()))))
+        (if (cdr (assoc "blocked" reply))
+            (error "llm-complete: call blocked by hook" (cdr (assoc "content" reply)))
+            (cdr (assoc "content" reply)))))))
+"#;
+
+/// Scheme source for the `(harness test)` module (tool tests).
+///
+/// `define-test` is NOT part of this library — like `define-tool` it lives in
+/// `HARNESS_PREAMBLE` so it can mutate the top-level `%test-registry%`. The
+/// `mock-*` and `tool-calls` foreign functions are registered before this
+/// module and re-exported; the assertions are plain scheme.
+///
+/// Mutation site: if the exported set changes, update the `harness-tools-docs`
+/// entries and `docs/plugins.md`.
+#[cfg(feature = "synthesised-tools")]
+const HARNESS_TEST_MODULE: &str = r#"
+(define-library (harness test)
+  (import (scheme base))
+  (export assert-equal assert-true assert-raises
+          mock-tool mock-tool-error mock-file mock-llm tool-calls)
+  (begin
+    (define (%assert-message msg default)
+      (if (pair? msg) (car msg) default))
+    (define (assert-equal expected actual . msg)
+      (if (not (equal? expected actual))
+          (error (%assert-message msg "assert-equal failed") expected actual)))
+    (define (assert-true value . msg)
+      (if (not value)
+          (error (%assert-message msg "assert-true failed") value)))
+    (define (assert-raises thunk . msg)
+      (if (guard (e (#t #f)) (thunk) #t)
+          (error (%assert-message msg "assert-raises: nothing was raised"))))))
+"#;
+
 /// Scheme source for the `(harness hooks)` module.
 ///
 /// `register-hook` is defined at top level in `HARNESS_PREAMBLE` so it can
@@ -354,10 +423,14 @@ pub(crate) static HARNESS_PREAMBLE: std::sync::LazyLock<String> = std::sync::Laz
 (define %tool-registry% '())
 
 ;; accumulates hook registrations. each entry is a list:
-;; (hook-name-string handler-procedure)
+;; (hook-name-string handler-procedure priority-integer)
 ;; rust reads %hook-registry% after evaluation to populate Tool.hooks.
 (define %hook-registry% '())
 
+;; accumulates define-test entries. each entry is a list:
+;; (name-string thunk). only the test-tool runner reads it.
+(define %test-registry% '())
+
 ;; name of the calling context — mutated by execute_synthesised before each call.
 ;; plugins read this to resolve /home/<ctx>/... VFS paths.
 (define %context-name% "")
@@ -370,7 +443,17 @@ pub(crate) static HARNESS_PREAMBLE: std::sync::LazyLock<String> = std::sync::Laz
   '((__module__ . "harness tools")
     (define-tool . "macro: (define-tool name (description DESC) [(category CAT)] [(summary-params (PARAM ...))] (parameters PARAMS-ALIST) (execute (lambda (args) ...))) — registers a persistent tool; args is ((\"key\" . val) ...) alist. Optional: category is a string like \"network\" or \"shell\"; summary-params is a list of parameter names used to build the permission-prompt summary for network tools without a URL parameter.")
     (call-tool . "procedure: (call-tool NAME ARGS-ALIST) -> string — invoke another registered tool; NAME is a string, ARGS-ALIST is ((\"key\" . \"val\") ...)")
-    (register-hook . "procedure: (register-hook HOOK-SYMBOL HANDLER) — register a hook callback; HOOK-SYMBOL e.g. 'pre_vfs_write, HANDLER is (lambda (payload) ...)")
+    (register-hook . "procedure: (register-hook HOOK-SYMBOL HANDLER [PRIORITY]) — register a hook callback; HOOK-SYMBOL e.g. 'pre_vfs_write, HANDLER is (lambda (payload) ...); optional integer PRIORITY (default 0) orders handlers, lower runs first")
+    (llm-chat . "procedure: (llm-chat MESSAGES [OPTIONS]) -> alist — from (harness llm); MESSAGES is a list of ((\"role\" . ROLE) (\"content\" . TEXT)) alists; returns ((\"content\" . REPLY) (\"model\" . MODEL) (\"blocked\" . BOOL)); OPTIONS alist keys: \"system\", \"model\", \"preset\", \"temperature\", \"max_tokens\", \"schema\" (JSON schema as alist or JSON string — REPLY is the parsed value), \"json\" (#t for any JSON object)")
+    (llm-complete . "procedure: (llm-complete PROMPT [OPTIONS]) -> string or value — from (harness llm); single user message, returns the reply (parsed when \"schema\" or \"json\" is set); raises if a pre_spawn_agent hook blocks the call")
+    (define-test . "macro: (define-test NAME BODY ...) — declares a test run by chibi --test-tool; NAME is a string. Loading only records it. Use (harness test) for assertions and mocks")
+    (assert-equal . "procedure: (assert-equal EXPECTED ACTUAL [MESSAGE]) — from (harness test); fails the test unless (equal? EXPECTED ACTUAL)")
+    (assert-true . "procedure: (assert-true VALUE [MESSAGE]) — from (harness test); fails the test if VALUE is #f")
+    (assert-raises . "procedure: (assert-raises THUNK [MESSAGE]) — from (harness test); fails the test unless calling THUNK raises")
+    (mock-tool . "procedure: (mock-tool NAME REPLY) — from (harness test); call-tool NAME returns the string REPLY for the rest of the test. Unmocked tools raise. (mock-tool-error NAME MESSAGE) makes it raise MESSAGE")
+    (mock-file . "procedure: (mock-file PATH CONTENT) — from (harness test); seeds the in-memory file map (harness io) uses during tests")
+    (mock-llm . "procedure: (mock-llm REPLY) — from (harness test); (harness llm) calls return REPLY (parsed if JSON was requested)")
+    (tool-calls . "procedure: (tool-calls NAME) -> list — from (harness test); argument alists of every call-tool NAME so far in this test")
     (generate-id . "procedure: (generate-id) -> string — returns an 8-hex-char random identifier (uuid v4 prefix)")
     (current-timestamp . "procedure: (current-timestamp) -> string — returns current UTC time as \"YYYYMMDD-HHMMz\"")))
 
@@ -421,13 +504,24 @@ pub(crate) static HARNESS_PREAMBLE: std::sync::LazyLock<String> = std::sync::Laz
        (cons (list (symbol->string 'name) desc params handler cat sp)
              %tool-registry%)))))
 
+;; declares a test: the body runs later, under chibi --test-tool, with
+;; call-tool, (harness io) and (harness llm) mocked. loading only records it.
+(define-syntax define-test
+  (syntax-rules ()
+    ((define-test name body ...)
+     (set! %test-registry%
+       (cons (list name (lambda () body ...)) %test-registry%)))))
+
 ;; registers a hook handler for a given hook point.
 ;; hook-name is a symbol (e.g. 'pre_vfs_write).
 ;; handler is a procedure taking one argument (the hook payload as an alist)
 ;; and returning an alist (or '() for no-op).
-(define (register-hook hook-name handler)
+;; optional priority is an integer (default 0); lower runs first.
+(define (register-hook hook-name handler . priority)
   (set! %hook-registry%
-    (cons (list (symbol->string hook-name) handler)
+    (cons (list (symbol->string hook-name)
+                handler
+                (if (pair? priority) (car priority) 0))
           %hook-registry%)))
 "#,
         hooks_docs_alist = hooks_docs_alist,
@@ -459,6 +553,9 @@ struct ActiveCallContext {
     /// Shared tool registry. Used by `call_tool_fn` for per-call dispatch.
     /// Embedded per-call so concurrent tests never overwrite each other's registry.
     registry: Arc<RwLock<ToolRegistry>>,
+    /// Set while a `test-tool` test runs: `call-tool`, `(harness io)` and
+    /// `(harness llm)` answer from its mocks instead of the real world.
+    test_harness: Option<Arc<crate::tools::tool_test::ToolTestHarness>>,
 }
 
 // SAFETY: the pointers in `ActiveCallContext` are only dereferenced on the
@@ -506,11 +603,27 @@ impl CallContextGuard {
                 },
                 runtime_handle: tokio::runtime::Handle::current(),
                 registry,
+                test_harness: None,
             },
         );
         CallContextGuard { thread_id }
     }
 
+    /// Set for a `test-tool` test: like `set`, with `harness` standing in for
+    /// tool dispatch, IO and model calls.
+    pub(crate) fn set_for_test(
+        ctx: &crate::tools::registry::ToolCallContext<'_>,
+        registry: Arc<RwLock<ToolRegistry>>,
+        thread_id: std::thread::ThreadId,
+        harness: Arc<crate::tools::tool_test::ToolTestHarness>,
+    ) -> Self {
+        let guard = Self::set(ctx, registry, thread_id);
+        if let Some(active) = BRIDGE_CALL_CTX.lock().unwrap().get_mut(&thread_id) {
+            active.test_harness = Some(harness);
+        }
+        guard
+    }
+
     /// Set from a `TeinHookContext` — used during hook dispatch to enable
     /// `call-tool` and `(harness io)` from tein hook callbacks.
     ///
@@ -530,6 +643,7 @@ impl CallContextGuard {
                 vfs_caller_context: String::new(), // System caller for hook dispatch
                 runtime_handle: tokio::runtime::Handle::current(),
                 registry: Arc::clone(&ctx.registry),
+                test_harness: None,
             },
         );
         CallContextGuard {
@@ -545,6 +659,17 @@ impl Drop for CallContextGuard {
     }
 }
 
+/// The test harness for the current tein worker thread, if a `test-tool`
+/// test is running on it.
+#[cfg(feature = "synthesised-tools")]
+fn active_test_harness() -> Option<Arc<crate::tools::tool_test::ToolTestHarness>> {
+    let tid = std::thread::current().id();
+    let guard = BRIDGE_CALL_CTX.lock().unwrap();
+    guard
+        .get(&tid)
+        .and_then(|active| active.test_harness.clone())
+}
+
 // --- call-tool foreign function bridge ---------------------------------------
 
 /// The `call-tool` foreign function: `(call-tool name args-alist) → string`
@@ -562,6 +687,9 @@ impl Drop for CallContextGuard {
 #[tein::tein_fn(name = "call-tool")]
 fn call_tool_fn(name: String, args: Value) -> Result<String, String> {
     let json_args = scheme_value_to_json(&args).map_err(|e| format!("args conversion: {e}"))?;
+    if let Some(harness) = active_test_harness() {
+        return harness.call_tool(&name, &json_args);
+    }
 
     // Extract all needed data from the per-thread context while holding the lock,
     // then drop the lock before dispatching (which may block_on async code).
@@ -656,6 +784,247 @@ fn current_timestamp_fn() -> String {
     format!("{:04}{:02}{:02}-{:02}{:02}z", y, mo, d, h, mi)
 }
 
+// --- harness llm foreign function bridge -------------------------------------
+
+/// A `(harness llm)` request decoded from its scheme arguments.
+#[cfg(feature = "synthesised-tools")]
+#[derive(Debug)]
+struct LlmRequest {
+    messages: Vec<serde_json::Value>,
+    options: crate::tools::SpawnOptions,
+    response_format: Option<crate::config::ResponseFormat>,
+}
+
+#[cfg(feature = "synthesised-tools")]
+impl LlmRequest {
+    /// Decode `messages` (a JSON array of `{role, content}` objects) and the
+    /// options object. A `"system"` option becomes a leading system message;
+    /// `"schema"` (an object or a JSON string) selects a JSON-schema response
+    /// format and `"json": true` a plain JSON object one. `null` options mean
+    /// no options.
+    fn from_json(messages: serde_json::Value, options: serde_json::Value) -> io::Result<Self> {
+        use crate::json_ext::JsonExt;
+
+        let options = match options {
+            serde_json::Value::Null => serde_json::json!({}),
+            serde_json::Value::Object(_) => options,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("options must be an alist, got {other}"),
+                ));
+            }
+        };
+        let serde_json::Value::Array(items) = messages else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "messages must be a non-empty list of alists",
+            ));
+        };
+        if items.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "messages must be a non-empty list of alists",
+            ));
+        }
+
+        let mut out = Vec::with_capacity(items.len() + 1);
+        if let Some(system) = options.get_str("system") {
+            out.push(serde_json::json!({ "role": "system", "content": system }));
+        }
+        for item in items {
+            let (Some(role), Some(content)) = (item.get_str("role"), item.get_str("content"))
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("message needs string \"role\" and \"content\": {item}"),
+                ));
+            };
+            out.push(serde_json::json!({ "role": role, "content": content }));
+        }
+
+        // A schema may be an alist or a JSON string; the string form avoids
+        // the alist ambiguity around nested single-key objects.
+        let schema = match options.get("schema") {
+            Some(serde_json::Value::String(text)) => {
+                Some(serde_json::from_str(text).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("schema is not valid JSON: {e}"),
+                    )
+                })?)
+            }
+            Some(serde_json::Value::Null) | None => None,
+            Some(schema) => Some(schema.clone()),
+        };
+        let response_format = match schema {
+            Some(schema) => Some(crate::config::ResponseFormat::JsonSchema {
+                json_schema: Some(schema),
+            }),
+            _ if options.get_bool_or("json", false) => {
+                Some(crate::config::ResponseFormat::JsonObject)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            messages: out,
+            options: crate::tools::SpawnOptions::from_args(&options),
+            response_format,
+        })
+    }
+}
+
+/// Shape a completion into the `llm-chat` reply as JSON. JSON-format replies
+/// are parsed so scheme receives a value rather than a string; blocked calls
+/// keep the hook's message as-is.
+#[cfg(feature = "synthesised-tools")]
+fn llm_reply_json(
+    completion: crate::tools::Completion,
+    structured: bool,
+) -> io::Result<serde_json::Value> {
+    let content = if structured && !completion.blocked {
+        serde_json::from_str(&completion.content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "model reply is not valid JSON ({e}): {}",
+                    completion.content
+                ),
+            )
+        })?
+    } else {
+        serde_json::Value::String(completion.content)
+    };
+    Ok(serde_json::json!({
+        "content": content,
+        "model": completion.model,
+        "blocked": completion.blocked,
+    }))
+}
+
+/// `(%llm-request messages options)` — the foreign function behind
+/// `llm-chat`. Returns a `(("content" . ...) ("model" . ...) ("blocked" . ...))`
+/// alist.
+///
+/// Hooks are collected from the bridge registry, skipping tools that share
+/// the caller's tein context: their worker thread is busy running this call,
+/// so dispatching a hook into it would deadlock.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "%llm-request")]
+fn llm_request_fn(messages: Value, options: Value) -> Result<Value, String> {
+    let messages = scheme_value_to_json(&messages).map_err(|e| format!("llm: messages: {e}"))?;
+    let options = scheme_value_to_json(&options).map_err(|e| format!("llm: options: {e}"))?;
+    let request = LlmRequest::from_json(messages, options).map_err(|e| format!("llm: {e}"))?;
+    let structured = request.response_format.is_some();
+
+    if let Some(harness) = active_test_harness() {
+        let content = harness
+            .llm_reply()
+            .ok_or("llm: no model in tests (declare a reply with mock-llm)")?;
+        let completion = crate::tools::Completion {
+            content,
+            model: request.options.model.unwrap_or_default(),
+            blocked: false,
+        };
+        let reply = llm_reply_json(completion, structured).map_err(|e| format!("llm: {e}"))?;
+        return tein::json_value_to_value(reply).map_err(|e| format!("llm: reply conversion: {e}"));
+    }
+
+    let (config_ptr, runtime_handle, registry) = {
+        let tid = std::thread::current().id();
+        let guard = BRIDGE_CALL_CTX.lock().unwrap();
+        let active = guard.get(&tid).ok_or_else(|| {
+            "llm: no active call context (called outside tool execute or hook dispatch?)"
+                .to_string()
+        })?;
+        (
+            active.config,
+            active.runtime_handle.clone(),
+            Arc::clone(&active.registry),
+        )
+    };
+
+    let tools: Vec<Tool> = {
+        let caller = std::thread::current().id();
+        let reg = registry.read().map_err(|e| format!("registry lock: {e}"))?;
+        reg.all()
+            .filter(|t| {
+                !matches!(&t.r#impl, ToolImpl::Synthesised { worker_thread_id, .. }
+                    if *worker_thread_id == caller)
+            })
+            .cloned()
+            .collect()
+    };
+
+    // SAFETY: config_ptr comes from BRIDGE_CALL_CTX, set by CallContextGuard for
+    // the duration of execute_synthesised or hook dispatch.
+    let config = unsafe { &*config_ptr };
+    let completion = runtime_handle
+        .block_on(crate::tools::complete(
+            config,
+            &request.messages,
+            &request.options,
+            request.response_format.as_ref(),
+            &tools,
+        ))
+        .map_err(|e| format!("llm: {e}"))?;
+    let reply = llm_reply_json(completion, structured).map_err(|e| format!("llm: {e}"))?;
+    tein::json_value_to_value(reply).map_err(|e| format!("llm: reply conversion: {e}"))
+}
+
+// --- harness test foreign function bridge ------------------------------------
+
+/// The running test's harness, or an error naming `what` outside a test run.
+#[cfg(feature = "synthesised-tools")]
+fn require_test_harness(
+    what: &str,
+) -> Result<Arc<crate::tools::tool_test::ToolTestHarness>, String> {
+    active_test_harness()
+        .ok_or_else(|| format!("{what}: only available in tests run by chibi --test-tool"))
+}
+
+/// `(mock-tool name reply)` — `call-tool name` returns `reply` for this test.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "mock-tool")]
+fn mock_tool_fn(name: String, reply: String) -> Result<Value, String> {
+    require_test_harness("mock-tool")?.mock_tool(&name, Ok(reply));
+    Ok(Value::Boolean(true))
+}
+
+/// `(mock-tool-error name message)` — `call-tool name` raises `message`.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "mock-tool-error")]
+fn mock_tool_error_fn(name: String, message: String) -> Result<Value, String> {
+    require_test_harness("mock-tool-error")?.mock_tool(&name, Err(message));
+    Ok(Value::Boolean(true))
+}
+
+/// `(mock-file path content)` — seed the test's in-memory file map.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "mock-file")]
+fn mock_file_fn(path: String, content: String) -> Result<Value, String> {
+    require_test_harness("mock-file")?.write_file(&path, &content);
+    Ok(Value::Boolean(true))
+}
+
+/// `(mock-llm reply)` — `(harness llm)` calls answer with `reply`.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "mock-llm")]
+fn mock_llm_fn(reply: String) -> Result<Value, String> {
+    require_test_harness("mock-llm")?.mock_llm(&reply);
+    Ok(Value::Boolean(true))
+}
+
+/// `(tool-calls name)` — argument alists of each `call-tool name` so far.
+#[cfg(feature = "synthesised-tools")]
+#[tein::tein_fn(name = "tool-calls")]
+fn tool_calls_fn(name: String) -> Result<Value, String> {
+    let calls = require_test_harness("tool-calls")?.calls_to(&name);
+    tein::json_value_to_value(serde_json::Value::Array(calls))
+        .map_err(|e| format!("tool-calls: {e}"))
+}
+
 // --- harness io foreign function bridge --------------------------------------
 
 /// Helper: extract VFS handle and runtime handle from `BRIDGE_CALL_CTX` for the
@@ -683,6 +1052,11 @@ fn io_bridge_ctx() -> Result<(tokio::runtime::Handle, *const Vfs), String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-read")]
 fn io_read_fn(path: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        return Ok(harness
+            .read_file(&path)
+            .map_or(Value::Boolean(false), Value::String));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -713,6 +1087,10 @@ fn io_read_fn(path: String) -> Result<Value, String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-write")]
 fn io_write_fn(path: String, data: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        harness.write_file(&path, &data);
+        return Ok(Value::Boolean(true));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -741,6 +1119,10 @@ fn io_write_fn(path: String, data: String) -> Result<Value, String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-append")]
 fn io_append_fn(path: String, data: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        harness.append_file(&path, &data);
+        return Ok(Value::Boolean(true));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -775,6 +1157,10 @@ fn io_append_fn(path: String, data: String) -> Result<Value, String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-list")]
 fn io_list_fn(path: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        let names = harness.list(&path).into_iter().map(Value::String);
+        return Ok(Value::List(names.collect()));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -817,6 +1203,9 @@ fn io_list_fn(path: String) -> Result<Value, String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-exists?")]
 fn io_exists_fn(path: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        return Ok(Value::Boolean(harness.exists(&path)));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -839,6 +1228,10 @@ fn io_exists_fn(path: String) -> Result<Value, String> {
 #[cfg(feature = "synthesised-tools")]
 #[tein::tein_fn(name = "io-delete")]
 fn io_delete_fn(path: String) -> Result<Value, String> {
+    if let Some(harness) = active_test_harness() {
+        harness.delete_file(&path)?;
+        return Ok(Value::Boolean(true));
+    }
     let (handle, vfs_ptr) = io_bridge_ctx()?;
     if let Some(vfs_path_str) = path.strip_prefix("vfs://") {
         let vp = VfsPath::new(vfs_path_str).map_err(|e| e.to_string())?;
@@ -885,6 +1278,10 @@ fn secs_to_ymdhmz(mut s: u64) -> (u32, u32, u32, u32, u32) {
 /// Build a `TeinSession` for a synthesised tool, registering
 /// `call-tool`, the harness preamble, and `(harness tools)` module.
 ///
+/// `libraries` are `(vfs path, source)` pairs of the `(lib ...)` scheme
+/// libraries the source imports, registered after the harness modules so they
+/// run under the same tier.
+///
 /// Returns the session and the tein worker thread's `ThreadId` (captured
 /// during init). The thread ID is the key for `BRIDGE_CALL_CTX` lookups.
 ///
@@ -897,6 +1294,7 @@ fn build_tein_context(
     tier: crate::config::SandboxTier,
     http_prefixes: Option<Vec<String>>,
     env_vars: Option<Vec<(String, String)>>,
+    libraries: Vec<(String, String)>,
 ) -> io::Result<(TeinSession, std::thread::ThreadId)> {
     let worker_thread_id = Arc::new(std::sync::Mutex::new(None::<std::thread::ThreadId>));
     let tid_capture = Arc::clone(&worker_thread_id);
@@ -921,6 +1319,18 @@ fn build_tein_context(
             .map_err(|e| tein::Error::EvalError(format!("harness hooks module: {e}")))?;
         ctx.register_module(HARNESS_DOCS_MODULE)
             .map_err(|e| tein::Error::EvalError(format!("harness docs module: {e}")))?;
+        // (harness test) — assertions and mocks for chibi --test-tool, both tiers.
+        ctx.define_fn_variadic("mock-tool", __tein_mock_tool_fn)?;
+        ctx.define_fn_variadic("mock-tool-error", __tein_mock_tool_error_fn)?;
+        ctx.define_fn_variadic("mock-file", __tein_mock_file_fn)?;
+        ctx.define_fn_variadic("mock-llm", __tein_mock_llm_fn)?;
+        ctx.define_fn_variadic("tool-calls", __tein_tool_calls_fn)?;
+        ctx.register_module(HARNESS_TEST_MODULE)
+            .map_err(|e| tein::Error::EvalError(format!("harness test module: {e}")))?;
+        // (harness llm) — model calls via flow::complete, both tiers.
+        ctx.define_fn_variadic("%llm-request", __tein_llm_request_fn)?;
+        ctx.register_module(HARNESS_LLM_MODULE)
+            .map_err(|e| tein::Error::EvalError(format!("harness llm module: {e}")))?;
         // (harness io) — privileged direct IO, available at Unsandboxed tier only.
         // Sandboxed contexts will get a module-not-found error on (import (harness io)).
         if tier == crate::config::SandboxTier::Unsandboxed {
@@ -933,6 +1343,11 @@ fn build_tein_context(
             ctx.register_module(HARNESS_IO_MODULE)
                 .map_err(|e| tein::Error::EvalError(format!("harness io module: {e}")))?;
         }
+        // (lib ...) — user libraries from /tools/lib/, visible per the tool's zone.
+        for (path, library) in &libraries {
+            ctx.register_module(library)
+                .map_err(|e| tein::Error::EvalError(format!("library {path}: {e}")))?;
+        }
         // Standard prelude — must run after all modules are registered (EVAL_PRELUDE
         // imports (harness tools), which is only available post-register_module).
         ctx.evaluate(EVAL_PRELUDE)?;
@@ -1010,6 +1425,7 @@ pub(crate) fn build_sandboxed_harness_context() -> io::Result<(TeinSession, std:
         crate::config::SandboxTier::Sandboxed,
         None,
         None,
+        Vec::new(),
     )
 }
 
@@ -1064,8 +1480,57 @@ pub fn load_tools_from_source(
     registry: &Arc<RwLock<ToolRegistry>>,
     tools_config: &crate::config::ToolsConfig,
 ) -> io::Result<Vec<Tool>> {
+    let (session, worker_thread_id) = build_tool_session(source, vfs_path, registry, tools_config)?;
+
+    // check if define-tool was used (%tool-registry% is non-empty list)
+    let multi = session.evaluate("%tool-registry%").ok();
+    let is_multi = matches!(
+        &multi,
+        Some(Value::List(items)) if !items.is_empty()
+    );
+
+    if is_multi {
+        extract_multi_tools(session, vfs_path, registry, worker_thread_id)
+    } else {
+        extract_single_tool(session, vfs_path, registry, worker_thread_id).map(|t| vec![t])
+    }
+}
+
+/// Evaluate a tool file's source in a tein context built for its VFS path:
+/// sandbox tier, env vars and HTTP allowlist all resolve from `tools_config`,
+/// and the `(lib ...)` libraries visible from the path come from `registry`.
+///
+/// Shared by `load_tools_from_source` and the `test-tool` runner, so tests
+/// run under exactly the configuration the tool runs under.
+#[cfg(feature = "synthesised-tools")]
+pub(crate) fn build_tool_session(
+    source: &str,
+    vfs_path: &VfsPath,
+    registry: &Arc<RwLock<ToolRegistry>>,
+    tools_config: &crate::config::ToolsConfig,
+) -> io::Result<(TeinSession, std::thread::ThreadId)> {
     let source_owned = source.to_string();
+    let libraries = registry
+        .read()
+        .unwrap()
+        .scheme_libs()
+        .resolve_imports(vfs_path, source);
     let tier = tools_config.resolve_tier(vfs_path.as_str());
+    // Libraries run inside the tool's context, at the tool's tier. Any context
+    // can write a shared library, so a library must be trusted at least as
+    // much as the tool importing it.
+    if let Some((path, _)) = libraries
+        .iter()
+        .find(|(path, _)| tools_config.resolve_tier(path) < tier)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{vfs_path} runs unsandboxed and cannot import {path}, which is sandboxed \
+                 (raise its tier in [tools.tiers] to allow this)"
+            ),
+        ));
+    }
     let env_vars = tools_config.resolve_env(vfs_path.as_str());
 
     let http_result = tools_config.resolve_http_allow(vfs_path.as_str());
@@ -1074,8 +1539,13 @@ pub fn load_tools_from_source(
         _ => None, // NeedDeclared resolved after phase 1
     };
 
-    let (session, worker_thread_id) =
-        build_tein_context(source_owned.clone(), tier, http_prefixes, env_vars.clone())?;
+    let (session, worker_thread_id) = build_tein_context(
+        source_owned.clone(),
+        tier,
+        http_prefixes,
+        env_vars.clone(),
+        libraries.clone(),
+    )?;
 
     // Phase 2: trust-declared HTTP — read tool-http-allow, rebuild if needed
     let (session, worker_thread_id) =
@@ -1099,26 +1569,20 @@ pub fn load_tools_from_source(
                 tools_config.resolve_http_allow_with_declared(vfs_path.as_str(), &declared)
             {
                 // Rebuild with the trusted prefixes
-                build_tein_context(source_owned, tier, Some(trusted_prefixes), env_vars)?
+                build_tein_context(
+                    source_owned,
+                    tier,
+                    Some(trusted_prefixes),
+                    env_vars,
+                    libraries,
+                )?
             } else {
                 (session, worker_thread_id)
             }
         } else {
             (session, worker_thread_id)
         };
-
-    // check if define-tool was used (%tool-registry% is non-empty list)
-    let multi = session.evaluate("%tool-registry%").ok();
-    let is_multi = matches!(
-        &multi,
-        Some(Value::List(items)) if !items.is_empty()
-    );
-
-    if is_multi {
-        extract_multi_tools(session, vfs_path, registry, worker_thread_id)
-    } else {
-        extract_single_tool(session, vfs_path, registry, worker_thread_id).map(|t| vec![t])
-    }
+    Ok((session, worker_thread_id))
 }
 
 /// Load a single synthesised tool from scheme source (convenience wrapper).
@@ -1201,13 +1665,18 @@ fn extract_single_tool(
         })
         .unwrap_or_default();
 
-    let (hooks, hook_bindings) = extract_hook_registrations(&session)?;
+    let HookRegistrations {
+        hooks,
+        priorities,
+        bindings: hook_bindings,
+    } = extract_hook_registrations(&session)?;
     let context = Arc::new(session);
     Ok(Tool {
         name,
         description,
         parameters,
         hooks,
+        hook_priorities: priorities,
         metadata: ToolMetadata::new(),
         summary_params,
         r#impl: ToolImpl::Synthesised {
@@ -1251,7 +1720,11 @@ fn extract_multi_tools(
         }
     };
 
-    let (hooks, hook_bindings) = extract_hook_registrations(&session)?;
+    let HookRegistrations {
+        hooks,
+        priorities,
+        bindings: hook_bindings,
+    } = extract_hook_registrations(&session)?;
     let context = Arc::new(session);
     let mut tools = Vec::with_capacity(entries.len());
 
@@ -1342,6 +1815,7 @@ fn extract_multi_tools(
             description,
             parameters,
             hooks: hooks.clone(),
+            hook_priorities: priorities.clone(),
             metadata: ToolMetadata::new(),
             summary_params,
             r#impl: ToolImpl::Synthesised {
@@ -1415,6 +1889,80 @@ pub async fn execute_synthesised(
     unreachable!("synthesised-tools feature not enabled")
 }
 
+/// Run the `define-test` tests declared in one tool file.
+///
+/// The file is evaluated in a fresh session built by `build_tool_session`, so
+/// tests see the tool's own sandbox tier. Each test gets a fresh
+/// `ToolTestHarness`, installed via `CallContextGuard::set_for_test`. A file
+/// that fails to evaluate yields a single failing `load` result.
+#[cfg(feature = "synthesised-tools")]
+pub(crate) fn run_file_tests(
+    source: &str,
+    file: &VfsPath,
+    tools_config: &crate::config::ToolsConfig,
+    call_ctx: &crate::tools::registry::ToolCallContext<'_>,
+    registry: &Arc<RwLock<ToolRegistry>>,
+) -> Vec<crate::tools::tool_test::ToolTestResult> {
+    use crate::tools::tool_test::{ToolTestHarness, ToolTestResult};
+
+    let outcome = |name: &str, error: Option<String>, stdout: String| ToolTestResult {
+        file: file.as_str().to_string(),
+        name: name.to_string(),
+        passed: error.is_none(),
+        message: error,
+        stdout,
+    };
+
+    let (session, worker_thread_id) = match build_tool_session(source, file, registry, tools_config)
+    {
+        Ok(built) => built,
+        Err(e) => return vec![outcome("load", Some(e.to_string()), String::new())],
+    };
+    let tests = match session.evaluate("%test-registry%") {
+        Ok(Value::List(items)) => items,
+        _ => Vec::new(),
+    };
+    let ctx_name_escaped = scheme_escape_string(call_ctx.context_name);
+
+    // %test-registry% is consed, so declaration order is the reverse.
+    tests
+        .into_iter()
+        .rev()
+        .map(|entry| {
+            let (name, thunk) = match entry {
+                Value::List(mut parts) if parts.len() == 2 => {
+                    let thunk = parts.pop().unwrap_or(Value::Nil);
+                    let name = parts[0].as_string().unwrap_or("<unnamed>").to_string();
+                    (name, thunk)
+                }
+                other => {
+                    let message = format!("malformed %test-registry% entry: {other}");
+                    return outcome("<unnamed>", Some(message), String::new());
+                }
+            };
+            let harness = Arc::new(ToolTestHarness::default());
+            let _guard = CallContextGuard::set_for_test(
+                call_ctx,
+                Arc::clone(registry),
+                worker_thread_id,
+                harness,
+            );
+            if let Err(e) =
+                session.evaluate(&format!("(set! %context-name% \"{ctx_name_escaped}\")"))
+            {
+                return outcome(
+                    &name,
+                    Some(format!("inject %context-name%: {e}")),
+                    String::new(),
+                );
+            }
+            let captured = session.with_capture(|ctx| ctx.call(&thunk, &[]));
+            let error = captured.value.err().map(|e| e.to_string());
+            outcome(&name, error, captured.stdout)
+        })
+        .collect()
+}
+
 // --- startup scan ------------------------------------------------------------
 
 /// Scan writable VFS zones for `.scm` tool files and register them.
@@ -1430,38 +1978,79 @@ pub async fn execute_synthesised(
 /// - `/tools/shared` — globally shared tools
 /// - `/tools/home/<ctx>/` — per-context tools (one dir per context name)
 /// - `/tools/flocks/<name>/` — flock-scoped tools
+///
+/// The matching `/tools/lib/<zone>/` directories are scanned first, so `.sld`
+/// libraries are known before any tool importing them is loaded.
 #[cfg(feature = "synthesised-tools")]
 pub async fn scan_and_register(
     vfs: &Vfs,
     registry: &Arc<RwLock<ToolRegistry>>,
     tools_config: &crate::config::ToolsConfig,
 ) -> io::Result<()> {
-    let mut zones = vec!["/tools/shared".to_string()];
-
-    // discover /tools/home/<ctx>/ directories
-    if let Ok(entries) = vfs
-        .list(VfsCaller::System, &VfsPath::new("/tools/home")?)
-        .await
-    {
-        for entry in entries {
-            zones.push(format!("/tools/home/{}", entry.name));
-        }
+    for zone in &discover_zones(vfs, "/tools/lib").await? {
+        scan_libraries(vfs, registry, zone).await;
+    }
+    for zone in &discover_zones(vfs, "/tools").await? {
+        scan_zone(vfs, registry, zone, tools_config).await?;
     }
+    Ok(())
+}
 
-    // discover /tools/flocks/<name>/ directories
-    if let Ok(entries) = vfs
-        .list(VfsCaller::System, &VfsPath::new("/tools/flocks")?)
-        .await
-    {
-        for entry in entries {
-            zones.push(format!("/tools/flocks/{}", entry.name));
+/// List the writable zone directories under `root`: `<root>/shared`,
+/// `<root>/home/<ctx>` and `<root>/flocks/<name>`.
+#[cfg(feature = "synthesised-tools")]
+async fn discover_zones(vfs: &Vfs, root: &str) -> io::Result<Vec<String>> {
+    let mut zones = vec![format!("{root}/shared")];
+
+    // discover <root>/home/<ctx>/ and <root>/flocks/<name>/ directories
+    for owners in ["home", "flocks"] {
+        if let Ok(entries) = vfs
+            .list(
+                VfsCaller::System,
+                &VfsPath::new(&format!("{root}/{owners}"))?,
+            )
+            .await
+        {
+            for entry in entries {
+                zones.push(format!("{root}/{owners}/{}", entry.name));
+            }
         }
     }
+    Ok(zones)
+}
 
-    for zone in &zones {
-        scan_zone(vfs, registry, zone, tools_config).await?;
+/// Scan a library zone directory (recursively) and store every `.sld` file
+/// found in the registry's scheme library store.
+#[cfg(feature = "synthesised-tools")]
+async fn scan_libraries(vfs: &Vfs, registry: &Arc<RwLock<ToolRegistry>>, dir: &str) {
+    let Ok(dir_path) = VfsPath::new(dir) else {
+        return;
+    };
+    let Ok(entries) = vfs.list(VfsCaller::System, &dir_path).await else {
+        return; // missing or unreadable — skip silently
+    };
+    for entry in entries {
+        let child = format!("{dir}/{}", entry.name);
+        if entry.kind == VfsEntryKind::Directory {
+            Box::pin(scan_libraries(vfs, registry, &child)).await;
+            continue;
+        }
+        let Ok(file_path) = VfsPath::new(&child) else {
+            continue;
+        };
+        if !crate::tools::scheme_lib::is_library_path(&file_path) {
+            continue;
+        }
+        if let Ok(bytes) = vfs.read(VfsCaller::System, &file_path).await
+            && let Ok(source) = String::from_utf8(bytes)
+        {
+            registry
+                .write()
+                .unwrap()
+                .scheme_libs_mut()
+                .insert(&file_path, source);
+        }
     }
-    Ok(())
 }
 
 /// Scan a single VFS zone directory and register all `.scm` tool files found.
@@ -1506,6 +2095,11 @@ async fn scan_zone(
         let Ok(source_str) = String::from_utf8(source) else {
             continue;
         };
+        registry
+            .write()
+            .unwrap()
+            .scheme_libs_mut()
+            .track_dependent(&file_path, &source_str);
         if let Ok(tools) = load_tools_from_source(&source_str, &file_path, registry, tools_config) {
             let mut reg = registry.write().unwrap();
             for tool in tools {
@@ -1540,6 +2134,11 @@ pub fn reload_tool_from_content(
     let Ok(source_str) = std::str::from_utf8(content) else {
         return;
     };
+    registry
+        .write()
+        .unwrap()
+        .scheme_libs_mut()
+        .track_dependent(path, source_str);
     if let Ok(tools) = load_tools_from_source(source_str, path, registry, tools_config) {
         let mut reg = registry.write().unwrap();
         // unregister all previous tools from this path
@@ -1561,12 +2160,44 @@ pub fn reload_tool_from_content(
 #[cfg(feature = "synthesised-tools")]
 pub fn unregister_tool_at_path(registry: &Arc<RwLock<ToolRegistry>>, path: &VfsPath) {
     let mut reg = registry.write().unwrap();
+    reg.scheme_libs_mut().forget_dependent(path);
     let names = reg.find_all_by_vfs_path(path);
     for name in names {
         reg.unregister(&name);
     }
 }
 
+/// Update a `/tools/lib/` scheme library and reload the tools depending on it.
+///
+/// Called synchronously from the `on_scm_change` callback for `.sld` paths:
+/// `content` is the written source, or `None` after a delete. Every tool file
+/// in scope that imports the library (directly or through another library)
+/// is reloaded via `reload_tool_from_content`, so a broken library leaves the
+/// previous tool versions registered.
+#[cfg(feature = "synthesised-tools")]
+pub fn reload_library(
+    registry: &Arc<RwLock<ToolRegistry>>,
+    path: &VfsPath,
+    content: Option<&[u8]>,
+    tools_config: &crate::config::ToolsConfig,
+) {
+    let dependents = {
+        let mut reg = registry.write().unwrap();
+        let libs = reg.scheme_libs_mut();
+        match content.map(|bytes| String::from_utf8(bytes.to_vec())) {
+            Some(Ok(source)) => libs.insert(path, source),
+            Some(Err(_)) => return,
+            None => {
+                libs.remove(path);
+            }
+        }
+        libs.dependents_of(path)
+    };
+    for (tool_path, source) in dependents {
+        reload_tool_from_content(registry, &tool_path, source.as_bytes(), tools_config);
+    }
+}
+
 // --- helpers -----------------------------------------------------------------
 
 /// Extract a scheme string binding from a `TeinSession`.
@@ -1583,35 +2214,46 @@ fn extract_string(session: &TeinSession, name: &str) -> io::Result<String> {
     })
 }
 
-/// Read `%hook-registry%` from a tein context and return (hooks, hook_bindings).
+/// Hook registrations read from `%hook-registry%`.
+#[cfg(feature = "synthesised-tools")]
+struct HookRegistrations {
+    hooks: Vec<super::hooks::HookPoint>,
+    priorities: std::collections::HashMap<super::hooks::HookPoint, i32>,
+    bindings: std::collections::HashMap<super::hooks::HookPoint, String>,
+}
+
+/// Read `%hook-registry%` from a tein context.
 ///
-/// Each entry in `%hook-registry%` is `(hook-name-string handler-procedure)`.
+/// Each entry in `%hook-registry%` is
+/// `(hook-name-string handler-procedure priority-integer)`.
 /// For each valid entry, we:
 /// 1. Parse the hook name string into a `HookPoint`.
 /// 2. Bind the handler to `%hook-{hook_name}%` in the context.
-/// 3. Record the mapping in `hook_bindings`.
+/// 3. Record the mapping in `bindings` and the priority in `priorities`.
 ///
 /// Invalid hook names are warned and skipped (same as plugin hook parsing).
 ///
 /// Mutation site: if `register-hook` alist shape changes, update this function
 /// and `HARNESS_PREAMBLE` accordingly.
 #[cfg(feature = "synthesised-tools")]
-fn extract_hook_registrations(
-    session: &TeinSession,
-) -> io::Result<(
-    Vec<super::hooks::HookPoint>,
-    std::collections::HashMap<super::hooks::HookPoint, String>,
-)> {
+fn extract_hook_registrations(session: &TeinSession) -> io::Result<HookRegistrations> {
     let registry_val = session
         .evaluate("%hook-registry%")
         .map_err(|e| io::Error::other(format!("reading %hook-registry%: {e}")))?;
 
     let entries = match registry_val {
         Value::List(items) if !items.is_empty() => items,
-        _ => return Ok((vec![], std::collections::HashMap::new())),
+        _ => {
+            return Ok(HookRegistrations {
+                hooks: vec![],
+                priorities: std::collections::HashMap::new(),
+                bindings: std::collections::HashMap::new(),
+            });
+        }
     };
 
     let mut hooks = Vec::new();
+    let mut priorities = std::collections::HashMap::new();
     let mut hook_bindings = std::collections::HashMap::new();
 
     // entries are LIFO (via cons); reverse for definition order
@@ -1645,6 +2287,18 @@ fn extract_hook_registrations(
             continue;
         }
 
+        // later registrations of the same hook point replace earlier ones,
+        // priority included (matches the binding below).
+        let priority = match fields.get(2) {
+            Some(Value::Integer(n)) => *n as i32,
+            None => 0,
+            Some(other) => {
+                eprintln!("[WARN] register-hook {hook_name}: priority {other} is not an integer");
+                0
+            }
+        };
+        priorities.insert(hook_point, priority);
+
         // bind handler to a well-known name so execute_hook can find it
         let binding = format!("%hook-{hook_name}%");
         let hook_name_escaped = scheme_escape_string(&hook_name);
@@ -1671,7 +2325,11 @@ fn extract_hook_registrations(
         });
     }
 
-    Ok((hooks, hook_bindings))
+    Ok(HookRegistrations {
+        hooks,
+        priorities,
+        bindings: hook_bindings,
+    })
 }
 
 /// Convert a scheme params alist to a JSON Schema object.
@@ -1890,8 +2548,8 @@ mod tests {
             crate::config::SandboxTier::Unsandboxed,
             crate::config::SandboxTier::Sandboxed,
         ] {
-            let (session, _) =
-                build_tein_context(String::new(), tier, None, None).expect("session should build");
+            let (session, _) = build_tein_context(String::new(), tier, None, None, Vec::new())
+                .expect("session should build");
             let cap = session.with_capture(|ctx| ctx.evaluate("(display 42)"));
             assert!(
                 cap.value.is_ok(),
@@ -1911,8 +2569,8 @@ mod tests {
             crate::config::SandboxTier::Sandboxed,
             crate::config::SandboxTier::Unsandboxed,
         ] {
-            let (session, _) =
-                build_tein_context(String::new(), tier, None, None).expect("session should build");
+            let (session, _) = build_tein_context(String::new(), tier, None, None, Vec::new())
+                .expect("session should build");
 
             let hooks_docs_ok = session
                 .evaluate("(and (pair? hooks-docs) (pair? harness-tools-docs))")
@@ -2457,6 +3115,198 @@ mod tests {
         );
     }
 
+    // --- scheme library tests ---
+
+    fn greet_library(greeting: &str) -> String {
+        format!(
+            r#"(define-library (lib greet)
+  (import (scheme base))
+  (export greet)
+  (begin (define (greet name) (string-append "{greeting}, " name))))"#
+        )
+    }
+
+    const GREETER_TOOL: &str = r#"
+(import (scheme base) (lib greet))
+(define tool-name "greeter")
+(define tool-description "greets the world")
+(define tool-parameters '())
+(define (tool-execute args) (greet "world"))
+"#;
+
+    fn run_greeter(registry: &Arc<RwLock<ToolRegistry>>) -> Option<String> {
+        let tool = registry.read().unwrap().get("greeter")?.clone();
+        let ToolImpl::Synthesised {
+            ref context,
+            ref exec_binding,
+            ..
+        } = tool.r#impl
+        else {
+            panic!("expected Synthesised impl");
+        };
+        let exec_fn = context.evaluate(exec_binding).unwrap();
+        let result = context.call(&exec_fn, &[Value::Nil]).unwrap();
+        result.as_string().map(str::to_string)
+    }
+
+    #[test]
+    fn test_tool_imports_library_from_own_zone() {
+        let registry = make_registry();
+        {
+            let mut reg = registry.write().unwrap();
+            let libs = reg.scheme_libs_mut();
+            libs.insert(
+                &VfsPath::new("/tools/lib/shared/greet.sld").unwrap(),
+                greet_library("hello"),
+            );
+            libs.insert(
+                &VfsPath::new("/tools/lib/home/alice/greet.sld").unwrap(),
+                greet_library("hi"),
+            );
+        }
+
+        let shared = VfsPath::new("/tools/shared/greeter.scm").unwrap();
+        reload_tool_from_content(
+            &registry,
+            &shared,
+            GREETER_TOOL.as_bytes(),
+            &crate::config::ToolsConfig::default(),
+        );
+        assert_eq!(run_greeter(&registry).as_deref(), Some("hello, world"));
+
+        // the home zone's library shadows the shared one
+        let home = VfsPath::new("/tools/home/alice/greeter.scm").unwrap();
+        reload_tool_from_content(
+            &registry,
+            &home,
+            GREETER_TOOL.as_bytes(),
+            &crate::config::ToolsConfig::default(),
+        );
+        assert_eq!(run_greeter(&registry).as_deref(), Some("hi, world"));
+    }
+
+    #[tokio::test]
+    async fn test_library_write_reloads_dependent_tools() {
+        use crate::vfs::ScmChangeKind;
+
+        let (_dir, mut vfs) = make_test_vfs();
+        let registry = make_registry();
+
+        let reg = Arc::clone(&registry);
+        let cfg = crate::config::ToolsConfig::default();
+        vfs.set_scm_change_callback(Arc::new(move |path, kind, content| match kind {
+            _ if crate::tools::scheme_lib::is_library_path(path) => {
+                reload_library(&reg, path, content, &cfg)
+            }
+            ScmChangeKind::Write => {
+                if let Some(bytes) = content {
+                    reload_tool_from_content(&reg, path, bytes, &cfg);
+                }
+            }
+            ScmChangeKind::Delete => unregister_tool_at_path(&reg, path),
+        }));
+
+        // the tool can't load until its library exists
+        let tool_path = VfsPath::new("/tools/shared/greeter.scm").unwrap();
+        vfs.write(VfsCaller::System, &tool_path, GREETER_TOOL.as_bytes())
+            .await
+            .unwrap();
+        assert!(run_greeter(&registry).is_none());
+
+        let lib_path = VfsPath::new("/tools/lib/shared/greet.sld").unwrap();
+        vfs.write(
+            VfsCaller::System,
+            &lib_path,
+            greet_library("hello").as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(run_greeter(&registry).as_deref(), Some("hello, world"));
+
+        vfs.write(
+            VfsCaller::System,
+            &lib_path,
+            greet_library("howdy").as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(run_greeter(&registry).as_deref(), Some("howdy, world"));
+
+        // deleting the library leaves the last good version registered
+        vfs.delete(VfsCaller::System, &lib_path).await.unwrap();
+        assert!(registry.read().unwrap().scheme_libs().is_empty());
+        assert_eq!(run_greeter(&registry).as_deref(), Some("howdy, world"));
+    }
+
+    #[tokio::test]
+    async fn test_unsandboxed_tool_refuses_sandboxed_library() {
+        let (_dir, mut vfs) = make_test_vfs();
+        let registry = make_registry();
+
+        let mut tiers = std::collections::HashMap::new();
+        tiers.insert("/tools/home/admin/".to_string(), 2u8);
+        let cfg = crate::config::ToolsConfig {
+            tiers: Some(tiers),
+            ..Default::default()
+        };
+        let reg = Arc::clone(&registry);
+        let callback_cfg = cfg.clone();
+        vfs.set_scm_change_callback(Arc::new(move |path, _, content| {
+            if crate::tools::scheme_lib::is_library_path(path) {
+                reload_library(&reg, path, content, &callback_cfg);
+            }
+        }));
+
+        // a sandboxed context plants a shared library
+        let lib_path = VfsPath::new("/tools/lib/shared/greet.sld").unwrap();
+        vfs.write(
+            VfsCaller::Context("mallory"),
+            &lib_path,
+            greet_library("pwned").as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        let admin = VfsPath::new("/tools/home/admin/greeter.scm").unwrap();
+        let Err(err) = load_tools_from_source(GREETER_TOOL, &admin, &registry, &cfg) else {
+            panic!("unsandboxed tool loaded a sandboxed library");
+        };
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        reload_tool_from_content(&registry, &admin, GREETER_TOOL.as_bytes(), &cfg);
+        assert!(run_greeter(&registry).is_none());
+
+        // a sandboxed tool may still use it
+        let shared = VfsPath::new("/tools/shared/greeter.scm").unwrap();
+        reload_tool_from_content(&registry, &shared, GREETER_TOOL.as_bytes(), &cfg);
+        assert_eq!(run_greeter(&registry).as_deref(), Some("pwned, world"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_loads_libraries_before_tools() {
+        let (_dir, vfs) = make_test_vfs();
+        let registry = make_registry();
+
+        let tool_path = VfsPath::new("/tools/home/alice/greeter.scm").unwrap();
+        vfs.write(VfsCaller::System, &tool_path, GREETER_TOOL.as_bytes())
+            .await
+            .unwrap();
+        let lib_path = VfsPath::new("/tools/lib/home/alice/util/greet.sld").unwrap();
+        vfs.write(
+            VfsCaller::System,
+            &lib_path,
+            greet_library("hey").as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        scan_and_register(&vfs, &registry, &crate::config::ToolsConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(registry.read().unwrap().scheme_libs().len(), 1);
+        assert_eq!(run_greeter(&registry).as_deref(), Some("hey, world"));
+    }
+
     const SIMPLE_TOOL: &str = r#"
 (import (scheme base))
 (define tool-name "word_count")
@@ -3628,4 +4478,166 @@ mod tests {
         let result = run_io_tool(&chibi, &registry, source).await;
         assert_eq!(result, "deleted");
     }
+
+    // --- (harness llm) ---
+
+    #[test]
+    fn test_llm_request_from_json_options() {
+        let req = LlmRequest::from_json(
+            serde_json::json!([{ "role": "user", "content": "classify this" }]),
+            serde_json::json!({
+                "system": "you are a classifier",
+                "preset": "fast",
+                "max_tokens": 50,
+                "schema": "{\"type\": \"object\"}",
+            }),
+        )
+        .unwrap();
+        assert_eq!(req.messages.len(), 2);
+        assert_eq!(req.messages[0]["role"], "system");
+        assert_eq!(req.messages[1]["content"], "classify this");
+        assert_eq!(req.options.preset.as_deref(), Some("fast"));
+        assert_eq!(req.options.max_tokens, Some(50));
+        assert!(matches!(
+            req.response_format,
+            Some(crate::config::ResponseFormat::JsonSchema { json_schema: Some(ref s) })
+                if s["type"] == "object"
+        ));
+
+        let req = LlmRequest::from_json(
+            serde_json::json!([{ "role": "user", "content": "hi" }]),
+            serde_json::json!({ "json": true }),
+        )
+        .unwrap();
+        assert!(matches!(
+            req.response_format,
+            Some(crate::config::ResponseFormat::JsonObject)
+        ));
+
+        let req = LlmRequest::from_json(
+            serde_json::json!([{ "role": "user", "content": "hi" }]),
+            serde_json::Value::Null,
+        )
+        .unwrap();
+        assert_eq!(req.messages.len(), 1);
+        assert!(req.response_format.is_none());
+    }
+
+    #[test]
+    fn test_llm_request_rejects_malformed_messages() {
+        let none = serde_json::Value::Null;
+        assert!(LlmRequest::from_json(serde_json::json!([]), none.clone()).is_err());
+        assert!(LlmRequest::from_json(serde_json::json!("hi"), none.clone()).is_err());
+        assert!(LlmRequest::from_json(serde_json::json!([{ "role": "user" }]), none).is_err());
+        assert!(
+            LlmRequest::from_json(
+                serde_json::json!([{ "role": "user", "content": "hi" }]),
+                serde_json::json!({ "schema": "{not json" }),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_llm_reply_json_parses_structured_content() {
+        let completion = |content: &str, blocked| crate::tools::Completion {
+            content: content.to_string(),
+            model: "m".to_string(),
+            blocked,
+        };
+        let reply = llm_reply_json(completion(r#"{"label": "spam"}"#, false), true).unwrap();
+        assert_eq!(reply["content"]["label"], "spam");
+        assert_eq!(reply["blocked"], false);
+
+        // Blocked replies keep the hook message even when JSON was requested.
+        let reply = llm_reply_json(completion("no budget", true), true).unwrap();
+        assert_eq!(reply["content"], "no budget");
+
+        assert!(llm_reply_json(completion("not json", false), true).is_err());
+        let reply = llm_reply_json(completion("plain", false), false).unwrap();
+        assert_eq!(reply["content"], "plain");
+    }
+
+    /// Register `hook_source` (a pre_spawn_agent hook tool) and an unsandboxed
+    /// `llm_test` tool, then run `llm_test` and return its result field.
+    async fn run_llm_tool(hook_source: &str, tool_source: &str) -> String {
+        use crate::test_support::create_test_chibi;
+        let (chibi, _tmp) = create_test_chibi();
+        let registry = chibi.registry.clone();
+        for (name, source) in [("llm_hook", hook_source), ("llm_test", tool_source)] {
+            let path = VfsPath::new(&format!("/tools/shared/{name}.scm")).unwrap();
+            let tools = load_tools_from_source(
+                source,
+                &path,
+                &registry,
+                &crate::config::ToolsConfig::default(),
+            )
+            .unwrap();
+            let mut reg = registry.write().unwrap();
+            for t in tools {
+                reg.register(t);
+            }
+        }
+        let raw = chibi
+            .execute_tool("default", "llm_test", serde_json::json!({}))
+            .await
+            .unwrap();
+        extract_result_field(&raw).to_string()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_harness_llm_structured_reply_from_hook() {
+        // The pre_spawn_agent hook answers in place of the model, so no
+        // network call is made; the reply is parsed against the schema.
+        let hook = r#"
+(import (scheme base))
+(import (harness hooks))
+(register-hook 'pre_spawn_agent
+  (lambda (payload) (list (cons "response" "{\"label\": \"spam\"}"))))
+(define tool-name "llm_hook")
+(define tool-description "answers sub-agent calls")
+(define tool-parameters '())
+(define (tool-execute args) "ok")
+"#;
+        let tool = r#"
+(import (scheme base))
+(import (harness llm))
+(define tool-name "llm_test")
+(define tool-description "classify via the model")
+(define tool-parameters '())
+(define (tool-execute args)
+  (let ((reply (llm-complete "buy now!!!"
+                 '(("system" . "label the message")
+                   ("schema" . "{\"type\": \"object\"}")))))
+    (cdr (assoc "label" reply))))
+"#;
+        assert_eq!(run_llm_tool(hook, tool).await, "spam");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_harness_llm_chat_reports_blocked() {
+        let hook = r#"
+(import (scheme base))
+(import (harness hooks))
+(register-hook 'pre_spawn_agent
+  (lambda (payload) (list (cons "block" #t) (cons "message" "over budget"))))
+(define tool-name "llm_hook")
+(define tool-description "blocks sub-agent calls")
+(define tool-parameters '())
+(define (tool-execute args) "ok")
+"#;
+        let tool = r#"
+(import (scheme base))
+(import (harness llm))
+(define tool-name "llm_test")
+(define tool-description "chat via the model")
+(define tool-parameters '())
+(define (tool-execute args)
+  (let ((reply (llm-chat (list (list (cons "role" "user") (cons "content" "hi"))))))
+    (if (cdr (assoc "blocked" reply))
+        (cdr (assoc "content" reply))
+        "not blocked")))
+"#;
+        assert_eq!(run_llm_tool(hook, tool).await, "over budget");
+    }
 }
@@ -0,0 +1,445 @@
+//! Test runner for synthesised scheme tools (`chibi --test-tool`).
+//!
+//! Tests are declared in the tool's own `.scm` file with `(harness test)`:
+//!
+//! ```scheme
+//! (import (harness test))
+//!
+//! (define-test "greets by name"
+//!   (assert-equal "hello bob" (tool-execute '(("name" . "bob")))))
+//!
+//! (define-test "looks up the user"
+//!   (mock-tool "read_user" "bob")
+//!   (assert-equal "hello bob" (tool-execute '()))
+//!   (assert-equal 1 (length (tool-calls "read_user"))))
+//! ```
+//!
+//! `define-test` only records the test, so files carrying tests load as
+//! ordinary tools. The runner evaluates the file in a fresh context built
+//! exactly as the loader builds it (same sandbox tier, env and HTTP
+//! allowlist), then runs each test with a fresh `ToolTestHarness`:
+//! `call-tool` answers from mocks, `(harness io)` reads and writes an
+//! in-memory file map, and `(harness llm)` answers from `mock-llm`, so tests
+//! never reach real tools, the VFS or a model.
+//!
+//! Everything else is left as the tool would have it. HTTP requests go out
+//! under the tool's allowlist, and at the unsandboxed tier the full R7RS
+//! library (`(scheme file)`, `(scheme process-context)`) touches the real
+//! filesystem and environment.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "synthesised-tools")]
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+#[cfg(feature = "synthesised-tools")]
+use std::io;
+#[cfg(feature = "synthesised-tools")]
+use std::sync::Mutex;
+
+#[cfg(feature = "synthesised-tools")]
+use crate::vfs::{VfsCaller, VfsEntryKind, VfsPath};
+
+/// Output format for `test-tool` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TestReportFormat {
+    /// Test Anything Protocol, version 13
+    #[default]
+    Tap,
+    /// A single JSON document
+    Json,
+}
+
+impl TestReportFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tap" => Some(TestReportFormat::Tap),
+            "json" => Some(TestReportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of one test, or of loading a file that failed to evaluate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolTestResult {
+    /// VFS path of the tool file
+    pub file: String,
+    /// Test name; `"load"` for a file that failed to evaluate
+    pub name: String,
+    pub passed: bool,
+    /// Failure message; `None` for passing tests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Anything the test wrote to stdout
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+}
+
+/// Results for every test in every file, in run order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolTestReport {
+    pub results: Vec<ToolTestResult>,
+}
+
+impl ToolTestReport {
+    pub fn failed(&self) -> usize {
+        self.results.iter().filter(|r| !r.passed).count()
+    }
+
+    pub fn render(&self, format: TestReportFormat) -> String {
+        match format {
+            TestReportFormat::Tap => self.render_tap(),
+            TestReportFormat::Json => {
+                let passed = self.results.len() - self.failed();
+                let doc = serde_json::json!({
+                    "tests": self.results,
+                    "passed": passed,
+                    "failed": self.failed(),
+                });
+                serde_json::to_string_pretty(&doc).unwrap_or_default()
+            }
+        }
+    }
+
+    fn render_tap(&self) -> String {
+        let mut out = format!("TAP version 13\n1..{}\n", self.results.len());
+        for (i, result) in self.results.iter().enumerate() {
+            let status = if result.passed { "ok" } else { "not ok" };
+            out.push_str(&format!(
+                "{status} {} - {}: {}\n",
+                i + 1,
+                result.file,
+                result.name
+            ));
+            if let Some(message) = &result.message {
+                out.push_str("  ---\n");
+                out.push_str(&format!("  message: {}\n", yaml_scalar(message)));
+                if !result.stdout.is_empty() {
+                    out.push_str(&format!("  stdout: {}\n", yaml_scalar(&result.stdout)));
+                }
+                out.push_str("  ...\n");
+            }
+        }
+        out
+    }
+}
+
+/// Quote a string for a TAP YAML block (JSON strings are valid YAML scalars).
+fn yaml_scalar(s: &str) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
+
+// --- test harness ------------------------------------------------------------
+
+/// Per-test stand-ins for the outside world, consulted by the `call-tool`,
+/// `(harness io)` and `(harness llm)` bridges while a test runs.
+#[cfg(feature = "synthesised-tools")]
+#[derive(Default)]
+pub(crate) struct ToolTestHarness {
+    state: Mutex<HarnessState>,
+}
+
+#[cfg(feature = "synthesised-tools")]
+#[derive(Default)]
+struct HarnessState {
+    /// Tool name → canned reply (`Err` raises in scheme)
+    mocks: HashMap<String, Result<String, String>>,
+    /// Every `call-tool` invocation, mocked or not, in call order
+    calls: Vec<(String, serde_json::Value)>,
+    /// In-memory files keyed by the path exactly as `(harness io)` sees it
+    files: BTreeMap<String, String>,
+    llm_reply: Option<String>,
+}
+
+#[cfg(feature = "synthesised-tools")]
+impl ToolTestHarness {
+    pub(crate) fn mock_tool(&self, name: &str, reply: Result<String, String>) {
+        let mut state = self.state.lock().unwrap();
+        state.mocks.insert(name.to_string(), reply);
+    }
+
+    /// Record the call and answer from the mocks. Unmocked tools fail rather
+    /// than falling through to the real registry.
+    pub(crate) fn call_tool(&self, name: &str, args: &serde_json::Value) -> Result<String, String> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push((name.to_string(), args.clone()));
+        state.mocks.get(name).cloned().unwrap_or_else(|| {
+            Err(format!(
+                "call-tool: no mock for '{name}' (declare one with mock-tool)"
+            ))
+        })
+    }
+
+    pub(crate) fn calls_to(&self, name: &str) -> Vec<serde_json::Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .calls
+            .iter()
+            .filter(|(called, _)| called == name)
+            .map(|(_, args)| args.clone())
+            .collect()
+    }
+
+    pub(crate) fn mock_llm(&self, reply: &str) {
+        self.state.lock().unwrap().llm_reply = Some(reply.to_string());
+    }
+
+    pub(crate) fn llm_reply(&self) -> Option<String> {
+        self.state.lock().unwrap().llm_reply.clone()
+    }
+
+    pub(crate) fn read_file(&self, path: &str) -> Option<String> {
+        self.state.lock().unwrap().files.get(path).cloned()
+    }
+
+    pub(crate) fn write_file(&self, path: &str, data: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.files.insert(path.to_string(), data.to_string());
+    }
+
+    pub(crate) fn append_file(&self, path: &str, data: &str) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .files
+            .entry(path.to_string())
+            .or_default()
+            .push_str(data);
+    }
+
+    pub(crate) fn delete_file(&self, path: &str) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        state
+            .files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| format!("{path}: not found"))
+    }
+
+    /// A path exists if it is a file or a directory prefix of one.
+    pub(crate) fn exists(&self, path: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        state.files.contains_key(path) || state.files.keys().any(|k| k.starts_with(&dir))
+    }
+
+    /// Immediate children of `path`, sorted; empty for unknown paths.
+    pub(crate) fn list(&self, path: &str) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        let names: BTreeSet<&str> = state
+            .files
+            .keys()
+            .filter_map(|k| k.strip_prefix(&dir))
+            .filter_map(|rest| rest.split('/').next())
+            .collect();
+        names.into_iter().map(String::from).collect()
+    }
+}
+
+// --- runner ------------------------------------------------------------------
+
+/// Run the tests in a tool file, or in every `.scm` file directly inside a
+/// directory, as `context_name`.
+///
+/// A file that fails to evaluate is reported as a failing `load` test, so a
+/// broken helper shows up instead of silently dropping the tool.
+#[cfg(feature = "synthesised-tools")]
+pub async fn run_tool_tests(
+    chibi: &crate::Chibi,
+    context_name: &str,
+    path: &str,
+) -> io::Result<ToolTestReport> {
+    let root = VfsPath::new(path)?;
+    let vfs = &chibi.app.vfs;
+    let files = match vfs.metadata(VfsCaller::System, &root).await?.kind {
+        VfsEntryKind::File => vec![root],
+        VfsEntryKind::Directory => {
+            let mut entries = vfs.list(VfsCaller::System, &root).await?;
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            entries
+                .into_iter()
+                .filter(|e| matches!(e.kind, VfsEntryKind::File) && e.name.ends_with(".scm"))
+                .map(|e| root.join(&e.name))
+                .collect::<io::Result<Vec<_>>>()?
+        }
+    };
+
+    let mut config = chibi.app.resolve_config(context_name, None)?;
+    super::ensure_project_root_allowed(&mut config, &chibi.project_root);
+    let call_ctx = super::ToolCallContext {
+        app: &chibi.app,
+        context_name,
+        config: &config,
+        project_root: &chibi.project_root,
+        vfs,
+        vfs_caller: VfsCaller::Context(context_name),
+    };
+
+    let mut report = ToolTestReport::default();
+    for file in &files {
+        let source = vfs.read(VfsCaller::System, file).await?;
+        let source = String::from_utf8_lossy(&source);
+        let results = super::synthesised::run_file_tests(
+            &source,
+            file,
+            &chibi.app.config.tools,
+            &call_ctx,
+            &chibi.registry,
+        );
+        report.results.extend(results);
+    }
+    Ok(report)
+}
+
+#[cfg(all(test, feature = "synthesised-tools"))]
+mod tests {
+    use super::*;
+    use crate::test_support::create_test_chibi;
+
+    async fn write(chibi: &crate::Chibi, path: &str, source: &str) {
+        chibi
+            .app
+            .vfs
+            .write(
+                VfsCaller::System,
+                &VfsPath::new(path).unwrap(),
+                source.as_bytes(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_report_renders_tap_and_json() {
+        let report = ToolTestReport {
+            results: vec![
+                ToolTestResult {
+                    file: "/tools/shared/a.scm".into(),
+                    name: "adds".into(),
+                    passed: true,
+                    message: None,
+                    stdout: String::new(),
+                },
+                ToolTestResult {
+                    file: "/tools/shared/a.scm".into(),
+                    name: "subtracts".into(),
+                    passed: false,
+                    message: Some("expected 1, got 2".into()),
+                    stdout: String::new(),
+                },
+            ],
+        };
+        let tap = report.render(TestReportFormat::Tap);
+        assert!(tap.starts_with("TAP version 13\n1..2\n"));
+        assert!(tap.contains("ok 1 - /tools/shared/a.scm: adds\n"));
+        assert!(tap.contains("not ok 2 - /tools/shared/a.scm: subtracts\n"));
+        assert!(tap.contains("  message: \"expected 1, got 2\"\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(TestReportFormat::Json)).unwrap();
+        assert_eq!(json["passed"], 1);
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["tests"][1]["name"], "subtracts");
+    }
+
+    #[test]
+    fn test_harness_in_memory_files() {
+        let harness = ToolTestHarness::default();
+        harness.write_file("vfs:///shared/notes/a.txt", "one");
+        harness.append_file("vfs:///shared/notes/a.txt", " two");
+        harness.write_file("vfs:///shared/notes/sub/b.txt", "b");
+        assert_eq!(
+            harness.read_file("vfs:///shared/notes/a.txt").as_deref(),
+            Some("one two")
+        );
+        assert_eq!(harness.list("vfs:///shared/notes"), vec!["a.txt", "sub"]);
+        assert!(harness.exists("vfs:///shared/notes/sub"));
+        harness.delete_file("vfs:///shared/notes/a.txt").unwrap();
+        assert!(!harness.exists("vfs:///shared/notes/a.txt"));
+        assert!(harness.delete_file("vfs:///shared/notes/a.txt").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_run_tool_tests_with_mocks() {
+        let (chibi, _tmp) = create_test_chibi();
+        write(
+            &chibi,
+            "/tools/shared/greet.scm",
+            r#"
+(import (scheme base))
+(import (harness tools))
+(import (harness test))
+(define tool-name "greet")
+(define tool-description "greets the current user")
+(define tool-parameters '())
+(define (tool-execute args)
+  (string-append "hello " (call-tool "whoami" '())))
+
+(define-test "uses the mocked user"
+  (mock-tool "whoami" "bob")
+  (assert-equal "hello bob" (tool-execute '()))
+  (assert-equal 1 (length (tool-calls "whoami"))))
+
+(define-test "mocks do not leak between tests"
+  (assert-raises (lambda () (tool-execute '()))))
+
+(define-test "fails"
+  (assert-equal "hello alice" (tool-execute '())))
+"#,
+        )
+        .await;
+
+        let report = run_tool_tests(&chibi, "default", "/tools/shared/greet.scm")
+            .await
+            .unwrap();
+        let outcomes: Vec<_> = report
+            .results
+            .iter()
+            .map(|r| (r.name.as_str(), r.passed))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("uses the mocked user", true),
+                ("mocks do not leak between tests", true),
+                ("fails", false),
+            ]
+        );
+        assert_eq!(report.failed(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_run_tool_tests_directory_reports_load_failures() {
+        let (chibi, _tmp) = create_test_chibi();
+        write(
+            &chibi,
+            "/tools/shared/broken.scm",
+            "(import (scheme base)) (",
+        )
+        .await;
+        write(
+            &chibi,
+            "/tools/shared/ok.scm",
+            r#"
+(import (scheme base))
+(import (harness test))
+(define tool-name "ok")
+(define tool-description "ok")
+(define tool-parameters '())
+(define (tool-execute args) "ok")
+(define-test "runs" (assert-true (string? (tool-execute '()))))
+"#,
+        )
+        .await;
+
+        let report = run_tool_tests(&chibi, "default", "/tools/shared")
+            .await
+            .unwrap();
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[0].name, "load");
+        assert!(!report.results[0].passed);
+        assert!(report.results[1].passed);
+    }
+}
@@ -5,7 +5,8 @@
 //! appropriate `Vfs` method, returning human-readable result strings.
 
 use super::{BuiltinToolDef, ToolPropertyDef, require_str_param};
-use crate::vfs::{Vfs, VfsCaller, VfsEntryKind, VfsPath};
+use crate::json_ext::JsonExt;
+use crate::vfs::{AclAccess, AclGrant, Vfs, VfsCaller, VfsEntryKind, VfsPath};
 use std::io;
 
 // === Tool Name Constants ===
@@ -16,6 +17,14 @@ pub const VFS_COPY_TOOL_NAME: &str = "vfs_copy";
 pub const VFS_MOVE_TOOL_NAME: &str = "vfs_move";
 pub const VFS_MKDIR_TOOL_NAME: &str = "vfs_mkdir";
 pub const VFS_DELETE_TOOL_NAME: &str = "vfs_delete";
+pub const VFS_HISTORY_TOOL_NAME: &str = "vfs_history";
+pub const VFS_READ_AT_TOOL_NAME: &str = "vfs_read_at";
+pub const VFS_RESTORE_TOOL_NAME: &str = "vfs_restore";
+pub const VFS_USAGE_TOOL_NAME: &str = "vfs_usage";
+pub const VFS_WATCH_TOOL_NAME: &str = "vfs_watch";
+pub const VFS_UNWATCH_TOOL_NAME: &str = "vfs_unwatch";
+pub const VFS_SHARE_TOOL_NAME: &str = "vfs_share";
+pub const VFS_UNSHARE_TOOL_NAME: &str = "vfs_unshare";
 
 // === Tool Definition Registry ===
 
@@ -109,6 +118,141 @@ pub static VFS_TOOL_DEFS: &[BuiltinToolDef] = &[
         required: &["path"],
         summary_params: &["path"],
     },
+    BuiltinToolDef {
+        name: VFS_HISTORY_TOOL_NAME,
+        description: "List recorded versions of a VFS file: number, time, author, operation and size.",
+        properties: &[ToolPropertyDef {
+            name: "path",
+            prop_type: "string",
+            description: "VFS URI of the file (e.g. vfs:///shared/plan.md)",
+            default: None,
+        }],
+        required: &["path"],
+        summary_params: &["path"],
+    },
+    BuiltinToolDef {
+        name: VFS_READ_AT_TOOL_NAME,
+        description: "Read an earlier version of a VFS file, by version number or point in time.",
+        properties: &[
+            ToolPropertyDef {
+                name: "path",
+                prop_type: "string",
+                description: "VFS URI of the file",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "version",
+                prop_type: "integer",
+                description: "Version number from vfs_history",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "at",
+                prop_type: "string",
+                description: "RFC 3339 timestamp; reads the version current at that time (used when version is absent)",
+                default: None,
+            },
+        ],
+        required: &["path"],
+        summary_params: &["path", "version", "at"],
+    },
+    BuiltinToolDef {
+        name: VFS_RESTORE_TOOL_NAME,
+        description: "Restore a VFS file to an earlier version. The restore is recorded as a new version.",
+        properties: &[
+            ToolPropertyDef {
+                name: "path",
+                prop_type: "string",
+                description: "VFS URI of the file",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "version",
+                prop_type: "integer",
+                description: "Version number from vfs_history",
+                default: None,
+            },
+        ],
+        required: &["path", "version"],
+        summary_params: &["path", "version"],
+    },
+    BuiltinToolDef {
+        name: VFS_USAGE_TOOL_NAME,
+        description: "Show bytes and files used in each VFS quota zone (context homes, flocks, shared, tools), with the configured limits.",
+        properties: &[],
+        required: &[],
+        summary_params: &[],
+    },
+    BuiltinToolDef {
+        name: VFS_WATCH_TOOL_NAME,
+        description: "Watch a VFS path or directory. Changes other contexts make under it arrive in your inbox as [vfs] messages.",
+        properties: &[ToolPropertyDef {
+            name: "path",
+            prop_type: "string",
+            description: "VFS URI of the file or directory to watch",
+            default: None,
+        }],
+        required: &["path"],
+        summary_params: &["path"],
+    },
+    BuiltinToolDef {
+        name: VFS_UNWATCH_TOOL_NAME,
+        description: "Stop watching a VFS path previously passed to vfs_watch.",
+        properties: &[ToolPropertyDef {
+            name: "path",
+            prop_type: "string",
+            description: "VFS URI of the watched path",
+            default: None,
+        }],
+        required: &["path"],
+        summary_params: &["path"],
+    },
+    BuiltinToolDef {
+        name: VFS_SHARE_TOOL_NAME,
+        description: "Change who can access a path in your home (/home/<you>/) and everything below it. Grant another context 'write' (read + write) or 'read', or use 'none' with context '*' to make the path private. The most specific grant wins.",
+        properties: &[
+            ToolPropertyDef {
+                name: "path",
+                prop_type: "string",
+                description: "VFS URI of a file or directory in your home",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "context",
+                prop_type: "string",
+                description: "Context to grant access to, or '*' for every other context",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "access",
+                prop_type: "string",
+                description: "'none', 'read' or 'write'",
+                default: None,
+            },
+        ],
+        required: &["path", "context", "access"],
+        summary_params: &["path", "context", "access"],
+    },
+    BuiltinToolDef {
+        name: VFS_UNSHARE_TOOL_NAME,
+        description: "Remove a grant made with vfs_share, restoring the default access.",
+        properties: &[
+            ToolPropertyDef {
+                name: "path",
+                prop_type: "string",
+                description: "VFS URI the grant was made on",
+                default: None,
+            },
+            ToolPropertyDef {
+                name: "context",
+                prop_type: "string",
+                description: "Context the grant was made for, or '*'",
+                default: None,
+            },
+        ],
+        required: &["path", "context"],
+        summary_params: &["path", "context"],
+    },
 ];
 
 // === Helpers ===
@@ -168,6 +312,11 @@ pub async fn execute_vfs_info(
     if let Some(modified) = meta.modified {
         parts.push(format!("modified: {}", modified));
     }
+    if crate::vfs::acl::home_owner(&path).is_some() {
+        for grant in vfs.grants(&path).await? {
+            parts.push(format!("grant: {}", format_grant(&grant)));
+        }
+    }
     Ok(parts.join("\n"))
 }
 
@@ -217,6 +366,167 @@ pub async fn execute_vfs_move(
     Ok(format!("Moved {} -> {}", src.as_str(), dst.as_str()))
 }
 
+/// List the recorded versions of a VFS file.
+pub async fn execute_vfs_history(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let history = vfs.history(caller, &path).await?;
+    if history.is_empty() {
+        return Ok(format!("No recorded versions of {}.", path.as_str()));
+    }
+    let lines: Vec<String> = history
+        .iter()
+        .map(|e| {
+            let mut line = format!(
+                "v{}  {}  {}  {}  {} bytes",
+                e.version,
+                e.timestamp
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                e.author,
+                e.op.as_str(),
+                e.size
+            );
+            if let Some(detail) = &e.detail {
+                line.push_str(&format!("  ({detail})"));
+            }
+            line
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// Read an earlier version of a VFS file by number or timestamp.
+pub async fn execute_vfs_read_at(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let data = if let Some(version) = args.get_u64("version") {
+        vfs.read_version(caller, &path, version).await?
+    } else {
+        let at = require_str_param(args, "at").map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Missing 'version' or 'at' parameter",
+            )
+        })?;
+        let at = chrono::DateTime::parse_from_rfc3339(&at)
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid 'at' timestamp '{at}': {e}"),
+                )
+            })?
+            .with_timezone(&chrono::Utc);
+        vfs.read_at(caller, &path, at).await?.1
+    };
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Restore a VFS file to an earlier version.
+pub async fn execute_vfs_restore(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let version = args.get_u64("version").ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Missing 'version' parameter")
+    })?;
+    let new_version = vfs.restore(caller, &path, version).await?;
+    Ok(format!(
+        "Restored {} to v{} (recorded as v{})",
+        path.as_str(),
+        version,
+        new_version
+    ))
+}
+
+/// Report usage per quota zone.
+pub async fn execute_vfs_usage(vfs: &Vfs) -> io::Result<String> {
+    let usage = vfs.usage().await?;
+    Ok(crate::vfs::quota::render_usage(&usage))
+}
+
+/// Watch a path for changes by other contexts.
+pub async fn execute_vfs_watch(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let watching = vfs.watch(caller, &path).await?;
+    Ok(format!("Watching: {}", watching.join(", ")))
+}
+
+/// Stop watching a path.
+pub async fn execute_vfs_unwatch(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let watching = vfs.unwatch(caller, &path).await?;
+    if watching.is_empty() {
+        Ok(format!(
+            "Stopped watching {}; no watches left",
+            path.as_str()
+        ))
+    } else {
+        Ok(format!(
+            "Stopped watching {}; still watching: {}",
+            path.as_str(),
+            watching.join(", ")
+        ))
+    }
+}
+
+/// Change sharing of a path in the caller's home.
+pub async fn execute_vfs_share(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let context = require_str_param(args, "context")?;
+    let access = AclAccess::parse(&require_str_param(args, "access")?)?;
+    let grants = vfs.share(caller, &path, &context, access).await?;
+    Ok(format_grants(&grants))
+}
+
+/// Remove a sharing grant.
+pub async fn execute_vfs_unshare(
+    vfs: &Vfs,
+    caller: VfsCaller<'_>,
+    args: &serde_json::Value,
+) -> io::Result<String> {
+    let path = require_vfs_path(args, "path")?;
+    let context = require_str_param(args, "context")?;
+    let grants = vfs.unshare(caller, &path, &context).await?;
+    Ok(format_grants(&grants))
+}
+
+fn format_grant(grant: &AclGrant) -> String {
+    format!(
+        "{} {} on {}",
+        grant.context,
+        grant.access.as_str(),
+        grant.prefix
+    )
+}
+
+fn format_grants(grants: &[AclGrant]) -> String {
+    if grants.is_empty() {
+        return "No grants; default access applies".to_string();
+    }
+    let lines: Vec<String> = grants.iter().map(format_grant).collect();
+    format!("Grants:\n{}", lines.join("\n"))
+}
+
 /// Register all VFS tools into the registry.
 pub fn register_vfs_tools(registry: &mut super::registry::ToolRegistry) {
     use super::Tool;
@@ -277,6 +587,14 @@ pub async fn execute_vfs_tool(
         VFS_MOVE_TOOL_NAME => Some(execute_vfs_move(vfs, caller, args).await),
         VFS_MKDIR_TOOL_NAME => Some(execute_vfs_mkdir(vfs, caller, args).await),
         VFS_DELETE_TOOL_NAME => Some(execute_vfs_delete(vfs, caller, args).await),
+        VFS_HISTORY_TOOL_NAME => Some(execute_vfs_history(vfs, caller, args).await),
+        VFS_READ_AT_TOOL_NAME => Some(execute_vfs_read_at(vfs, caller, args).await),
+        VFS_RESTORE_TOOL_NAME => Some(execute_vfs_restore(vfs, caller, args).await),
+        VFS_USAGE_TOOL_NAME => Some(execute_vfs_usage(vfs).await),
+        VFS_WATCH_TOOL_NAME => Some(execute_vfs_watch(vfs, caller, args).await),
+        VFS_UNWATCH_TOOL_NAME => Some(execute_vfs_unwatch(vfs, caller, args).await),
+        VFS_SHARE_TOOL_NAME => Some(execute_vfs_share(vfs, caller, args).await),
+        VFS_UNSHARE_TOOL_NAME => Some(execute_vfs_unshare(vfs, caller, args).await),
         _ => None,
     }
 }
@@ -418,6 +736,116 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_vfs_history_read_at_and_restore() {
+        let dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(dir.path().to_path_buf());
+        let vfs = Vfs::builder("test-site-0000")
+            .mount("/", Box::new(backend))
+            .versioning(crate::config::VersioningConfig::default())
+            .build();
+        let ctx = VfsCaller::Context("ctx");
+        let path = VfsPath::new("/shared/plan.md").unwrap();
+        vfs.write(ctx, &path, b"first").await.unwrap();
+        vfs.write(ctx, &path, b"second").await.unwrap();
+
+        let args = serde_json::json!({"path": "vfs:///shared/plan.md"});
+        let history = execute_vfs_history(&vfs, ctx, &args).await.unwrap();
+        assert_eq!(history.lines().count(), 2);
+        assert!(history.contains("v1") && history.contains("ctx  write  5 bytes"));
+
+        let args = serde_json::json!({"path": "vfs:///shared/plan.md", "version": 1});
+        assert_eq!(
+            execute_vfs_read_at(&vfs, ctx, &args).await.unwrap(),
+            "first"
+        );
+        let args =
+            serde_json::json!({"path": "vfs:///shared/plan.md", "at": "2000-01-01T00:00:00Z"});
+        assert!(execute_vfs_read_at(&vfs, ctx, &args).await.is_err());
+
+        let args = serde_json::json!({"path": "vfs:///shared/plan.md", "version": 1});
+        let result = execute_vfs_restore(&vfs, ctx, &args).await.unwrap();
+        assert!(result.contains("recorded as v3"), "{result}");
+        assert_eq!(vfs.read(ctx, &path).await.unwrap(), b"first");
+    }
+
+    #[tokio::test]
+    async fn test_vfs_usage_reports_zones() {
+        let (_dir, vfs) = setup_vfs();
+        let path = VfsPath::new("/home/ctx/notes.md").unwrap();
+        vfs.write(VfsCaller::Context("ctx"), &path, b"hello")
+            .await
+            .unwrap();
+        let args = serde_json::json!({});
+        let report = execute_vfs_tool(&vfs, VfsCaller::Context("ctx"), VFS_USAGE_TOOL_NAME, &args)
+            .await
+            .unwrap()
+            .unwrap();
+        let home = report
+            .lines()
+            .find(|l| l.contains("/home/ctx"))
+            .expect(&report);
+        assert!(home.starts_with("home") && home.contains(" 5 "), "{home}");
+        assert!(report.contains("/shared"));
+    }
+
+    #[tokio::test]
+    async fn test_vfs_watch_and_unwatch() {
+        let (_dir, vfs) = setup_vfs();
+        let ctx = VfsCaller::Context("ctx");
+        let args = serde_json::json!({"path": "vfs:///shared/tasks"});
+        let result = execute_vfs_watch(&vfs, ctx, &args).await.unwrap();
+        assert_eq!(result, "Watching: /shared/tasks");
+        assert_eq!(vfs.watches().await.unwrap()[0].context, "ctx");
+
+        let result = execute_vfs_unwatch(&vfs, ctx, &args).await.unwrap();
+        assert!(result.contains("no watches left"), "{result}");
+        assert!(execute_vfs_unwatch(&vfs, ctx, &args).await.is_err());
+        // SYSTEM has no inbox to deliver to.
+        assert!(
+            execute_vfs_watch(&vfs, VfsCaller::System, &args)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vfs_share_shows_in_info() {
+        let (_dir, vfs) = setup_vfs();
+        let alice = VfsCaller::Context("alice");
+        vfs.write(
+            alice,
+            &VfsPath::new("/home/alice/notes/a.md").unwrap(),
+            b"hi",
+        )
+        .await
+        .unwrap();
+        let args = serde_json::json!({
+            "path": "vfs:///home/alice/notes",
+            "context": "bob",
+            "access": "write"
+        });
+        let result = execute_vfs_share(&vfs, alice, &args).await.unwrap();
+        assert_eq!(result, "Grants:\nbob write on /home/alice/notes");
+
+        let args = serde_json::json!({"path": "vfs:///home/alice/notes/a.md"});
+        let info = execute_vfs_info(&vfs, alice, &args).await.unwrap();
+        assert!(
+            info.contains("grant: bob write on /home/alice/notes"),
+            "{info}"
+        );
+
+        let args = serde_json::json!({"path": "vfs:///home/alice/notes", "context": "bob"});
+        let result = execute_vfs_unshare(&vfs, alice, &args).await.unwrap();
+        assert!(result.starts_with("No grants"));
+        let args = serde_json::json!({
+            "path": "vfs:///home/alice/notes",
+            "context": "bob",
+            "access": "admin"
+        });
+        assert!(execute_vfs_share(&vfs, alice, &args).await.is_err());
+    }
+
     #[test]
     fn test_vfs_tool_defs_have_descriptions() {
         for def in VFS_TOOL_DEFS {
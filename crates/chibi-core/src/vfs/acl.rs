@@ -0,0 +1,248 @@
+//! Per-path sharing grants layered over the zone rules.
+//!
+//! A context can change what other contexts may do inside its own home:
+//! share a subdirectory read-write with one context, or make part of it
+//! private. Grants are stored as a JSON array at `/sys/acl.json`, which only
+//! SYSTEM writes (through `Vfs::share` / `Vfs::unshare`):
+//!
+//! ```text
+//! [{"prefix": "/home/alice/notes", "context": "*", "access": "none", "created_at": 1767225600},
+//!  {"prefix": "/home/alice/notes", "context": "bob", "access": "read", "created_at": 1767225600}]
+//! ```
+//!
+//! For a context other than the home's owner, the grants covering a path
+//! that name the caller or `*` are considered. The longest prefix wins, and
+//! on the same prefix a named grant beats `*`. With no such grant the zone
+//! rules apply. The owner and SYSTEM are never restricted.
+//!
+//! Version histories under `/sys/versions/` follow the grants of the file
+//! they record.
+
+use std::io::{self, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use super::path::VfsPath;
+use super::versions::VERSIONS_ROOT;
+
+/// VFS path of the grant list.
+pub const ACL_PATH: &str = "/sys/acl.json";
+
+/// Grantee matching every context except the owner.
+pub const EVERYONE: &str = "*";
+
+/// Access a grant gives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AclAccess {
+    /// No access: the path is private.
+    None,
+    Read,
+    /// Read and write.
+    Write,
+}
+
+impl AclAccess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+
+    /// Parse `none`, `read` or `write`.
+    pub fn parse(s: &str) -> io::Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            other => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid access '{other}' (expected 'none', 'read' or 'write')"),
+            )),
+        }
+    }
+}
+
+/// One grant: `context` gets `access` to `prefix` and everything below it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AclGrant {
+    pub prefix: String,
+    /// Grantee context name, or `*`.
+    pub context: String,
+    pub access: AclAccess,
+    pub created_at: u64,
+}
+
+impl AclGrant {
+    fn covers(&self, path: &str) -> bool {
+        path == self.prefix || path.starts_with(&format!("{}/", self.prefix))
+    }
+}
+
+/// The stored set of grants.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Acl {
+    grants: Vec<AclGrant>,
+}
+
+impl Acl {
+    pub fn grants(&self) -> &[AclGrant] {
+        &self.grants
+    }
+
+    /// Parse the stored grant list. A missing list is empty.
+    pub(super) fn parse(read: io::Result<Vec<u8>>) -> io::Result<Self> {
+        match read {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(|grants| Self { grants })
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("acl: {e}"))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub(super) fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(&self.grants)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Access `context` has to `path` by grant, or `None` when the zone rules
+    /// apply. Does not special-case the owner; see `home_owner`.
+    pub fn access_for(&self, context: &str, path: &VfsPath) -> Option<AclAccess> {
+        let subject = subject(path);
+        self.grants
+            .iter()
+            .filter(|g| g.context == context || g.context == EVERYONE)
+            .filter(|g| g.covers(subject))
+            .max_by_key(|g| (g.prefix.len(), g.context != EVERYONE))
+            .map(|g| g.access)
+    }
+
+    /// Grants on `path` or any directory above it, shortest prefix first.
+    pub fn covering(&self, path: &VfsPath) -> Vec<AclGrant> {
+        let subject = subject(path);
+        let mut grants: Vec<AclGrant> = self
+            .grants
+            .iter()
+            .filter(|g| g.covers(subject))
+            .cloned()
+            .collect();
+        grants.sort_by_key(|g| g.prefix.len());
+        grants
+    }
+
+    /// Grants on `prefix` or below it.
+    pub fn under(&self, prefix: &VfsPath) -> Vec<AclGrant> {
+        let prefix = prefix.as_str();
+        self.grants
+            .iter()
+            .filter(|g| g.prefix == prefix || g.prefix.starts_with(&format!("{prefix}/")))
+            .cloned()
+            .collect()
+    }
+
+    /// Add `grant`, replacing any grant for the same prefix and context.
+    pub(super) fn set(&mut self, grant: AclGrant) {
+        self.remove(&grant.prefix, &grant.context);
+        self.grants.push(grant);
+    }
+
+    /// Remove the grant for `prefix` and `context`. Returns whether one existed.
+    pub(super) fn remove(&mut self, prefix: &str, context: &str) -> bool {
+        let before = self.grants.len();
+        self.grants
+            .retain(|g| !(g.prefix == prefix && g.context == context));
+        self.grants.len() != before
+    }
+}
+
+/// The path whose grants apply to `path`: a version history maps to the
+/// file it records (`/sys/versions/home/a/f.md.versions/3` → `/home/a/f.md`).
+fn subject(path: &VfsPath) -> &str {
+    let p = path.as_str();
+    let Some(rest) = p.strip_prefix(VERSIONS_ROOT) else {
+        return p;
+    };
+    if !rest.starts_with('/') {
+        return p;
+    }
+    match rest.find(".versions/") {
+        Some(end) => &rest[..end],
+        None => rest.strip_suffix(".versions").unwrap_or(rest),
+    }
+}
+
+/// Owner of the home containing `path` (grants only apply inside homes).
+pub fn home_owner(path: &VfsPath) -> Option<&str> {
+    let owner = subject(path).strip_prefix("/home/")?.split('/').next()?;
+    (!owner.is_empty()).then_some(owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(prefix: &str, context: &str, access: AclAccess) -> AclGrant {
+        AclGrant {
+            prefix: prefix.to_string(),
+            context: context.to_string(),
+            access,
+            created_at: 0,
+        }
+    }
+
+    fn p(s: &str) -> VfsPath {
+        VfsPath::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_access_for_most_specific_grant_wins() {
+        let mut acl = Acl::default();
+        acl.set(grant("/home/alice", EVERYONE, AclAccess::None));
+        acl.set(grant("/home/alice/proj", "bob", AclAccess::Write));
+        acl.set(grant("/home/alice/proj/keys", EVERYONE, AclAccess::None));
+        acl.set(grant("/home/alice/pub", EVERYONE, AclAccess::Read));
+
+        assert_eq!(
+            acl.access_for("bob", &p("/home/alice/proj/a.md")),
+            Some(AclAccess::Write)
+        );
+        assert_eq!(
+            acl.access_for("carol", &p("/home/alice/proj/a.md")),
+            Some(AclAccess::None)
+        );
+        assert_eq!(
+            acl.access_for("bob", &p("/home/alice/proj/keys/k")),
+            Some(AclAccess::None)
+        );
+        assert_eq!(
+            acl.access_for("carol", &p("/home/alice/pub")),
+            Some(AclAccess::Read)
+        );
+        assert_eq!(acl.access_for("bob", &p("/home/alicex/a")), None);
+
+        // A named grant beats `*` on the same prefix.
+        acl.set(grant("/home/alice/pub", "carol", AclAccess::None));
+        assert_eq!(
+            acl.access_for("carol", &p("/home/alice/pub/x")),
+            Some(AclAccess::None)
+        );
+        assert!(acl.remove("/home/alice/pub", "carol"));
+        assert!(!acl.remove("/home/alice/pub", "carol"));
+    }
+
+    #[test]
+    fn test_version_histories_follow_their_file() {
+        let mut acl = Acl::default();
+        acl.set(grant("/home/alice/secret.md", EVERYONE, AclAccess::None));
+        let blob = p("/sys/versions/home/alice/secret.md.versions/2");
+        assert_eq!(home_owner(&blob), Some("alice"));
+        assert_eq!(acl.access_for("bob", &blob), Some(AclAccess::None));
+        let dir = p("/sys/versions/home/alice/secret.md.versions");
+        assert_eq!(acl.access_for("bob", &dir), Some(AclAccess::None));
+        assert_eq!(home_owner(&p("/home")), None);
+        assert_eq!(home_owner(&p("/shared/x")), None);
+    }
+}
@@ -48,6 +48,19 @@ pub trait VfsBackend: Send + Sync {
     /// Write (create or overwrite) a file with the given contents.
     fn write<'a>(&'a self, path: &'a VfsPath, data: &'a [u8]) -> BoxFuture<'a, io::Result<()>>;
 
+    /// Write several files as one operation.
+    ///
+    /// Backends with transactions apply all writes or none; the default
+    /// writes them in order and stops at the first error.
+    fn write_batch<'a>(&'a self, files: &'a [(VfsPath, Vec<u8>)]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            for (path, data) in files {
+                self.write(path, data).await?;
+            }
+            Ok(())
+        })
+    }
+
     /// Append data to an existing file, creating it if it doesn't exist.
     fn append<'a>(&'a self, path: &'a VfsPath, data: &'a [u8]) -> BoxFuture<'a, io::Result<()>>;
 
@@ -18,6 +18,7 @@ use serde::Serialize;
 use super::backend::{BoxFuture, ReadOnlyVfsBackend};
 use super::flock::{FlockRegistry, resolve_flock_vfs_root, site_flock_name};
 use super::path::VfsPath;
+use super::sqlite::SqliteBackend;
 use super::types::{VfsEntry, VfsEntryKind, VfsMetadata};
 use crate::context::{ContextEntry, ContextState};
 use crate::partition::{Manifest, PartitionManager, StorageConfig};
@@ -42,6 +43,9 @@ pub struct ContextsBackend {
     data_dir: PathBuf,
     /// Site identifier for flock membership lookups.
     site_id: String,
+    /// Store holding the flock registry when the root backend is `sqlite`.
+    /// `None` means the registry is a file under `<data_dir>/vfs/`.
+    registry_store: Option<SqliteBackend>,
 }
 
 /// JSON structure for `/sys/contexts/<name>/state.json`.
@@ -69,9 +73,16 @@ impl ContextsBackend {
             state,
             data_dir,
             site_id,
+            registry_store: None,
         }
     }
 
+    /// Read the flock registry from `store` instead of `<data_dir>/vfs/`.
+    pub fn with_registry_store(mut self, store: SqliteBackend) -> Self {
+        self.registry_store = Some(store);
+        self
+    }
+
     /// Look up a context entry by name. Returns `NotFound` if missing.
     fn find_context(&self, name: &str) -> io::Result<ContextEntry> {
         let state = self
@@ -106,16 +117,24 @@ impl ContextsBackend {
     /// Load flock registry from disk and return all flock names for `name`.
     ///
     /// Returns `(site_flock, explicit_flocks)`. Reads the registry directly from
-    /// disk (not VFS) to avoid a circular dependency — `ContextsBackend` is itself
-    /// a VFS backend.
+    /// storage (not VFS) to avoid a circular dependency — `ContextsBackend` is
+    /// itself a VFS backend.
     fn flocks_for_context(&self, name: &str) -> io::Result<(String, Vec<String>)> {
-        let vfs_root = self.data_dir.join("vfs");
-        let registry_path = vfs_root.join("flocks").join("registry.json");
-        let registry: FlockRegistry = if registry_path.exists() {
-            let data = std::fs::read_to_string(&registry_path)?;
-            serde_json::from_str(&data).unwrap_or_default()
+        let registry: FlockRegistry = if let Some(store) = &self.registry_store {
+            match store.read_blocking(&VfsPath::new("/flocks/registry.json")?) {
+                Ok(data) => serde_json::from_slice(&data).unwrap_or_default(),
+                Err(e) if e.kind() == ErrorKind::NotFound => FlockRegistry::default(),
+                Err(e) => return Err(e),
+            }
         } else {
-            FlockRegistry::default()
+            let vfs_root = self.data_dir.join("vfs");
+            let registry_path = vfs_root.join("flocks").join("registry.json");
+            if registry_path.exists() {
+                let data = std::fs::read_to_string(&registry_path)?;
+                serde_json::from_str(&data).unwrap_or_default()
+            } else {
+                FlockRegistry::default()
+            }
         };
         let site_flock = site_flock_name(&self.site_id);
         let explicit = registry.flocks_for(name, &site_flock);
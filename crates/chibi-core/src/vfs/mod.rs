@@ -21,6 +21,7 @@
 //! - **Middleware layers**: Composable tower-style layers (logging, caching)
 //!   wrapping backends (approach C in the design doc). Refactor when needed.
 
+pub mod acl;
 pub mod backend;
 pub mod caller;
 pub mod contexts_backend;
@@ -28,20 +29,109 @@ pub mod flock;
 pub mod local;
 pub mod path;
 pub mod permissions;
+pub mod quota;
+pub mod sqlite;
 pub mod tools_backend;
 pub mod types;
+pub mod versions;
 #[allow(clippy::module_inception)]
 mod vfs;
+pub mod watch;
 
+pub use acl::{Acl, AclAccess, AclGrant};
 pub use backend::{ReadOnlyVfsBackend, VfsBackend};
 pub use caller::VfsCaller;
 pub use contexts_backend::ContextsBackend;
 pub use local::LocalBackend;
 pub use path::VfsPath;
 pub use permissions::{check_read, check_write, is_reserved_caller_name};
+pub use quota::{QuotaZone, ZoneUsage};
+pub use sqlite::SqliteBackend;
 pub use tools_backend::ToolsBackend;
 pub use types::{VfsEntry, VfsEntryKind, VfsMetadata};
+pub use versions::{VersionEntry, VersionOp};
 pub use vfs::{ScmChangeCallback, ScmChangeKind, Vfs, VfsBuilder};
+pub use watch::{VfsChange, VfsChangeCallback, VfsChangeKind, Watch};
+
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::config::VfsConfig;
+
+/// The root (`/`) storage backend selected by `[vfs] backend`.
+pub enum RootBackend {
+    /// Files under `<chibi_dir>/vfs/`.
+    Local(LocalBackend),
+    /// One database at `<chibi_dir>/vfs.db`.
+    Sqlite(SqliteBackend),
+}
+
+impl RootBackend {
+    pub fn boxed(self) -> Box<dyn VfsBackend> {
+        match self {
+            Self::Local(backend) => Box::new(backend),
+            Self::Sqlite(backend) => Box::new(backend),
+        }
+    }
+}
+
+/// Open the root backend named by `config.backend` and bootstrap `/site/`.
+///
+/// The first time the `sqlite` backend is opened, an existing
+/// `<chibi_dir>/vfs/` tree is imported into the new database.
+pub fn open_root_backend(config: &VfsConfig, chibi_dir: &Path) -> io::Result<RootBackend> {
+    let vfs_root = chibi_dir.join("vfs");
+    match config.backend.as_str() {
+        "local" => {
+            std::fs::create_dir_all(&vfs_root)?;
+            // Bootstrap /site/ VFS directory (sync, directly on filesystem).
+            let _ = std::fs::create_dir_all(vfs_root.join("site"));
+            Ok(RootBackend::Local(LocalBackend::new(vfs_root)))
+        }
+        "sqlite" => {
+            let db_path = chibi_dir.join("vfs.db");
+            if !db_path.exists() && vfs_root.is_dir() {
+                import_local_tree(&vfs_root, &db_path)?;
+            }
+            let store = SqliteBackend::open(&db_path)?;
+            let _ = store.mkdir_blocking(&VfsPath::new("/site")?);
+            Ok(RootBackend::Sqlite(store))
+        }
+        other => Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("unsupported VFS backend '{other}' (built in: 'local', 'sqlite')"),
+        )),
+    }
+}
+
+/// Import `vfs_root` into a new database at `db_path`. The import is built in
+/// a side file and renamed into place once committed, so a failed import
+/// leaves no `vfs.db` behind and is retried on the next open.
+fn import_local_tree(vfs_root: &Path, db_path: &Path) -> io::Result<()> {
+    let staging = db_path.with_extension("db.import");
+    remove_db_files(&staging)?;
+    // Dropping the store closes the connection, which checkpoints the WAL
+    // into the main file before the rename.
+    let imported = SqliteBackend::open(&staging).and_then(|store| store.import_tree(vfs_root));
+    if let Err(e) = imported {
+        let _ = remove_db_files(&staging);
+        return Err(e);
+    }
+    std::fs::rename(&staging, db_path)
+}
+
+/// Remove a database file and its WAL sidecars, if present.
+fn remove_db_files(db_path: &Path) -> io::Result<()> {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
 
 #[cfg(test)]
 mod integration_tests {
@@ -250,4 +340,34 @@ mod integration_tests {
             .unwrap_err();
         assert_eq!(err.kind(), ErrorKind::PermissionDenied);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_sqlite_import_is_retried() {
+        let dir = TempDir::new().unwrap();
+        let tree = dir.path().join("vfs");
+        std::fs::create_dir_all(tree.join("shared")).unwrap();
+        std::fs::write(tree.join("shared/a.md"), b"alpha").unwrap();
+        // A dangling symlink can't be read, so the import fails.
+        let broken = tree.join("shared/broken");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &broken).unwrap();
+
+        let config = VfsConfig {
+            backend: "sqlite".to_string(),
+            ..Default::default()
+        };
+        assert!(open_root_backend(&config, dir.path()).is_err());
+        assert!(!dir.path().join("vfs.db").exists());
+
+        std::fs::remove_file(&broken).unwrap();
+        let RootBackend::Sqlite(store) = open_root_backend(&config, dir.path()).unwrap() else {
+            panic!("expected the sqlite backend");
+        };
+        assert_eq!(
+            store
+                .read_blocking(&VfsPath::new("/shared/a.md").unwrap())
+                .unwrap(),
+            b"alpha"
+        );
+    }
 }
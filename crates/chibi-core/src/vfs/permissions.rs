@@ -11,10 +11,15 @@
 //! - `/tools/shared/` — world-writable
 //! - `/tools/home/<context>/` — owner-writable
 //! - `/tools/flocks/<name>/` — flock members only
+//! - `/tools/lib/<zone>/` — scheme libraries, same rules as `/tools/<zone>/`
 //! - everything else at root level — read-only (only SYSTEM can write)
+//!
+//! Inside `/home/<context>/`, the owner's sharing grants (`acl`) can make a
+//! path private or give another context write access.
 
 use std::io::{self, ErrorKind};
 
+use super::acl::{Acl, AclAccess, home_owner};
 use super::caller::VfsCaller;
 use super::flock::{FlockRegistry, site_flock_name};
 use super::path::VfsPath;
@@ -24,8 +29,24 @@ pub fn is_reserved_caller_name(name: &str) -> bool {
     name.eq_ignore_ascii_case("SYSTEM")
 }
 
-/// Check read permission. Always succeeds — all zones are world-readable.
-pub fn check_read(_caller: VfsCaller<'_>, _path: &VfsPath) -> io::Result<()> {
+/// Check read permission. All zones are world-readable, except paths in
+/// another context's home that a grant in `acl` makes private.
+///
+/// Pass `None` for `acl` when no grants are loaded (everything is readable).
+pub fn check_read(caller: VfsCaller<'_>, path: &VfsPath, acl: Option<&Acl>) -> io::Result<()> {
+    let VfsCaller::Context(name) = caller else {
+        return Ok(());
+    };
+    if let Some(owner) = home_owner(path)
+        && owner != name
+        && let Some(acl) = acl
+        && acl.access_for(name, path) == Some(AclAccess::None)
+    {
+        return Err(io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("'{}' is private to context '{}'", path, owner),
+        ));
+    }
     Ok(())
 }
 
@@ -34,17 +55,19 @@ pub fn check_read(_caller: VfsCaller<'_>, _path: &VfsPath) -> io::Result<()> {
 /// `VfsCaller::System` has unrestricted write access to all zones.
 /// `VfsCaller::Context` is subject to zone-based rules:
 /// - `/shared/` — world-writable
-/// - `/home/<ctx>/` — owner-writable
+/// - `/home/<ctx>/` — owner-writable, plus contexts granted `write` in `acl`
 /// - `/site/` — world-writable
 /// - `/flocks/registry.json` — System only
 /// - `/flocks/<name>/*` — flock members (requires `flock_ctx`)
 ///
 /// `flock_ctx` is `Some((registry, site_id))` when the caller has flock membership data
 /// available. Pass `None` to skip flock membership checks (permission denied for `/flocks/`).
+/// `acl` holds the sharing grants; `None` means no grants apply.
 pub fn check_write(
     caller: VfsCaller<'_>,
     path: &VfsPath,
     flock_ctx: Option<(&FlockRegistry, &str)>,
+    acl: Option<&Acl>,
 ) -> io::Result<()> {
     let p = path.as_str();
 
@@ -76,6 +99,11 @@ pub fn check_write(
         if owner == name {
             return Ok(());
         }
+        if let Some(acl) = acl
+            && acl.access_for(name, path) == Some(AclAccess::Write)
+        {
+            return Ok(());
+        }
     }
 
     // /site/ — world-writable (shared site-flock area)
@@ -113,6 +141,13 @@ pub fn check_write(
         }
     }
 
+    // /tools/lib/<zone>/ — scheme libraries follow the rules of the tool zone
+    // they mirror (/tools/lib/home/<ctx>/ is owner-writable, and so on).
+    let lib_mirror = p
+        .strip_prefix("/tools/lib/")
+        .map(|rest| format!("/tools/{rest}"));
+    let p = lib_mirror.as_deref().unwrap_or(p);
+
     // /tools/shared/ — world-writable (synthesised tools shared across all contexts)
     if p == "/tools/shared" || p.starts_with("/tools/shared/") {
         return Ok(());
@@ -161,6 +196,7 @@ pub fn check_write(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vfs::acl::{AclGrant, EVERYONE};
     use crate::vfs::{VfsPath, flock::FlockRegistry};
 
     #[test]
@@ -169,40 +205,40 @@ mod tests {
         let shared = VfsPath::new("/shared/foo").unwrap();
         let home = VfsPath::new("/home/ctx/file").unwrap();
         let flocks_reg = VfsPath::new("/flocks/registry.json").unwrap();
-        assert!(check_write(VfsCaller::System, &sys, None).is_ok());
-        assert!(check_write(VfsCaller::System, &shared, None).is_ok());
-        assert!(check_write(VfsCaller::System, &home, None).is_ok());
-        assert!(check_write(VfsCaller::System, &flocks_reg, None).is_ok());
+        assert!(check_write(VfsCaller::System, &sys, None, None).is_ok());
+        assert!(check_write(VfsCaller::System, &shared, None, None).is_ok());
+        assert!(check_write(VfsCaller::System, &home, None, None).is_ok());
+        assert!(check_write(VfsCaller::System, &flocks_reg, None, None).is_ok());
     }
 
     #[test]
     fn test_context_can_write_shared() {
         let p = VfsPath::new("/shared/tasks.md").unwrap();
-        assert!(check_write(VfsCaller::Context("planner"), &p, None).is_ok());
+        assert!(check_write(VfsCaller::Context("planner"), &p, None, None).is_ok());
     }
 
     #[test]
     fn test_context_can_write_own_home() {
         let p = VfsPath::new("/home/planner/notes.md").unwrap();
-        assert!(check_write(VfsCaller::Context("planner"), &p, None).is_ok());
+        assert!(check_write(VfsCaller::Context("planner"), &p, None, None).is_ok());
     }
 
     #[test]
     fn test_context_cannot_write_other_home() {
         let p = VfsPath::new("/home/coder/notes.md").unwrap();
-        assert!(check_write(VfsCaller::Context("planner"), &p, None).is_err());
+        assert!(check_write(VfsCaller::Context("planner"), &p, None, None).is_err());
     }
 
     #[test]
     fn test_context_cannot_write_sys() {
         let p = VfsPath::new("/sys/info").unwrap();
-        assert!(check_write(VfsCaller::Context("planner"), &p, None).is_err());
+        assert!(check_write(VfsCaller::Context("planner"), &p, None, None).is_err());
     }
 
     #[test]
     fn test_context_cannot_write_root() {
         let p = VfsPath::new("/random_file").unwrap();
-        assert!(check_write(VfsCaller::Context("planner"), &p, None).is_err());
+        assert!(check_write(VfsCaller::Context("planner"), &p, None, None).is_err());
     }
 
     #[test]
@@ -210,7 +246,7 @@ mod tests {
         let paths = ["/shared/x", "/home/other/x", "/sys/x", "/root_file"];
         for p in &paths {
             let path = VfsPath::new(p).unwrap();
-            assert!(check_read(VfsCaller::Context("anyctx"), &path).is_ok());
+            assert!(check_read(VfsCaller::Context("anyctx"), &path, None).is_ok());
         }
     }
 
@@ -227,13 +263,13 @@ mod tests {
     #[test]
     fn test_context_can_write_site() {
         let path = VfsPath::new("/site/goals.md").unwrap();
-        assert!(check_write(VfsCaller::Context("any"), &path, None).is_ok());
+        assert!(check_write(VfsCaller::Context("any"), &path, None, None).is_ok());
     }
 
     #[test]
     fn test_context_can_write_site_root() {
         let path = VfsPath::new("/site").unwrap();
-        assert!(check_write(VfsCaller::Context("any"), &path, None).is_ok());
+        assert!(check_write(VfsCaller::Context("any"), &path, None, None).is_ok());
     }
 
     // --- /flocks/ zone ---
@@ -241,13 +277,13 @@ mod tests {
     #[test]
     fn test_context_cannot_write_flocks_registry() {
         let path = VfsPath::new("/flocks/registry.json").unwrap();
-        assert!(check_write(VfsCaller::Context("ctx"), &path, None).is_err());
+        assert!(check_write(VfsCaller::Context("ctx"), &path, None, None).is_err());
     }
 
     #[test]
     fn test_system_can_write_flocks_registry() {
         let path = VfsPath::new("/flocks/registry.json").unwrap();
-        assert!(check_write(VfsCaller::System, &path, None).is_ok());
+        assert!(check_write(VfsCaller::System, &path, None, None).is_ok());
     }
 
     #[test]
@@ -255,21 +291,37 @@ mod tests {
         let mut reg = FlockRegistry::default();
         reg.add_member("frontend", "ui-dev", "site:abc");
         let path = VfsPath::new("/flocks/frontend/goals.md").unwrap();
-        assert!(check_write(VfsCaller::Context("ui-dev"), &path, Some((&reg, "abc"))).is_ok());
+        assert!(
+            check_write(
+                VfsCaller::Context("ui-dev"),
+                &path,
+                Some((&reg, "abc")),
+                None
+            )
+            .is_ok()
+        );
     }
 
     #[test]
     fn test_non_member_cannot_write_flock() {
         let reg = FlockRegistry::default();
         let path = VfsPath::new("/flocks/frontend/goals.md").unwrap();
-        assert!(check_write(VfsCaller::Context("outsider"), &path, Some((&reg, "abc"))).is_err());
+        assert!(
+            check_write(
+                VfsCaller::Context("outsider"),
+                &path,
+                Some((&reg, "abc")),
+                None
+            )
+            .is_err()
+        );
     }
 
     #[test]
     fn test_flock_write_without_registry_denied() {
         let path = VfsPath::new("/flocks/frontend/goals.md").unwrap();
         // No registry provided → denied
-        assert!(check_write(VfsCaller::Context("ui-dev"), &path, None).is_err());
+        assert!(check_write(VfsCaller::Context("ui-dev"), &path, None, None).is_err());
     }
 
     // --- /tools/ zones ---
@@ -277,50 +329,50 @@ mod tests {
     #[test]
     fn test_tools_sys_read_allowed() {
         let path = VfsPath::new("/tools/sys/shell_exec").unwrap();
-        assert!(check_read(VfsCaller::Context("any"), &path).is_ok());
+        assert!(check_read(VfsCaller::Context("any"), &path, None).is_ok());
     }
 
     #[test]
     fn test_tools_sys_write_denied_for_context() {
         let path = VfsPath::new("/tools/sys/shell_exec").unwrap();
-        assert!(check_write(VfsCaller::Context("any"), &path, None).is_err());
+        assert!(check_write(VfsCaller::Context("any"), &path, None, None).is_err());
     }
 
     #[test]
     fn test_tools_sys_write_denied_for_system() {
         // /tools/sys/ is virtual — even SYSTEM cannot write
         let path = VfsPath::new("/tools/sys/anything").unwrap();
-        assert!(check_write(VfsCaller::System, &path, None).is_err());
+        assert!(check_write(VfsCaller::System, &path, None, None).is_err());
     }
 
     #[test]
     fn test_tools_sys_root_write_denied_for_system() {
         let path = VfsPath::new("/tools/sys").unwrap();
-        assert!(check_write(VfsCaller::System, &path, None).is_err());
+        assert!(check_write(VfsCaller::System, &path, None, None).is_err());
     }
 
     #[test]
     fn test_tools_shared_writable_by_context() {
         let path = VfsPath::new("/tools/shared/my_tool.scm").unwrap();
-        assert!(check_write(VfsCaller::Context("any"), &path, None).is_ok());
+        assert!(check_write(VfsCaller::Context("any"), &path, None, None).is_ok());
     }
 
     #[test]
     fn test_tools_shared_root_writable() {
         let path = VfsPath::new("/tools/shared").unwrap();
-        assert!(check_write(VfsCaller::Context("any"), &path, None).is_ok());
+        assert!(check_write(VfsCaller::Context("any"), &path, None, None).is_ok());
     }
 
     #[test]
     fn test_tools_home_owner_writable() {
         let path = VfsPath::new("/tools/home/alice/my_tool.scm").unwrap();
-        assert!(check_write(VfsCaller::Context("alice"), &path, None).is_ok());
+        assert!(check_write(VfsCaller::Context("alice"), &path, None, None).is_ok());
     }
 
     #[test]
     fn test_tools_home_non_owner_denied() {
         let path = VfsPath::new("/tools/home/alice/my_tool.scm").unwrap();
-        assert!(check_write(VfsCaller::Context("bob"), &path, None).is_err());
+        assert!(check_write(VfsCaller::Context("bob"), &path, None, None).is_err());
     }
 
     #[test]
@@ -328,13 +380,62 @@ mod tests {
         let mut reg = FlockRegistry::default();
         reg.add_member("devteam", "alice", "site:abc");
         let path = VfsPath::new("/tools/flocks/devteam/shared_tool.scm").unwrap();
-        assert!(check_write(VfsCaller::Context("alice"), &path, Some((&reg, "abc"))).is_ok());
+        assert!(
+            check_write(
+                VfsCaller::Context("alice"),
+                &path,
+                Some((&reg, "abc")),
+                None
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_tools_lib_follows_zone_rules() {
+        let shared = VfsPath::new("/tools/lib/shared/strings.sld").unwrap();
+        assert!(check_write(VfsCaller::Context("any"), &shared, None, None).is_ok());
+        let home = VfsPath::new("/tools/lib/home/alice/strings.sld").unwrap();
+        assert!(check_write(VfsCaller::Context("alice"), &home, None, None).is_ok());
+        assert!(check_write(VfsCaller::Context("bob"), &home, None, None).is_err());
+        let flock = VfsPath::new("/tools/lib/flocks/devteam/strings.sld").unwrap();
+        assert!(check_write(VfsCaller::Context("bob"), &flock, None, None).is_err());
+        let root = VfsPath::new("/tools/lib/strings.sld").unwrap();
+        assert!(check_write(VfsCaller::Context("any"), &root, None, None).is_err());
     }
 
     #[test]
     fn test_tools_flocks_non_member_denied() {
         let reg = FlockRegistry::default();
         let path = VfsPath::new("/tools/flocks/devteam/shared_tool.scm").unwrap();
-        assert!(check_write(VfsCaller::Context("bob"), &path, Some((&reg, "abc"))).is_err());
+        assert!(check_write(VfsCaller::Context("bob"), &path, Some((&reg, "abc")), None).is_err());
+    }
+
+    // --- sharing grants ---
+
+    #[test]
+    fn test_acl_private_and_shared_paths() {
+        let mut acl = Acl::default();
+        for (prefix, context, access) in [
+            ("/home/alice/private", EVERYONE, AclAccess::None),
+            ("/home/alice/proj", "bob", AclAccess::Write),
+        ] {
+            acl.set(AclGrant {
+                prefix: prefix.to_string(),
+                context: context.to_string(),
+                access,
+                created_at: 0,
+            });
+        }
+        let acl = Some(&acl);
+        let secret = VfsPath::new("/home/alice/private/keys.md").unwrap();
+        let proj = VfsPath::new("/home/alice/proj/plan.md").unwrap();
+
+        assert!(check_read(VfsCaller::Context("bob"), &secret, acl).is_err());
+        assert!(check_read(VfsCaller::Context("alice"), &secret, acl).is_ok());
+        assert!(check_read(VfsCaller::System, &secret, acl).is_ok());
+        assert!(check_write(VfsCaller::Context("bob"), &proj, None, acl).is_ok());
+        assert!(check_write(VfsCaller::Context("carol"), &proj, None, acl).is_err());
+        assert!(check_read(VfsCaller::Context("carol"), &proj, acl).is_ok());
     }
 }
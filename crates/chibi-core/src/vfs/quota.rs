@@ -0,0 +1,205 @@
+//! Per-zone storage quotas enforced by the `Vfs` router.
+//!
+//! Each quota zone instance is one directory tree:
+//!
+//! ```text
+//! /home/<ctx>/      home    (one budget per context)
+//! /flocks/<name>/   flock   (one budget per flock)
+//! /site/            flock
+//! /shared/          shared
+//! /tools/           tools   (excluding the virtual /tools/sys/)
+//! ```
+//!
+//! Everything else (`/sys/`, `/flocks/registry.json`) is unlimited. Usage is
+//! measured by walking a zone the first time it is written to, then tracked
+//! by the router as files change.
+
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind};
+
+use serde::Serialize;
+
+use super::path::VfsPath;
+use crate::config::{QuotaConfig, ZoneQuota};
+
+/// Kind of quota zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaZone {
+    Home,
+    Flock,
+    Shared,
+    Tools,
+}
+
+impl QuotaZone {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Home => "home",
+            Self::Flock => "flock",
+            Self::Shared => "shared",
+            Self::Tools => "tools",
+        }
+    }
+
+    /// Configured limits for this kind of zone.
+    pub fn limits(self, config: &QuotaConfig) -> ZoneQuota {
+        match self {
+            Self::Home => config.home,
+            Self::Flock => config.flock,
+            Self::Shared => config.shared,
+            Self::Tools => config.tools,
+        }
+    }
+}
+
+/// Bytes and files currently stored in a zone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZoneTotals {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// Usage report for one zone instance.
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneUsage {
+    pub zone: QuotaZone,
+    /// Zone root, e.g. `/home/alice`.
+    pub root: String,
+    pub bytes: u64,
+    pub files: u64,
+    pub max_bytes: Option<u64>,
+    pub max_files: Option<u64>,
+}
+
+/// Quota zone containing `path`, with the zone's root directory.
+pub fn zone_of(path: &VfsPath) -> Option<(QuotaZone, VfsPath)> {
+    let p = path.as_str();
+    let mut parts = p.trim_start_matches('/').splitn(3, '/');
+    let top = parts.next()?;
+    let second = parts.next();
+    let root = |s: &str| VfsPath::new(s).ok();
+    match (top, second) {
+        ("shared", _) => Some((QuotaZone::Shared, root("/shared")?)),
+        ("site", _) => Some((QuotaZone::Flock, root("/site")?)),
+        ("tools", Some("sys")) => None,
+        ("tools", _) => Some((QuotaZone::Tools, root("/tools")?)),
+        ("home", Some(ctx)) => Some((QuotaZone::Home, root(&format!("/home/{ctx}"))?)),
+        // A bare `/flocks/<name>` file (the registry) is not a flock directory.
+        ("flocks", Some(name)) if p.len() > "/flocks/".len() + name.len() => {
+            Some((QuotaZone::Flock, root(&format!("/flocks/{name}"))?))
+        }
+        _ => None,
+    }
+}
+
+/// Fail with `QuotaExceeded` if going from `before` to `after` grows the zone
+/// past a limit. Changes that shrink an over-quota zone are allowed.
+pub(super) fn check(
+    zone: QuotaZone,
+    root: &VfsPath,
+    limits: ZoneQuota,
+    before: ZoneTotals,
+    after: ZoneTotals,
+) -> io::Result<()> {
+    let over = |limit: Option<u64>, before: u64, after: u64| {
+        limit.filter(|&max| after > max && after > before)
+    };
+    if let Some(max) = over(limits.max_bytes, before.bytes, after.bytes) {
+        return Err(io::Error::new(
+            ErrorKind::QuotaExceeded,
+            format!(
+                "VFS quota exceeded for {root}: {} bytes would exceed the {} limit of {max} bytes",
+                after.bytes,
+                zone.as_str()
+            ),
+        ));
+    }
+    if let Some(max) = over(limits.max_files, before.files, after.files) {
+        return Err(io::Error::new(
+            ErrorKind::QuotaExceeded,
+            format!(
+                "VFS quota exceeded for {root}: {} files would exceed the {} limit of {max} files",
+                after.files,
+                zone.as_str()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Render a usage report as aligned text, one zone per line.
+pub fn render_usage(rows: &[ZoneUsage]) -> String {
+    let limit = |value: Option<u64>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+    let mut out = format!(
+        "{:<7} {:<28} {:>12} {:>12} {:>8} {:>8}\n",
+        "zone", "root", "bytes", "max_bytes", "files", "max_files"
+    );
+    for row in rows {
+        let _ = writeln!(
+            out,
+            "{:<7} {:<28} {:>12} {:>12} {:>8} {:>8}",
+            row.zone.as_str(),
+            row.root,
+            row.bytes,
+            limit(row.max_bytes),
+            row.files,
+            limit(row.max_files)
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(s: &str) -> Option<(QuotaZone, String)> {
+        zone_of(&VfsPath::new(s).unwrap()).map(|(z, root)| (z, root.as_str().to_string()))
+    }
+
+    #[test]
+    fn test_zone_of() {
+        assert_eq!(
+            zone("/home/alice/notes/a.md"),
+            Some((QuotaZone::Home, "/home/alice".into()))
+        );
+        assert_eq!(
+            zone("/flocks/dev/goals.md"),
+            Some((QuotaZone::Flock, "/flocks/dev".into()))
+        );
+        assert_eq!(
+            zone("/site/prompt.md"),
+            Some((QuotaZone::Flock, "/site".into()))
+        );
+        assert_eq!(
+            zone("/shared/x"),
+            Some((QuotaZone::Shared, "/shared".into()))
+        );
+        assert_eq!(
+            zone("/tools/home/alice/t.scm"),
+            Some((QuotaZone::Tools, "/tools".into()))
+        );
+        assert_eq!(zone("/tools/sys/shell_exec"), None);
+        assert_eq!(zone("/flocks/registry.json"), None);
+        assert_eq!(zone("/sys/tool_cache/ctx/x"), None);
+        assert_eq!(zone("/home"), None);
+    }
+
+    #[test]
+    fn test_check_allows_shrinking_over_quota() {
+        let root = VfsPath::new("/shared").unwrap();
+        let limits = ZoneQuota {
+            max_bytes: Some(100),
+            max_files: Some(2),
+        };
+        let totals = |bytes, files| ZoneTotals { bytes, files };
+        let run = |before, after| check(QuotaZone::Shared, &root, limits, before, after);
+        assert!(run(totals(50, 1), totals(100, 2)).is_ok());
+        let err = run(totals(50, 1), totals(101, 2)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::QuotaExceeded);
+        assert!(err.to_string().contains("shared limit of 100 bytes"));
+        assert!(run(totals(50, 2), totals(50, 3)).is_err());
+        assert!(run(totals(200, 3), totals(150, 3)).is_ok());
+    }
+}
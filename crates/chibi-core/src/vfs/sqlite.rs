@@ -0,0 +1,634 @@
+//! SQLite backend for the VFS.
+//!
+//! Stores the whole VFS tree in one database file (typically
+//! `~/.chibi/vfs.db`), in WAL mode like the codebase index. One row per
+//! entry; directories are explicit rows and `/` is implicit:
+//!
+//! ```text
+//! entries(path PRIMARY KEY, parent, name, is_dir, data, created, modified)
+//! ```
+//!
+//! `parent` is indexed, so listing a directory is a single index range scan no
+//! matter how many siblings it has. `write_batch` commits several files in one
+//! transaction. Backing up or copying a chibi home means copying one file (or
+//! `backup_to`, which also works while chibi is running).
+//!
+//! Calls run synchronously inside the returned futures: statements are short,
+//! and `rusqlite::Connection` is guarded by a mutex, since `VfsBackend` must be
+//! `Sync`. Clones share the same connection.
+
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+
+use super::backend::{BoxFuture, VfsBackend};
+use super::path::VfsPath;
+use super::types::{VfsEntry, VfsEntryKind, VfsMetadata};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        path     TEXT PRIMARY KEY,
+        parent   TEXT NOT NULL,
+        name     TEXT NOT NULL,
+        is_dir   INTEGER NOT NULL,
+        data     BLOB,
+        created  TEXT NOT NULL,
+        modified TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_entries_parent ON entries(parent);
+";
+
+/// SQLite-backed VFS storage.
+#[derive(Clone)]
+pub struct SqliteBackend {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn sql_err(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("vfs sqlite: {e}"))
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("'{path}' not found"))
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339()
+}
+
+/// Upper bound for a descendant range scan: every path below `dir` sorts in
+/// `[dir/, dir0)` because `'0'` is the byte after `'/'`.
+fn subtree_bounds(dir: &str) -> (String, String) {
+    if dir == "/" {
+        ("/".to_string(), "0".to_string())
+    } else {
+        (format!("{dir}/"), format!("{dir}0"))
+    }
+}
+
+impl SqliteBackend {
+    /// Open (or create) the database at `path`, enable WAL mode and create the
+    /// schema.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let conn = Connection::open(path).map_err(sql_err)?;
+        conn.pragma_update(None, "journal_mode", "wal")
+            .map_err(sql_err)?;
+        conn.execute_batch(SCHEMA).map_err(sql_err)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn lock(&self) -> io::Result<MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| io::Error::other("vfs sqlite: connection mutex poisoned"))
+    }
+
+    /// Read a file synchronously. For components that can't await, such as
+    /// `ContextsBackend` reading the flock registry.
+    pub fn read_blocking(&self, path: &VfsPath) -> io::Result<Vec<u8>> {
+        let conn = self.lock()?;
+        let row: Option<(bool, Option<Vec<u8>>)> = conn
+            .query_row(
+                "SELECT is_dir, data FROM entries WHERE path = ?1",
+                [path.as_str()],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .optional()
+            .map_err(sql_err)?;
+        match row {
+            Some((false, data)) => Ok(data.unwrap_or_default()),
+            Some((true, _)) => Err(io::Error::new(
+                ErrorKind::IsADirectory,
+                format!("'{path}' is a directory"),
+            )),
+            None if path.as_str() == "/" => Err(io::Error::new(
+                ErrorKind::IsADirectory,
+                "'/' is a directory",
+            )),
+            None => Err(not_found(path.as_str())),
+        }
+    }
+
+    /// Create a directory (and its parents) synchronously.
+    pub fn mkdir_blocking(&self, path: &VfsPath) -> io::Result<()> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(sql_err)?;
+        ensure_dirs(&tx, path)?;
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Copy every file and directory under the OS directory `root` into the
+    /// database, in one transaction. Used to migrate a `local` VFS tree.
+    pub fn import_tree(&self, root: &Path) -> io::Result<usize> {
+        fn walk(tx: &Transaction<'_>, dir: &Path, vfs_dir: &str) -> io::Result<usize> {
+            let mut imported = 0;
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue; // non-UTF-8 names can't be VFS paths
+                };
+                let child = if vfs_dir == "/" {
+                    format!("/{name}")
+                } else {
+                    format!("{vfs_dir}/{name}")
+                };
+                let Ok(path) = VfsPath::new(&child) else {
+                    continue;
+                };
+                if entry.file_type()?.is_dir() {
+                    ensure_dirs(tx, &path)?;
+                    imported += walk(tx, &entry.path(), &child)?;
+                } else {
+                    put_file(tx, &path, &std::fs::read(entry.path())?)?;
+                    imported += 1;
+                }
+            }
+            Ok(imported)
+        }
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(sql_err)?;
+        let imported = walk(&tx, root, "/")?;
+        tx.commit().map_err(sql_err)?;
+        Ok(imported)
+    }
+
+    /// Write a consistent copy of the database to `dest` (`VACUUM INTO`).
+    /// Safe while other connections are reading or writing.
+    pub fn backup_to(&self, dest: &Path) -> io::Result<()> {
+        let dest = dest.to_str().ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidInput, "backup path must be valid UTF-8")
+        })?;
+        self.lock()?
+            .execute("VACUUM INTO ?1", [dest])
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+}
+
+/// `Some(true)` for a directory, `Some(false)` for a file, `None` if absent.
+fn kind_of(conn: &Connection, path: &str) -> io::Result<Option<bool>> {
+    if path == "/" {
+        return Ok(Some(true));
+    }
+    conn.query_row("SELECT is_dir FROM entries WHERE path = ?1", [path], |r| {
+        r.get(0)
+    })
+    .optional()
+    .map_err(sql_err)
+}
+
+/// Create `dir` and any missing ancestors. Fails if one of them is a file.
+fn ensure_dirs(tx: &Transaction<'_>, dir: &VfsPath) -> io::Result<()> {
+    let mut missing = Vec::new();
+    let mut current = Some(dir.clone());
+    while let Some(path) = current {
+        match kind_of(tx, path.as_str())? {
+            Some(true) => break,
+            Some(false) => {
+                return Err(io::Error::new(
+                    ErrorKind::NotADirectory,
+                    format!("'{path}' is a file"),
+                ));
+            }
+            None => {
+                current = path.parent();
+                missing.push(path);
+            }
+        }
+    }
+    let ts = now();
+    for path in missing.iter().rev() {
+        let parent = path.parent().map(|p| p.as_str().to_string());
+        tx.execute(
+            "INSERT INTO entries (path, parent, name, is_dir, data, created, modified)
+             VALUES (?1, ?2, ?3, 1, NULL, ?4, ?4)",
+            params![
+                path.as_str(),
+                parent.unwrap_or_default(),
+                path.file_name().unwrap_or_default(),
+                ts
+            ],
+        )
+        .map_err(sql_err)?;
+    }
+    Ok(())
+}
+
+/// Create or overwrite the file at `path`, creating parent directories.
+fn put_file(tx: &Transaction<'_>, path: &VfsPath, data: &[u8]) -> io::Result<()> {
+    if kind_of(tx, path.as_str())? == Some(true) {
+        return Err(io::Error::new(
+            ErrorKind::IsADirectory,
+            format!("'{path}' is a directory"),
+        ));
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| io::Error::new(ErrorKind::IsADirectory, "'/' is a directory"))?;
+    ensure_dirs(tx, &parent)?;
+    tx.execute(
+        "INSERT INTO entries (path, parent, name, is_dir, data, created, modified)
+         VALUES (?1, ?2, ?3, 0, ?4, ?5, ?5)
+         ON CONFLICT(path) DO UPDATE SET data = excluded.data, modified = excluded.modified",
+        params![
+            path.as_str(),
+            parent.as_str(),
+            path.file_name().unwrap_or_default(),
+            data,
+            now()
+        ],
+    )
+    .map_err(sql_err)?;
+    Ok(())
+}
+
+fn parse_ts(ts: Option<String>) -> Option<DateTime<Utc>> {
+    ts.and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+impl VfsBackend for SqliteBackend {
+    fn read<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<Vec<u8>>> {
+        Box::pin(async move { self.read_blocking(path) })
+    }
+
+    fn write<'a>(&'a self, path: &'a VfsPath, data: &'a [u8]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            put_file(&tx, path, data)?;
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn write_batch<'a>(&'a self, files: &'a [(VfsPath, Vec<u8>)]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            for (path, data) in files {
+                put_file(&tx, path, data)?;
+            }
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn append<'a>(&'a self, path: &'a VfsPath, data: &'a [u8]) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            let existing: Option<Option<Vec<u8>>> = tx
+                .query_row(
+                    "SELECT data FROM entries WHERE path = ?1 AND is_dir = 0",
+                    [path.as_str()],
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(sql_err)?;
+            let mut content = existing.flatten().unwrap_or_default();
+            content.extend_from_slice(data);
+            put_file(&tx, path, &content)?;
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn delete<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            match kind_of(&tx, path.as_str())? {
+                None => return Err(not_found(path.as_str())),
+                Some(true) => {
+                    let (lo, hi) = subtree_bounds(path.as_str());
+                    tx.execute(
+                        "DELETE FROM entries WHERE path >= ?1 AND path < ?2",
+                        params![lo, hi],
+                    )
+                    .map_err(sql_err)?;
+                }
+                Some(false) => {}
+            }
+            tx.execute("DELETE FROM entries WHERE path = ?1", [path.as_str()])
+                .map_err(sql_err)?;
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn list<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<Vec<VfsEntry>>> {
+        Box::pin(async move {
+            let conn = self.lock()?;
+            match kind_of(&conn, path.as_str())? {
+                None => return Ok(Vec::new()),
+                Some(false) => {
+                    return Err(io::Error::new(
+                        ErrorKind::NotADirectory,
+                        format!("'{path}' is a file"),
+                    ));
+                }
+                Some(true) => {}
+            }
+            let mut stmt = conn
+                .prepare_cached("SELECT name, is_dir FROM entries WHERE parent = ?1 ORDER BY name")
+                .map_err(sql_err)?;
+            let rows = stmt
+                .query_map([path.as_str()], |r| {
+                    let is_dir: bool = r.get(1)?;
+                    Ok(VfsEntry {
+                        name: r.get(0)?,
+                        kind: if is_dir {
+                            VfsEntryKind::Directory
+                        } else {
+                            VfsEntryKind::File
+                        },
+                    })
+                })
+                .map_err(sql_err)?;
+            rows.collect::<Result<_, _>>().map_err(sql_err)
+        })
+    }
+
+    fn exists<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<bool>> {
+        Box::pin(async move { Ok(kind_of(&*self.lock()?, path.as_str())?.is_some()) })
+    }
+
+    fn mkdir<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move { self.mkdir_blocking(path) })
+    }
+
+    fn copy<'a>(&'a self, src: &'a VfsPath, dst: &'a VfsPath) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let data = self.read_blocking(src)?;
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            put_file(&tx, dst, &data)?;
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn rename<'a>(&'a self, src: &'a VfsPath, dst: &'a VfsPath) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let mut conn = self.lock()?;
+            let tx = conn.transaction().map_err(sql_err)?;
+            let src_is_dir = kind_of(&tx, src.as_str())?.ok_or_else(|| not_found(src.as_str()))?;
+            let dst_parent = dst
+                .parent()
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "cannot rename onto '/'"))?;
+            if src_is_dir && dst.as_str().starts_with(&format!("{src}/")) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("cannot move '{src}' into itself"),
+                ));
+            }
+            match (src_is_dir, kind_of(&tx, dst.as_str())?) {
+                (_, None) => {}
+                (false, Some(false)) => {
+                    tx.execute("DELETE FROM entries WHERE path = ?1", [dst.as_str()])
+                        .map_err(sql_err)?;
+                }
+                (_, Some(_)) => {
+                    return Err(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("'{dst}' already exists"),
+                    ));
+                }
+            }
+            ensure_dirs(&tx, &dst_parent)?;
+            // Rewrite the entry and (for directories) every descendant.
+            let (lo, hi) = subtree_bounds(src.as_str());
+            tx.execute(
+                "UPDATE entries SET
+                     path = ?2 || substr(path, length(?1) + 1),
+                     parent = CASE WHEN path = ?1 THEN ?3
+                                   ELSE ?2 || substr(parent, length(?1) + 1) END,
+                     name = CASE WHEN path = ?1 THEN ?4 ELSE name END
+                 WHERE path = ?1 OR (path >= ?5 AND path < ?6)",
+                params![
+                    src.as_str(),
+                    dst.as_str(),
+                    dst_parent.as_str(),
+                    dst.file_name().unwrap_or_default(),
+                    lo,
+                    hi
+                ],
+            )
+            .map_err(sql_err)?;
+            tx.commit().map_err(sql_err)
+        })
+    }
+
+    fn metadata<'a>(&'a self, path: &'a VfsPath) -> BoxFuture<'a, io::Result<VfsMetadata>> {
+        Box::pin(async move {
+            if path.as_str() == "/" {
+                return Ok(VfsMetadata {
+                    size: 0,
+                    created: None,
+                    modified: None,
+                    kind: VfsEntryKind::Directory,
+                });
+            }
+            let conn = self.lock()?;
+            let row: Option<(bool, i64, Option<String>, Option<String>)> = conn
+                .query_row(
+                    "SELECT is_dir, COALESCE(length(data), 0), created, modified
+                     FROM entries WHERE path = ?1",
+                    [path.as_str()],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+                )
+                .optional()
+                .map_err(sql_err)?;
+            let (is_dir, size, created, modified) = row.ok_or_else(|| not_found(path.as_str()))?;
+            Ok(VfsMetadata {
+                size: size as u64,
+                created: parse_ts(created),
+                modified: parse_ts(modified),
+                kind: if is_dir {
+                    VfsEntryKind::Directory
+                } else {
+                    VfsEntryKind::File
+                },
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, SqliteBackend) {
+        let dir = TempDir::new().unwrap();
+        let backend = SqliteBackend::open(&dir.path().join("vfs.db")).unwrap();
+        (dir, backend)
+    }
+
+    fn p(s: &str) -> VfsPath {
+        VfsPath::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_wal_mode_enabled() {
+        let (_dir, backend) = setup();
+        let mode: String = backend
+            .lock()
+            .unwrap()
+            .pragma_query_value(None, "journal_mode", |r| r.get(0))
+            .unwrap();
+        assert_eq!(mode.to_lowercase(), "wal");
+    }
+
+    #[tokio::test]
+    async fn test_write_read_append_creates_parents() {
+        let (_dir, backend) = setup();
+        let path = p("/home/ctx/deep/log.txt");
+        backend.append(&path, b"line1\n").await.unwrap();
+        backend.append(&path, b"line2\n").await.unwrap();
+        assert_eq!(backend.read(&path).await.unwrap(), b"line1\nline2\n");
+        let parent = backend.metadata(&p("/home/ctx/deep")).await.unwrap();
+        assert_eq!(parent.kind, VfsEntryKind::Directory);
+
+        backend.write(&path, b"fresh").await.unwrap();
+        let meta = backend.metadata(&path).await.unwrap();
+        assert_eq!(meta.size, 5);
+        assert!(meta.modified.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_not_found_and_kind_errors() {
+        let (_dir, backend) = setup();
+        let err = backend.read(&p("/shared/nope")).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(
+            backend.delete(&p("/shared/nope")).await.unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert!(backend.list(&p("/shared/nope")).await.unwrap().is_empty());
+
+        backend.write(&p("/shared/file"), b"x").await.unwrap();
+        let err = backend.write(&p("/shared/file/child"), b"y").await;
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::NotADirectory);
+        let err = backend.read(&p("/shared")).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::IsADirectory);
+    }
+
+    #[tokio::test]
+    async fn test_list_is_sorted_and_direct_children_only() {
+        let (_dir, backend) = setup();
+        for name in ["c", "a", "b"] {
+            backend
+                .write(&p(&format!("/shared/{name}.txt")), b"x")
+                .await
+                .unwrap();
+        }
+        backend
+            .write(&p("/shared/sub/inner.txt"), b"x")
+            .await
+            .unwrap();
+        let entries = backend.list(&p("/shared")).await.unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a.txt", "b.txt", "c.txt", "sub"]);
+        assert_eq!(entries[3].kind, VfsEntryKind::Directory);
+
+        let root: Vec<_> = backend
+            .list(&p("/"))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(root, vec!["shared"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_directory_removes_subtree_only() {
+        let (_dir, backend) = setup();
+        backend.write(&p("/shared/a/x.txt"), b"x").await.unwrap();
+        backend.write(&p("/shared/a/b/y.txt"), b"y").await.unwrap();
+        backend
+            .write(&p("/shared/a-sibling.txt"), b"z")
+            .await
+            .unwrap();
+        backend.delete(&p("/shared/a")).await.unwrap();
+        assert!(!backend.exists(&p("/shared/a/b/y.txt")).await.unwrap());
+        assert!(!backend.exists(&p("/shared/a")).await.unwrap());
+        assert!(backend.exists(&p("/shared/a-sibling.txt")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_rename() {
+        let (_dir, backend) = setup();
+        backend
+            .write(&p("/shared/orig.txt"), b"content")
+            .await
+            .unwrap();
+        backend
+            .copy(&p("/shared/orig.txt"), &p("/home/ctx/copy.txt"))
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.read(&p("/home/ctx/copy.txt")).await.unwrap(),
+            b"content"
+        );
+
+        backend.write(&p("/shared/dir/f.txt"), b"f").await.unwrap();
+        backend
+            .rename(&p("/shared/dir"), &p("/shared/moved/dir"))
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.read(&p("/shared/moved/dir/f.txt")).await.unwrap(),
+            b"f"
+        );
+        assert!(!backend.exists(&p("/shared/dir")).await.unwrap());
+        let listed = backend.list(&p("/shared/moved/dir")).await.unwrap();
+        assert_eq!(listed.len(), 1);
+
+        let err = backend
+            .rename(&p("/shared/moved"), &p("/shared/moved/inner"))
+            .await;
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_write_batch_is_atomic() {
+        let (_dir, backend) = setup();
+        backend.write(&p("/shared/file"), b"x").await.unwrap();
+        let batch = vec![
+            (p("/shared/one.txt"), b"1".to_vec()),
+            (p("/shared/file/bad.txt"), b"2".to_vec()),
+        ];
+        assert!(backend.write_batch(&batch).await.is_err());
+        assert!(!backend.exists(&p("/shared/one.txt")).await.unwrap());
+
+        let batch = vec![
+            (p("/shared/one.txt"), b"1".to_vec()),
+            (p("/shared/two.txt"), b"2".to_vec()),
+        ];
+        backend.write_batch(&batch).await.unwrap();
+        assert_eq!(backend.read(&p("/shared/two.txt")).await.unwrap(), b"2");
+    }
+
+    #[tokio::test]
+    async fn test_import_tree_and_backup() {
+        let (dir, backend) = setup();
+        let tree = dir.path().join("vfs");
+        std::fs::create_dir_all(tree.join("shared/notes")).unwrap();
+        std::fs::create_dir_all(tree.join("site")).unwrap();
+        std::fs::write(tree.join("shared/notes/a.md"), b"alpha").unwrap();
+        assert_eq!(backend.import_tree(&tree).unwrap(), 1);
+        assert_eq!(
+            backend.read(&p("/shared/notes/a.md")).await.unwrap(),
+            b"alpha"
+        );
+        assert!(backend.exists(&p("/site")).await.unwrap());
+
+        let copy_path = dir.path().join("backup.db");
+        backend.backup_to(&copy_path).unwrap();
+        let copy = SqliteBackend::open(&copy_path).unwrap();
+        assert_eq!(copy.read(&p("/shared/notes/a.md")).await.unwrap(), b"alpha");
+    }
+}
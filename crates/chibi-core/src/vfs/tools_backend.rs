@@ -137,6 +137,7 @@ mod tests {
             description: format!("description for {name}"),
             parameters: serde_json::json!({}),
             hooks: vec![],
+            hook_priorities: Default::default(),
             metadata: ToolMetadata::new(),
             summary_params: vec![],
             r#impl: ToolImpl::Builtin(handler),
@@ -0,0 +1,203 @@
+//! File version history recorded by the `Vfs` router.
+//!
+//! Every mutation of a versioned path appends a `VersionEntry` to that file's
+//! history. Histories are stored through the backend mounted at `/sys/`, so
+//! they work with any backend and double as a read-only view:
+//!
+//! ```text
+//! /sys/versions/<path>.versions/index.json   JSON array of VersionEntry
+//! /sys/versions/<path>.versions/<n>          file content after version n
+//! ```
+//!
+//! The `.versions` suffix keeps a file's history directory apart from the
+//! histories of files nested below a same-named directory.
+//!
+//! Excluded paths: `/sys/` (including the histories themselves), `/tools/sys/`
+//! and `/flocks/registry.json`. Directories are not versioned.
+
+use std::io::{self, ErrorKind};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::path::VfsPath;
+
+/// VFS root under which version histories are stored.
+pub const VERSIONS_ROOT: &str = "/sys/versions";
+
+/// The operation that produced a version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionOp {
+    /// Content the file already had when its history started.
+    Baseline,
+    Write,
+    Append,
+    /// Tombstone: the file was deleted (or renamed away). No content.
+    Delete,
+    /// Written by a copy or rename onto this path.
+    Copy,
+    Rename,
+    /// Content restored from an earlier version.
+    Restore,
+}
+
+impl VersionOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Baseline => "baseline",
+            Self::Write => "write",
+            Self::Append => "append",
+            Self::Delete => "delete",
+            Self::Copy => "copy",
+            Self::Rename => "rename",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+/// One recorded version of a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionEntry {
+    /// Monotonic per-file version number, starting at 1.
+    pub version: u64,
+    /// Caller that made the change (`SYSTEM`, a context name, or `unknown`
+    /// for baselines).
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub op: VersionOp,
+    /// Content size in bytes (0 for deletions).
+    pub size: u64,
+    /// Extra context, e.g. `renamed from /shared/old.md` or `restored v3`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl VersionEntry {
+    /// Whether this version has stored content (everything but deletions).
+    pub fn has_content(&self) -> bool {
+        self.op != VersionOp::Delete
+    }
+}
+
+/// Whether mutations of `path` are recorded at all.
+pub(super) fn is_versioned(path: &VfsPath) -> bool {
+    let p = path.as_str();
+    !(p == "/sys"
+        || p.starts_with("/sys/")
+        || p == "/tools/sys"
+        || p.starts_with("/tools/sys/")
+        || p == "/flocks/registry.json")
+}
+
+/// History directory for `path` (`/shared/a.md` → `/sys/versions/shared/a.md.versions`).
+pub fn history_dir(path: &VfsPath) -> io::Result<VfsPath> {
+    VfsPath::new(&format!("{VERSIONS_ROOT}{}.versions", path.as_str()))
+}
+
+pub(super) fn index_path(path: &VfsPath) -> io::Result<VfsPath> {
+    history_dir(path)?.join("index.json")
+}
+
+pub(super) fn blob_path(path: &VfsPath, version: u64) -> io::Result<VfsPath> {
+    history_dir(path)?.join(&version.to_string())
+}
+
+/// Parse a history index read from storage. A missing index is an empty history.
+pub(super) fn parse_index(read: io::Result<Vec<u8>>) -> io::Result<Vec<VersionEntry>> {
+    match read {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("version index: {e}"))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Drop the oldest entries beyond `keep`, returning the versions whose
+/// content blobs should be deleted.
+pub(super) fn prune(index: &mut Vec<VersionEntry>, keep: usize) -> Vec<u64> {
+    let excess = index.len().saturating_sub(keep);
+    index
+        .drain(..excess)
+        .filter(|e| e.has_content())
+        .map(|e| e.version)
+        .collect()
+}
+
+/// The version in effect at `at`: the newest entry recorded at or before it.
+pub fn version_at(index: &[VersionEntry], at: DateTime<Utc>) -> Option<&VersionEntry> {
+    index.iter().rev().find(|e| e.timestamp <= at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(version: u64, op: VersionOp, hour: u32) -> VersionEntry {
+        VersionEntry {
+            version,
+            author: "alice".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 1, hour, 0, 0).unwrap(),
+            op,
+            size: 0,
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_history_layout() {
+        let path = VfsPath::new("/shared/notes.md").unwrap();
+        assert_eq!(
+            index_path(&path).unwrap().as_str(),
+            "/sys/versions/shared/notes.md.versions/index.json"
+        );
+        assert_eq!(
+            blob_path(&path, 3).unwrap().as_str(),
+            "/sys/versions/shared/notes.md.versions/3"
+        );
+    }
+
+    #[test]
+    fn test_is_versioned_excludes_system_paths() {
+        let p = |s: &str| VfsPath::new(s).unwrap();
+        assert!(is_versioned(&p("/shared/a.md")));
+        assert!(is_versioned(&p("/tools/shared/t.scm")));
+        assert!(!is_versioned(&p("/sys/tool_cache/ctx/x")));
+        assert!(!is_versioned(&p("/sys/versions/shared/a.md.versions/1")));
+        assert!(!is_versioned(&p("/tools/sys/shell_exec")));
+        assert!(!is_versioned(&p("/flocks/registry.json")));
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_and_skips_tombstones() {
+        let mut index = vec![
+            entry(1, VersionOp::Write, 1),
+            entry(2, VersionOp::Delete, 2),
+            entry(3, VersionOp::Write, 3),
+            entry(4, VersionOp::Write, 4),
+        ];
+        assert_eq!(prune(&mut index, 2), vec![1]);
+        assert_eq!(
+            index.iter().map(|e| e.version).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert!(prune(&mut index, 5).is_empty());
+    }
+
+    #[test]
+    fn test_version_at() {
+        let index = vec![entry(1, VersionOp::Write, 1), entry(2, VersionOp::Write, 3)];
+        let at = |hour| Utc.with_ymd_and_hms(2026, 1, 1, hour, 30, 0).unwrap();
+        assert!(version_at(&index, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()).is_none());
+        assert_eq!(version_at(&index, at(2)).unwrap().version, 1);
+        assert_eq!(version_at(&index, at(5)).unwrap().version, 2);
+    }
+
+    #[test]
+    fn test_parse_index_missing_is_empty() {
+        let missing = Err(io::Error::new(ErrorKind::NotFound, "nope"));
+        assert!(parse_index(missing).unwrap().is_empty());
+        assert!(parse_index(Ok(b"not json".to_vec())).is_err());
+    }
+}
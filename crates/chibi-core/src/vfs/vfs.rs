@@ -13,22 +13,52 @@
 //! `Vfs::new(backend, site_id)` mounts a single backend at `/` (convenience
 //! for the common single-backend case).
 //!
+//! # Version history
+//!
+//! When built with `VfsBuilder::versioning`, every mutation of a file outside
+//! `/sys/` is recorded (author, timestamp, content) under `/sys/versions/`;
+//! see `versions` for the layout. Backends are unaware of it.
+//!
+//! # Quotas
+//!
+//! When built with `VfsBuilder::quotas`, writes, appends, copies and renames
+//! that would grow a zone past its byte or file limit fail with
+//! `QuotaExceeded`; see `quota` for the zones.
+//!
+//! # Sharing grants
+//!
+//! Reads and writes inside `/home/<context>/` by other contexts are also
+//! checked against the owner's grants (`Vfs::share`); see `acl`.
+//!
+//! # Change notifications
+//!
+//! Every mutation outside `/sys/` is passed to the change callback, if set,
+//! with the contexts whose watches match it; see `watch`.
+//!
 //! # Middleware layers
 //!
 //! Future evolution: composable tower-style layers wrapping the backend
 //! (approach C). The public API on `Vfs` stays unchanged.
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io;
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+
+use super::acl::{self, Acl, AclAccess, AclGrant};
 use super::backend::VfsBackend;
 use super::flock::{
     FlockEntry, FlockRegistry, resolve_flock_vfs_root, site_flock_name, validate_flock_name,
 };
 use super::path::VfsPath;
 use super::permissions;
-use super::types::{VfsEntry, VfsMetadata};
+use super::quota::{self, ZoneTotals, ZoneUsage};
+use super::types::{VfsEntry, VfsEntryKind, VfsMetadata};
+use super::versions::{self, VersionEntry, VersionOp};
+use super::watch::{self, VfsChange, VfsChangeCallback, VfsChangeKind, Watch};
+use crate::config::{QuotaConfig, VersioningConfig};
 use crate::vfs::caller::VfsCaller;
 
 /// Path to the flock registry file within the VFS.
@@ -70,18 +100,35 @@ pub struct Vfs {
     // in load_registry(). If adding new async methods that touch this cache,
     // ensure the RefCell borrow is dropped before any .await.
     registry_cache: RefCell<Option<FlockRegistry>>,
+    /// Cached sharing grants. Loaded lazily, invalidated when they change.
+    /// Same borrow rule as `registry_cache`.
+    acl_cache: RefCell<Option<Acl>>,
     /// Optional callback fired after a successful write or delete on any path
     /// matching `/tools/{shared,home/**,flocks/**}/*.scm`.
     ///
     /// Set via `Vfs::set_scm_change_callback`. The callback is synchronous
     /// and must not block; use `Handle::current().block_on(...)` for async work.
     pub on_scm_change: Option<ScmChangeCallback>,
+    /// Optional callback fired after every successful mutation outside
+    /// `/sys/`, with the contexts watching the changed path.
+    ///
+    /// Set via `Vfs::set_change_callback`. Same rules as `on_scm_change`.
+    on_change: Option<VfsChangeCallback>,
+    /// Version history settings. `None` (the default) records nothing.
+    versioning: Option<VersioningConfig>,
+    /// Zone quotas. `None` (the default) enforces nothing.
+    quotas: Option<QuotaConfig>,
+    /// Current totals per quota zone root, measured on first use and updated
+    /// as files change. Same borrow rule as `registry_cache`.
+    quota_usage: RefCell<HashMap<String, ZoneTotals>>,
 }
 
 /// Builder for `Vfs` with multiple backend mounts.
 pub struct VfsBuilder {
     mounts: Vec<(String, Box<dyn VfsBackend>)>,
     site_id: String,
+    versioning: Option<VersioningConfig>,
+    quotas: Option<QuotaConfig>,
 }
 
 impl VfsBuilder {
@@ -89,9 +136,24 @@ impl VfsBuilder {
         Self {
             mounts: Vec::new(),
             site_id: site_id.into(),
+            versioning: None,
+            quotas: None,
         }
     }
 
+    /// Record file version history under `/sys/versions/` with the given
+    /// retention settings.
+    pub fn versioning(mut self, config: VersioningConfig) -> Self {
+        self.versioning = Some(config);
+        self
+    }
+
+    /// Enforce per-zone byte and file-count quotas.
+    pub fn quotas(mut self, config: QuotaConfig) -> Self {
+        self.quotas = Some(config);
+        self
+    }
+
     /// Mount a backend at the given path prefix (e.g. `"/"`, `"/tools/sys"`).
     pub fn mount(mut self, prefix: &str, backend: Box<dyn VfsBackend>) -> Self {
         self.mounts.push((prefix.to_string(), backend));
@@ -106,7 +168,12 @@ impl VfsBuilder {
             mounts: self.mounts,
             site_id: self.site_id,
             registry_cache: RefCell::new(None),
+            acl_cache: RefCell::new(None),
             on_scm_change: None,
+            on_change: None,
+            versioning: self.versioning,
+            quotas: self.quotas,
+            quota_usage: RefCell::new(HashMap::new()),
         }
     }
 }
@@ -127,8 +194,8 @@ impl Vfs {
         VfsBuilder::new(site_id)
     }
 
-    /// Set a callback to fire whenever a `.scm` file under `/tools/` is
-    /// written or deleted.
+    /// Set a callback to fire whenever a `.scm` tool file under `/tools/` or a
+    /// `.sld` library under `/tools/lib/` is written or deleted.
     ///
     /// The callback receives the full VFS path, a `ScmChangeKind`, and
     /// (on write) the bytes that were written. On delete, the content is `None`.
@@ -137,13 +204,22 @@ impl Vfs {
         self.on_scm_change = Some(cb);
     }
 
-    /// Returns `true` if `path` is a `.scm` file in a writable tools zone.
+    /// Set a callback to fire after every write, append, delete, mkdir, copy
+    /// and rename outside `/sys/`. It receives the change and the contexts
+    /// (other than the caller) watching a prefix of the path.
+    pub fn set_change_callback(&mut self, cb: VfsChangeCallback) {
+        self.on_change = Some(cb);
+    }
+
+    /// Returns `true` if `path` is a `.scm` file in a writable tools zone, or
+    /// a `.sld` scheme library in a writable `/tools/lib/` zone.
     fn is_scm_tool_path(path: &VfsPath) -> bool {
         let s = path.as_str();
-        s.ends_with(".scm")
+        (s.ends_with(".scm")
             && (s.starts_with("/tools/shared/")
                 || s.starts_with("/tools/home/")
-                || s.starts_with("/tools/flocks/"))
+                || s.starts_with("/tools/flocks/")))
+            || crate::tools::scheme_lib::is_library_path(path)
     }
 
     /// Resolve the backend and stripped path for the given VFS path.
@@ -225,28 +301,69 @@ impl Vfs {
         }
     }
 
-    // -- read operations (always allowed) --
+    /// Load the sharing grants, using the in-memory cache when available.
+    async fn load_acl(&self) -> io::Result<Acl> {
+        if let Some(cached) = self.acl_cache.borrow().as_ref() {
+            return Ok(cached.clone());
+        }
+        let path = VfsPath::new(acl::ACL_PATH)?;
+        let (backend, stripped) = self.resolve_backend(&path);
+        let acl = Acl::parse(backend.read(&stripped).await)?;
+        *self.acl_cache.borrow_mut() = Some(acl.clone());
+        Ok(acl)
+    }
+
+    /// Grants needed to check `caller` on `path`: loaded only when a context
+    /// touches another context's home. A grant list that can't be read fails
+    /// the operation rather than exposing private paths.
+    async fn acl_for_check(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+    ) -> io::Result<Option<Acl>> {
+        match caller {
+            VfsCaller::Context(name) if acl::home_owner(path).is_some_and(|o| o != name) => {
+                self.load_acl().await.map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn check_read(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<()> {
+        let acl = self.acl_for_check(caller, path).await?;
+        permissions::check_read(caller, path, acl.as_ref())
+    }
+
+    // -- read operations (world-readable except private grants) --
 
     pub async fn read(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<Vec<u8>> {
-        permissions::check_read(caller, path)?;
+        self.check_read(caller, path).await?;
         let (backend, stripped) = self.resolve_backend(path);
         backend.read(&stripped).await
     }
 
+    /// List a directory. Entries the caller may not read are left out.
     pub async fn list(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<Vec<VfsEntry>> {
-        permissions::check_read(caller, path)?;
+        self.check_read(caller, path).await?;
         let (backend, stripped) = self.resolve_backend(path);
-        backend.list(&stripped).await
+        let mut entries = backend.list(&stripped).await?;
+        if let Some(acl) = self.acl_for_check(caller, path).await? {
+            entries.retain(|e| {
+                path.join(&e.name)
+                    .is_ok_and(|p| permissions::check_read(caller, &p, Some(&acl)).is_ok())
+            });
+        }
+        Ok(entries)
     }
 
     pub async fn exists(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<bool> {
-        permissions::check_read(caller, path)?;
+        self.check_read(caller, path).await?;
         let (backend, stripped) = self.resolve_backend(path);
         backend.exists(&stripped).await
     }
 
     pub async fn metadata(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<VfsMetadata> {
-        permissions::check_read(caller, path)?;
+        self.check_read(caller, path).await?;
         let (backend, stripped) = self.resolve_backend(path);
         backend.metadata(&stripped).await
     }
@@ -258,20 +375,108 @@ impl Vfs {
         caller: VfsCaller<'_>,
         path: &VfsPath,
         data: &[u8],
+    ) -> io::Result<()> {
+        self.write_as(caller, path, data, VersionOp::Write, None)
+            .await
+    }
+
+    /// `write`, recording the new version as `op` with an optional detail.
+    async fn write_as(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        data: &[u8],
+        op: VersionOp,
+        detail: Option<String>,
     ) -> io::Result<()> {
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, path, flock_ctx)?;
+        let acl = self.acl_for_check(caller, path).await?;
+        permissions::check_write(caller, path, flock_ctx, acl.as_ref())?;
+        let charge = self.check_quota(path, data.len() as u64, false).await?;
+        self.ensure_baseline(path).await;
         let (backend, stripped) = self.resolve_backend(path);
         backend.write(&stripped, data).await?;
+        self.commit_quota(charge);
+        self.record_version(path, caller, op, Some(data), detail)
+            .await;
         if path.as_str() == REGISTRY_PATH {
             self.invalidate_registry_cache();
         }
+        if path.as_str() == acl::ACL_PATH {
+            *self.acl_cache.borrow_mut() = None;
+        }
         if Self::is_scm_tool_path(path)
             && let Some(cb) = &self.on_scm_change
         {
             cb(path, ScmChangeKind::Write, Some(data));
         }
+        self.notify(caller, VfsChangeKind::Write, path, None).await;
+        Ok(())
+    }
+
+    /// Write several files in one backend operation: all or nothing on
+    /// backends with transactions (e.g. `sqlite`). Every path is
+    /// permission-checked before anything is written, and all must resolve
+    /// to the same mount.
+    pub async fn write_batch(
+        &self,
+        caller: VfsCaller<'_>,
+        files: &[(VfsPath, Vec<u8>)],
+    ) -> io::Result<()> {
+        let Some((first, _)) = files.first() else {
+            return Ok(());
+        };
+        let registry = self.flock_ctx_for_check().await;
+        let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
+        let (backend, _) = self.resolve_backend(first);
+        let mut stripped_files = Vec::with_capacity(files.len());
+        for (path, data) in files {
+            let acl = self.acl_for_check(caller, path).await?;
+            permissions::check_write(caller, path, flock_ctx, acl.as_ref())?;
+            let (other, stripped) = self.resolve_backend(path);
+            if !std::ptr::eq(other, backend) {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("batch write spans VFS mounts ('{first}' and '{path}')"),
+                ));
+            }
+            stripped_files.push((stripped, data.clone()));
+        }
+        // Charge each file as it's checked so later files in the same zone
+        // see the earlier ones; undo by re-measuring if anything fails.
+        for (path, data) in files {
+            match self.check_quota(path, data.len() as u64, false).await {
+                Ok(charge) => self.commit_quota(charge),
+                Err(e) => {
+                    files.iter().for_each(|(p, _)| self.forget_quota_usage(p));
+                    return Err(e);
+                }
+            }
+        }
+        for (path, _) in files {
+            self.ensure_baseline(path).await;
+        }
+        if let Err(e) = backend.write_batch(&stripped_files).await {
+            files.iter().for_each(|(p, _)| self.forget_quota_usage(p));
+            return Err(e);
+        }
+        for (path, data) in files {
+            self.record_version(path, caller, VersionOp::Write, Some(data), None)
+                .await;
+            if path.as_str() == REGISTRY_PATH {
+                self.invalidate_registry_cache();
+            }
+            if path.as_str() == acl::ACL_PATH {
+                *self.acl_cache.borrow_mut() = None;
+            }
+            if Self::is_scm_tool_path(path)
+                && let Some(cb) = &self.on_scm_change
+            {
+                cb(path, ScmChangeKind::Write, Some(data));
+            }
+            self.notify(caller, VfsChangeKind::Write, path, None).await;
+        }
         Ok(())
     }
 
@@ -283,9 +488,15 @@ impl Vfs {
     ) -> io::Result<()> {
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, path, flock_ctx)?;
+        let acl = self.acl_for_check(caller, path).await?;
+        permissions::check_write(caller, path, flock_ctx, acl.as_ref())?;
+        let charge = self.check_quota(path, data.len() as u64, true).await?;
+        self.ensure_baseline(path).await;
         let (backend, stripped) = self.resolve_backend(path);
         backend.append(&stripped, data).await?;
+        self.commit_quota(charge);
+        self.record_current(path, caller, VersionOp::Append, None)
+            .await;
         // Appending mutates a .scm tool file — re-read the full content so
         // the hot-reload callback can update the registry to match what's on
         // disk (same contract as `write`, which passes the new content inline).
@@ -295,29 +506,41 @@ impl Vfs {
             let full = backend.read(&stripped).await.unwrap_or_default();
             cb(path, ScmChangeKind::Write, Some(&full));
         }
+        self.notify(caller, VfsChangeKind::Append, path, None).await;
         Ok(())
     }
 
     pub async fn delete(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<()> {
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, path, flock_ctx)?;
+        let acl = self.acl_for_check(caller, path).await?;
+        permissions::check_write(caller, path, flock_ctx, acl.as_ref())?;
+        let was_file = self.ensure_baseline(path).await;
         let (backend, stripped) = self.resolve_backend(path);
         backend.delete(&stripped).await?;
+        self.forget_quota_usage(path);
+        if was_file {
+            self.record_version(path, caller, VersionOp::Delete, None, None)
+                .await;
+        }
         if Self::is_scm_tool_path(path)
             && let Some(cb) = &self.on_scm_change
         {
             cb(path, ScmChangeKind::Delete, None);
         }
+        self.notify(caller, VfsChangeKind::Delete, path, None).await;
         Ok(())
     }
 
     pub async fn mkdir(&self, caller: VfsCaller<'_>, path: &VfsPath) -> io::Result<()> {
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, path, flock_ctx)?;
+        let acl = self.acl_for_check(caller, path).await?;
+        permissions::check_write(caller, path, flock_ctx, acl.as_ref())?;
         let (backend, stripped) = self.resolve_backend(path);
-        backend.mkdir(&stripped).await
+        backend.mkdir(&stripped).await?;
+        self.notify(caller, VfsChangeKind::Mkdir, path, None).await;
+        Ok(())
     }
 
     /// Copy a file. Caller must have read on src and write on dst.
@@ -330,10 +553,11 @@ impl Vfs {
         src: &VfsPath,
         dst: &VfsPath,
     ) -> io::Result<()> {
-        permissions::check_read(caller, src)?;
+        self.check_read(caller, src).await?;
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, dst, flock_ctx)?;
+        let acl = self.acl_for_check(caller, dst).await?;
+        permissions::check_write(caller, dst, flock_ctx, acl.as_ref())?;
         let (src_backend, src_stripped) = self.resolve_backend(src);
         let (dst_backend, dst_stripped) = self.resolve_backend(dst);
         // Backends are trait objects; compare via raw pointer identity.
@@ -343,7 +567,17 @@ impl Vfs {
                 "cross-backend copy not supported; use read + write",
             ));
         }
-        src_backend.copy(&src_stripped, &dst_stripped).await
+        let size = src_backend.metadata(&src_stripped).await?.size;
+        let charge = self.check_quota(dst, size, false).await?;
+        self.ensure_baseline(dst).await;
+        src_backend.copy(&src_stripped, &dst_stripped).await?;
+        self.commit_quota(charge);
+        let detail = format!("copied from {src}");
+        self.record_current(dst, caller, VersionOp::Copy, Some(detail))
+            .await;
+        self.notify(caller, VfsChangeKind::Copy, dst, Some(src))
+            .await;
+        Ok(())
     }
 
     /// Rename (move) a file. Caller must have write on both src and dst.
@@ -359,8 +593,10 @@ impl Vfs {
         let registry = self.flock_ctx_for_check().await;
         let flock_ctx_src = registry.as_ref().map(|r| (r, self.site_id.as_str()));
         let flock_ctx_dst = registry.as_ref().map(|r| (r, self.site_id.as_str()));
-        permissions::check_write(caller, src, flock_ctx_src)?;
-        permissions::check_write(caller, dst, flock_ctx_dst)?;
+        let acl = self.acl_for_check(caller, src).await?;
+        permissions::check_write(caller, src, flock_ctx_src, acl.as_ref())?;
+        let acl = self.acl_for_check(caller, dst).await?;
+        permissions::check_write(caller, dst, flock_ctx_dst, acl.as_ref())?;
         let (src_backend, src_stripped) = self.resolve_backend(src);
         let (dst_backend, dst_stripped) = self.resolve_backend(dst);
         if !std::ptr::eq(src_backend, dst_backend) {
@@ -369,7 +605,574 @@ impl Vfs {
                 "cross-backend rename not supported; use read + write + delete",
             ));
         }
-        src_backend.rename(&src_stripped, &dst_stripped).await
+        // Moving a file into another zone charges that zone. Directory moves
+        // are not checked; both zones are re-measured afterwards.
+        let zone_root = |p: &VfsPath| quota::zone_of(p).map(|(_, root)| root);
+        if zone_root(src) != zone_root(dst) {
+            let meta = src_backend.metadata(&src_stripped).await?;
+            if meta.kind == VfsEntryKind::File {
+                self.check_quota(dst, meta.size, false).await?;
+            }
+        }
+        let src_was_file = self.ensure_baseline(src).await;
+        self.ensure_baseline(dst).await;
+        src_backend.rename(&src_stripped, &dst_stripped).await?;
+        self.forget_quota_usage(src);
+        self.forget_quota_usage(dst);
+        if src_was_file {
+            let detail = format!("renamed to {dst}");
+            self.record_version(src, caller, VersionOp::Delete, None, Some(detail))
+                .await;
+            let detail = format!("renamed from {src}");
+            self.record_current(dst, caller, VersionOp::Rename, Some(detail))
+                .await;
+        }
+        self.notify(caller, VfsChangeKind::Rename, dst, Some(src))
+            .await;
+        Ok(())
+    }
+
+    // -- sharing grants --
+
+    /// Give `context` (or `*`, every context but the owner) `access` to
+    /// `path` and everything below it. `path` must be in a context home;
+    /// only its owner or SYSTEM may share it. Returns the grants in the
+    /// owner's home.
+    pub async fn share(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        context: &str,
+        access: AclAccess,
+    ) -> io::Result<Vec<AclGrant>> {
+        let owner = Self::share_owner(caller, path)?;
+        if context.is_empty() || context == owner || permissions::is_reserved_caller_name(context) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot share '{path}' with '{context}'"),
+            ));
+        }
+        let mut acl = self.load_acl().await?;
+        acl.set(AclGrant {
+            prefix: path.as_str().to_string(),
+            context: context.to_string(),
+            access,
+            created_at: crate::context::now_timestamp(),
+        });
+        self.save_acl(&acl).await?;
+        Ok(acl.under(&VfsPath::new(&format!("/home/{owner}"))?))
+    }
+
+    /// Remove the grant `share` made for `path` and `context`. Returns the
+    /// remaining grants in the owner's home.
+    pub async fn unshare(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        context: &str,
+    ) -> io::Result<Vec<AclGrant>> {
+        let owner = Self::share_owner(caller, path)?;
+        let mut acl = self.load_acl().await?;
+        if !acl.remove(path.as_str(), context) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no grant on '{path}' for '{context}'"),
+            ));
+        }
+        self.save_acl(&acl).await?;
+        Ok(acl.under(&VfsPath::new(&format!("/home/{owner}"))?))
+    }
+
+    /// Grants that apply to `path` (on it or a directory above it), shortest
+    /// prefix first.
+    pub async fn grants(&self, path: &VfsPath) -> io::Result<Vec<AclGrant>> {
+        Ok(self.load_acl().await?.covering(path))
+    }
+
+    /// Grants on `prefix` or anything below it.
+    pub async fn grants_under(&self, prefix: &VfsPath) -> io::Result<Vec<AclGrant>> {
+        Ok(self.load_acl().await?.under(prefix))
+    }
+
+    /// Whether a `none` grant makes `path`, a directory above it, or anything
+    /// below it private to some context.
+    pub async fn is_private(&self, path: &VfsPath) -> io::Result<bool> {
+        let acl = self.load_acl().await?;
+        Ok(acl
+            .covering(path)
+            .iter()
+            .chain(&acl.under(path))
+            .any(|g| g.access == AclAccess::None))
+    }
+
+    fn share_owner<'p>(caller: VfsCaller<'_>, path: &'p VfsPath) -> io::Result<&'p str> {
+        let owner = acl::home_owner(path)
+            .filter(|_| path.as_str().starts_with("/home/"))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("only paths under /home/<context>/ can be shared, not '{path}'"),
+                )
+            })?;
+        match caller {
+            VfsCaller::Context(name) if name != owner => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("only '{owner}' can change sharing of '{path}'"),
+            )),
+            _ => Ok(owner),
+        }
+    }
+
+    async fn save_acl(&self, acl: &Acl) -> io::Result<()> {
+        let path = VfsPath::new(acl::ACL_PATH)?;
+        let (backend, stripped) = self.resolve_backend(&path);
+        backend.write(&stripped, &acl.to_bytes()?).await?;
+        *self.acl_cache.borrow_mut() = Some(acl.clone());
+        Ok(())
+    }
+
+    // -- watches --
+
+    /// Watch `prefix` for changes made by others. Returns the calling
+    /// context's watched prefixes.
+    pub async fn watch(&self, caller: VfsCaller<'_>, prefix: &VfsPath) -> io::Result<Vec<String>> {
+        let context = Self::watch_owner(caller)?;
+        self.check_read(caller, prefix).await?;
+        let mut watches = self.load_watches().await?;
+        if !watches
+            .iter()
+            .any(|w| w.context == context && w.prefix == prefix.as_str())
+        {
+            watches.push(Watch {
+                context: context.to_string(),
+                prefix: prefix.as_str().to_string(),
+                created_at: crate::context::now_timestamp(),
+            });
+            self.save_watches(&watches).await?;
+        }
+        Ok(Self::prefixes_of(&watches, context))
+    }
+
+    /// Stop watching `prefix`. Returns the remaining watched prefixes.
+    pub async fn unwatch(
+        &self,
+        caller: VfsCaller<'_>,
+        prefix: &VfsPath,
+    ) -> io::Result<Vec<String>> {
+        let context = Self::watch_owner(caller)?;
+        let mut watches = self.load_watches().await?;
+        let before = watches.len();
+        watches.retain(|w| !(w.context == context && w.prefix == prefix.as_str()));
+        if watches.len() == before {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{context}' is not watching '{prefix}'"),
+            ));
+        }
+        self.save_watches(&watches).await?;
+        Ok(Self::prefixes_of(&watches, context))
+    }
+
+    /// All watch subscriptions.
+    pub async fn watches(&self) -> io::Result<Vec<Watch>> {
+        self.load_watches().await
+    }
+
+    fn watch_owner(caller: VfsCaller<'_>) -> io::Result<&str> {
+        match caller {
+            VfsCaller::Context(name) => Ok(name),
+            VfsCaller::System => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only contexts can watch VFS paths",
+            )),
+        }
+    }
+
+    fn prefixes_of(watches: &[Watch], context: &str) -> Vec<String> {
+        watches
+            .iter()
+            .filter(|w| w.context == context)
+            .map(|w| w.prefix.clone())
+            .collect()
+    }
+
+    async fn load_watches(&self) -> io::Result<Vec<Watch>> {
+        let path = VfsPath::new(watch::WATCHES_PATH)?;
+        let (backend, stripped) = self.resolve_backend(&path);
+        watch::parse_watches(backend.read(&stripped).await)
+    }
+
+    async fn save_watches(&self, watches: &[Watch]) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(watches)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let path = VfsPath::new(watch::WATCHES_PATH)?;
+        let (backend, stripped) = self.resolve_backend(&path);
+        backend.write(&stripped, &data).await
+    }
+
+    /// After a mutation: pass the change and its watchers to the change
+    /// callback. A broken watch list is logged and treated as empty.
+    async fn notify(
+        &self,
+        caller: VfsCaller<'_>,
+        kind: VfsChangeKind,
+        path: &VfsPath,
+        from: Option<&VfsPath>,
+    ) {
+        let Some(cb) = &self.on_change else {
+            return;
+        };
+        if !watch::is_watchable(path) {
+            return;
+        }
+        let change = VfsChange {
+            path: path.clone(),
+            kind,
+            caller: caller.to_string(),
+            from: from.cloned(),
+        };
+        let watches = self.load_watches().await.unwrap_or_else(|e| {
+            eprintln!("[vfs] warning: failed to load watch list: {e}");
+            Vec::new()
+        });
+        let mut watchers = watch::subscribers(&watches, &change);
+        // Private paths are only reported to contexts that can read them.
+        if !watchers.is_empty()
+            && let Some(owner) = acl::home_owner(path)
+        {
+            let acl = self.load_acl().await.ok();
+            watchers.retain(|w| {
+                w == owner
+                    || acl.as_ref().is_some_and(|acl| {
+                        permissions::check_read(VfsCaller::Context(w), path, Some(acl)).is_ok()
+                    })
+            });
+        }
+        cb(&change, &watchers);
+    }
+
+    // -- quotas --
+
+    /// Current usage of every quota zone, with the configured limits. Zones
+    /// are measured afresh.
+    pub async fn usage(&self) -> io::Result<Vec<ZoneUsage>> {
+        let mut roots = Vec::new();
+        for parent in ["/home", "/flocks"] {
+            let parent = VfsPath::new(parent)?;
+            let (backend, stripped) = self.resolve_backend(&parent);
+            for entry in backend.list(&stripped).await? {
+                if entry.kind == VfsEntryKind::Directory {
+                    roots.push(parent.join(&entry.name)?);
+                }
+            }
+        }
+        for root in ["/site", "/shared", "/tools"] {
+            roots.push(VfsPath::new(root)?);
+        }
+
+        let config = self.quotas.clone().unwrap_or_default();
+        let mut usage = Vec::with_capacity(roots.len());
+        for root in roots {
+            let Some((zone, _)) = quota::zone_of(&root.join("x")?) else {
+                continue;
+            };
+            let totals = self.measure(&root).await?;
+            self.quota_usage
+                .borrow_mut()
+                .insert(root.as_str().to_string(), totals);
+            let limits = zone.limits(&config);
+            usage.push(ZoneUsage {
+                zone,
+                root: root.as_str().to_string(),
+                bytes: totals.bytes,
+                files: totals.files,
+                max_bytes: limits.max_bytes,
+                max_files: limits.max_files,
+            });
+        }
+        Ok(usage)
+    }
+
+    /// Walk `root` and total its files. `/tools/sys/` is skipped.
+    async fn measure(&self, root: &VfsPath) -> io::Result<ZoneTotals> {
+        let mut totals = ZoneTotals::default();
+        let mut pending = vec![root.clone()];
+        while let Some(dir) = pending.pop() {
+            let (backend, stripped) = self.resolve_backend(&dir);
+            for entry in backend.list(&stripped).await? {
+                let path = dir.join(&entry.name)?;
+                if entry.kind == VfsEntryKind::Directory {
+                    if path.as_str() != "/tools/sys" {
+                        pending.push(path);
+                    }
+                } else {
+                    let (backend, stripped) = self.resolve_backend(&path);
+                    totals.bytes += backend.metadata(&stripped).await?.size;
+                    totals.files += 1;
+                }
+            }
+        }
+        Ok(totals)
+    }
+
+    /// Check that writing `len` bytes to `path` (appending if `append`)
+    /// keeps its zone within quota. Returns the zone's totals after the
+    /// write, to pass to `commit_quota` once it succeeds.
+    async fn check_quota(
+        &self,
+        path: &VfsPath,
+        len: u64,
+        append: bool,
+    ) -> io::Result<Option<(String, ZoneTotals)>> {
+        let Some(config) = &self.quotas else {
+            return Ok(None);
+        };
+        let Some((zone, root)) = quota::zone_of(path) else {
+            return Ok(None);
+        };
+        let limits = zone.limits(config);
+        if limits.is_unlimited() {
+            return Ok(None);
+        }
+        let cached = self.quota_usage.borrow().get(root.as_str()).copied();
+        let before = match cached {
+            Some(totals) => totals,
+            None => self.measure(&root).await?,
+        };
+        let (backend, stripped) = self.resolve_backend(path);
+        let existing = match backend.metadata(&stripped).await {
+            Ok(meta) if meta.kind == VfsEntryKind::File => Some(meta.size),
+            _ => None,
+        };
+        let after = ZoneTotals {
+            bytes: if append {
+                before.bytes + len
+            } else {
+                before.bytes.saturating_sub(existing.unwrap_or(0)) + len
+            },
+            files: before.files + u64::from(existing.is_none()),
+        };
+        quota::check(zone, &root, limits, before, after)?;
+        Ok(Some((root.as_str().to_string(), after)))
+    }
+
+    /// Record the totals computed by `check_quota` after a successful write.
+    fn commit_quota(&self, charge: Option<(String, ZoneTotals)>) {
+        if let Some((root, totals)) = charge {
+            self.quota_usage.borrow_mut().insert(root, totals);
+        }
+    }
+
+    /// Drop the cached totals for the zone containing `path`; the next
+    /// quota check re-measures it.
+    fn forget_quota_usage(&self, path: &VfsPath) {
+        if let Some((_, root)) = quota::zone_of(path) {
+            self.quota_usage.borrow_mut().remove(root.as_str());
+        }
+    }
+
+    // -- version history --
+
+    /// Version history of `path`, oldest first. Empty if nothing was recorded.
+    pub async fn history(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+    ) -> io::Result<Vec<VersionEntry>> {
+        self.check_read(caller, path).await?;
+        self.load_history(path).await
+    }
+
+    /// Content of `path` as of `version`.
+    pub async fn read_version(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        version: u64,
+    ) -> io::Result<Vec<u8>> {
+        self.check_read(caller, path).await?;
+        let index = self.load_history(path).await?;
+        let entry = index.iter().find(|e| e.version == version).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no version {version} of '{path}' (pruned or never recorded)"),
+            )
+        })?;
+        self.read_entry(path, entry).await
+    }
+
+    /// Content of `path` as it was at `at`, with the version that was current.
+    pub async fn read_at(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        at: DateTime<Utc>,
+    ) -> io::Result<(VersionEntry, Vec<u8>)> {
+        self.check_read(caller, path).await?;
+        let index = self.load_history(path).await?;
+        let entry = versions::version_at(&index, at).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no recorded version of '{path}' at or before {at}"),
+            )
+        })?;
+        let data = self.read_entry(path, entry).await?;
+        Ok((entry.clone(), data))
+    }
+
+    /// Restore `path` to the content of `version`. Requires write permission;
+    /// the restore is itself recorded as a new version, which is returned.
+    pub async fn restore(
+        &self,
+        caller: VfsCaller<'_>,
+        path: &VfsPath,
+        version: u64,
+    ) -> io::Result<u64> {
+        let data = self.read_version(caller, path, version).await?;
+        let detail = format!("restored v{version}");
+        self.write_as(caller, path, &data, VersionOp::Restore, Some(detail))
+            .await?;
+        let index = self.load_history(path).await?;
+        Ok(index.last().map_or(0, |e| e.version))
+    }
+
+    /// Versions to keep for `path`, or `None` when it isn't versioned.
+    fn version_retention(&self, path: &VfsPath) -> Option<usize> {
+        let keep = self.versioning.as_ref()?.retention_for(path.as_str());
+        (keep > 0 && versions::is_versioned(path)).then_some(keep)
+    }
+
+    async fn load_history(&self, path: &VfsPath) -> io::Result<Vec<VersionEntry>> {
+        let index = versions::index_path(path)?;
+        let (backend, stripped) = self.resolve_backend(&index);
+        versions::parse_index(backend.read(&stripped).await)
+    }
+
+    async fn read_entry(&self, path: &VfsPath, entry: &VersionEntry) -> io::Result<Vec<u8>> {
+        if !entry.has_content() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{path}' was deleted in version {}", entry.version),
+            ));
+        }
+        let blob = versions::blob_path(path, entry.version)?;
+        let (backend, stripped) = self.resolve_backend(&blob);
+        backend.read(&stripped).await
+    }
+
+    /// Before a mutation: if `path` is a versioned file with no history yet,
+    /// record its current content as a baseline so the change can be undone.
+    ///
+    /// Returns whether `path` is a versioned, existing file.
+    async fn ensure_baseline(&self, path: &VfsPath) -> bool {
+        if self.version_retention(path).is_none() {
+            return false;
+        }
+        let (backend, stripped) = self.resolve_backend(path);
+        let Ok(meta) = backend.metadata(&stripped).await else {
+            return false; // doesn't exist yet
+        };
+        if meta.kind != VfsEntryKind::File {
+            return false;
+        }
+        let result = async {
+            if !self.load_history(path).await?.is_empty() {
+                return Ok(());
+            }
+            let data = backend.read(&stripped).await?;
+            let entry = VersionEntry {
+                version: 0,
+                author: "unknown".to_string(),
+                timestamp: meta.modified.unwrap_or_else(Utc::now),
+                op: VersionOp::Baseline,
+                size: 0,
+                detail: None,
+            };
+            self.append_version(path, entry, Some(&data)).await
+        }
+        .await;
+        if let Err(e) = result {
+            eprintln!("[vfs] warning: failed to record baseline version of {path}: {e}");
+        }
+        true
+    }
+
+    /// After a mutation: record a version of `path` by re-reading its content.
+    async fn record_current(
+        &self,
+        path: &VfsPath,
+        caller: VfsCaller<'_>,
+        op: VersionOp,
+        detail: Option<String>,
+    ) {
+        if self.version_retention(path).is_none() {
+            return;
+        }
+        let (backend, stripped) = self.resolve_backend(path);
+        match backend.read(&stripped).await {
+            Ok(data) => {
+                self.record_version(path, caller, op, Some(&data), detail)
+                    .await
+            }
+            Err(e) => eprintln!("[vfs] warning: failed to record version of {path}: {e}"),
+        }
+    }
+
+    /// After a mutation: record a version of `path`. `content` is `None` for
+    /// deletions. Failures are logged, never surfaced — the mutation itself
+    /// already succeeded.
+    async fn record_version(
+        &self,
+        path: &VfsPath,
+        caller: VfsCaller<'_>,
+        op: VersionOp,
+        content: Option<&[u8]>,
+        detail: Option<String>,
+    ) {
+        if self.version_retention(path).is_none() {
+            return;
+        }
+        let entry = VersionEntry {
+            version: 0,
+            author: caller.to_string(),
+            timestamp: Utc::now(),
+            op,
+            size: 0,
+            detail,
+        };
+        if let Err(e) = self.append_version(path, entry, content).await {
+            eprintln!("[vfs] warning: failed to record version of {path}: {e}");
+        }
+    }
+
+    /// Append `entry` (numbered and sized here) to the history of `path`,
+    /// storing `content` and pruning to the retention limit.
+    async fn append_version(
+        &self,
+        path: &VfsPath,
+        mut entry: VersionEntry,
+        content: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let keep = self.version_retention(path).unwrap_or(0);
+        let mut index = self.load_history(path).await?;
+        entry.version = index.last().map_or(1, |e| e.version + 1);
+        entry.size = content.map_or(0, |data| data.len() as u64);
+        if let Some(data) = content {
+            let blob = versions::blob_path(path, entry.version)?;
+            let (backend, stripped) = self.resolve_backend(&blob);
+            backend.write(&stripped, data).await?;
+        }
+        index.push(entry);
+        for old in versions::prune(&mut index, keep) {
+            let blob = versions::blob_path(path, old)?;
+            let (backend, stripped) = self.resolve_backend(&blob);
+            let _ = backend.delete(&stripped).await;
+        }
+        let data = serde_json::to_vec_pretty(&index)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let index_path = versions::index_path(path)?;
+        let (backend, stripped) = self.resolve_backend(&index_path);
+        backend.write(&stripped, &data).await
     }
 
     // -- flock management --
@@ -763,4 +1566,350 @@ mod tests {
         let all = vfs.flock_list_all().await.unwrap();
         assert_eq!(all.len(), 2);
     }
+
+    fn setup_versioned(retention: &[(&str, usize)]) -> (TempDir, Vfs) {
+        let dir = TempDir::new().unwrap();
+        let backend = LocalBackend::new(dir.path().to_path_buf());
+        let mut config = VersioningConfig::default();
+        config.retention = retention
+            .iter()
+            .map(|(prefix, keep)| (prefix.to_string(), *keep))
+            .collect();
+        let vfs = Vfs::builder("test-site-0000")
+            .mount("/", Box::new(backend))
+            .versioning(config)
+            .build();
+        (dir, vfs)
+    }
+
+    #[tokio::test]
+    async fn test_versioning_records_author_and_content() {
+        let (_dir, vfs) = setup_versioned(&[]);
+        let path = VfsPath::new("/shared/plan.md").unwrap();
+        vfs.write(VfsCaller::Context("alice"), &path, b"one")
+            .await
+            .unwrap();
+        vfs.append(VfsCaller::Context("bob"), &path, b" two")
+            .await
+            .unwrap();
+        vfs.write(VfsCaller::System, &path, b"three").await.unwrap();
+
+        let history = vfs.history(VfsCaller::System, &path).await.unwrap();
+        let summary: Vec<_> = history
+            .iter()
+            .map(|e| (e.version, e.author.as_str(), e.op, e.size))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "alice", VersionOp::Write, 3),
+                (2, "bob", VersionOp::Append, 7),
+                (3, "SYSTEM", VersionOp::Write, 5),
+            ]
+        );
+        let v2 = vfs.read_version(VfsCaller::System, &path, 2).await.unwrap();
+        assert_eq!(v2, b"one two");
+
+        let (entry, data) = vfs
+            .read_at(VfsCaller::System, &path, history[0].timestamp)
+            .await
+            .unwrap();
+        assert_eq!(entry.version, 1);
+        assert_eq!(data, b"one");
+
+        // histories are readable through the /sys/ view
+        let index = VfsPath::new("/sys/versions/shared/plan.md.versions/index.json").unwrap();
+        assert!(vfs.exists(VfsCaller::System, &index).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_versioning_delete_rename_and_restore() {
+        let (_dir, vfs) = setup_versioned(&[]);
+        let ctx = VfsCaller::Context("alice");
+        let src = VfsPath::new("/home/alice/a.md").unwrap();
+        let dst = VfsPath::new("/home/alice/b.md").unwrap();
+        vfs.write(ctx, &src, b"draft").await.unwrap();
+        vfs.rename(ctx, &src, &dst).await.unwrap();
+
+        let src_history = vfs.history(ctx, &src).await.unwrap();
+        assert_eq!(src_history.last().unwrap().op, VersionOp::Delete);
+        assert!(vfs.read_version(ctx, &src, 2).await.is_err());
+        let dst_history = vfs.history(ctx, &dst).await.unwrap();
+        assert_eq!(dst_history[0].op, VersionOp::Rename);
+        assert_eq!(
+            dst_history[0].detail.as_deref(),
+            Some("renamed from /home/alice/a.md")
+        );
+
+        vfs.delete(ctx, &dst).await.unwrap();
+        let restored = vfs.restore(ctx, &dst, 1).await.unwrap();
+        assert_eq!(restored, 3);
+        assert_eq!(vfs.read(ctx, &dst).await.unwrap(), b"draft");
+
+        // restore needs write permission on the path
+        let err = vfs.restore(VfsCaller::Context("bob"), &dst, 1).await;
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_versioning_baseline_and_retention() {
+        let (dir, vfs) = setup_versioned(&[("/shared/", 2), ("/shared/scratch/", 0)]);
+        // a file that predates versioning gets a baseline on first change
+        std::fs::create_dir_all(dir.path().join("shared")).unwrap();
+        std::fs::write(dir.path().join("shared/old.md"), b"legacy").unwrap();
+        let path = VfsPath::new("/shared/old.md").unwrap();
+        vfs.write(VfsCaller::System, &path, b"new").await.unwrap();
+        let history = vfs.history(VfsCaller::System, &path).await.unwrap();
+        assert_eq!(history[0].op, VersionOp::Baseline);
+        assert_eq!(history[0].author, "unknown");
+        assert_eq!(
+            vfs.read_version(VfsCaller::System, &path, 1).await.unwrap(),
+            b"legacy"
+        );
+
+        vfs.write(VfsCaller::System, &path, b"newer").await.unwrap();
+        let history = vfs.history(VfsCaller::System, &path).await.unwrap();
+        let versions: Vec<_> = history.iter().map(|e| e.version).collect();
+        assert_eq!(versions, vec![2, 3]);
+        let pruned = VfsPath::new("/sys/versions/shared/old.md.versions/1").unwrap();
+        assert!(!vfs.exists(VfsCaller::System, &pruned).await.unwrap());
+
+        let scratch = VfsPath::new("/shared/scratch/tmp").unwrap();
+        vfs.write(VfsCaller::System, &scratch, b"x").await.unwrap();
+        assert!(
+            vfs.history(VfsCaller::System, &scratch)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_versioning_off_by_default() {
+        let (_dir, vfs) = setup();
+        let path = VfsPath::new("/shared/plan.md").unwrap();
+        vfs.write(VfsCaller::System, &path, b"one").await.unwrap();
+        assert!(
+            vfs.history(VfsCaller::System, &path)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_batch_checks_all_paths_first() {
+        let (_dir, vfs) = setup();
+        let alice = VfsCaller::Context("alice");
+        let p = |s: &str| VfsPath::new(s).unwrap();
+        let files = vec![
+            (p("/home/alice/a.md"), b"a".to_vec()),
+            (p("/home/bob/b.md"), b"b".to_vec()),
+        ];
+        let err = vfs.write_batch(alice, &files).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!vfs.exists(alice, &p("/home/alice/a.md")).await.unwrap());
+
+        let files = vec![
+            (p("/home/alice/a.md"), b"a".to_vec()),
+            (p("/shared/b.md"), b"b".to_vec()),
+        ];
+        vfs.write_batch(alice, &files).await.unwrap();
+        assert_eq!(vfs.read(alice, &p("/shared/b.md")).await.unwrap(), b"b");
+    }
+
+    #[tokio::test]
+    async fn test_quotas_limit_each_home_and_report_usage() {
+        let dir = TempDir::new().unwrap();
+        let mut config = QuotaConfig::default();
+        config.home.max_bytes = Some(10);
+        config.home.max_files = Some(2);
+        let vfs = Vfs::builder("test-site-0000")
+            .mount("/", Box::new(LocalBackend::new(dir.path().to_path_buf())))
+            .quotas(config)
+            .build();
+        let alice = VfsCaller::Context("alice");
+        let p = |s: &str| VfsPath::new(s).unwrap();
+
+        vfs.write(alice, &p("/home/alice/a"), b"123456")
+            .await
+            .unwrap();
+        let err = vfs
+            .append(alice, &p("/home/alice/a"), b"12345")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::QuotaExceeded);
+        assert!(err.to_string().contains("/home/alice"));
+
+        // Overwriting replaces the old size rather than adding to it.
+        vfs.write(alice, &p("/home/alice/a"), b"1234")
+            .await
+            .unwrap();
+        vfs.write(alice, &p("/home/alice/b"), b"123456")
+            .await
+            .unwrap();
+        let err = vfs
+            .write(alice, &p("/home/alice/c"), b"")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::QuotaExceeded);
+
+        // Each home has its own budget; /shared is unlimited here.
+        let bob = VfsCaller::Context("bob");
+        vfs.write(bob, &p("/home/bob/a"), b"123456789")
+            .await
+            .unwrap();
+        vfs.write(bob, &p("/shared/big"), &[0; 64]).await.unwrap();
+        let err = vfs
+            .copy(bob, &p("/shared/big"), &p("/home/bob/big"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::QuotaExceeded);
+
+        vfs.delete(alice, &p("/home/alice/b")).await.unwrap();
+        vfs.write(alice, &p("/home/alice/c"), b"12").await.unwrap();
+
+        let usage = vfs.usage().await.unwrap();
+        let home = |root: &str| usage.iter().find(|u| u.root == root).unwrap();
+        assert_eq!(
+            (home("/home/alice").bytes, home("/home/alice").files),
+            (6, 2)
+        );
+        assert_eq!(home("/home/alice").max_bytes, Some(10));
+        assert_eq!(home("/shared").bytes, 64);
+        assert_eq!(home("/shared").max_bytes, None);
+    }
+
+    #[tokio::test]
+    async fn test_share_grants_private_and_writable_paths() {
+        let (_dir, vfs) = setup();
+        let p = |s: &str| VfsPath::new(s).unwrap();
+        let alice = VfsCaller::Context("alice");
+        let bob = VfsCaller::Context("bob");
+        let carol = VfsCaller::Context("carol");
+        vfs.write(alice, &p("/home/alice/keys/token.md"), b"secret")
+            .await
+            .unwrap();
+        vfs.write(alice, &p("/home/alice/proj/plan.md"), b"plan")
+            .await
+            .unwrap();
+
+        // Only the owner can change sharing, and only inside a home.
+        assert!(
+            vfs.share(bob, &p("/home/alice/keys"), "*", AclAccess::None)
+                .await
+                .is_err()
+        );
+        assert!(
+            vfs.share(alice, &p("/shared/x"), "bob", AclAccess::Read)
+                .await
+                .is_err()
+        );
+        vfs.share(alice, &p("/home/alice/keys"), "*", AclAccess::None)
+            .await
+            .unwrap();
+        let grants = vfs
+            .share(alice, &p("/home/alice/proj"), "bob", AclAccess::Write)
+            .await
+            .unwrap();
+        assert_eq!(grants.len(), 2);
+
+        let err = vfs
+            .read(bob, &p("/home/alice/keys/token.md"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(
+            vfs.read(alice, &p("/home/alice/keys/token.md"))
+                .await
+                .is_ok()
+        );
+        let names: Vec<String> = vfs
+            .list(carol, &p("/home/alice"))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["proj"]);
+
+        vfs.write(bob, &p("/home/alice/proj/plan.md"), b"bob's plan")
+            .await
+            .unwrap();
+        assert!(
+            vfs.write(carol, &p("/home/alice/proj/plan.md"), b"no")
+                .await
+                .is_err()
+        );
+
+        vfs.unshare(alice, &p("/home/alice/keys"), "*")
+            .await
+            .unwrap();
+        assert!(vfs.read(bob, &p("/home/alice/keys/token.md")).await.is_ok());
+        assert_eq!(
+            vfs.grants(&p("/home/alice/proj/plan.md"))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_callback_reports_watchers() {
+        use std::sync::Mutex;
+
+        let (_dir, mut vfs) = setup();
+        let seen: Arc<Mutex<Vec<(String, Vec<String>)>>> = Arc::default();
+        let sink = Arc::clone(&seen);
+        vfs.set_change_callback(Arc::new(move |change, watchers| {
+            sink.lock()
+                .unwrap()
+                .push((change.to_string(), watchers.to_vec()));
+        }));
+        let p = |s: &str| VfsPath::new(s).unwrap();
+        let planner = VfsCaller::Context("planner");
+        let coder = VfsCaller::Context("coder");
+
+        let watched = vfs.watch(planner, &p("/shared/tasks")).await.unwrap();
+        assert_eq!(watched, vec!["/shared/tasks"]);
+        assert!(vfs.watch(VfsCaller::System, &p("/shared")).await.is_err());
+
+        vfs.write(coder, &p("/shared/tasks/1.task"), b"x")
+            .await
+            .unwrap();
+        vfs.write(planner, &p("/shared/tasks/2.task"), b"y")
+            .await
+            .unwrap();
+        vfs.write(coder, &p("/shared/other"), b"z").await.unwrap();
+        vfs.rename(coder, &p("/shared/tasks/1.task"), &p("/shared/done.task"))
+            .await
+            .unwrap();
+
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(
+            seen,
+            vec![
+                (
+                    "write /shared/tasks/1.task by coder".to_string(),
+                    vec!["planner".to_string()]
+                ),
+                ("write /shared/tasks/2.task by planner".to_string(), vec![]),
+                ("write /shared/other by coder".to_string(), vec![]),
+                (
+                    "rename /shared/done.task by coder (from /shared/tasks/1.task)".to_string(),
+                    vec!["planner".to_string()]
+                ),
+            ],
+            "the watch list itself lives in /sys/ and produces no events"
+        );
+
+        assert!(
+            vfs.unwatch(planner, &p("/shared/tasks"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(vfs.unwatch(planner, &p("/shared/tasks")).await.is_err());
+    }
 }
@@ -0,0 +1,180 @@
+//! VFS change events and watch subscriptions.
+//!
+//! Every successful mutation outside `/sys/` produces a `VfsChange`. When a
+//! change callback is installed (`Vfs::set_change_callback`), the router
+//! passes it each change together with the contexts whose watches match.
+//! `Chibi` turns those into inbox messages and `vfs_changed` hooks.
+//!
+//! Watches are stored as a JSON array at `/sys/watches.json`:
+//!
+//! ```text
+//! [{"context": "planner", "prefix": "/flocks/dev/tasks", "created_at": 1767225600}]
+//! ```
+//!
+//! A watch on a prefix matches the prefix itself and everything below it. A
+//! context is never notified of its own changes.
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::path::VfsPath;
+
+/// VFS path of the watch list.
+pub const WATCHES_PATH: &str = "/sys/watches.json";
+
+/// What happened to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VfsChangeKind {
+    Write,
+    Append,
+    Delete,
+    Mkdir,
+    Copy,
+    Rename,
+}
+
+impl VfsChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Write => "write",
+            Self::Append => "append",
+            Self::Delete => "delete",
+            Self::Mkdir => "mkdir",
+            Self::Copy => "copy",
+            Self::Rename => "rename",
+        }
+    }
+}
+
+/// One change made through the VFS router.
+#[derive(Debug, Clone, Serialize)]
+pub struct VfsChange {
+    pub path: VfsPath,
+    pub kind: VfsChangeKind,
+    /// Caller that made the change (`SYSTEM` or a context name).
+    pub caller: String,
+    /// Source path for copies and renames.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<VfsPath>,
+}
+
+impl fmt::Display for VfsChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} by {}", self.kind.as_str(), self.path, self.caller)?;
+        if let Some(from) = &self.from {
+            write!(f, " (from {from})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Callback receiving each change and the contexts watching it.
+pub type VfsChangeCallback = Arc<dyn Fn(&VfsChange, &[String]) + Send + Sync>;
+
+/// A context's interest in changes under `prefix`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watch {
+    pub context: String,
+    pub prefix: String,
+    pub created_at: u64,
+}
+
+impl Watch {
+    fn covers(&self, path: &VfsPath) -> bool {
+        let p = path.as_str();
+        self.prefix == "/" || p == self.prefix || p.starts_with(&format!("{}/", self.prefix))
+    }
+}
+
+/// Whether changes to `path` produce events. `/sys/` is internal
+/// bookkeeping (tool cache, version histories, the watch list itself).
+pub(super) fn is_watchable(path: &VfsPath) -> bool {
+    let p = path.as_str();
+    !(p == "/sys" || p.starts_with("/sys/"))
+}
+
+/// Parse the stored watch list. A missing list is empty.
+pub(super) fn parse_watches(read: io::Result<Vec<u8>>) -> io::Result<Vec<Watch>> {
+    match read {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("watch list: {e}"))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Contexts to notify about `change`, deduplicated, excluding the caller.
+pub(super) fn subscribers(watches: &[Watch], change: &VfsChange) -> Vec<String> {
+    let mut contexts: Vec<String> = watches
+        .iter()
+        .filter(|w| w.context != change.caller)
+        .filter(|w| w.covers(&change.path) || change.from.as_ref().is_some_and(|f| w.covers(f)))
+        .map(|w| w.context.clone())
+        .collect();
+    contexts.sort();
+    contexts.dedup();
+    contexts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watch(context: &str, prefix: &str) -> Watch {
+        Watch {
+            context: context.to_string(),
+            prefix: prefix.to_string(),
+            created_at: 0,
+        }
+    }
+
+    fn change(path: &str, caller: &str, from: Option<&str>) -> VfsChange {
+        VfsChange {
+            path: VfsPath::new(path).unwrap(),
+            kind: VfsChangeKind::Write,
+            caller: caller.to_string(),
+            from: from.map(|f| VfsPath::new(f).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_subscribers_match_prefix_and_skip_caller() {
+        let watches = vec![
+            watch("planner", "/flocks/dev/tasks"),
+            watch("reviewer", "/flocks/dev"),
+            watch("reviewer", "/flocks/dev/tasks"),
+            watch("other", "/flocks/dev/tasks-archive"),
+        ];
+        assert_eq!(
+            subscribers(&watches, &change("/flocks/dev/tasks/1.task", "coder", None)),
+            vec!["planner", "reviewer"]
+        );
+        assert_eq!(
+            subscribers(
+                &watches,
+                &change("/flocks/dev/tasks/1.task", "planner", None)
+            ),
+            vec!["reviewer"]
+        );
+        assert!(subscribers(&watches, &change("/shared/x", "coder", None)).is_empty());
+        // Moving a file out of a watched dir notifies its watchers.
+        assert_eq!(
+            subscribers(
+                &watches,
+                &change("/shared/1.task", "coder", Some("/flocks/dev/tasks/1.task"))
+            ),
+            vec!["planner", "reviewer"]
+        );
+    }
+
+    #[test]
+    fn test_change_display() {
+        let c = change("/shared/b", "alice", Some("/shared/a"));
+        assert_eq!(c.to_string(), "write /shared/b by alice (from /shared/a)");
+        assert!(!is_watchable(&VfsPath::new("/sys/watches.json").unwrap()));
+    }
+}
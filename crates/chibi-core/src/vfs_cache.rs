@@ -1,13 +1,45 @@
 //! VFS-backed tool output cache.
 //!
-//! Stores large tool outputs under `vfs:///sys/tool_cache/<context>/<id>`,
-//! written as SYSTEM_CALLER and world-readable. Replaces the old `cache.rs`
-//! flat-file system.
+//! Large tool outputs are stored content-addressed, once per distinct
+//! content, and each cached call leaves a small reference in its context's
+//! cache directory:
+//!
+//! ```text
+//! /sys/blobs/<hh>/<sha256>            output content (SYSTEM-written, world-readable)
+//! /sys/tool_cache/<context>/<id>      reference: JSON `BlobRef`
+//! ```
+//!
+//! The LLM is given the blob URI, so identical outputs from different calls
+//! or contexts share one file. Clearing a context's cache or age-based
+//! cleanup removes references; `gc_blobs` then deletes blobs nothing
+//! references and evicts the least recently referenced blobs until the
+//! store fits its size budget. Replaces the old `cache.rs` flat-file system.
+//!
+//! Blobs carry no sharing grants, so outputs of reads from paths a grant
+//! made private are never cached (`reads_private_path`).
 
+use std::collections::HashMap;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::io::{self, ErrorKind};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::vfs::{Vfs, VfsCaller, VfsEntryKind, VfsPath};
+
+/// VFS directory holding cached output blobs.
+pub const BLOBS_ROOT: &str = "/sys/blobs";
+
+/// VFS directory holding per-context cache references.
+const TOOL_CACHE_ROOT: &str = "/sys/tool_cache";
+
+/// Unreferenced blobs younger than this are kept by `gc_blobs`, so a blob
+/// stored by another process is not collected before its reference lands.
+const UNREFERENCED_GRACE_SECS: i64 = 3600;
+
 /// Generate a unique cache ID.
 /// Format: `{tool}_{timestamp_hex}_{args_hash}` — globally unique, no collision risk.
 pub fn generate_cache_id(tool_name: &str, args: &serde_json::Value) -> String {
@@ -45,6 +77,23 @@ pub fn should_cache(content: &str, threshold: usize) -> bool {
     content.len() > threshold
 }
 
+/// Whether a tool call read a VFS `path` that a grant made private. Its output
+/// must not become a world-readable blob. Fails closed if the grants can't
+/// be read.
+pub async fn reads_private_path(vfs: &Vfs, args: &serde_json::Value) -> bool {
+    let Some(uri) = args
+        .get("path")
+        .and_then(|p| p.as_str())
+        .filter(|p| VfsPath::is_vfs_uri(p))
+    else {
+        return false;
+    };
+    match VfsPath::from_uri(uri) {
+        Ok(path) => vfs.is_private(&path).await.unwrap_or(true),
+        Err(_) => false,
+    }
+}
+
 /// Extract human-readable preview content from tool output.
 ///
 /// When the output is a single JSON line with `stdout` and/or `stderr` fields
@@ -114,6 +163,213 @@ pub fn truncated_message(
     )
 }
 
+/// Reference from one cached tool call to the blob holding its output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlobRef {
+    /// Hex sha256 of the content.
+    pub blob: String,
+    pub size: u64,
+    pub tool: String,
+    /// Tool call id of the transcript entry the output belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+}
+
+/// Result of `store_blob`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBlob {
+    pub hash: String,
+    /// `vfs:///sys/blobs/...` URI of the content.
+    pub uri: String,
+    /// The content was already stored by an earlier call.
+    pub deduplicated: bool,
+}
+
+/// What `gc_blobs` removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlobGcReport {
+    /// Blobs deleted because nothing referenced them.
+    pub unreferenced: usize,
+    /// Referenced blobs evicted to fit the size budget.
+    pub evicted: usize,
+    pub freed_bytes: u64,
+}
+
+impl BlobGcReport {
+    pub fn removed(&self) -> usize {
+        self.unreferenced + self.evicted
+    }
+}
+
+/// VFS path of the blob with hex sha256 `hash`.
+pub fn blob_path_for(hash: &str) -> io::Result<VfsPath> {
+    let prefix = hash.get(..2).unwrap_or(hash);
+    VfsPath::new(&format!("{BLOBS_ROOT}/{prefix}/{hash}"))
+}
+
+/// Store `content` once and record a reference to it at
+/// `/sys/tool_cache/<context>/<cache_id>`.
+pub async fn store_blob(
+    vfs: &Vfs,
+    context_name: &str,
+    cache_id: &str,
+    tool_name: &str,
+    call_id: Option<&str>,
+    content: &[u8],
+) -> io::Result<StoredBlob> {
+    let hash = format!("{:x}", Sha256::digest(content));
+    let blob = blob_path_for(&hash)?;
+    let deduplicated = vfs.exists(VfsCaller::System, &blob).await?;
+    if !deduplicated {
+        vfs.write(VfsCaller::System, &blob, content).await?;
+    }
+    let reference = BlobRef {
+        blob: hash.clone(),
+        size: content.len() as u64,
+        tool: tool_name.to_string(),
+        call_id: call_id.map(str::to_string),
+    };
+    let data =
+        serde_json::to_vec(&reference).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    let ref_path = VfsPath::new(&vfs_path_for(context_name, cache_id))?;
+    vfs.write(VfsCaller::System, &ref_path, &data).await?;
+    Ok(StoredBlob {
+        uri: format!("vfs://{blob}"),
+        hash,
+        deduplicated,
+    })
+}
+
+/// A blob as seen by `gc_blobs`.
+struct BlobInfo {
+    path: VfsPath,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+/// The references to one blob.
+#[derive(Default)]
+struct BlobRefs {
+    paths: Vec<VfsPath>,
+    /// Newest reference time: the blob's place in LRU order.
+    last_used: Option<DateTime<Utc>>,
+}
+
+/// Delete unreferenced blobs, then evict the least recently referenced
+/// blobs (and their references) until the store holds at most `max_bytes`.
+/// `max_bytes = 0` means no size limit.
+pub async fn gc_blobs(vfs: &Vfs, max_bytes: u64) -> io::Result<BlobGcReport> {
+    let refs = collect_refs(vfs).await?;
+    let mut blobs = collect_blobs(vfs).await?;
+    let mut report = BlobGcReport::default();
+    let grace_cutoff = Utc::now() - chrono::Duration::seconds(UNREFERENCED_GRACE_SECS);
+
+    let mut kept = Vec::new();
+    for (hash, info) in blobs.drain() {
+        if refs.contains_key(&hash) {
+            kept.push((hash, info));
+        } else if info.modified.is_none_or(|m| m < grace_cutoff) {
+            vfs.delete(VfsCaller::System, &info.path).await?;
+            report.unreferenced += 1;
+            report.freed_bytes += info.size;
+        }
+    }
+
+    let mut total: u64 = kept.iter().map(|(_, info)| info.size).sum();
+    if max_bytes == 0 || total <= max_bytes {
+        return Ok(report);
+    }
+    kept.sort_by_key(|(hash, _)| refs.get(hash).and_then(|r| r.last_used));
+    for (hash, info) in kept {
+        if total <= max_bytes {
+            break;
+        }
+        vfs.delete(VfsCaller::System, &info.path).await?;
+        for path in refs
+            .get(&hash)
+            .map(|r| r.paths.as_slice())
+            .unwrap_or_default()
+        {
+            let _ = vfs.delete(VfsCaller::System, path).await;
+        }
+        total -= info.size;
+        report.evicted += 1;
+        report.freed_bytes += info.size;
+    }
+    Ok(report)
+}
+
+/// List a directory, treating a missing one as empty.
+async fn list_or_empty(vfs: &Vfs, dir: &VfsPath) -> io::Result<Vec<crate::vfs::VfsEntry>> {
+    match vfs.list(VfsCaller::System, dir).await {
+        Ok(entries) => Ok(entries),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Every blob reference, keyed by blob hash. Cache entries that aren't
+/// references (written before the blob store existed) are skipped.
+async fn collect_refs(vfs: &Vfs) -> io::Result<HashMap<String, BlobRefs>> {
+    let mut refs: HashMap<String, BlobRefs> = HashMap::new();
+    let root = VfsPath::new(TOOL_CACHE_ROOT)?;
+    for ctx_dir in list_or_empty(vfs, &root).await? {
+        if ctx_dir.kind != VfsEntryKind::Directory {
+            continue;
+        }
+        let dir = root.join(&ctx_dir.name)?;
+        for entry in list_or_empty(vfs, &dir).await? {
+            if entry.kind != VfsEntryKind::File {
+                continue;
+            }
+            let path = dir.join(&entry.name)?;
+            let Ok(data) = vfs.read(VfsCaller::System, &path).await else {
+                continue;
+            };
+            let Ok(reference) = serde_json::from_slice::<BlobRef>(&data) else {
+                continue;
+            };
+            let modified = vfs
+                .metadata(VfsCaller::System, &path)
+                .await
+                .ok()
+                .and_then(|m| m.modified);
+            let slot = refs.entry(reference.blob).or_default();
+            slot.paths.push(path);
+            slot.last_used = slot.last_used.max(modified);
+        }
+    }
+    Ok(refs)
+}
+
+/// Every stored blob, keyed by hash.
+async fn collect_blobs(vfs: &Vfs) -> io::Result<HashMap<String, BlobInfo>> {
+    let mut blobs = HashMap::new();
+    let root = VfsPath::new(BLOBS_ROOT)?;
+    for shard in list_or_empty(vfs, &root).await? {
+        if shard.kind != VfsEntryKind::Directory {
+            continue;
+        }
+        let dir = root.join(&shard.name)?;
+        for entry in list_or_empty(vfs, &dir).await? {
+            if entry.kind != VfsEntryKind::File {
+                continue;
+            }
+            let path = dir.join(&entry.name)?;
+            let meta = vfs.metadata(VfsCaller::System, &path).await?;
+            blobs.insert(
+                entry.name,
+                BlobInfo {
+                    path,
+                    size: meta.size,
+                    modified: meta.modified,
+                },
+            );
+        }
+    }
+    Ok(blobs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,4 +459,117 @@ mod tests {
         let content = "line1\nline2";
         assert_eq!(extract_preview_content(content), content);
     }
+
+    fn blob_vfs() -> (tempfile::TempDir, Vfs) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let backend = crate::vfs::LocalBackend::new(dir.path().to_path_buf());
+        (dir, Vfs::new(Box::new(backend), "test-site-0000"))
+    }
+
+    /// Backdate a file stored by the local backend.
+    fn backdate(dir: &tempfile::TempDir, path: &VfsPath, secs: u64) {
+        let os_path = dir.path().join(&path.as_str()[1..]);
+        let past = SystemTime::now() - std::time::Duration::from_secs(secs);
+        std::fs::File::options()
+            .write(true)
+            .open(os_path)
+            .unwrap()
+            .set_times(std::fs::FileTimes::new().set_modified(past))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_private_path() {
+        let (_dir, vfs) = blob_vfs();
+        let secret = VfsPath::new("/home/alice/secret").unwrap();
+        vfs.share(VfsCaller::System, &secret, "*", crate::vfs::AclAccess::None)
+            .await
+            .unwrap();
+
+        let read = |path: &str| serde_json::json!({ "path": path });
+        assert!(reads_private_path(&vfs, &read("vfs:///home/alice/secret/key.md")).await);
+        // A directory holding a private path counts too (e.g. a grep over it)
+        assert!(reads_private_path(&vfs, &read("vfs:///home/alice")).await);
+        assert!(!reads_private_path(&vfs, &read("vfs:///home/alice/notes.md")).await);
+        assert!(!reads_private_path(&vfs, &read("vfs:///shared/notes.md")).await);
+        assert!(!reads_private_path(&vfs, &read("/home/alice/secret")).await);
+    }
+
+    #[tokio::test]
+    async fn test_store_blob_deduplicates_across_contexts() {
+        let (_dir, vfs) = blob_vfs();
+        let a = store_blob(
+            &vfs,
+            "alice",
+            "grep_1",
+            "grep_files",
+            Some("call_1"),
+            b"same",
+        )
+        .await
+        .unwrap();
+        let b = store_blob(&vfs, "bob", "grep_2", "grep_files", None, b"same")
+            .await
+            .unwrap();
+        assert!(!a.deduplicated && b.deduplicated);
+        assert_eq!(a.uri, b.uri);
+        assert!(
+            a.uri
+                .starts_with(&format!("vfs://{BLOBS_ROOT}/{}/", &a.hash[..2]))
+        );
+
+        let blob = blob_path_for(&a.hash).unwrap();
+        assert_eq!(vfs.read(VfsCaller::System, &blob).await.unwrap(), b"same");
+        let reference = VfsPath::new(&vfs_path_for("alice", "grep_1")).unwrap();
+        let reference: BlobRef =
+            serde_json::from_slice(&vfs.read(VfsCaller::System, &reference).await.unwrap())
+                .unwrap();
+        assert_eq!(reference.blob, a.hash);
+        assert_eq!(reference.call_id.as_deref(), Some("call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_gc_blobs_drops_unreferenced_and_evicts_lru() {
+        let (dir, vfs) = blob_vfs();
+        let old = store_blob(&vfs, "ctx", "old", "t", None, &[b'o'; 100])
+            .await
+            .unwrap();
+        let new = store_blob(&vfs, "ctx", "new", "t", None, &[b'n'; 100])
+            .await
+            .unwrap();
+        let orphan = store_blob(&vfs, "gone", "x", "t", None, &[b'x'; 50])
+            .await
+            .unwrap();
+        backdate(
+            &dir,
+            &VfsPath::new(&vfs_path_for("ctx", "old")).unwrap(),
+            600,
+        );
+        let gone = VfsPath::new("/sys/tool_cache/gone").unwrap();
+        vfs.delete(VfsCaller::System, &gone).await.unwrap();
+
+        // A fresh orphan is kept for the grace period.
+        let report = gc_blobs(&vfs, 0).await.unwrap();
+        assert_eq!(report, BlobGcReport::default());
+
+        backdate(&dir, &blob_path_for(&orphan.hash).unwrap(), 2 * 3600);
+        let report = gc_blobs(&vfs, 150).await.unwrap();
+        assert_eq!((report.unreferenced, report.evicted), (1, 1));
+        assert_eq!(report.freed_bytes, 150);
+        let exists = |hash: String| {
+            let vfs = &vfs;
+            async move {
+                vfs.exists(VfsCaller::System, &blob_path_for(&hash).unwrap())
+                    .await
+                    .unwrap()
+            }
+        };
+        assert!(
+            !exists(old.hash).await,
+            "least recently referenced blob is evicted"
+        );
+        assert!(exists(new.hash).await);
+        let old_ref = VfsPath::new(&vfs_path_for("ctx", "old")).unwrap();
+        assert!(!vfs.exists(VfsCaller::System, &old_ref).await.unwrap());
+    }
 }
@@ -1,7 +1,7 @@
 use std::io::{self, Read};
 
 use chibi_core::input::Command;
-use chibi_core::{Chibi, LoadOptions, OutputSink};
+use chibi_core::{Chibi, ChibiError, LoadOptions, OutputSink};
 
 mod input;
 mod output;
@@ -86,7 +86,7 @@ async fn run() -> io::Result<()> {
             .collect();
         resolved
             .apply_overrides_from_pairs(&pairs)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            .map_err(|message| ChibiError::ConfigInvalid { message })?;
     }
     let mut response_sink = sink::JsonResponseSink::new();
 
@@ -102,6 +102,11 @@ async fn run() -> io::Result<()> {
     )
     .await?;
 
+    // Running out of fuel ends the turn normally, but the task is unfinished
+    if let Some(total) = response_sink.fuel_exhausted {
+        return Err(ChibiError::FuelExhausted { total }.into());
+    }
+
     // Handle config inspection effects — JSON mode only has core fields
     match &effect {
         chibi_core::CommandEffect::InspectConfigField { field, .. } => {
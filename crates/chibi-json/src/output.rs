@@ -1,16 +1,23 @@
-use chibi_core::OutputSink;
 use chibi_core::context::TranscriptEntry;
 use chibi_core::output::CommandEvent;
+use chibi_core::{ChibiError, OutputSink};
 use std::io::{self, Write};
 
-/// Map `io::ErrorKind` to a stable coarse-grained error code string.
+/// Map an error to a stable code string.
+///
+/// Typed `ChibiError`s report their own fine-grained code; anything else
+/// falls back to a coarse code from its `io::ErrorKind`.
 fn error_code(e: &io::Error) -> &'static str {
+    if let Some(typed) = ChibiError::find(e) {
+        return typed.code();
+    }
     match e.kind() {
         io::ErrorKind::NotFound => "not_found",
         io::ErrorKind::InvalidInput => "invalid_input",
         io::ErrorKind::PermissionDenied => "permission_denied",
         io::ErrorKind::InvalidData => "invalid_data",
         io::ErrorKind::AlreadyExists => "already_exists",
+        io::ErrorKind::TimedOut => "timed_out",
         _ => "internal_error",
     }
 }
@@ -27,6 +34,11 @@ impl OutputSink for JsonOutputSink {
         println!("{}", json);
     }
 
+    fn emit_structured(&self, value: &serde_json::Value) {
+        let json = serde_json::json!({"type": "result", "content": value});
+        println!("{}", json);
+    }
+
     fn emit_event(&self, event: CommandEvent) {
         let json = match event {
             CommandEvent::AutoDestroyed { count } => {
@@ -89,6 +101,33 @@ impl OutputSink for JsonOutputSink {
                 serde_json::json!({"type": "rolling_compaction_complete",
                                    "archived": archived, "remaining": remaining})
             }
+            CommandEvent::CompactionStrategyFallback { strategy } => {
+                serde_json::json!({"type": "compaction_strategy_fallback", "strategy": strategy})
+            }
+            CommandEvent::LockWaiting {
+                context,
+                waited_secs,
+                timeout_secs,
+            } => serde_json::json!({"type": "lock_waiting", "context": context,
+                                   "waited_secs": waited_secs, "timeout_secs": timeout_secs}),
+            CommandEvent::LockAcquired {
+                context,
+                waited_secs,
+            } => serde_json::json!({"type": "lock_acquired", "context": context,
+                                   "waited_secs": waited_secs}),
+            CommandEvent::PromptQueued { context } => {
+                serde_json::json!({"type": "prompt_queued", "context": context})
+            }
+            CommandEvent::QueuedPromptsRunning { context, count } => {
+                serde_json::json!({"type": "queued_prompts_running", "context": context,
+                                   "count": count})
+            }
+            CommandEvent::StructuredOutputRetry {
+                attempt,
+                max_attempts,
+                errors,
+            } => serde_json::json!({"type": "structured_output_retry", "attempt": attempt,
+                                    "max_attempts": max_attempts, "errors": errors}),
             CommandEvent::LoadSummary {
                 builtin_count,
                 builtin_names,
@@ -124,12 +163,28 @@ impl OutputSink for JsonOutputSink {
     fn emit_done(&self, result: &io::Result<()>) {
         let json = match result {
             Ok(()) => serde_json::json!({"type": "done", "ok": true}),
-            Err(e) => serde_json::json!({
-                "type": "done",
-                "ok": false,
-                "code": error_code(e),
-                "message": e.to_string(),
-            }),
+            Err(e) => {
+                let typed = ChibiError::find(e);
+                let mut json = serde_json::json!({
+                    "type": "done",
+                    "ok": false,
+                    "code": error_code(e),
+                    "message": e.to_string(),
+                    "retryable": typed.is_some_and(ChibiError::is_retryable),
+                });
+                match typed {
+                    Some(ChibiError::Provider {
+                        status: Some(status),
+                        ..
+                    }) => json["status"] = serde_json::json!(status),
+                    Some(ChibiError::RateLimited {
+                        retry_after_secs: Some(secs),
+                        ..
+                    }) => json["retry_after_secs"] = serde_json::json!(secs),
+                    _ => {}
+                }
+                json
+            }
         };
         eprintln!("{}", json);
     }
@@ -170,6 +225,23 @@ mod tests {
         assert_eq!(error_code(&e), "already_exists");
     }
 
+    #[test]
+    fn error_code_timed_out() {
+        let e = io::Error::new(io::ErrorKind::TimedOut, "x");
+        assert_eq!(error_code(&e), "timed_out");
+    }
+
+    #[test]
+    fn error_code_typed_overrides_kind() {
+        let e: io::Error = ChibiError::ContextNotFound {
+            context: "x".to_string(),
+        }
+        .into();
+        assert_eq!(error_code(&e), "context_not_found");
+        let e: io::Error = ChibiError::provider("Gateway error: 429 Too Many Requests").into();
+        assert_eq!(error_code(&e), "rate_limited");
+    }
+
     #[test]
     fn error_code_fallback() {
         let e = io::Error::new(io::ErrorKind::BrokenPipe, "x");
@@ -5,11 +5,17 @@ use std::io::{self, Write};
 ///
 /// Emits complete transcript entries and diagnostics as JSONL.
 /// No streaming partial text — programmatic consumers want complete records.
-pub struct JsonResponseSink;
+pub struct JsonResponseSink {
+    /// Fuel budget of a turn that ran out of fuel, reported as a
+    /// `fuel_exhausted` error in the `done` signal.
+    pub fuel_exhausted: Option<usize>,
+}
 
 impl JsonResponseSink {
     pub fn new() -> Self {
-        Self
+        Self {
+            fuel_exhausted: None,
+        }
     }
 }
 
@@ -85,6 +91,7 @@ impl ResponseSink for JsonResponseSink {
                 eprintln!("{}", j);
             }
             ResponseEvent::FuelExhausted { total } => {
+                self.fuel_exhausted = Some(total);
                 eprintln!(
                     "{}",
                     serde_json::json!({
@@ -121,6 +128,34 @@ impl ResponseSink for JsonResponseSink {
                     })
                 );
             }
+            ResponseEvent::ProviderRetry {
+                attempt,
+                max_retries,
+                delay_ms,
+                error,
+            } => {
+                eprintln!(
+                    "{}",
+                    serde_json::json!({
+                        "type": "provider_retry",
+                        "attempt": attempt,
+                        "max_retries": max_retries,
+                        "delay_ms": delay_ms,
+                        "error": error,
+                    })
+                );
+            }
+            ResponseEvent::ModelFallback { from, to, reason } => {
+                eprintln!(
+                    "{}",
+                    serde_json::json!({
+                        "type": "model_fallback",
+                        "from": from,
+                        "to": to,
+                        "reason": reason,
+                    })
+                );
+            }
         }
         Ok(())
     }